@@ -0,0 +1,412 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+const REQUEST_TIMEOUT_SECONDS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannerCounter {
+    Runs,
+    SuccessfulRuns,
+    ConnectionFailures,
+    DesinfectionEnabled,
+    HeatingBlocked,
+}
+
+impl PlannerCounter {
+    pub fn all() -> Vec<PlannerCounter> {
+        vec![
+            PlannerCounter::Runs,
+            PlannerCounter::SuccessfulRuns,
+            PlannerCounter::ConnectionFailures,
+            PlannerCounter::DesinfectionEnabled,
+            PlannerCounter::HeatingBlocked,
+        ]
+    }
+
+    fn metric_name(&self) -> &'static str {
+        match self {
+            PlannerCounter::Runs => "alpha_innotec_planner_runs_total",
+            PlannerCounter::SuccessfulRuns => "alpha_innotec_planner_successful_runs_total",
+            PlannerCounter::ConnectionFailures => "alpha_innotec_planner_connection_failures_total",
+            PlannerCounter::DesinfectionEnabled => {
+                "alpha_innotec_planner_desinfection_enabled_total"
+            }
+            PlannerCounter::HeatingBlocked => "alpha_innotec_planner_heating_blocked_total",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            PlannerCounter::Runs => "Planning runs started",
+            PlannerCounter::SuccessfulRuns => "Planning runs that finished successfully",
+            PlannerCounter::ConnectionFailures => {
+                "Connections to the heatpump that failed or were closed halfway"
+            }
+            PlannerCounter::DesinfectionEnabled => "Times continuous desinfection got enabled",
+            PlannerCounter::HeatingBlocked => {
+                "Times a heating schedule blocking heating got written"
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct HealthState {
+    last_successful_plan_at: Arc<AtomicI64>,
+    energy_counters_kwh: Arc<Mutex<BTreeMap<String, f64>>>,
+    planner_counters: Arc<[AtomicU64; 5]>,
+}
+
+impl HealthState {
+    pub fn mark_plan_succeeded(&self, at: DateTime<Utc>) {
+        self.last_successful_plan_at
+            .store(at.timestamp(), Ordering::SeqCst);
+    }
+
+    pub fn last_successful_plan_at(&self) -> Option<DateTime<Utc>> {
+        match self.last_successful_plan_at.load(Ordering::SeqCst) {
+            0 => None,
+            timestamp => DateTime::from_timestamp(timestamp, 0),
+        }
+    }
+
+    pub fn set_energy_counters(&self, energy_counters_kwh: BTreeMap<String, f64>) {
+        *self.energy_counters_kwh.lock().unwrap() = energy_counters_kwh;
+    }
+
+    pub fn increment(&self, counter: PlannerCounter) {
+        self.planner_counters[counter as usize].fetch_add(1, Ordering::SeqCst);
+    }
+
+    // counters only live as long as the process, a cronjob run starts from 0 every time
+    pub fn get_counter(&self, counter: PlannerCounter) -> u64 {
+        self.planner_counters[counter as usize].load(Ordering::SeqCst)
+    }
+
+    pub fn get_metrics(&self) -> String {
+        // prometheus text exposition format, the heatpump's kwh counters only ever increase
+        let mut metrics = "# HELP alpha_innotec_energy_kwh_total Energy counters read from the heatpump's Energie menu\n# TYPE alpha_innotec_energy_kwh_total counter\n".to_string();
+        for (name, value) in self.energy_counters_kwh.lock().unwrap().iter() {
+            metrics.push_str(&format!(
+                "alpha_innotec_energy_kwh_total{{counter=\"{}\"}} {}\n",
+                name.replace('\\', "\\\\").replace('"', "\\\""),
+                value
+            ));
+        }
+
+        for counter in PlannerCounter::all() {
+            metrics.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} counter\n{} {}\n",
+                counter.metric_name(),
+                counter.help(),
+                counter.metric_name(),
+                counter.metric_name(),
+                self.get_counter(counter)
+            ));
+        }
+
+        metrics
+    }
+
+    pub fn is_ready(&self, now: DateTime<Utc>, staleness: Duration) -> bool {
+        match self.last_successful_plan_at() {
+            Some(last_successful_plan_at) => now - last_successful_plan_at <= staleness,
+            None => false,
+        }
+    }
+}
+
+pub struct HealthServerConfig {
+    port: u16,
+    readiness_staleness: Duration,
+    health_state: HealthState,
+}
+
+impl HealthServerConfig {
+    pub fn new(
+        port: u16,
+        readiness_staleness: Duration,
+        health_state: HealthState,
+    ) -> Result<Self, Box<dyn Error>> {
+        debug!(
+            "HealthServerConfig::new(port: {}, readiness_staleness: {})",
+            port, readiness_staleness
+        );
+
+        Ok(Self {
+            port,
+            readiness_staleness,
+            health_state,
+        })
+    }
+
+    pub fn from_env(health_state: HealthState) -> Result<Option<Self>, Box<dyn Error>> {
+        let port: u16 = match env::var("HEALTH_PORT") {
+            Ok(port) => port.parse()?,
+            Err(_) => return Ok(None),
+        };
+        let readiness_staleness_minutes: i64 = env::var("HEALTH_READINESS_STALENESS_MINUTES")
+            .unwrap_or_else(|_| "1440".to_string())
+            .parse()?;
+
+        Ok(Some(Self::new(
+            port,
+            Duration::minutes(readiness_staleness_minutes),
+            health_state,
+        )?))
+    }
+}
+
+pub struct HealthServer {
+    config: HealthServerConfig,
+}
+
+impl HealthServer {
+    pub fn new(config: HealthServerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn from_env(health_state: HealthState) -> Result<Option<Self>, Box<dyn Error>> {
+        Ok(HealthServerConfig::from_env(health_state)?.map(Self::new))
+    }
+
+    pub async fn run(self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let listener = TcpListener::bind(("0.0.0.0", self.config.port)).await?;
+        info!("Serving health endpoints on port {}", self.config.port);
+
+        self.serve(listener).await
+    }
+
+    async fn serve(self, listener: TcpListener) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // each connection gets its own task, so a client that never sends its request can't hold up the probes
+        let health_server = Arc::new(self);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let health_server = Arc::clone(&health_server);
+            tokio::spawn(async move {
+                if let Err(e) = health_server.handle(stream).await {
+                    warn!("Failed handling health request: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn handle(&self, mut stream: TcpStream) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut buffer = [0; 1024];
+        let length = tokio::time::timeout(
+            std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS),
+            stream.read(&mut buffer),
+        )
+        .await
+        .map_err(|_| {
+            format!(
+                "No health request received within {} seconds",
+                REQUEST_TIMEOUT_SECONDS
+            )
+        })??;
+        let request = String::from_utf8_lossy(&buffer[..length]);
+
+        let (status, body) = self.get_response(request.lines().next().unwrap_or_default());
+
+        stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .as_bytes(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn get_response(&self, request_line: &str) -> (&'static str, String) {
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+        match path {
+            "/healthz" => ("200 OK", "ok".to_string()),
+            "/metrics" => ("200 OK", self.config.health_state.get_metrics()),
+            "/readyz" => {
+                let body = match self.config.health_state.last_successful_plan_at() {
+                    Some(at) => format!("last successful plan at {}", at),
+                    None => "no successful plan yet".to_string(),
+                };
+
+                if self
+                    .config
+                    .health_state
+                    .is_ready(Utc::now(), self.config.readiness_staleness)
+                {
+                    ("200 OK", body)
+                } else {
+                    ("503 Service Unavailable", body)
+                }
+            }
+            _ => ("404 Not Found", "not found".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn is_ready_returns_false_before_first_successful_plan() {
+        let health_state = HealthState::default();
+
+        // act
+        let is_ready = health_state.is_ready(Utc::now(), Duration::minutes(60));
+
+        assert!(!is_ready);
+    }
+
+    #[test]
+    fn is_ready_returns_true_if_last_successful_plan_is_within_staleness_window() {
+        let health_state = HealthState::default();
+        health_state.mark_plan_succeeded(Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap());
+
+        // act
+        let is_ready = health_state.is_ready(
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 45, 0).unwrap(),
+            Duration::minutes(60),
+        );
+
+        assert!(is_ready);
+    }
+
+    #[test]
+    fn is_ready_returns_false_if_last_successful_plan_is_stale() {
+        let health_state = HealthState::default();
+        health_state.mark_plan_succeeded(Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap());
+
+        // act
+        let is_ready = health_state.is_ready(
+            Utc.with_ymd_and_hms(2022, 4, 21, 14, 30, 0).unwrap(),
+            Duration::minutes(60),
+        );
+
+        assert!(!is_ready);
+    }
+
+    #[test]
+    fn get_metrics_returns_energy_counters() {
+        let health_state = HealthState::default();
+        health_state.set_energy_counters(BTreeMap::from([
+            ("Warmwater".to_string(), 1234.5),
+            ("Verwarming".to_string(), 8765.0),
+        ]));
+
+        // act
+        let metrics = health_state.get_metrics();
+
+        assert!(metrics.contains("# TYPE alpha_innotec_energy_kwh_total counter\n"));
+        assert!(metrics.contains("alpha_innotec_energy_kwh_total{counter=\"Warmwater\"} 1234.5\n"));
+        assert!(metrics.contains("alpha_innotec_energy_kwh_total{counter=\"Verwarming\"} 8765\n"));
+    }
+
+    #[test]
+    fn get_metrics_returns_planner_counters() {
+        let health_state = HealthState::default();
+        health_state.increment(PlannerCounter::Runs);
+        health_state.increment(PlannerCounter::Runs);
+        health_state.increment(PlannerCounter::HeatingBlocked);
+
+        // act
+        let metrics = health_state.get_metrics();
+
+        assert!(metrics.contains("# TYPE alpha_innotec_planner_runs_total counter\n"));
+        assert!(metrics.contains("alpha_innotec_planner_runs_total 2\n"));
+        assert!(metrics.contains("alpha_innotec_planner_successful_runs_total 0\n"));
+        assert!(metrics.contains("alpha_innotec_planner_heating_blocked_total 1\n"));
+    }
+
+    #[test]
+    fn get_counter_is_shared_between_clones() {
+        let health_state = HealthState::default();
+
+        // act
+        health_state
+            .clone()
+            .increment(PlannerCounter::ConnectionFailures);
+
+        assert_eq!(
+            health_state.get_counter(PlannerCounter::ConnectionFailures),
+            1
+        );
+        assert_eq!(health_state.get_counter(PlannerCounter::Runs), 0);
+    }
+
+    #[test]
+    fn get_response_returns_status_per_path() {
+        let health_state = HealthState::default();
+        let health_server = HealthServer::new(
+            HealthServerConfig::new(8080, Duration::minutes(60), health_state.clone()).unwrap(),
+        );
+
+        assert_eq!(
+            health_server.get_response("GET /healthz HTTP/1.1").0,
+            "200 OK"
+        );
+        assert_eq!(
+            health_server.get_response("GET /readyz HTTP/1.1").0,
+            "503 Service Unavailable"
+        );
+        assert_eq!(
+            health_server.get_response("GET / HTTP/1.1").0,
+            "404 Not Found"
+        );
+
+        health_state.mark_plan_succeeded(Utc::now());
+
+        assert_eq!(
+            health_server.get_response("GET /readyz HTTP/1.1").0,
+            "200 OK"
+        );
+    }
+
+    #[tokio::test]
+    async fn serve_answers_requests_while_another_connection_stays_idle() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let health_server = HealthServer::new(
+            HealthServerConfig::new(
+                address.port(),
+                Duration::minutes(60),
+                HealthState::default(),
+            )
+            .unwrap(),
+        );
+        tokio::spawn(health_server.serve(listener));
+        let _idle_stream = TcpStream::connect(address).await.unwrap();
+
+        // act
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /healthz HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(2),
+            stream.read_to_string(&mut response),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nok"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::error::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+// the notification webhook and the http spot price source are expected on the local network, so this only speaks plain
+// http; requests go out as http/1.0, which keeps the server from answering with a chunked body, and the body is read
+// until the server closes the connection
+pub(crate) struct HttpResponse {
+    pub status_line: String,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        matches!(self.status_line.split_whitespace().nth(1), Some(status) if status.starts_with('2'))
+    }
+}
+
+pub(crate) async fn get(url: &str, timeout_seconds: u64) -> Result<HttpResponse, Box<dyn Error>> {
+    request(
+        url,
+        timeout_seconds,
+        "GET",
+        "Accept: application/json\r\n",
+        "",
+    )
+    .await
+}
+
+pub(crate) async fn post_json(
+    url: &str,
+    body: &str,
+    timeout_seconds: u64,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    request(
+        url,
+        timeout_seconds,
+        "POST",
+        &format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n",
+            body.len()
+        ),
+        body,
+    )
+    .await
+}
+
+async fn request(
+    url: &str,
+    timeout_seconds: u64,
+    method: &str,
+    headers: &str,
+    body: &str,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    let (host, path) = get_host_and_path(url)?;
+    let address = if host.contains(':') {
+        host.clone()
+    } else {
+        format!("{}:80", host)
+    };
+    let request = format!(
+        "{} {} HTTP/1.0\r\nHost: {}\r\n{}\r\n{}",
+        method, path, host, headers, body
+    );
+
+    let response = timeout(Duration::from_secs(timeout_seconds), async {
+        let mut stream = TcpStream::connect(address).await?;
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await?;
+
+        Ok::<Vec<u8>, Box<dyn Error>>(response)
+    })
+    .await
+    .map_err(|_| {
+        format!(
+            "{} {} timed out after {} seconds",
+            method, url, timeout_seconds
+        )
+    })??;
+
+    let response = String::from_utf8(response)?;
+    Ok(match response.split_once("\r\n\r\n") {
+        Some((head, body)) => HttpResponse {
+            status_line: head.lines().next().unwrap_or_default().to_string(),
+            body: body.to_string(),
+        },
+        None => HttpResponse {
+            status_line: response.lines().next().unwrap_or_default().to_string(),
+            body: String::new(),
+        },
+    })
+}
+
+pub(crate) fn get_host_and_path(url: &str) -> Result<(String, String), Box<dyn Error>> {
+    let without_scheme = match url.strip_prefix("http://") {
+        Some(without_scheme) => without_scheme,
+        None => {
+            return Err(Box::<dyn Error>::from(format!(
+                "Url {} should start with http://",
+                url
+            )))
+        }
+    };
+
+    Ok(match without_scheme.find('/') {
+        Some(index) => (
+            without_scheme[..index].to_string(),
+            without_scheme[index..].to_string(),
+        ),
+        None => (without_scheme.to_string(), "/".to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn get_host_and_path_splits_url() {
+        // act
+        let (host, path) =
+            get_host_and_path("http://192.168.1.2:8123/api/webhook/heatpump").unwrap();
+
+        assert_eq!(host, "192.168.1.2:8123".to_string());
+        assert_eq!(path, "/api/webhook/heatpump".to_string());
+    }
+
+    #[test]
+    fn get_host_and_path_returns_error_for_https_url() {
+        // act
+        let result = get_host_and_path("https://example.com/webhook");
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn get_returns_status_line_and_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 1024];
+            let length = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.0 404 Not Found\r\nContent-Type: text/plain\r\n\r\nno prices")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..length]).to_string()
+        });
+
+        // act
+        let response = get(&format!("http://127.0.0.1:{}/prices", port), 5)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_line, "HTTP/1.0 404 Not Found".to_string());
+        assert_eq!(response.body, "no prices".to_string());
+        assert!(!response.is_success());
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /prices HTTP/1.0\r\n"));
+    }
+}
@@ -1,13 +1,89 @@
+mod health_server;
+mod http_client;
 mod model;
+mod prices;
+mod schedule;
+mod scheduler;
+mod shutdown;
+mod sim;
+mod spot_price_source;
+mod spot_prices_retrier;
 mod state_client;
+mod webhook_notifier;
 mod websocket_client;
 
+use clap::{Parser, Subcommand};
+use health_server::HealthServer;
 use jarvis_lib::config_client::{ConfigClient, ConfigClientConfig};
-use jarvis_lib::planner_service::{PlannerService, PlannerServiceConfig};
+use jarvis_lib::model::{SpotPricePlanner, SpotPricePlannerConfig};
 use jarvis_lib::spot_prices_state_client::{SpotPricesStateClient, SpotPricesStateClientConfig};
+use model::ClockProgram;
+use scheduler::Scheduler;
+use shutdown::GracefulShutdown;
+use sim::SimulatedHeatPump;
+use spot_price_source::{CsvSpotPriceSource, SpotPriceSource};
+use spot_prices_retrier::SpotPricesRetrier;
 use state_client::StateClient;
+use std::fs;
+use std::future::Future;
+use tracing::error;
 use websocket_client::WebsocketClient;
 
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Plan tap water heating and heating blocking and write the schedules to the heatpump
+    Plan {
+        /// Print the planned schedule instead of writing it to the heatpump
+        #[arg(long)]
+        dry: bool,
+    },
+    /// Print all temperatures read from the heatpump
+    Temps,
+    /// Reset the clock programs of the heatpump to 00:00 - 00:00 without planning anything
+    Reset {
+        /// Only reset this clock program instead of all of them
+        #[arg(long, value_enum)]
+        program: Option<ClockProgram>,
+    },
+    /// Heat tap water right away for a number of hours, until the next plan restores the planned schedule
+    Boost {
+        /// Number of hours to heat tap water for, starting now
+        #[arg(long)]
+        hours: i64,
+    },
+    /// Set a numeric setting of the heatpump, like the heating curve offset, by its navigation path
+    Set {
+        /// Navigation path ending with the item to set, like 'Instelling > Verwarming > Temperatuur +-'
+        #[arg(long)]
+        path: String,
+        /// Value to set the item to
+        #[arg(long, allow_hyphen_values = true)]
+        value: f64,
+        /// Size of the steps the item changes in
+        #[arg(long, default_value_t = 0.5)]
+        step: f64,
+    },
+    /// Replay planning runs hour by hour over a file of spot prices without touching the heatpump, to compare configurations
+    Replay {
+        /// Csv file with the spot prices to replay, in the format of the csv spot price source
+        #[arg(long)]
+        prices: String,
+        /// Config file to plan with
+        #[arg(long)]
+        config: String,
+        /// State file to start from, like the last-state.yaml the planner stores
+        #[arg(long)]
+        state: String,
+    },
+}
+
 #[tokio::main]
 pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt()
@@ -15,23 +91,168 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
-    let spot_prices_state_client_config = SpotPricesStateClientConfig::from_env().await?;
-    let spot_prices_state_client = SpotPricesStateClient::new(spot_prices_state_client_config);
+    let cli = Cli::parse();
+
+    match cli.command {
+        None => plan(false).await,
+        Some(Command::Plan { dry }) => plan(dry).await,
+        Some(Command::Temps) => print_temperatures(),
+        Some(Command::Reset { program }) => reset_schedules(program),
+        Some(Command::Boost { hours }) => boost_tap_water(hours).await,
+        Some(Command::Set { path, value, step }) => set_value(&path, value, step).await,
+        Some(Command::Replay {
+            prices,
+            config,
+            state,
+        }) => replay(prices, config, &state).await,
+    }
+}
+
+async fn plan(dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // the jarvis spot price state is read like any other source, so every source gets retried the same way
+    let spot_price_source: Box<dyn SpotPriceSource> = match spot_price_source::from_env()? {
+        Some(spot_price_source) => spot_price_source,
+        None => Box::new(SpotPricesStateClient::new(
+            SpotPricesStateClientConfig::from_env().await?,
+        )),
+    };
+    let spot_prices_retrier = SpotPricesRetrier::from_env()?;
 
     let config_client_config = ConfigClientConfig::from_env()?;
     let config_client = ConfigClient::new(config_client_config);
 
     let state_client = StateClient::from_env().await?;
-    let websocket_client = WebsocketClient::from_env(Some(state_client))?;
+    let websocket_client = WebsocketClient::from_env(Some(state_client), dry_run)?;
+
+    if let Some(health_server) = HealthServer::from_env(websocket_client.health_state())? {
+        tokio::spawn(async move {
+            if let Err(e) = health_server.run().await {
+                error!("Health server failed: {}", e);
+            }
+        });
+    }
+
+    // a dry run plans once and writes nothing, so only a run that writes has to be able to restore the setpoint
+    let scheduler = Scheduler::from_env()?;
+    if !dry_run {
+        let config: model::Config = config_client.read_config_from_file()?;
+        config.validate_run_interval(
+            scheduler
+                .as_ref()
+                .map(|scheduler| scheduler.run_interval_minutes()),
+        )?;
+    }
+
+    run_planner(dry_run, scheduler, || {
+        spot_price_source::plan_from_source(
+            &config_client,
+            spot_price_source.as_ref(),
+            &spot_prices_retrier,
+            &websocket_client,
+        )
+    })
+    .await
+}
+
+async fn run_planner<F, Fut>(
+    dry_run: bool,
+    scheduler: Option<Scheduler>,
+    mut run: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), Box<dyn std::error::Error>>>,
+{
+    let graceful_shutdown = GracefulShutdown::from_env()?;
+
+    match scheduler {
+        Some(scheduler) if !dry_run => scheduler.run(|| graceful_shutdown.run(run())).await?,
+        _ => graceful_shutdown.run(run()).await?,
+    }
+
+    Ok(())
+}
+
+fn print_temperatures() -> Result<(), Box<dyn std::error::Error>> {
+    let websocket_client = WebsocketClient::from_env(None, false)?;
+
+    let mut temperatures: Vec<(String, f64)> =
+        websocket_client.get_temperatures()?.into_iter().collect();
+    temperatures.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (name, value) in temperatures {
+        println!("{:<24} {:>8.1}", name, value);
+    }
+
+    Ok(())
+}
+
+fn reset_schedules(program: Option<ClockProgram>) -> Result<(), Box<dyn std::error::Error>> {
+    let websocket_client = WebsocketClient::from_env(None, false)?;
+
+    let programs = match program {
+        Some(program) => vec![program],
+        None => ClockProgram::all(),
+    };
+
+    for program in programs {
+        websocket_client.reset_schedule(&program)?;
+    }
+
+    Ok(())
+}
+
+async fn set_value(path: &str, value: f64, step: f64) -> Result<(), Box<dyn std::error::Error>> {
+    let websocket_client = WebsocketClient::from_env(None, false)?;
+
+    websocket_client.set_value(path, value, step).await?;
+
+    Ok(())
+}
+
+async fn boost_tap_water(hours: i64) -> Result<(), Box<dyn std::error::Error>> {
+    let config_client = ConfigClient::new(ConfigClientConfig::from_env()?);
+    let mut config: model::Config = config_client.read_config_from_file()?;
+    config.apply_env_overrides()?;
+
+    let state_client = StateClient::from_env().await?;
+    let websocket_client = WebsocketClient::from_env(Some(state_client), false)?;
+
+    websocket_client.boost_tap_water(&config, hours).await
+}
+
+async fn replay(
+    prices_path: String,
+    config_path: String,
+    state_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config_client = ConfigClient::new(ConfigClientConfig::new(config_path)?);
+    let config: model::Config = config_client.read_config_from_file()?;
+    let spot_price_planner_config: SpotPricePlannerConfig =
+        config_client.read_config_from_file()?;
+
+    let spot_prices = CsvSpotPriceSource::new(prices_path).fetch().await?;
+    let initial_state: model::State = serde_yaml::from_str(&fs::read_to_string(state_path)?)?;
 
-    let planner_service_config = PlannerServiceConfig::new(
-        config_client,
-        spot_prices_state_client,
-        Box::new(websocket_client),
+    let summary = SimulatedHeatPump::for_replay()?.replay(
+        &config,
+        SpotPricePlanner::new(spot_price_planner_config),
+        &spot_prices,
+        &initial_state,
     )?;
-    let planner_service = PlannerService::new(planner_service_config);
 
-    planner_service.run().await?;
+    for decision in summary.decisions.iter() {
+        println!(
+            "{} {:?} {} - {}, {} hours since last desinfection, €{:.2}",
+            decision.decided_at,
+            decision.strategy,
+            decision.from,
+            decision.till,
+            decision.hours_since_last_desinfection,
+            decision.cost_eur
+        );
+    }
+    println!("{}", summary);
 
     Ok(())
 }
@@ -2,11 +2,15 @@ use chrono::prelude::*;
 use chrono_tz::Tz;
 use jarvis_lib::config_client::SetDefaults;
 use jarvis_lib::model::LoadProfile;
+use jarvis_lib::model::LoadProfileSection;
 use jarvis_lib::model::SpotPrice;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::error::Error;
+use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
     pub load_profile: LoadProfile,
@@ -18,9 +22,409 @@ pub struct Config {
     pub max_hours_since_last_desinfection: i64,
     pub jitter_max_minutes: i64,
     pub enable_blocking_worst_heating_times: bool,
+    #[serde(default = "default_force_desinfection_when_overdue")]
+    pub force_desinfection_when_overdue: bool,
+    #[serde(default = "PriceComponent::all")]
+    pub price_components: Vec<PriceComponent>,
+    #[serde(default)]
+    pub price_component_multipliers: HashMap<PriceComponent, f64>,
+    #[serde(default)]
+    pub away_until: Option<DateTime<Utc>>,
+    #[serde(default = "default_schedule_granularity_minutes")]
+    pub schedule_granularity_minutes: i64,
+    #[serde(default)]
+    pub schedule_program: ScheduleProgram,
+    #[serde(default)]
+    pub fail_on_unknown_operating_mode: bool,
+    #[serde(default)]
+    pub minimum_tap_water_temperature: Option<f64>,
+    #[serde(default)]
+    pub notification_webhook_url: Option<String>,
+    #[serde(default)]
+    pub max_spot_price_age_hours: Option<i64>,
+    #[serde(default)]
+    pub max_tap_water_heating_hours: Option<i64>,
+    #[serde(default)]
+    pub max_acceptable_price: Option<f64>,
+    #[serde(default)]
+    pub read_fault_buffer: bool,
+    #[serde(default)]
+    pub abort_on_fault: bool,
+    #[serde(default)]
+    pub conditional_desinfection_load_profiles: Vec<ConditionalLoadProfile>,
+    #[serde(default)]
+    pub boost_tap_water_temperature: Option<f64>,
+    #[serde(default = "default_desinfection_lookahead_hours")]
+    pub desinfection_lookahead_hours: i64,
+    #[serde(default = "default_desinfection_defer_hours")]
+    pub desinfection_defer_hours: i64,
+    #[serde(default)]
+    pub jitter_seed: Option<u64>,
+    #[serde(default = "default_desinfection_cooldown_hours")]
+    pub desinfection_cooldown_hours: i64,
+    #[serde(default)]
+    pub heating_rate_celsius_per_hour: Option<f64>,
+    #[serde(default)]
+    pub read_energy_stats: bool,
+    #[serde(default = "default_enable_tap_water_scheduling")]
+    pub enable_tap_water_scheduling: bool,
+    #[serde(default)]
+    pub desinfection_allowed_weekdays: Vec<Weekday>,
+    #[serde(default)]
+    pub use_fixed_offset_for_schedule: bool,
+    #[serde(default)]
+    pub tap_water_blocking_gap_minutes: i64,
+    #[serde(default)]
+    pub temperature_unit: TemperatureUnit,
+    #[serde(default)]
+    pub max_clock_skew_minutes: Option<i64>,
+    #[serde(default)]
+    pub abort_on_clock_skew: bool,
+    #[serde(default)]
+    pub blocking_strategy: BlockingStrategy,
+    #[serde(default)]
+    pub protected_windows: Vec<TimeSlot>,
+    #[serde(default)]
+    pub enable_adaptive_setpoint: bool,
+    #[serde(default = "default_adaptive_setpoint_hours")]
+    pub adaptive_setpoint_hours: i64,
+    #[serde(default)]
+    pub eco_desinfection_temperature: Option<f64>,
+    #[serde(default)]
+    pub eco_desinfection_load_profile: Option<LoadProfile>,
+    #[serde(default = "default_min_hours_since_last_eco_desinfection")]
+    pub min_hours_since_last_eco_desinfection: i64,
+    #[serde(default)]
+    pub eco_desinfection_max_price: Option<f64>,
+    #[serde(default)]
+    pub read_desinfection_enabled: bool,
+    #[serde(default)]
+    pub enable_cooling_scheduling: bool,
+    #[serde(default = "default_cooling_min_outside_temperature")]
+    pub cooling_min_outside_temperature: f64,
+    #[serde(default)]
+    pub cooling_load_profile: Option<LoadProfile>,
+    #[serde(default)]
+    pub mirror_schedule_to_all_programs: bool,
+    #[serde(default)]
+    pub past_slot_policy: PastSlotPolicy,
+    #[serde(default)]
+    pub enable_price_based_desinfection: bool,
+    #[serde(default = "default_desinfection_hysteresis_margin")]
+    pub desinfection_hysteresis_margin: f64,
+    #[serde(default)]
+    pub spot_price_fetch_retries: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionalLoadProfile {
+    pub when_outside_below: f64,
+    pub profile: LoadProfile,
+}
+
+const MINIMUM_DESIRED_TAP_WATER_CELSIUS: f64 = 30.0;
+const MAXIMUM_DESIRED_TAP_WATER_CELSIUS: f64 = 65.0;
+
+fn default_force_desinfection_when_overdue() -> bool {
+    true
+}
+
+fn default_enable_tap_water_scheduling() -> bool {
+    true
+}
+
+fn default_schedule_granularity_minutes() -> i64 {
+    1
+}
+
+fn default_desinfection_lookahead_hours() -> i64 {
+    24
+}
+
+fn default_desinfection_defer_hours() -> i64 {
+    12
+}
+
+fn default_adaptive_setpoint_hours() -> i64 {
+    12
+}
+
+fn default_min_hours_since_last_eco_desinfection() -> i64 {
+    72
+}
+
+fn default_cooling_min_outside_temperature() -> f64 {
+    22.0
+}
+
+fn default_desinfection_cooldown_hours() -> i64 {
+    24
+}
+
+fn default_desinfection_hysteresis_margin() -> f64 {
+    0.1
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum PriceComponent {
+    MarketPrice,
+    MarketPriceTax,
+    SourcingMarkupPrice,
+    EnergyTaxPrice,
+}
+
+impl PriceComponent {
+    pub fn all() -> Vec<PriceComponent> {
+        vec![
+            PriceComponent::MarketPrice,
+            PriceComponent::MarketPriceTax,
+            PriceComponent::SourcingMarkupPrice,
+            PriceComponent::EnergyTaxPrice,
+        ]
+    }
+
+    pub fn get_price(&self, spot_price: &SpotPrice) -> f64 {
+        match self {
+            PriceComponent::MarketPrice => spot_price.market_price,
+            PriceComponent::MarketPriceTax => spot_price.market_price_tax,
+            PriceComponent::SourcingMarkupPrice => spot_price.sourcing_markup_price,
+            PriceComponent::EnergyTaxPrice => spot_price.energy_tax_price,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduleProgram {
+    #[default]
+    Week,
+    FivePlusTwo,
+    PerDay,
+}
+
+impl ScheduleProgram {
+    pub fn all() -> Vec<ScheduleProgram> {
+        vec![
+            ScheduleProgram::Week,
+            ScheduleProgram::FivePlusTwo,
+            ScheduleProgram::PerDay,
+        ]
+    }
+
+    pub fn navigation_name(&self) -> &'static str {
+        match self {
+            ScheduleProgram::Week => "Week",
+            ScheduleProgram::FivePlusTwo => "5+2",
+            ScheduleProgram::PerDay => "Dagen (Ma, Di,...)",
+        }
+    }
+
+    pub fn number_of_segments(&self) -> usize {
+        match self {
+            ScheduleProgram::Week => 1,
+            ScheduleProgram::FivePlusTwo => 2,
+            ScheduleProgram::PerDay => 7,
+        }
+    }
+
+    // the controller lists the segments of a program starting on monday
+    pub fn get_segment_index(&self, weekday: Weekday) -> usize {
+        match self {
+            ScheduleProgram::Week => 0,
+            ScheduleProgram::FivePlusTwo => match weekday {
+                Weekday::Sat | Weekday::Sun => 1,
+                _ => 0,
+            },
+            ScheduleProgram::PerDay => weekday.num_days_from_monday() as usize,
+        }
+    }
+}
+
+// the controller shows and takes temperatures in the unit it's set to, so the config has to be in the same unit
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    // a single press on the remote control changes the desired temperature by this much
+    pub fn step(&self) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => 0.5,
+            TemperatureUnit::Fahrenheit => 1.0,
+        }
+    }
+
+    pub fn convert_from_celsius(&self, celsius: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn convert_to_celsius(&self, temperature: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => temperature,
+            TemperatureUnit::Fahrenheit => (temperature - 32.0) * 5.0 / 9.0,
+        }
+    }
+}
+
+// which slots to block heating in, the single most expensive hour or every slot above a price or percentile of the day
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum BlockingStrategy {
+    #[default]
+    SingleWorstHour,
+    AbovePrice(f64),
+    AbovePercentile(f64),
+}
+
+// what to do with a slot that jitter or rounding moved to start before now, the controller may start it right away or
+// skip it altogether
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PastSlotPolicy {
+    #[default]
+    ClampToNow,
+    DropPastSlots,
+    Allow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ClockProgram {
+    TapWater,
+    Heating,
+    Cooling,
+}
+
+impl ClockProgram {
+    pub fn all() -> Vec<ClockProgram> {
+        vec![
+            ClockProgram::TapWater,
+            ClockProgram::Heating,
+            ClockProgram::Cooling,
+        ]
+    }
+
+    pub fn menu(&self) -> &'static str {
+        match self {
+            ClockProgram::TapWater => "Klokprogramma > Warmwater",
+            ClockProgram::Heating => "Klokprogramma > Verwarmen",
+            ClockProgram::Cooling => "Klokprogramma > Koelen",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperatingMode {
+    Automatic,
+    SecondHeatSource,
+    Party,
+    Holiday,
+    Off,
+    Unknown(String),
+}
+
+impl OperatingMode {
+    pub fn from_value(value: &str) -> Self {
+        match value.trim() {
+            "Automatisch" => OperatingMode::Automatic,
+            "Zw. WEZ" => OperatingMode::SecondHeatSource,
+            "Party" => OperatingMode::Party,
+            "Vakantie" => OperatingMode::Holiday,
+            "Uit" => OperatingMode::Off,
+            value => OperatingMode::Unknown(value.to_string()),
+        }
+    }
+}
+
+// the planner's navigation sequences were built against the version 3 firmware of the luxtronik 2 controller
+const KNOWN_GOOD_FIRMWARE_MAJOR_VERSIONS: &[u32] = &[3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FirmwareVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FirmwareVersion {
+    pub fn from_value(value: &str) -> Option<Self> {
+        // versions are shown like V3.88.2, sometimes without the patch number
+        let mut parts = value
+            .trim()
+            .trim_start_matches(['V', 'v'])
+            .split('.')
+            .map(|part| part.trim().parse::<u32>());
+
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), None) => Some(Self {
+                major,
+                minor,
+                patch: 0,
+            }),
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch))) => Some(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_known_good(&self) -> bool {
+        KNOWN_GOOD_FIRMWARE_MAJOR_VERSIONS.contains(&self.major)
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "V{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fault {
+    pub timestamp: NaiveDateTime,
+    pub code: u32,
+    pub description: String,
+}
+
+impl Fault {
+    pub fn from_item(name: &str, value: &str) -> Option<Self> {
+        // fault buffer entries look like <name>21.04.22 10:22:13</name><value>717 Flow monitoring</value>, in heatpump local time
+        let timestamp = NaiveDateTime::parse_from_str(name.trim(), "%d.%m.%y %H:%M:%S").ok()?;
+
+        let value = value.trim();
+        let code_length = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        let code = value[..code_length].parse::<u32>().ok()?;
+        let description = value[code_length..]
+            .trim_start_matches([' ', '-', ':'])
+            .to_string();
+
+        Some(Self {
+            timestamp,
+            code,
+            description,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TimeSlot {
     pub from: NaiveTime,
@@ -29,9 +433,409 @@ pub struct TimeSlot {
 }
 
 impl Config {
+    pub fn apply_env_overrides(&mut self) -> Result<(), Box<dyn Error>> {
+        self.apply_load_profile_overrides(
+            env::var("LOAD_PROFILE_JSON").ok(),
+            env::var("DESINFECTION_LOAD_PROFILE_JSON").ok(),
+        )?;
+
+        if let Ok(jitter_seed) = env::var("JITTER_SEED") {
+            self.jitter_seed = Some(
+                jitter_seed
+                    .parse()
+                    .map_err(|e| format!("Failed parsing JITTER_SEED: {}", e))?,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn apply_load_profile_overrides(
+        &mut self,
+        load_profile_json: Option<String>,
+        desinfection_load_profile_json: Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        // load profiles from the environment take precedence over the ones in the config file
+        if let Some(load_profile_json) = load_profile_json {
+            self.load_profile = serde_json::from_str(&load_profile_json)
+                .map_err(|e| format!("Failed parsing LOAD_PROFILE_JSON: {}", e))?;
+        }
+        if let Some(desinfection_load_profile_json) = desinfection_load_profile_json {
+            self.desinfection_load_profile = serde_json::from_str(&desinfection_load_profile_json)
+                .map_err(|e| format!("Failed parsing DESINFECTION_LOAD_PROFILE_JSON: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        // a section without duration or with negative power makes the planner find a degenerate window
+        validate_load_profile("loadProfile", &self.load_profile)?;
+        validate_load_profile("desinfectionLoadProfile", &self.desinfection_load_profile)?;
+        for conditional_load_profile in &self.conditional_desinfection_load_profiles {
+            validate_load_profile(
+                "conditionalDesinfectionLoadProfiles",
+                &conditional_load_profile.profile,
+            )?;
+        }
+        if let Some(eco_desinfection_load_profile) = &self.eco_desinfection_load_profile {
+            validate_load_profile("ecoDesinfectionLoadProfile", eco_desinfection_load_profile)?;
+        }
+        if let Some(cooling_load_profile) = &self.cooling_load_profile {
+            validate_load_profile("coolingLoadProfile", cooling_load_profile)?;
+        }
+
+        // a desinfection window found beyond the lookahead can't be deferred to, so the defer horizon has to fit in it
+        if self.desinfection_defer_hours <= 0
+            || self.desinfection_defer_hours > self.desinfection_lookahead_hours
+        {
+            return Err(Box::<dyn Error>::from(format!(
+                "Config desinfectionDeferHours {} should be larger than 0 and at most desinfectionLookaheadHours {}",
+                self.desinfection_defer_hours, self.desinfection_lookahead_hours
+            )));
+        }
+
+        // a desired temperature outside of this range was most likely written in the other unit
+        let desired_tap_water_celsius = self
+            .temperature_unit
+            .convert_to_celsius(self.desired_tap_water_temperature);
+        if !(MINIMUM_DESIRED_TAP_WATER_CELSIUS..=MAXIMUM_DESIRED_TAP_WATER_CELSIUS)
+            .contains(&desired_tap_water_celsius)
+        {
+            return Err(Box::<dyn Error>::from(format!(
+                "Config desiredTapWaterTemperature {}{} should be between {}{} and {}{}, check temperatureUnit",
+                self.desired_tap_water_temperature,
+                self.temperature_unit.symbol(),
+                self.temperature_unit
+                    .convert_from_celsius(MINIMUM_DESIRED_TAP_WATER_CELSIUS),
+                self.temperature_unit.symbol(),
+                self.temperature_unit
+                    .convert_from_celsius(MAXIMUM_DESIRED_TAP_WATER_CELSIUS),
+                self.temperature_unit.symbol()
+            )));
+        }
+
+        if self.tap_water_blocking_gap_minutes < 0 {
+            return Err(Box::<dyn Error>::from(format!(
+                "Config tapWaterBlockingGapMinutes {} should not be negative",
+                self.tap_water_blocking_gap_minutes
+            )));
+        }
+
+        if let Some(max_clock_skew_minutes) = self.max_clock_skew_minutes {
+            if max_clock_skew_minutes <= 0 {
+                return Err(Box::<dyn Error>::from(format!(
+                    "Config maxClockSkewMinutes {} should be larger than 0",
+                    max_clock_skew_minutes
+                )));
+            }
+        }
+
+        // an eco heat-up only helps if it heats above the normal setpoint, but it isn't a full desinfection cycle
+        if let Some(eco_desinfection_temperature) = self.eco_desinfection_temperature {
+            let eco_desinfection_celsius = self
+                .temperature_unit
+                .convert_to_celsius(eco_desinfection_temperature);
+            if eco_desinfection_celsius <= desired_tap_water_celsius
+                || eco_desinfection_celsius > MAXIMUM_DESIRED_TAP_WATER_CELSIUS
+            {
+                return Err(Box::<dyn Error>::from(format!(
+                    "Config ecoDesinfectionTemperature {}{} should be above desiredTapWaterTemperature {}{} and at most {}{}",
+                    eco_desinfection_temperature,
+                    self.temperature_unit.symbol(),
+                    self.desired_tap_water_temperature,
+                    self.temperature_unit.symbol(),
+                    self.temperature_unit
+                        .convert_from_celsius(MAXIMUM_DESIRED_TAP_WATER_CELSIUS),
+                    self.temperature_unit.symbol()
+                )));
+            }
+
+            if self.min_hours_since_last_eco_desinfection <= 0 {
+                return Err(Box::<dyn Error>::from(format!(
+                    "Config minHoursSinceLastEcoDesinfection {} should be larger than 0",
+                    self.min_hours_since_last_eco_desinfection
+                )));
+            }
+        }
+
+        // lowering the setpoint needs a floor to lower it to
+        if self.enable_adaptive_setpoint && self.minimum_tap_water_temperature.is_none() {
+            return Err(Box::<dyn Error>::from(
+                "Config enableAdaptiveSetpoint needs minimumTapWaterTemperature to lower the setpoint to",
+            ));
+        }
+
+        if let BlockingStrategy::AbovePercentile(percentile) = self.blocking_strategy {
+            if !(0.0..=100.0).contains(&percentile) {
+                return Err(Box::<dyn Error>::from(format!(
+                    "Config blockingStrategy AbovePercentile {} should be between 0 and 100",
+                    percentile
+                )));
+            }
+        }
+
+        // the price threshold grows from nothing at the minimum to the highest price at the maximum hours
+        if self.enable_price_based_desinfection
+            && self.max_hours_since_last_desinfection <= self.min_hours_since_last_desinfection
+        {
+            return Err(Box::<dyn Error>::from(format!(
+                "Config maxHoursSinceLastDesinfection {} should be larger than minHoursSinceLastDesinfection {} for price based desinfection",
+                self.max_hours_since_last_desinfection, self.min_hours_since_last_desinfection
+            )));
+        }
+
+        if !(0.0..1.0).contains(&self.desinfection_hysteresis_margin) {
+            return Err(Box::<dyn Error>::from(format!(
+                "Config desinfectionHysteresisMargin {} should be at least 0 and less than 1",
+                self.desinfection_hysteresis_margin
+            )));
+        }
+
+        if let Some(heating_rate_celsius_per_hour) = self.heating_rate_celsius_per_hour {
+            if heating_rate_celsius_per_hour <= 0.0 {
+                return Err(Box::<dyn Error>::from(format!(
+                    "Config heatingRateCelsiusPerHour {} should be larger than 0",
+                    heating_rate_celsius_per_hour
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_run_interval(
+        &self,
+        run_interval_minutes: Option<u64>,
+    ) -> Result<(), Box<dyn Error>> {
+        // a lowered setpoint is only restored by the first run once its window has started, without that the window
+        // heats to the floor as well
+        if !self.enable_adaptive_setpoint {
+            return Ok(());
+        }
+
+        match run_interval_minutes {
+            Some(run_interval_minutes)
+                if (run_interval_minutes as i64) < self.adaptive_setpoint_hours * 60 =>
+            {
+                Ok(())
+            }
+            Some(run_interval_minutes) => Err(Box::<dyn Error>::from(format!(
+                "Config enableAdaptiveSetpoint needs RUN_INTERVAL_MINUTES {} to be shorter than adaptiveSetpointHours {} to restore the setpoint",
+                run_interval_minutes, self.adaptive_setpoint_hours
+            ))),
+            None => Err(Box::<dyn Error>::from(
+                "Config enableAdaptiveSetpoint needs RUN_INTERVAL_MINUTES to run the planner often enough to restore the setpoint",
+            )),
+        }
+    }
+
+    pub fn get_load_profile_for_temperature_gap(
+        &self,
+        heating_rate_celsius_per_hour: f64,
+        measured_tap_water_temperature: f64,
+        desired_tap_water_temperature: f64,
+    ) -> LoadProfile {
+        // heat for as long as it takes to close the gap, but never shorter than a single slot of the clock program
+        let temperature_gap = (self
+            .temperature_unit
+            .convert_to_celsius(desired_tap_water_temperature)
+            - self
+                .temperature_unit
+                .convert_to_celsius(measured_tap_water_temperature))
+        .max(0.0);
+        let granularity_seconds = self.schedule_granularity_minutes * 60;
+        let needed_seconds =
+            (temperature_gap / heating_rate_celsius_per_hour * 3600.0).ceil() as i64;
+        let duration_seconds = ((needed_seconds + granularity_seconds - 1) / granularity_seconds)
+            .max(1)
+            * granularity_seconds;
+
+        // the static profile still tells what the heatpump draws while heating tap water
+        let static_duration_seconds: i64 = self
+            .load_profile
+            .sections
+            .iter()
+            .map(|section| section.duration_seconds)
+            .sum();
+        let power_draw_watt = if static_duration_seconds > 0 {
+            self.load_profile
+                .sections
+                .iter()
+                .map(|section| section.power_draw_watt * section.duration_seconds as f64)
+                .sum::<f64>()
+                / static_duration_seconds as f64
+        } else {
+            0.0
+        };
+
+        LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds,
+                power_draw_watt,
+            }],
+        }
+    }
+
+    pub fn get_desinfection_load_profile(&self, outside_temperature: Option<f64>) -> &LoadProfile {
+        // the coldest matching condition is the most specific one
+        outside_temperature
+            .and_then(|outside_temperature| {
+                self.conditional_desinfection_load_profiles
+                    .iter()
+                    .filter(|conditional| outside_temperature < conditional.when_outside_below)
+                    .min_by(|a, b| a.when_outside_below.total_cmp(&b.when_outside_below))
+            })
+            .map(|conditional| &conditional.profile)
+            .unwrap_or(&self.desinfection_load_profile)
+    }
+
+    pub fn get_eco_desinfection_load_profile(&self) -> &LoadProfile {
+        // without a profile of its own an eco heat-up takes as long as regular tap water heating
+        self.eco_desinfection_load_profile
+            .as_ref()
+            .unwrap_or(&self.load_profile)
+    }
+
+    pub fn get_cooling_load_profile(&self) -> &LoadProfile {
+        self.cooling_load_profile
+            .as_ref()
+            .unwrap_or(&self.load_profile)
+    }
+
+    pub fn is_cooling_season(&self, average_outside_temperature: Option<f64>) -> bool {
+        // without an outside temperature it's unknown whether it's summer
+        self.enable_cooling_scheduling
+            && average_outside_temperature.is_some_and(|average_outside_temperature| {
+                average_outside_temperature > self.cooling_min_outside_temperature
+            })
+    }
+
+    pub fn get_local_time_zone(&self) -> Result<Tz, Box<dyn Error>> {
+        Ok(self.local_time_zone.parse::<Tz>()?)
+    }
+
     pub fn get_heatpump_time_zone(&self) -> Result<Tz, Box<dyn Error>> {
         Ok(self.heatpump_time_zone.parse::<Tz>()?)
     }
+
+    pub fn get_effective_price_component(
+        &self,
+        price_component: PriceComponent,
+        spot_price: &SpotPrice,
+    ) -> f64 {
+        if !self.price_components.contains(&price_component) {
+            return 0.0;
+        }
+
+        // components without a configured multiplier count fully, to allow for regional vat differences
+        let multiplier = self
+            .price_component_multipliers
+            .get(&price_component)
+            .copied()
+            .unwrap_or(1.0);
+
+        price_component.get_price(spot_price) * multiplier
+    }
+
+    pub fn effective_price(&self, spot_price: &SpotPrice) -> f64 {
+        PriceComponent::all()
+            .into_iter()
+            .map(|price_component| self.get_effective_price_component(price_component, spot_price))
+            .sum()
+    }
+}
+
+// a config with every field set, tests override only the fields they're about
+#[cfg(test)]
+pub(crate) fn test_config() -> Config {
+    Config {
+        local_time_zone: "Europe/Amsterdam".to_string(),
+        heatpump_time_zone: "UTC".to_string(),
+        desired_tap_water_temperature: 50.0,
+        min_hours_since_last_desinfection: 96,
+        max_hours_since_last_desinfection: 240,
+        load_profile: LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 2000.0,
+            }],
+        },
+        desinfection_load_profile: LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 9000,
+                power_draw_watt: 2000.0,
+            }],
+        },
+        jitter_max_minutes: 0,
+        enable_blocking_worst_heating_times: true,
+        force_desinfection_when_overdue: true,
+        price_components: PriceComponent::all(),
+        price_component_multipliers: HashMap::new(),
+        away_until: None,
+        schedule_granularity_minutes: 1,
+        schedule_program: ScheduleProgram::Week,
+        fail_on_unknown_operating_mode: false,
+        minimum_tap_water_temperature: None,
+        notification_webhook_url: None,
+        max_spot_price_age_hours: None,
+        max_tap_water_heating_hours: None,
+        max_acceptable_price: None,
+        read_fault_buffer: false,
+        abort_on_fault: false,
+        conditional_desinfection_load_profiles: vec![],
+        boost_tap_water_temperature: None,
+        desinfection_lookahead_hours: 24,
+        desinfection_defer_hours: 12,
+        jitter_seed: None,
+        desinfection_cooldown_hours: 24,
+        heating_rate_celsius_per_hour: None,
+        read_energy_stats: false,
+        enable_tap_water_scheduling: true,
+        desinfection_allowed_weekdays: vec![],
+        use_fixed_offset_for_schedule: false,
+        tap_water_blocking_gap_minutes: 0,
+        temperature_unit: TemperatureUnit::Celsius,
+        max_clock_skew_minutes: None,
+        abort_on_clock_skew: false,
+        blocking_strategy: BlockingStrategy::SingleWorstHour,
+        protected_windows: vec![],
+        enable_adaptive_setpoint: false,
+        adaptive_setpoint_hours: 12,
+        eco_desinfection_temperature: None,
+        eco_desinfection_load_profile: None,
+        min_hours_since_last_eco_desinfection: 72,
+        eco_desinfection_max_price: None,
+        read_desinfection_enabled: false,
+        enable_cooling_scheduling: false,
+        cooling_min_outside_temperature: 22.0,
+        cooling_load_profile: None,
+        mirror_schedule_to_all_programs: false,
+        past_slot_policy: PastSlotPolicy::ClampToNow,
+        enable_price_based_desinfection: false,
+        desinfection_hysteresis_margin: 0.1,
+        spot_price_fetch_retries: None,
+    }
+}
+
+fn validate_load_profile(name: &str, load_profile: &LoadProfile) -> Result<(), Box<dyn Error>> {
+    for (index, section) in load_profile.sections.iter().enumerate() {
+        if section.duration_seconds <= 0 {
+            return Err(Box::<dyn Error>::from(format!(
+                "Config {} section {} durationSeconds {} should be larger than 0",
+                name, index, section.duration_seconds
+            )));
+        }
+
+        if section.power_draw_watt < 0.0 {
+            return Err(Box::<dyn Error>::from(format!(
+                "Config {} section {} powerDrawWatt {} should not be negative",
+                name, index, section.power_draw_watt
+            )));
+        }
+    }
+
+    Ok(())
 }
 
 impl SetDefaults for Config {
@@ -41,7 +845,8 @@ impl SetDefaults for Config {
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename = "Content")]
 pub struct Content {
-    pub item: ContentItem,
+    #[serde(rename = "item", default)]
+    pub segments: Vec<ContentItem>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -62,18 +867,346 @@ pub struct Item {
     pub raw: String,
 }
 
+impl Item {
+    pub fn parse_time_range(&self) -> Result<Option<(NaiveTime, NaiveTime)>, Box<dyn Error>> {
+        // timer values look like 10:00 - 00:00, where 00:00 - 00:00 means the timer is disabled
+        let (from, till) = match self.value.split_once(" - ") {
+            Some((from, till)) => (from.trim(), till.trim()),
+            None => {
+                return Err(Box::<dyn Error>::from(format!(
+                    "Value {} of item {} is not a time range",
+                    self.value, self.name
+                )))
+            }
+        };
+
+        let from = NaiveTime::parse_from_str(from, "%H:%M")?;
+        let till = NaiveTime::parse_from_str(till, "%H:%M")?;
+
+        if from == NaiveTime::MIN && till == NaiveTime::MIN {
+            return Ok(None);
+        }
+
+        Ok(Some((from, till)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    pub from: NaiveTime,
+    pub till: NaiveTime,
+}
+
+impl TimeRange {
+    pub fn new(from: NaiveTime, till: NaiveTime) -> Self {
+        Self { from, till }
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.from == NaiveTime::MIN && self.till == NaiveTime::MIN
+    }
+
+    pub fn from_raw(raw: u32) -> Result<Self, Box<dyn Error>> {
+        // the controller packs a timer as minutes since midnight, from in the low and till in the high 16 bits
+        let to_time = |minutes: u32| {
+            NaiveTime::from_num_seconds_from_midnight_opt(minutes * 60, 0).ok_or_else(|| {
+                Box::<dyn Error>::from(format!("Raw timer value {} is out of range", raw))
+            })
+        };
+
+        Ok(Self::new(to_time(raw % 65536)?, to_time(raw / 65536)?))
+    }
+
+    pub fn to_raw(self) -> u32 {
+        60 * self.from.hour()
+            + self.from.minute()
+            + 65536 * (60 * self.till.hour() + self.till.minute())
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} - {}",
+            self.from.format("%H:%M"),
+            self.till.format("%H:%M")
+        )
+    }
+}
+
+// the timer items of a single segment of a clock program, in the order the controller lists them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeeklySchedule {
+    pub item_ids: Vec<String>,
+    pub slots: Vec<TimeRange>,
+}
+
+impl WeeklySchedule {
+    pub fn from_content(content: &ContentItem) -> Result<Self, Box<dyn Error>> {
+        let slots = content
+            .item
+            .iter()
+            .map(|item| {
+                let raw = item.raw.parse::<u32>().map_err(|e| {
+                    format!(
+                        "Raw value {} of item {} is not a timer: {}",
+                        item.raw, item.name, e
+                    )
+                })?;
+                TimeRange::from_raw(raw)
+            })
+            .collect::<Result<Vec<TimeRange>, Box<dyn Error>>>()?;
+
+        Ok(Self {
+            item_ids: content.item.iter().map(|item| item.id.clone()).collect(),
+            slots,
+        })
+    }
+
+    pub fn from_raw_values(
+        content: &ContentItem,
+        raw_values: &[u32],
+    ) -> Result<Self, Box<dyn Error>> {
+        if content.item.len() != raw_values.len() {
+            return Err(Box::<dyn Error>::from(format!(
+                "Segment {} has {} items, got {} raw values",
+                content.name,
+                content.item.len(),
+                raw_values.len()
+            )));
+        }
+
+        Ok(Self {
+            item_ids: content.item.iter().map(|item| item.id.clone()).collect(),
+            slots: raw_values
+                .iter()
+                .map(|raw_value| TimeRange::from_raw(*raw_value))
+                .collect::<Result<Vec<TimeRange>, Box<dyn Error>>>()?,
+        })
+    }
+
+    pub fn raw_values(&self) -> Vec<u32> {
+        self.slots.iter().map(|slot| slot.to_raw()).collect()
+    }
+
+    pub fn to_set_commands(&self) -> Vec<String> {
+        // every item is cleared first, otherwise a block that moves to another item would be programmed twice
+        let reset_commands = self.item_ids.iter().map(|id| format!("SET;set_{};0", id));
+        let set_commands = self
+            .item_ids
+            .iter()
+            .zip(self.slots.iter())
+            .filter(|(_, slot)| !slot.is_disabled())
+            .map(|(id, slot)| format!("SET;set_{};{}", id, slot.to_raw()));
+
+        reset_commands.chain(set_commands).collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename = "Content")]
+pub struct ValuesContent {
+    #[serde(rename = "item", default)]
+    pub items: Vec<ValueItem>,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename = "item")]
+pub struct ValueItem {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ItemValue {
     #[serde(rename = "$value")]
     pub value: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct State {
     pub desinfection_enabled: bool,
     pub desinfection_finished_at: Option<DateTime<Utc>>,
     pub planned_spot_prices: Option<Vec<SpotPrice>>,
+    #[serde(default)]
+    pub planning_history: Vec<PlanRecord>,
+    #[serde(default)]
+    pub planned_cost_eur: Option<f64>,
+    #[serde(default)]
+    pub away_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub written_schedule: Option<Vec<(String, i64)>>,
+    #[serde(default)]
+    pub energy_stats_history: Vec<EnergyStats>,
+    #[serde(default)]
+    pub blocked_spot_prices: Option<Vec<SpotPrice>>,
+    #[serde(default)]
+    pub desinfection_decisions: Vec<DesinfectionDecision>,
+    #[serde(default)]
+    pub tap_water_setpoint: Option<f64>,
+    #[serde(default)]
+    pub do_not_disturb_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub eco_desinfection_finished_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub cooling_spot_prices: Option<Vec<SpotPrice>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnergyStats {
+    pub read_at: DateTime<Utc>,
+    pub counters_kwh: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanRecord {
+    pub planned_at: DateTime<Utc>,
+    pub strategy: TapWaterStrategy,
+    pub desinfection_desired: bool,
+    pub planned_from: Option<DateTime<Utc>>,
+    pub planned_till: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum TapWaterStrategy {
+    TapWaterHeating,
+    Desinfection,
+    EcoDesinfection,
+}
+
+// a full desinfection runs the legionella cycle of the controller, an eco one only raises the tap water setpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DesinfectionMode {
+    None,
+    Eco,
+    Full,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "reason", rename_all_fields = "camelCase")]
+pub enum DesinfectionDecision {
+    Overdue {
+        hours_since_last_desinfection: i64,
+        max_hours_since_last_desinfection: i64,
+    },
+    Deferred {
+        defer_hours: i64,
+    },
+    WithinCooldown {
+        hours_since_last_desinfection: i64,
+        cooldown_hours: i64,
+    },
+    Weekday {
+        weekday: Weekday,
+        desired_weekday: Weekday,
+    },
+    EcoHeatUp {
+        hours_since_last_heat_up: i64,
+        min_hours_since_last_heat_up: i64,
+    },
+    PriceThreshold {
+        hours_since_last_desinfection: i64,
+        lowest_price_per_hour: f64,
+        threshold_price_per_hour: f64,
+    },
+}
+
+impl fmt::Display for DesinfectionDecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DesinfectionDecision::Overdue {
+                hours_since_last_desinfection,
+                max_hours_since_last_desinfection,
+            } => write!(
+                f,
+                "desinfection is overdue ({} > {} hours), forced in the cheapest available window",
+                hours_since_last_desinfection, max_hours_since_last_desinfection
+            ),
+            DesinfectionDecision::Deferred { defer_hours } => write!(
+                f,
+                "cheapest desinfection window starts more than {} hours ahead, deferred to a next run",
+                defer_hours
+            ),
+            DesinfectionDecision::WithinCooldown {
+                hours_since_last_desinfection,
+                cooldown_hours,
+            } => write!(
+                f,
+                "hours since last desinfection are within cooldown ({} < {}), desinfection is not desired",
+                hours_since_last_desinfection, cooldown_hours
+            ),
+            DesinfectionDecision::Weekday {
+                weekday,
+                desired_weekday,
+            } => write!(
+                f,
+                "desinfection is desired on {}, today is {}",
+                desired_weekday, weekday
+            ),
+            DesinfectionDecision::EcoHeatUp {
+                hours_since_last_heat_up,
+                min_hours_since_last_heat_up,
+            } => write!(
+                f,
+                "full desinfection is not desired, eco desinfection heats up after {} hours since the last heat-up (at least {})",
+                hours_since_last_heat_up, min_hours_since_last_heat_up
+            ),
+            DesinfectionDecision::PriceThreshold {
+                hours_since_last_desinfection,
+                lowest_price_per_hour,
+                threshold_price_per_hour,
+            } => write!(
+                f,
+                "cheapest desinfection window averages {:.4} per hour against a threshold of {:.4} per hour {} hours since the last desinfection, desinfection is {}",
+                lowest_price_per_hour,
+                threshold_price_per_hour,
+                hours_since_last_desinfection,
+                if lowest_price_per_hour < threshold_price_per_hour {
+                    "desired"
+                } else {
+                    "not desired"
+                }
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum PlanAction {
+    HeatTapWater,
+    Desinfection,
+    EcoDesinfection,
+    BlockHeating,
+    Idle,
+}
+
+// what a planning run decided and wrote, so callers can act on it without scraping the logs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanningOutcome {
+    pub strategy: TapWaterStrategy,
+    pub best_from: Option<DateTime<Utc>>,
+    pub best_till: Option<DateTime<Utc>>,
+    pub total_price: Option<f64>,
+    pub planned_cost_eur: Option<f64>,
+    pub away_mode_active: bool,
+    pub desinfection_desired: bool,
+    pub hours_since_last_desinfection: i64,
+    pub tap_water_schedule_written: bool,
+    pub desinfection_toggled: bool,
+    pub blocked_from: Option<DateTime<Utc>>,
+    pub blocked_till: Option<DateTime<Utc>>,
+    pub heating_schedule_written: bool,
+    pub cooling_schedule_written: bool,
 }
 
 #[cfg(test)]
@@ -124,13 +1257,88 @@ mod tests {
         // act
         let content: Content = from_str(response).unwrap();
 
-        assert_eq!(content.item.name, "Maandag - Zondag");
-        assert_eq!(content.item.item.len(), 5);
-        assert_eq!(content.item.item[0].id, "0xa57344".to_string());
-        assert_eq!(content.item.item[0].value, "10:00 - 00:00".to_string());
-        assert_eq!(content.item.item[0].name, "1)".to_string());
-        assert_eq!(content.item.item[0].r#type, "timer".to_string());
-        assert_eq!(content.item.item[0].raw, "600".to_string());
+        assert_eq!(content.segments[0].name, "Maandag - Zondag");
+        assert_eq!(content.segments[0].item.len(), 5);
+        assert_eq!(content.segments[0].item[0].id, "0xa57344".to_string());
+        assert_eq!(
+            content.segments[0].item[0].value,
+            "10:00 - 00:00".to_string()
+        );
+        assert_eq!(content.segments[0].item[0].name, "1)".to_string());
+        assert_eq!(content.segments[0].item[0].r#type, "timer".to_string());
+        assert_eq!(content.segments[0].item[0].raw, "600".to_string());
+    }
+
+    #[test]
+    fn deserialize_state_without_planning_history() {
+        let state_yaml = r#"desinfectionEnabled: false
+desinfectionFinishedAt: 2022-04-21T15:00:00Z
+plannedSpotPrices: []
+"#;
+
+        // act
+        let state: State = serde_yaml::from_str(state_yaml).unwrap();
+
+        assert!(!state.desinfection_enabled);
+        assert_eq!(state.planning_history.len(), 0);
+        assert_eq!(state.energy_stats_history.len(), 0);
+        assert_eq!(state.planned_cost_eur, None);
+        assert_eq!(state.away_until, None);
+        assert_eq!(state.written_schedule, None);
+        assert_eq!(state.desinfection_decisions, vec![]);
+        assert_eq!(state.tap_water_setpoint, None);
+        assert_eq!(state.do_not_disturb_until, None);
+        assert_eq!(state.eco_desinfection_finished_at, None);
+        assert!(state.cooling_spot_prices.is_none());
+    }
+
+    #[test]
+    fn deserialize_state_with_do_not_disturb_until_written_by_other_tool() {
+        let state_yaml = r#"desinfectionEnabled: false
+desinfectionFinishedAt: 2022-04-21T15:00:00Z
+plannedSpotPrices: []
+doNotDisturbUntil: 2022-04-22T08:00:00Z
+"#;
+
+        // act
+        let state: State = serde_yaml::from_str(state_yaml).unwrap();
+
+        assert_eq!(
+            state.do_not_disturb_until,
+            Some(Utc.with_ymd_and_hms(2022, 4, 22, 8, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn deserialize_state_with_desinfection_decisions() {
+        let state_yaml = r#"desinfectionEnabled: false
+desinfectionFinishedAt: 2022-04-21T15:00:00Z
+plannedSpotPrices: []
+desinfectionDecisions:
+- reason: WithinCooldown
+  hoursSinceLastDesinfection: 12
+  cooldownHours: 24
+- reason: Weekday
+  weekday: Sat
+  desiredWeekday: Sun
+"#;
+
+        // act
+        let state: State = serde_yaml::from_str(state_yaml).unwrap();
+
+        assert_eq!(
+            state.desinfection_decisions,
+            vec![
+                DesinfectionDecision::WithinCooldown {
+                    hours_since_last_desinfection: 12,
+                    cooldown_hours: 24,
+                },
+                DesinfectionDecision::Weekday {
+                    weekday: Weekday::Sat,
+                    desired_weekday: Weekday::Sun,
+                },
+            ]
+        );
     }
 
     #[test]
@@ -145,5 +1353,767 @@ mod tests {
         assert_eq!(config.desired_tap_water_temperature, 50.0);
         assert_eq!(config.min_hours_since_last_desinfection, 96);
         assert_eq!(config.max_hours_since_last_desinfection, 240);
+        assert!(config.force_desinfection_when_overdue);
+        assert_eq!(config.price_components, PriceComponent::all());
+        assert_eq!(config.price_component_multipliers.len(), 0);
+        assert_eq!(config.away_until, None);
+        assert_eq!(config.schedule_granularity_minutes, 1);
+        assert_eq!(config.schedule_program, ScheduleProgram::Week);
+        assert!(!config.fail_on_unknown_operating_mode);
+        assert_eq!(config.minimum_tap_water_temperature, None);
+        assert_eq!(config.notification_webhook_url, None);
+        assert_eq!(config.max_spot_price_age_hours, None);
+        assert_eq!(config.max_tap_water_heating_hours, None);
+        assert_eq!(config.max_acceptable_price, None);
+        assert!(!config.read_fault_buffer);
+        assert!(!config.abort_on_fault);
+        assert_eq!(config.conditional_desinfection_load_profiles.len(), 0);
+        assert_eq!(config.boost_tap_water_temperature, None);
+        assert_eq!(config.desinfection_lookahead_hours, 24);
+        assert_eq!(config.desinfection_defer_hours, 12);
+        assert_eq!(config.jitter_seed, None);
+        assert_eq!(config.desinfection_cooldown_hours, 24);
+        assert_eq!(config.heating_rate_celsius_per_hour, None);
+        assert!(!config.read_energy_stats);
+        assert!(config.enable_tap_water_scheduling);
+        assert_eq!(config.desinfection_allowed_weekdays, vec![]);
+        assert!(!config.use_fixed_offset_for_schedule);
+        assert_eq!(config.tap_water_blocking_gap_minutes, 0);
+        assert_eq!(config.temperature_unit, TemperatureUnit::Celsius);
+        assert_eq!(config.max_clock_skew_minutes, None);
+        assert!(!config.abort_on_clock_skew);
+        assert_eq!(config.blocking_strategy, BlockingStrategy::SingleWorstHour);
+        assert_eq!(config.protected_windows, vec![]);
+        assert!(!config.enable_adaptive_setpoint);
+        assert_eq!(config.adaptive_setpoint_hours, 12);
+        assert_eq!(config.eco_desinfection_temperature, None);
+        assert!(config.eco_desinfection_load_profile.is_none());
+        assert_eq!(config.min_hours_since_last_eco_desinfection, 72);
+        assert_eq!(config.eco_desinfection_max_price, None);
+        assert!(!config.read_desinfection_enabled);
+        assert!(!config.enable_cooling_scheduling);
+        assert_eq!(config.cooling_min_outside_temperature, 22.0);
+        assert!(config.cooling_load_profile.is_none());
+        assert!(!config.mirror_schedule_to_all_programs);
+        assert_eq!(config.past_slot_policy, PastSlotPolicy::ClampToNow);
+        assert!(!config.enable_price_based_desinfection);
+        assert_eq!(config.desinfection_hysteresis_margin, 0.1);
+        assert_eq!(config.spot_price_fetch_retries, None);
+        assert!(config.validate().is_ok());
+    }
+
+    fn load_profile(duration_seconds: i64) -> LoadProfile {
+        LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds,
+                power_draw_watt: 2000.0,
+            }],
+        }
+    }
+
+    fn conditional_config() -> Config {
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new("test-config.yaml".to_string()).unwrap());
+        let mut config: Config = config_client.read_config_from_file().unwrap();
+        config.desinfection_load_profile = load_profile(3600);
+        config.conditional_desinfection_load_profiles = vec![
+            ConditionalLoadProfile {
+                when_outside_below: 10.0,
+                profile: load_profile(7200),
+            },
+            ConditionalLoadProfile {
+                when_outside_below: 0.0,
+                profile: load_profile(10800),
+            },
+        ];
+
+        config
+    }
+
+    #[test]
+    fn apply_load_profile_overrides_replaces_load_profiles_from_file() {
+        let mut config = conditional_config();
+
+        // act
+        config
+            .apply_load_profile_overrides(
+                Some(r#"{"sections":[{"durationSeconds":5400,"powerDrawWatt":1500}]}"#.to_string()),
+                Some(r#"{"sections":[{"durationSeconds":9000,"powerDrawWatt":3000}]}"#.to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(config.load_profile.sections[0].duration_seconds, 5400);
+        assert_eq!(config.load_profile.sections[0].power_draw_watt, 1500.0);
+        assert_eq!(
+            config.desinfection_load_profile.sections[0].duration_seconds,
+            9000
+        );
+    }
+
+    #[test]
+    fn apply_load_profile_overrides_keeps_load_profiles_from_file_without_overrides() {
+        let mut config = conditional_config();
+
+        // act
+        config.apply_load_profile_overrides(None, None).unwrap();
+
+        assert_eq!(config.load_profile.sections[0].duration_seconds, 7200);
+        assert_eq!(
+            config.desinfection_load_profile.sections[0].duration_seconds,
+            3600
+        );
+    }
+
+    #[test]
+    fn apply_load_profile_overrides_returns_error_for_invalid_json() {
+        let mut config = conditional_config();
+
+        // act
+        let result =
+            config.apply_load_profile_overrides(Some("{\"sections\": [".to_string()), None);
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Failed parsing LOAD_PROFILE_JSON"));
+    }
+
+    #[test]
+    fn validate_run_interval_returns_error_if_adaptive_setpoint_is_enabled_without_scheduler() {
+        let mut config = conditional_config();
+        config.enable_adaptive_setpoint = true;
+
+        // act
+        let result = config.validate_run_interval(None);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config enableAdaptiveSetpoint needs RUN_INTERVAL_MINUTES to run the planner often enough to restore the setpoint"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn validate_run_interval_returns_error_if_interval_is_not_shorter_than_adaptive_setpoint_hours()
+    {
+        let mut config = conditional_config();
+        config.enable_adaptive_setpoint = true;
+        config.adaptive_setpoint_hours = 12;
+
+        // act
+        let results = (
+            config.validate_run_interval(Some(719)),
+            config.validate_run_interval(Some(720)),
+        );
+
+        assert!(results.0.is_ok());
+        assert_eq!(
+            results.1.unwrap_err().to_string(),
+            "Config enableAdaptiveSetpoint needs RUN_INTERVAL_MINUTES 720 to be shorter than adaptiveSetpointHours 12 to restore the setpoint"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn validate_run_interval_accepts_any_interval_without_adaptive_setpoint() {
+        let config = conditional_config();
+
+        // act
+        let result = config.validate_run_interval(None);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn validate_returns_error_if_adaptive_setpoint_is_enabled_without_minimum_temperature() {
+        let mut config = conditional_config();
+        config.enable_adaptive_setpoint = true;
+        config.minimum_tap_water_temperature = None;
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config enableAdaptiveSetpoint needs minimumTapWaterTemperature to lower the setpoint to"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn validate_returns_error_if_blocking_percentile_is_above_100() {
+        let mut config = conditional_config();
+        config.blocking_strategy = BlockingStrategy::AbovePercentile(150.0);
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config blockingStrategy AbovePercentile 150 should be between 0 and 100".to_string()
+        );
+    }
+
+    #[test]
+    fn deserialize_blocking_strategy_with_price() {
+        // act
+        let blocking_strategy: BlockingStrategy = serde_yaml::from_str("!AbovePrice 0.35").unwrap();
+
+        assert_eq!(blocking_strategy, BlockingStrategy::AbovePrice(0.35));
+    }
+
+    #[test]
+    fn validate_returns_error_if_max_clock_skew_minutes_is_not_positive() {
+        let mut config = conditional_config();
+        config.max_clock_skew_minutes = Some(0);
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config maxClockSkewMinutes 0 should be larger than 0".to_string()
+        );
+    }
+
+    #[test]
+    fn validate_accepts_desired_tap_water_temperature_in_fahrenheit() {
+        let mut config = conditional_config();
+        config.temperature_unit = TemperatureUnit::Fahrenheit;
+        config.desired_tap_water_temperature = 122.0;
+
+        // act
+        let result = config.validate();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn validate_returns_error_if_desired_tap_water_temperature_is_in_other_unit() {
+        let mut config = conditional_config();
+        config.temperature_unit = TemperatureUnit::Fahrenheit;
+        config.desired_tap_water_temperature = 50.0;
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config desiredTapWaterTemperature 50°F should be between 86°F and 149°F, check temperatureUnit"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn validate_accepts_eco_desinfection_temperature_between_desired_and_maximum() {
+        let mut config = conditional_config();
+        config.eco_desinfection_temperature = Some(config.desired_tap_water_temperature + 5.0);
+
+        // act
+        let result = config.validate();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn validate_returns_error_if_eco_desinfection_temperature_is_not_above_desired() {
+        let mut config = conditional_config();
+        config.eco_desinfection_temperature = Some(config.desired_tap_water_temperature);
+
+        // act
+        let result = config.validate();
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Config ecoDesinfectionTemperature"));
+    }
+
+    #[test]
+    fn validate_returns_error_if_eco_desinfection_temperature_is_above_maximum() {
+        let mut config = conditional_config();
+        config.eco_desinfection_temperature = Some(70.0);
+
+        // act
+        let result = config.validate();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validate_returns_error_if_min_hours_since_last_eco_desinfection_is_not_positive() {
+        let mut config = conditional_config();
+        config.eco_desinfection_temperature = Some(config.desired_tap_water_temperature + 5.0);
+        config.min_hours_since_last_eco_desinfection = 0;
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config minHoursSinceLastEcoDesinfection 0 should be larger than 0".to_string()
+        );
+    }
+
+    #[test]
+    fn get_eco_desinfection_load_profile_falls_back_to_load_profile() {
+        let mut config = conditional_config();
+        config.load_profile = load_profile(5400);
+
+        // act
+        let load_profile = config.get_eco_desinfection_load_profile();
+
+        assert_eq!(load_profile.sections[0].duration_seconds, 5400);
+    }
+
+    #[test]
+    fn get_load_profile_for_temperature_gap_converts_fahrenheit_gap_to_celsius() {
+        let mut config = conditional_config();
+        config.temperature_unit = TemperatureUnit::Fahrenheit;
+        config.schedule_granularity_minutes = 30;
+
+        // act
+        let load_profile = config.get_load_profile_for_temperature_gap(10.0, 113.0, 131.0);
+
+        assert_eq!(load_profile.sections[0].duration_seconds, 3600);
+    }
+
+    #[test]
+    fn validate_returns_error_if_desinfection_defer_hours_exceed_lookahead() {
+        let mut config = conditional_config();
+        config.desinfection_lookahead_hours = 18;
+        config.desinfection_defer_hours = 20;
+
+        // act
+        let result = config.validate();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validate_accepts_desinfection_defer_hours_equal_to_lookahead() {
+        let mut config = conditional_config();
+        config.desinfection_lookahead_hours = 18;
+        config.desinfection_defer_hours = 18;
+
+        // act
+        let result = config.validate();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn validate_returns_error_if_heating_rate_is_not_positive() {
+        let mut config = conditional_config();
+        config.heating_rate_celsius_per_hour = Some(0.0);
+
+        // act
+        let result = config.validate();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validate_returns_error_with_index_if_load_profile_section_has_zero_duration() {
+        let mut config = conditional_config();
+        config.load_profile.sections.push(LoadProfileSection {
+            duration_seconds: 0,
+            power_draw_watt: 2000.0,
+        });
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config loadProfile section 1 durationSeconds 0 should be larger than 0"
+        );
+    }
+
+    #[test]
+    fn validate_returns_error_if_desinfection_load_profile_section_has_zero_duration() {
+        let mut config = conditional_config();
+        config.desinfection_load_profile = load_profile(0);
+
+        // act
+        let result = config.validate();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validate_returns_error_if_load_profile_section_has_negative_power() {
+        let mut config = conditional_config();
+        config.load_profile.sections[0].power_draw_watt = -1.0;
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Config loadProfile section 0 powerDrawWatt -1 should not be negative"
+        );
+    }
+
+    #[test]
+    fn get_load_profile_for_temperature_gap_rounds_up_to_schedule_granularity() {
+        let mut config = conditional_config();
+        config.schedule_granularity_minutes = 30;
+
+        // act
+        let load_profile = config.get_load_profile_for_temperature_gap(10.0, 45.0, 57.0);
+
+        assert_eq!(load_profile.sections.len(), 1);
+        assert_eq!(load_profile.sections[0].duration_seconds, 5400);
+        assert_eq!(load_profile.sections[0].power_draw_watt, 2000.0);
+    }
+
+    #[test]
+    fn get_load_profile_for_temperature_gap_returns_single_slot_when_already_at_temperature() {
+        let mut config = conditional_config();
+        config.schedule_granularity_minutes = 30;
+
+        // act
+        let load_profile = config.get_load_profile_for_temperature_gap(10.0, 58.0, 57.0);
+
+        assert_eq!(load_profile.sections[0].duration_seconds, 1800);
+    }
+
+    #[test]
+    fn get_desinfection_load_profile_selects_coldest_matching_profile() {
+        let config = conditional_config();
+
+        // act
+        let load_profile = config.get_desinfection_load_profile(Some(-2.5));
+
+        assert_eq!(load_profile.sections[0].duration_seconds, 10800);
+    }
+
+    #[test]
+    fn get_desinfection_load_profile_selects_profile_below_threshold() {
+        let config = conditional_config();
+
+        // act
+        let load_profile = config.get_desinfection_load_profile(Some(5.0));
+
+        assert_eq!(load_profile.sections[0].duration_seconds, 7200);
+    }
+
+    #[test]
+    fn get_desinfection_load_profile_falls_back_to_default_profile_if_none_matches() {
+        let config = conditional_config();
+
+        // act
+        let load_profile = config.get_desinfection_load_profile(Some(15.0));
+
+        assert_eq!(load_profile.sections[0].duration_seconds, 3600);
+    }
+
+    #[test]
+    fn get_desinfection_load_profile_falls_back_to_default_profile_without_outside_temperature() {
+        let config = conditional_config();
+
+        // act
+        let load_profile = config.get_desinfection_load_profile(None);
+
+        assert_eq!(load_profile.sections[0].duration_seconds, 3600);
+    }
+
+    #[test]
+    fn is_cooling_season_returns_true_above_min_outside_temperature() {
+        let mut config = conditional_config();
+        config.enable_cooling_scheduling = true;
+
+        // act
+        let cooling_season = config.is_cooling_season(Some(25.0));
+
+        assert!(cooling_season);
+    }
+
+    #[test]
+    fn is_cooling_season_returns_false_at_or_below_min_outside_temperature() {
+        let mut config = conditional_config();
+        config.enable_cooling_scheduling = true;
+
+        // act
+        let cooling_season = [Some(22.0), Some(10.0)]
+            .iter()
+            .map(|outside_temperature| config.is_cooling_season(*outside_temperature))
+            .collect::<Vec<bool>>();
+
+        assert_eq!(cooling_season, vec![false, false]);
+    }
+
+    #[test]
+    fn is_cooling_season_returns_false_without_outside_temperature() {
+        let mut config = conditional_config();
+        config.enable_cooling_scheduling = true;
+
+        // act
+        let cooling_season = config.is_cooling_season(None);
+
+        assert!(!cooling_season);
+    }
+
+    #[test]
+    fn is_cooling_season_returns_false_if_cooling_scheduling_is_disabled() {
+        let config = conditional_config();
+
+        // act
+        let cooling_season = config.is_cooling_season(Some(30.0));
+
+        assert!(!cooling_season);
+    }
+
+    fn schedule_segment(raw_values: &[&str]) -> ContentItem {
+        ContentItem {
+            name: "Maandag - Zondag".to_string(),
+            item: raw_values
+                .iter()
+                .enumerate()
+                .map(|(index, raw)| Item {
+                    id: format!("0xa5734{}", index),
+                    value: String::new(),
+                    name: format!("{})", index + 1),
+                    r#type: "timer".to_string(),
+                    raw: raw.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn weekly_schedule_from_content_decodes_block_split_at_midnight() {
+        // act
+        let schedule =
+            WeeklySchedule::from_content(&schedule_segment(&["11796480", "0", "1320"])).unwrap();
+
+        assert_eq!(
+            schedule.slots,
+            vec![
+                TimeRange::new(
+                    NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(3, 0, 0).unwrap()
+                ),
+                TimeRange::new(NaiveTime::MIN, NaiveTime::MIN),
+                TimeRange::new(
+                    NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(0, 0, 0).unwrap()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_schedule_to_set_commands_round_trips_raw_values() {
+        let mut schedule =
+            WeeklySchedule::from_content(&schedule_segment(&["11796480", "0", "1320"])).unwrap();
+        schedule.slots[1] = TimeRange::new(
+            NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        );
+
+        // act
+        let set_commands = schedule.to_set_commands();
+
+        assert_eq!(
+            set_commands,
+            vec![
+                "SET;set_0xa57340;0".to_string(),
+                "SET;set_0xa57341;0".to_string(),
+                "SET;set_0xa57342;0".to_string(),
+                format!("SET;set_0xa57340;{}", 65536 * 180),
+                format!("SET;set_0xa57341;{}", 420 + 65536 * 540),
+                "SET;set_0xa57342;1320".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_schedule_to_set_commands_encodes_block_from_midnight_in_high_bits() {
+        let schedule = WeeklySchedule {
+            item_ids: vec!["0xa57344".to_string()],
+            slots: vec![TimeRange::new(
+                NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            )],
+        };
+
+        // act
+        let set_commands = schedule.to_set_commands();
+
+        assert_eq!(
+            set_commands,
+            vec![
+                "SET;set_0xa57344;0".to_string(),
+                format!("SET;set_0xa57344;{}", 120 + 65536 * 1320),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_schedule_from_content_returns_error_for_out_of_range_raw_value() {
+        // act
+        let result = WeeklySchedule::from_content(&schedule_segment(&["1440"]));
+
+        assert!(result.is_err());
+    }
+
+    fn timer_item(value: &str) -> Item {
+        Item {
+            id: "0xa57344".to_string(),
+            value: value.to_string(),
+            name: "1)".to_string(),
+            r#type: "timer".to_string(),
+            raw: "0".to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_time_range_returns_from_and_till() {
+        // act
+        let time_range = timer_item("10:00 - 00:00").parse_time_range().unwrap();
+
+        assert_eq!(
+            time_range,
+            Some((
+                NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(0, 0, 0).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_time_range_returns_from_and_till_for_block_starting_at_midnight() {
+        // act
+        let time_range = timer_item("00:00 - 03:00").parse_time_range().unwrap();
+
+        assert_eq!(
+            time_range,
+            Some((
+                NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(3, 0, 0).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_time_range_returns_none_for_disabled_timer() {
+        // act
+        let time_range = timer_item("00:00 - 00:00").parse_time_range().unwrap();
+
+        assert_eq!(time_range, None);
+    }
+
+    #[test]
+    fn parse_time_range_returns_error_for_invalid_value() {
+        // act
+        let result = timer_item("---").parse_time_range();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_segment_index_maps_weekdays_onto_program_segments() {
+        assert_eq!(ScheduleProgram::Week.get_segment_index(Weekday::Sat), 0);
+        assert_eq!(
+            ScheduleProgram::FivePlusTwo.get_segment_index(Weekday::Fri),
+            0
+        );
+        assert_eq!(
+            ScheduleProgram::FivePlusTwo.get_segment_index(Weekday::Sat),
+            1
+        );
+        assert_eq!(
+            ScheduleProgram::FivePlusTwo.get_segment_index(Weekday::Sun),
+            1
+        );
+        assert_eq!(ScheduleProgram::PerDay.get_segment_index(Weekday::Mon), 0);
+        assert_eq!(ScheduleProgram::PerDay.get_segment_index(Weekday::Sun), 6);
+    }
+
+    #[test]
+    fn deserialize_content_returns_item_per_segment() {
+        let xml_string = "<Content><item><name>Maandag - Vrijdag</name><item id='0xa57344'><value>00:00 - 03:00</value><name>1)</name><type>timer</type><raw>11796480</raw></item></item><item><name>Zaterdag - Zondag</name><item id='0xa53c8c'><value>00:00 - 00:00</value><name>1)</name><type>timer</type><raw>0</raw></item></item></Content>";
+
+        // act
+        let content: Content = from_str(xml_string).unwrap();
+
+        assert_eq!(content.segments.len(), 2);
+        assert_eq!(content.segments[0].name, "Maandag - Vrijdag".to_string());
+        assert_eq!(content.segments[1].item[0].id, "0xa53c8c".to_string());
+    }
+
+    #[test]
+    fn operating_mode_from_value_returns_unknown_for_unrecognized_value() {
+        assert_eq!(
+            OperatingMode::from_value("Automatisch"),
+            OperatingMode::Automatic
+        );
+        assert_eq!(
+            OperatingMode::from_value("Vakantie"),
+            OperatingMode::Holiday
+        );
+        assert_eq!(
+            OperatingMode::from_value("Koelen"),
+            OperatingMode::Unknown("Koelen".to_string())
+        );
+    }
+
+    #[test]
+    fn firmware_version_from_value_parses_version_with_and_without_patch_number() {
+        assert_eq!(
+            FirmwareVersion::from_value("V3.88.2"),
+            Some(FirmwareVersion {
+                major: 3,
+                minor: 88,
+                patch: 2
+            })
+        );
+        assert_eq!(
+            FirmwareVersion::from_value(" V3.90 "),
+            Some(FirmwareVersion {
+                major: 3,
+                minor: 90,
+                patch: 0
+            })
+        );
+        assert_eq!(FirmwareVersion::from_value("---"), None);
+    }
+
+    #[test]
+    fn fault_from_item_parses_timestamp_code_and_description() {
+        // act
+        let fault = Fault::from_item("21.04.22 10:22:13", "717 - Flow monitoring");
+
+        assert_eq!(
+            fault,
+            Some(Fault {
+                timestamp: NaiveDate::from_ymd_opt(2022, 4, 21)
+                    .unwrap()
+                    .and_hms_opt(10, 22, 13)
+                    .unwrap(),
+                code: 717,
+                description: "Flow monitoring".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn fault_from_item_returns_none_for_empty_entry() {
+        // act
+        let fault = Fault::from_item("---", "---");
+
+        assert_eq!(fault, None);
+    }
+
+    #[test]
+    fn firmware_version_is_known_good_only_for_known_major_versions() {
+        assert!(FirmwareVersion::from_value("V3.88.2")
+            .unwrap()
+            .is_known_good());
+        assert!(!FirmwareVersion::from_value("V1.86.0")
+            .unwrap()
+            .is_known_good());
     }
 }
@@ -0,0 +1,1568 @@
+use crate::model::{Config, PastSlotPolicy, PriceComponent, ScheduleProgram, TimeSlot};
+use chrono::{prelude::*, Duration, Utc};
+use chrono_tz::Tz;
+use jarvis_lib::model::{LoadProfile, SpotPrice};
+use std::error::Error;
+use tracing::{debug, info};
+
+pub(crate) fn get_cheapest_contiguous_spot_prices(
+    spot_prices: &[SpotPrice],
+    config: &Config,
+    duration_seconds: i64,
+    now: DateTime<Utc>,
+) -> Vec<SpotPrice> {
+    let mut cheapest: Option<(f64, Vec<SpotPrice>)> = None;
+
+    for window in get_contiguous_windows(spot_prices, duration_seconds, now) {
+        let total_price = get_total_price(&window, config);
+        match cheapest {
+            Some((cheapest_total_price, _)) if cheapest_total_price <= total_price => {}
+            _ => cheapest = Some((total_price, window)),
+        }
+    }
+
+    cheapest.map(|(_, window)| window).unwrap_or_default()
+}
+
+pub(crate) fn filter_spot_prices_on_weekdays(
+    spot_prices: &[SpotPrice],
+    allowed_weekdays: &[Weekday],
+    heatpump_time_zone: &Tz,
+) -> Vec<SpotPrice> {
+    // no allowed weekdays means every day is allowed
+    spot_prices
+        .iter()
+        .filter(|sp| {
+            allowed_weekdays.is_empty()
+                || allowed_weekdays.contains(&sp.from.with_timezone(heatpump_time_zone).weekday())
+        })
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn get_spot_prices_within_day(
+    spot_prices: &[SpotPrice],
+    now: DateTime<Utc>,
+) -> Vec<SpotPrice> {
+    // the same horizon the planner gets for the single worst hour
+    spot_prices
+        .iter()
+        .filter(|sp| sp.from >= now && sp.till <= now + Duration::hours(24))
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn get_spot_prices_above_price(
+    spot_prices: &[SpotPrice],
+    config: &Config,
+    blocking_price: f64,
+) -> Vec<SpotPrice> {
+    // the slots are blocked as they are, the heating writer splits them in contiguous blocks
+    spot_prices
+        .iter()
+        .filter(|sp| config.effective_price(sp) > blocking_price)
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn get_price_percentile(
+    spot_prices: &[SpotPrice],
+    config: &Config,
+    percentile: f64,
+) -> Option<f64> {
+    let mut prices: Vec<f64> = spot_prices
+        .iter()
+        .map(|sp| config.effective_price(sp))
+        .collect();
+    if prices.is_empty() {
+        return None;
+    }
+    // a NaN price from a csv source sorts above every other price instead of panicking
+    prices.sort_by(|a, b| a.total_cmp(b));
+
+    // nearest rank, so the 75th percentile of 24 slots leaves the 6 most expensive ones above it
+    let rank = (percentile / 100.0 * prices.len() as f64).ceil() as usize;
+    Some(prices[rank.clamp(1, prices.len()) - 1])
+}
+
+pub(crate) fn filter_spot_prices_above_price(
+    spot_prices: &[SpotPrice],
+    config: &Config,
+    max_acceptable_price: f64,
+) -> Vec<SpotPrice> {
+    let acceptable_spot_prices: Vec<SpotPrice> = spot_prices
+        .iter()
+        .filter(|sp| config.effective_price(sp) < max_acceptable_price)
+        .cloned()
+        .collect();
+
+    if acceptable_spot_prices.len() < spot_prices.len() {
+        info!(
+            "Filtered out {} of {} spot price slots with an effective price above maximum acceptable price of {}",
+            spot_prices.len() - acceptable_spot_prices.len(),
+            spot_prices.len(),
+            max_acceptable_price
+        );
+    }
+
+    acceptable_spot_prices
+}
+
+pub(crate) fn cap_spot_prices_to_duration(
+    spot_prices: &[SpotPrice],
+    config: &Config,
+    max_duration_seconds: i64,
+) -> Vec<SpotPrice> {
+    let duration_seconds: i64 = spot_prices
+        .iter()
+        .map(|sp| (sp.till - sp.from).num_seconds())
+        .sum();
+    if duration_seconds <= max_duration_seconds {
+        return spot_prices.to_vec();
+    }
+
+    let capped_spot_prices = get_cheapest_contiguous_spot_prices(
+        spot_prices,
+        config,
+        max_duration_seconds,
+        spot_prices.first().unwrap().from,
+    );
+    if capped_spot_prices.is_empty() {
+        return spot_prices.to_vec();
+    }
+
+    info!(
+        "Capped tap water heating window of {} spot price slots to {} slots to stay within {} seconds",
+        spot_prices.len(),
+        capped_spot_prices.len(),
+        max_duration_seconds
+    );
+
+    capped_spot_prices
+}
+
+pub(crate) fn get_earliest_contiguous_spot_prices(
+    spot_prices: &[SpotPrice],
+    duration_seconds: i64,
+    now: DateTime<Utc>,
+) -> Vec<SpotPrice> {
+    get_contiguous_windows(spot_prices, duration_seconds, now)
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+}
+
+fn get_contiguous_windows(
+    spot_prices: &[SpotPrice],
+    duration_seconds: i64,
+    now: DateTime<Utc>,
+) -> Vec<Vec<SpotPrice>> {
+    let future_spot_prices: Vec<SpotPrice> = spot_prices
+        .iter()
+        .filter(|sp| sp.from >= now)
+        .cloned()
+        .collect();
+
+    let mut windows = vec![];
+
+    for start in 0..future_spot_prices.len() {
+        let mut end = start;
+        let mut covered_seconds = 0;
+        while end < future_spot_prices.len() && covered_seconds < duration_seconds {
+            if end > start && future_spot_prices[end - 1].till != future_spot_prices[end].from {
+                break;
+            }
+            covered_seconds +=
+                (future_spot_prices[end].till - future_spot_prices[end].from).num_seconds();
+            end += 1;
+        }
+
+        if covered_seconds < duration_seconds {
+            continue;
+        }
+
+        windows.push(future_spot_prices[start..end].to_vec());
+    }
+
+    windows
+}
+
+pub(crate) fn are_spot_prices_stale(
+    spot_prices: &[SpotPrice],
+    max_spot_price_age_hours: i64,
+    now: DateTime<Utc>,
+) -> bool {
+    match spot_prices.iter().map(|sp| sp.till).max() {
+        Some(newest_till) => newest_till < now - Duration::hours(max_spot_price_age_hours),
+        None => false,
+    }
+}
+
+pub(crate) fn is_plan_unchanged(
+    planned_spot_prices: &[SpotPrice],
+    best_spot_prices: &[SpotPrice],
+    config: &Config,
+) -> bool {
+    // the stored plan got jittered and rounded before it was written, so only larger shifts count as a change
+    let tolerance =
+        Duration::minutes(config.jitter_max_minutes + config.schedule_granularity_minutes);
+
+    !best_spot_prices.is_empty()
+        && planned_spot_prices.len() == best_spot_prices.len()
+        && planned_spot_prices
+            .iter()
+            .zip(best_spot_prices.iter())
+            .all(|(planned, best)| {
+                (planned.from - best.from).abs() <= tolerance
+                    && (planned.till - best.till).abs() <= tolerance
+            })
+}
+
+pub(crate) fn round_spot_prices_to_granularity(
+    spot_prices: &[SpotPrice],
+    granularity_minutes: i64,
+) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+    if granularity_minutes <= 0 || 60 % granularity_minutes != 0 {
+        return Err(Box::<dyn Error>::from(format!(
+            "Schedule granularity of {} minutes does not evenly divide an hour",
+            granularity_minutes
+        )));
+    }
+
+    let round = |time: DateTime<Utc>| -> DateTime<Utc> {
+        let granularity_seconds = 60 * granularity_minutes;
+        let rounded_seconds = (time.timestamp() + granularity_seconds / 2)
+            .div_euclid(granularity_seconds)
+            * granularity_seconds;
+
+        DateTime::from_timestamp(rounded_seconds, 0).unwrap_or(time)
+    };
+
+    Ok(spot_prices
+        .iter()
+        .map(|spot_price| SpotPrice {
+            from: round(spot_price.from),
+            till: round(spot_price.till),
+            ..spot_price.clone()
+        })
+        .collect())
+}
+
+pub(crate) fn remove_protected_windows_from_blocking(
+    worst_spot_prices: &[SpotPrice],
+    protected_windows: &[TimeSlot],
+    local_time_zone: &Tz,
+) -> Vec<SpotPrice> {
+    // protected windows are never blocked, whatever the price, so slots touching one are left out altogether
+    worst_spot_prices
+        .iter()
+        .filter(|spot_price| {
+            let protected_window = protected_windows.iter().find(|protected_window| {
+                is_overlapping_time_slot(spot_price, protected_window, local_time_zone)
+            });
+            if let Some(protected_window) = protected_window {
+                info!(
+                    "Not blocking heating {} - {}, it overlaps protected window {} - {}",
+                    spot_price.from,
+                    spot_price.till,
+                    protected_window.from.format("%H:%M"),
+                    protected_window.till.format("%H:%M")
+                );
+            }
+
+            protected_window.is_none()
+        })
+        .cloned()
+        .collect()
+}
+
+fn is_overlapping_time_slot(
+    spot_price: &SpotPrice,
+    time_slot: &TimeSlot,
+    local_time_zone: &Tz,
+) -> bool {
+    let from = spot_price.from.with_timezone(local_time_zone).naive_local();
+    let till = spot_price.till.with_timezone(local_time_zone).naive_local();
+
+    // a slot ending at or before its start runs past midnight, one from 0:00 till 0:00 covers the whole day
+    let slot_duration = if time_slot.till > time_slot.from {
+        time_slot.till - time_slot.from
+    } else {
+        time_slot.till - time_slot.from + Duration::days(1)
+    };
+
+    // a slot that started the day before can still be running
+    let mut date = from.date().pred_opt().unwrap();
+    while date <= till.date() {
+        let slot_from = date.and_time(time_slot.from);
+        let slot_till = slot_from + slot_duration;
+        if from < slot_till && till > slot_from {
+            return true;
+        }
+        date = date.succ_opt().unwrap();
+    }
+
+    false
+}
+
+pub(crate) fn remove_tap_water_overlap_from_blocking(
+    worst_spot_prices: &[SpotPrice],
+    tap_water_spot_prices: &[SpotPrice],
+    gap_minutes: i64,
+) -> Vec<SpotPrice> {
+    // heating tap water while heating is blocked defeats the purpose, so shrink blocking away from the tap water windows
+    let gap = Duration::minutes(gap_minutes);
+    let tap_water_windows: Vec<(DateTime<Utc>, DateTime<Utc>)> =
+        get_contiguous_blocks(tap_water_spot_prices)
+            .into_iter()
+            .map(|block| {
+                (
+                    block.first().unwrap().from - gap,
+                    block.last().unwrap().till + gap,
+                )
+            })
+            .collect();
+
+    let mut remaining_spot_prices = vec![];
+    for spot_price in worst_spot_prices {
+        let mut parts = vec![(spot_price.from, spot_price.till)];
+        for (window_from, window_till) in tap_water_windows.iter() {
+            parts = parts
+                .into_iter()
+                .flat_map(|(from, till)| {
+                    if till <= *window_from || from >= *window_till {
+                        return vec![(from, till)];
+                    }
+
+                    let mut remaining_parts = vec![];
+                    if from < *window_from {
+                        remaining_parts.push((from, *window_from));
+                    }
+                    if till > *window_till {
+                        remaining_parts.push((*window_till, till));
+                    }
+                    remaining_parts
+                })
+                .collect();
+        }
+
+        if parts != vec![(spot_price.from, spot_price.till)] {
+            info!(
+                "Shrinking heating block {} - {} to stay {} minutes clear of tap water heating",
+                spot_price.from, spot_price.till, gap_minutes
+            );
+        }
+
+        for (from, till) in parts {
+            remaining_spot_prices.push(SpotPrice {
+                from,
+                till,
+                ..spot_price.clone()
+            });
+        }
+    }
+
+    remaining_spot_prices
+}
+
+pub(crate) fn get_running_spot_prices(
+    spot_prices: &[SpotPrice],
+    now: DateTime<Utc>,
+) -> Vec<SpotPrice> {
+    // finished blocks are left out, the clock program repeats every day and would block them again tomorrow
+    get_contiguous_blocks(spot_prices)
+        .into_iter()
+        .filter(|block| block.first().unwrap().from < now && block.last().unwrap().till > now)
+        .flat_map(|block| block.iter().cloned())
+        .collect()
+}
+
+pub(crate) fn merge_running_spot_prices(
+    running_spot_prices: &[SpotPrice],
+    planned_spot_prices: &[SpotPrice],
+) -> Vec<SpotPrice> {
+    let mut merged_spot_prices = running_spot_prices.to_vec();
+    merged_spot_prices.extend(
+        planned_spot_prices
+            .iter()
+            .filter(|planned| {
+                !running_spot_prices
+                    .iter()
+                    .any(|running| planned.from < running.till && planned.till > running.from)
+            })
+            .cloned(),
+    );
+    merged_spot_prices.sort_by_key(|sp| sp.from);
+
+    merged_spot_prices
+}
+
+pub(crate) fn get_contiguous_blocks(spot_prices: &[SpotPrice]) -> Vec<&[SpotPrice]> {
+    let mut blocks: Vec<&[SpotPrice]> = vec![];
+
+    let mut block_start = 0;
+    for i in 1..spot_prices.len() {
+        if spot_prices[i - 1].till != spot_prices[i].from {
+            blocks.push(&spot_prices[block_start..i]);
+            block_start = i;
+        }
+    }
+    if block_start < spot_prices.len() {
+        blocks.push(&spot_prices[block_start..]);
+    }
+
+    blocks
+}
+
+pub(crate) fn split_spot_prices_per_schedule_segment(
+    heatpump_time_zone: &Tz,
+    schedule_program: &ScheduleProgram,
+    spot_prices: &[SpotPrice],
+) -> Vec<Vec<SpotPrice>> {
+    let mut segments = vec![vec![]; schedule_program.number_of_segments()];
+
+    for spot_price in spot_prices {
+        let weekday = spot_price.from.with_timezone(heatpump_time_zone).weekday();
+        segments[schedule_program.get_segment_index(weekday)].push(spot_price.clone());
+    }
+
+    segments
+}
+
+pub(crate) fn get_schedule_segment_spot_prices(
+    heatpump_time_zone: &Tz,
+    schedule_program: &ScheduleProgram,
+    spot_prices: &[SpotPrice],
+    segment_index: usize,
+) -> Vec<SpotPrice> {
+    // a block crossing midnight is programmed in the segment of the day it starts on
+    get_contiguous_blocks(spot_prices)
+        .into_iter()
+        .filter(|block| {
+            let weekday = block[0].from.with_timezone(heatpump_time_zone).weekday();
+            schedule_program.get_segment_index(weekday) == segment_index
+        })
+        .flat_map(|block| block.iter().cloned())
+        .collect()
+}
+
+pub(crate) fn clamp_jitter_shift_minutes(
+    heatpump_time_zone: &Tz,
+    spot_prices: &[SpotPrice],
+    now: DateTime<Utc>,
+    shift_minutes: i64,
+    past_slot_policy: PastSlotPolicy,
+) -> i64 {
+    let from = spot_prices.first().unwrap().from;
+    let till = spot_prices.last().unwrap().till;
+
+    // don't let the start move into the past; if it already started don't move it any earlier
+    let mut min_shift_minutes = if from < now {
+        0
+    } else if past_slot_policy == PastSlotPolicy::ClampToNow {
+        -(from - now).num_minutes()
+    } else {
+        i64::MIN
+    };
+    let mut max_shift_minutes = i64::MAX;
+
+    // don't let a window that lies within a single day of the heatpump cross midnight
+    let from_time = from.with_timezone(heatpump_time_zone);
+    let till_time = till.with_timezone(heatpump_time_zone);
+    if from_time.date_naive() == (till_time - Duration::minutes(1)).date_naive() {
+        let from_minutes_of_day = (60 * from_time.hour() + from_time.minute()) as i64;
+        let till_minutes_of_day = match (60 * till_time.hour() + till_time.minute()) as i64 {
+            0 => 24 * 60,
+            minutes => minutes,
+        };
+
+        min_shift_minutes = std::cmp::max(min_shift_minutes, -from_minutes_of_day);
+        max_shift_minutes = 24 * 60 - till_minutes_of_day;
+    }
+
+    let clamped_shift_minutes = std::cmp::max(
+        std::cmp::min(shift_minutes, max_shift_minutes),
+        min_shift_minutes,
+    );
+    if clamped_shift_minutes != shift_minutes {
+        debug!(
+            "Clamped jitter from {} to {} minutes",
+            shift_minutes, clamped_shift_minutes
+        );
+    }
+
+    clamped_shift_minutes
+}
+
+pub(crate) fn apply_past_slot_policy(
+    spot_prices: &[SpotPrice],
+    jittered_spot_prices: &[SpotPrice],
+    now: DateTime<Utc>,
+    past_slot_policy: PastSlotPolicy,
+    granularity_minutes: i64,
+) -> Vec<SpotPrice> {
+    let mut updated_spot_prices: Vec<SpotPrice> = vec![];
+
+    // slots that were running before jitter are left alone, only the ones it moved into the past are affected
+    for (spot_price, jittered_spot_price) in spot_prices.iter().zip(jittered_spot_prices.iter()) {
+        if spot_price.from < now
+            || jittered_spot_price.from >= now
+            || past_slot_policy == PastSlotPolicy::Allow
+        {
+            updated_spot_prices.push(jittered_spot_price.clone());
+            continue;
+        }
+
+        if past_slot_policy == PastSlotPolicy::DropPastSlots {
+            info!(
+                "Dropping slot {} - {}, jitter moved its start before now",
+                jittered_spot_price.from, jittered_spot_price.till
+            );
+            continue;
+        }
+
+        // clamp to the first start the clock program can represent that isn't in the past
+        let granularity_seconds = 60 * granularity_minutes;
+        let clamped_from = DateTime::from_timestamp(
+            (now.timestamp() + granularity_seconds - 1).div_euclid(granularity_seconds)
+                * granularity_seconds,
+            0,
+        )
+        .unwrap_or(now);
+        if clamped_from >= jittered_spot_price.till {
+            info!(
+                "Dropping slot {} - {}, it ends before it can be clamped to {}",
+                jittered_spot_price.from, jittered_spot_price.till, clamped_from
+            );
+            continue;
+        }
+
+        info!(
+            "Clamping start of slot {} - {} to {}, jitter moved it before now",
+            jittered_spot_price.from, jittered_spot_price.till, clamped_from
+        );
+        updated_spot_prices.push(SpotPrice {
+            from: clamped_from,
+            ..jittered_spot_price.clone()
+        });
+    }
+
+    updated_spot_prices
+}
+
+pub(crate) fn get_total_price(spot_prices: &[SpotPrice], config: &Config) -> f64 {
+    // weighted by slot length, so quarter hour slots add up to the same total as the hours they cover
+    spot_prices
+        .iter()
+        .map(|sp| config.effective_price(sp) * (sp.till - sp.from).num_seconds() as f64 / 3600.0)
+        .sum()
+}
+
+pub(crate) fn get_effective_spot_price(spot_price: &SpotPrice, config: &Config) -> SpotPrice {
+    SpotPrice {
+        market_price: config.get_effective_price_component(PriceComponent::MarketPrice, spot_price),
+        market_price_tax: config
+            .get_effective_price_component(PriceComponent::MarketPriceTax, spot_price),
+        sourcing_markup_price: config
+            .get_effective_price_component(PriceComponent::SourcingMarkupPrice, spot_price),
+        energy_tax_price: config
+            .get_effective_price_component(PriceComponent::EnergyTaxPrice, spot_price),
+        ..spot_price.clone()
+    }
+}
+
+pub(crate) fn get_planned_cost(
+    spot_prices: &[SpotPrice],
+    load_profile: &LoadProfile,
+    config: &Config,
+) -> f64 {
+    let mut section_from = match spot_prices.first() {
+        Some(spot_price) => spot_price.from,
+        None => return 0.0,
+    };
+
+    // spread the energy of each load profile section over the spot price slots it overlaps with
+    let mut planned_cost = 0.0;
+    for section in &load_profile.sections {
+        let section_till = section_from + Duration::seconds(section.duration_seconds);
+
+        for spot_price in spot_prices {
+            let overlap_from = std::cmp::max(spot_price.from, section_from);
+            let overlap_till = std::cmp::min(spot_price.till, section_till);
+
+            if overlap_till > overlap_from {
+                let overlap_hours = (overlap_till - overlap_from).num_seconds() as f64 / 3600.0;
+                planned_cost += overlap_hours * section.power_draw_watt / 1000.0
+                    * config.effective_price(spot_price);
+            }
+        }
+
+        section_from = section_till;
+    }
+
+    planned_cost
+}
+
+pub(crate) fn get_average_effective_price_per_hour(
+    config: &Config,
+    spot_prices: &[SpotPrice],
+) -> Option<f64> {
+    // weighted by slot length, so a window of quarter hours averages the same as the hours it covers
+    let total_hours: f64 = spot_prices
+        .iter()
+        .map(|sp| (sp.till - sp.from).num_seconds() as f64 / 3600.0)
+        .sum();
+    if total_hours <= 0.0 {
+        return None;
+    }
+
+    Some(
+        spot_prices
+            .iter()
+            .map(|sp| {
+                config.effective_price(sp) * (sp.till - sp.from).num_seconds() as f64 / 3600.0
+            })
+            .sum::<f64>()
+            / total_hours,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_config;
+    use jarvis_lib::model::LoadProfileSection;
+
+    fn spot_price(from: DateTime<Utc>, till: DateTime<Utc>) -> SpotPrice {
+        SpotPrice {
+            id: None,
+            source: None,
+            from,
+            till,
+            market_price: 0.157,
+            market_price_tax: 0.0330708,
+            sourcing_markup_price: 0.017,
+            energy_tax_price: 0.081,
+        }
+    }
+
+    fn hourly_spot_prices(from: DateTime<Utc>, market_prices: &[f64]) -> Vec<SpotPrice> {
+        market_prices
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| SpotPrice {
+                market_price: *market_price,
+                ..spot_price(
+                    from + Duration::hours(i as i64),
+                    from + Duration::hours(i as i64 + 1),
+                )
+            })
+            .collect()
+    }
+
+    fn quarter_hourly_spot_prices(from: DateTime<Utc>, market_prices: &[f64]) -> Vec<SpotPrice> {
+        market_prices
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| SpotPrice {
+                market_price: *market_price,
+                ..spot_price(
+                    from + Duration::minutes(15 * i as i64),
+                    from + Duration::minutes(15 * (i as i64 + 1)),
+                )
+            })
+            .collect()
+    }
+
+    fn price_components_spot_prices() -> Vec<SpotPrice> {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        vec![
+            SpotPrice {
+                market_price: 0.1,
+                energy_tax_price: 0.3,
+                ..spot_price(now, now + Duration::hours(1))
+            },
+            SpotPrice {
+                market_price: 0.2,
+                energy_tax_price: 0.0,
+                ..spot_price(now + Duration::hours(1), now + Duration::hours(2))
+            },
+        ]
+    }
+
+    fn time_ranges(spot_prices: &[SpotPrice]) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        spot_prices.iter().map(|sp| (sp.from, sp.till)).collect()
+    }
+
+    fn past_slot_spot_prices() -> (Vec<SpotPrice>, Vec<SpotPrice>) {
+        let spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 13, 5, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 14, 5, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 14, 5, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 15, 5, 0).unwrap(),
+            ),
+        ];
+        let jittered_spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 12, 55, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 13, 55, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 13, 55, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 14, 55, 0).unwrap(),
+            ),
+        ];
+
+        (spot_prices, jittered_spot_prices)
+    }
+
+    #[test]
+    fn get_contiguous_blocks_splits_spot_prices_on_gaps() {
+        let spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 1, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 2, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 2, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 5, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 6, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let blocks = get_contiguous_blocks(&spot_prices);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].len(), 2);
+        assert_eq!(blocks[0][0].from, spot_prices[0].from);
+        assert_eq!(blocks[1].len(), 1);
+        assert_eq!(blocks[1][0].from, spot_prices[2].from);
+    }
+
+    #[test]
+    fn get_contiguous_blocks_returns_single_block_for_contiguous_spot_prices() {
+        let spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 1, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 2, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 2, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let blocks = get_contiguous_blocks(&spot_prices);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].len(), 2);
+    }
+
+    #[test]
+    fn clamp_jitter_shift_minutes_keeps_start_from_moving_before_now() {
+        let spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 5, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 15, 5, 0).unwrap(),
+        )];
+
+        // act
+        let shift_minutes = clamp_jitter_shift_minutes(
+            &Tz::UTC,
+            &spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap(),
+            -10,
+            PastSlotPolicy::ClampToNow,
+        );
+
+        assert_eq!(shift_minutes, -5);
+    }
+
+    #[test]
+    fn clamp_jitter_shift_minutes_keeps_end_from_crossing_midnight() {
+        let spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 21, 55, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 23, 55, 0).unwrap(),
+        )];
+
+        // act
+        let shift_minutes = clamp_jitter_shift_minutes(
+            &Tz::UTC,
+            &spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap(),
+            10,
+            PastSlotPolicy::ClampToNow,
+        );
+
+        assert_eq!(shift_minutes, 5);
+    }
+
+    #[test]
+    fn clamp_jitter_shift_minutes_returns_shift_if_no_clamp_is_needed() {
+        let spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap(),
+        )];
+
+        // act
+        let shift_minutes = clamp_jitter_shift_minutes(
+            &Tz::UTC,
+            &spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap(),
+            -10,
+            PastSlotPolicy::ClampToNow,
+        );
+
+        assert_eq!(shift_minutes, -10);
+    }
+
+    #[test]
+    fn clamp_jitter_shift_minutes_lets_start_move_before_now_unless_clamping_to_now() {
+        let spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 5, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 15, 5, 0).unwrap(),
+        )];
+
+        // act
+        let shift_minutes = clamp_jitter_shift_minutes(
+            &Tz::UTC,
+            &spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap(),
+            -10,
+            PastSlotPolicy::Allow,
+        );
+
+        assert_eq!(shift_minutes, -10);
+    }
+
+    #[test]
+    fn apply_past_slot_policy_clamps_start_to_now_rounded_up_to_granularity() {
+        let (spot_prices, jittered_spot_prices) = past_slot_spot_prices();
+
+        // act
+        let updated_spot_prices = apply_past_slot_policy(
+            &spot_prices,
+            &jittered_spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 2, 0).unwrap(),
+            PastSlotPolicy::ClampToNow,
+            5,
+        );
+
+        assert_eq!(updated_spot_prices.len(), 2);
+        assert_eq!(
+            updated_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 5, 0).unwrap()
+        );
+        assert_eq!(updated_spot_prices[0].till, jittered_spot_prices[0].till);
+        assert_eq!(
+            time_ranges(&updated_spot_prices[1..]),
+            time_ranges(&jittered_spot_prices[1..])
+        );
+    }
+
+    #[test]
+    fn apply_past_slot_policy_drops_slots_starting_before_now() {
+        let (spot_prices, jittered_spot_prices) = past_slot_spot_prices();
+
+        // act
+        let updated_spot_prices = apply_past_slot_policy(
+            &spot_prices,
+            &jittered_spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 2, 0).unwrap(),
+            PastSlotPolicy::DropPastSlots,
+            5,
+        );
+
+        assert_eq!(
+            time_ranges(&updated_spot_prices),
+            time_ranges(&jittered_spot_prices[1..])
+        );
+    }
+
+    #[test]
+    fn apply_past_slot_policy_allows_slots_starting_before_now() {
+        let (spot_prices, jittered_spot_prices) = past_slot_spot_prices();
+
+        // act
+        let updated_spot_prices = apply_past_slot_policy(
+            &spot_prices,
+            &jittered_spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 2, 0).unwrap(),
+            PastSlotPolicy::Allow,
+            5,
+        );
+
+        assert_eq!(
+            time_ranges(&updated_spot_prices),
+            time_ranges(&jittered_spot_prices)
+        );
+    }
+
+    #[test]
+    fn apply_past_slot_policy_keeps_slots_that_were_already_running() {
+        let (spot_prices, jittered_spot_prices) = past_slot_spot_prices();
+
+        // act
+        let updated_spot_prices = apply_past_slot_policy(
+            &spot_prices,
+            &jittered_spot_prices,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 30, 0).unwrap(),
+            PastSlotPolicy::DropPastSlots,
+            5,
+        );
+
+        assert_eq!(
+            time_ranges(&updated_spot_prices),
+            time_ranges(&jittered_spot_prices)
+        );
+    }
+
+    #[test]
+    fn remove_protected_windows_from_blocking_keeps_slots_outside_protected_windows() {
+        let worst_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 20, 0, 0).unwrap(),
+            &[0.4; 4],
+        );
+
+        // act
+        let remaining_spot_prices = remove_protected_windows_from_blocking(
+            &worst_spot_prices,
+            &[TimeSlot {
+                from: NaiveTime::from_hms_opt(23, 30, 0).unwrap(),
+                till: NaiveTime::from_hms_opt(0, 30, 0).unwrap(),
+                if_price_below: None,
+            }],
+            &Tz::Europe__Amsterdam,
+        );
+
+        // 21:00 - 22:00 and 22:00 - 23:00 utc overlap 23:30 - 0:30 in amsterdam
+        assert_eq!(
+            remaining_spot_prices
+                .iter()
+                .map(|sp| sp.from.hour())
+                .collect::<Vec<u32>>(),
+            vec![20, 23]
+        );
+    }
+
+    #[test]
+    fn remove_protected_windows_from_blocking_removes_all_slots_for_whole_day_window() {
+        let worst_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 20, 0, 0).unwrap(),
+            &[0.4; 4],
+        );
+
+        // act
+        let remaining_spot_prices = remove_protected_windows_from_blocking(
+            &worst_spot_prices,
+            &[TimeSlot {
+                from: NaiveTime::MIN,
+                till: NaiveTime::MIN,
+                if_price_below: None,
+            }],
+            &Tz::Europe__Amsterdam,
+        );
+
+        assert!(remaining_spot_prices.is_empty());
+    }
+
+    #[test]
+    fn split_spot_prices_per_schedule_segment_groups_spot_prices_by_weekday() {
+        let spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 22, 23, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 23, 0, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 23, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 23, 1, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let segments = split_spot_prices_per_schedule_segment(
+            &Tz::UTC,
+            &ScheduleProgram::FivePlusTwo,
+            &spot_prices,
+        );
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].len(), 1);
+        assert_eq!(segments[0][0].from, spot_prices[0].from);
+        assert_eq!(segments[1].len(), 1);
+        assert_eq!(segments[1][0].from, spot_prices[1].from);
+    }
+
+    #[test]
+    fn get_schedule_segment_spot_prices_keeps_block_crossing_midnight_in_segment_of_its_start() {
+        let spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 22, 23, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 23, 0, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 23, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 23, 1, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let weekday_spot_prices = get_schedule_segment_spot_prices(
+            &Tz::UTC,
+            &ScheduleProgram::FivePlusTwo,
+            &spot_prices,
+            0,
+        );
+        let weekend_spot_prices = get_schedule_segment_spot_prices(
+            &Tz::UTC,
+            &ScheduleProgram::FivePlusTwo,
+            &spot_prices,
+            1,
+        );
+
+        assert_eq!(weekday_spot_prices.len(), 2);
+        assert!(weekend_spot_prices.is_empty());
+    }
+
+    #[test]
+    fn get_average_effective_price_per_hour_weighs_slots_by_length() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let config = Config {
+            price_components: vec![PriceComponent::MarketPrice],
+            ..test_config()
+        };
+        let mut spot_prices = vec![spot_price(now, now + Duration::hours(3))];
+        spot_prices[0].market_price = 0.1;
+        spot_prices.extend(hourly_spot_prices(now + Duration::hours(3), &[0.5]));
+
+        // act
+        let price_per_hour = get_average_effective_price_per_hour(&config, &spot_prices).unwrap();
+
+        assert!((price_per_hour - 0.2).abs() < 1e-9);
+        assert_eq!(get_average_effective_price_per_hour(&config, &[]), None);
+    }
+
+    #[test]
+    fn get_average_effective_price_per_hour_includes_taxes_and_markup() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let config = test_config();
+        let spot_prices = hourly_spot_prices(now, &[0.1, 0.1]);
+
+        // act
+        let price_per_hour = get_average_effective_price_per_hour(&config, &spot_prices).unwrap();
+
+        assert!((price_per_hour - config.effective_price(&spot_prices[0])).abs() < 1e-9);
+        assert!(price_per_hour > 0.1);
+    }
+
+    #[test]
+    fn get_cheapest_contiguous_spot_prices_returns_cheapest_window_covering_duration() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.3, 0.1, 0.2, 0.05, 0.4]);
+
+        // act
+        let cheapest_spot_prices =
+            get_cheapest_contiguous_spot_prices(&spot_prices, &test_config(), 9000, now);
+
+        assert_eq!(cheapest_spot_prices.len(), 3);
+        assert_eq!(
+            cheapest_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_cheapest_contiguous_spot_prices_skips_windows_with_gaps_or_in_the_past() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut spot_prices = hourly_spot_prices(now - Duration::hours(1), &[0.0, 0.0]);
+        spot_prices.extend(hourly_spot_prices(now + Duration::hours(2), &[0.3, 0.3]));
+
+        // act
+        let cheapest_spot_prices =
+            get_cheapest_contiguous_spot_prices(&spot_prices, &test_config(), 7200, now);
+
+        assert_eq!(cheapest_spot_prices.len(), 2);
+        assert_eq!(
+            cheapest_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 21, 14, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_earliest_contiguous_spot_prices_returns_first_window_covering_duration_regardless_of_price(
+    ) {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.9, 0.8, 0.1, 0.05]);
+
+        // act
+        let earliest_spot_prices = get_earliest_contiguous_spot_prices(&spot_prices, 7200, now);
+
+        assert_eq!(earliest_spot_prices.len(), 2);
+        assert_eq!(earliest_spot_prices[0].from, now);
+    }
+
+    #[test]
+    fn filter_spot_prices_on_weekdays_keeps_weekend_of_a_week_in_heatpump_time_zone() {
+        // monday 00:00 in amsterdam till the next monday
+        let spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 17, 22, 0, 0).unwrap(),
+            &[0.2; 7 * 24],
+        );
+
+        // act
+        let weekend_spot_prices = filter_spot_prices_on_weekdays(
+            &spot_prices,
+            &[Weekday::Sat, Weekday::Sun],
+            &Tz::Europe__Amsterdam,
+        );
+
+        assert_eq!(weekend_spot_prices.len(), 48);
+        assert_eq!(
+            weekend_spot_prices.first().unwrap().from,
+            Utc.with_ymd_and_hms(2022, 4, 22, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            weekend_spot_prices.last().unwrap().till,
+            Utc.with_ymd_and_hms(2022, 4, 24, 22, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_price_percentile_returns_nearest_rank() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.30, 0.10, 0.20, 0.40]);
+        let config = test_config();
+
+        // act
+        let percentile = get_price_percentile(&spot_prices, &config, 50.0);
+
+        assert_eq!(percentile, Some(config.effective_price(&spot_prices[2])));
+        assert_eq!(get_price_percentile(&[], &config, 50.0), None);
+    }
+
+    #[test]
+    fn get_price_percentile_does_not_panic_on_nan_price() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.30, f64::NAN, 0.10, 0.20]);
+        let config = Config {
+            price_components: vec![PriceComponent::MarketPrice],
+            ..test_config()
+        };
+
+        // act
+        let percentile = get_price_percentile(&spot_prices, &config, 50.0);
+
+        assert_eq!(percentile, Some(0.20));
+    }
+
+    #[test]
+    fn cap_spot_prices_to_duration_selects_cheapest_consecutive_slots_within_maximum() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.10, 0.09, 0.08, 0.05, 0.06, 0.09]);
+
+        // act
+        let capped_spot_prices = cap_spot_prices_to_duration(&spot_prices, &test_config(), 7200);
+
+        assert_eq!(capped_spot_prices.len(), 2);
+        assert_eq!(capped_spot_prices[0].from, now + Duration::hours(3));
+        assert_eq!(capped_spot_prices[1].from, now + Duration::hours(4));
+    }
+
+    #[test]
+    fn cap_spot_prices_to_duration_selects_cheapest_consecutive_quarter_hour_slots() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices =
+            quarter_hourly_spot_prices(now, &[0.10, 0.09, 0.08, 0.05, 0.06, 0.04, 0.07, 0.09]);
+
+        // act
+        let capped_spot_prices = cap_spot_prices_to_duration(&spot_prices, &test_config(), 3600);
+
+        assert_eq!(capped_spot_prices.len(), 4);
+        assert_eq!(capped_spot_prices[0].from, now + Duration::minutes(45));
+        assert_eq!(capped_spot_prices[3].till, now + Duration::minutes(105));
+    }
+
+    #[test]
+    fn get_cheapest_contiguous_spot_prices_compares_windows_of_different_slot_lengths() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut spot_prices = hourly_spot_prices(now, &[0.10]);
+        spot_prices.extend(quarter_hourly_spot_prices(
+            now + Duration::hours(1),
+            &[0.05; 4],
+        ));
+        let config = Config {
+            price_components: vec![PriceComponent::MarketPrice],
+            ..test_config()
+        };
+
+        // act
+        let cheapest_spot_prices =
+            get_cheapest_contiguous_spot_prices(&spot_prices, &config, 3600, now);
+
+        assert_eq!(cheapest_spot_prices.len(), 4);
+        assert_eq!(cheapest_spot_prices[0].from, now + Duration::hours(1));
+    }
+
+    #[test]
+    fn cap_spot_prices_to_duration_keeps_window_within_maximum() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.10, 0.09]);
+
+        // act
+        let capped_spot_prices = cap_spot_prices_to_duration(&spot_prices, &test_config(), 7200);
+
+        assert_eq!(capped_spot_prices.len(), 2);
+        assert_eq!(capped_spot_prices[0].from, now);
+    }
+
+    #[test]
+    fn filter_spot_prices_above_price_keeps_only_slots_below_maximum() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.30, 0.20, 0.45, 0.10]);
+        let config = test_config();
+        let max_acceptable_price = config.effective_price(&spot_prices[0]);
+
+        // act
+        let acceptable_spot_prices =
+            filter_spot_prices_above_price(&spot_prices, &config, max_acceptable_price);
+
+        assert_eq!(acceptable_spot_prices.len(), 2);
+        assert_eq!(acceptable_spot_prices[0].from, now + Duration::hours(1));
+        assert_eq!(acceptable_spot_prices[1].from, now + Duration::hours(3));
+    }
+
+    #[test]
+    fn filter_spot_prices_above_price_returns_empty_if_all_slots_are_too_expensive() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.30, 0.20]);
+
+        // act
+        let acceptable_spot_prices =
+            filter_spot_prices_above_price(&spot_prices, &test_config(), 0.0);
+
+        assert!(acceptable_spot_prices.is_empty());
+    }
+
+    #[test]
+    fn get_planned_cost_spreads_load_profile_sections_over_spot_prices() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.1, 0.2, 0.3]);
+        let load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 2000.0,
+                },
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 8000.0,
+                },
+            ],
+        };
+
+        // act
+        let planned_cost = get_planned_cost(&spot_prices, &load_profile, &test_config());
+
+        assert!((planned_cost - 2.8485664).abs() < 0.0000001);
+    }
+
+    #[test]
+    fn get_planned_cost_returns_zero_for_empty_spot_prices() {
+        // act
+        let planned_cost = get_planned_cost(&[], &test_config().load_profile, &test_config());
+
+        assert_eq!(planned_cost, 0.0);
+    }
+
+    #[test]
+    fn get_total_price_only_sums_configured_price_components() {
+        let spot_prices = price_components_spot_prices();
+
+        // act
+        let total_price = get_total_price(
+            &spot_prices,
+            &Config {
+                price_components: vec![PriceComponent::MarketPrice],
+                ..test_config()
+            },
+        );
+
+        assert!((total_price - 0.3).abs() < 0.0000001);
+    }
+
+    #[test]
+    fn get_total_price_weighs_quarter_hour_slots_by_their_length() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let config = Config {
+            price_components: vec![PriceComponent::MarketPrice],
+            ..test_config()
+        };
+
+        // act
+        let total_price = get_total_price(&quarter_hourly_spot_prices(now, &[0.2; 8]), &config);
+
+        assert!(
+            (total_price - get_total_price(&hourly_spot_prices(now, &[0.2; 2]), &config)).abs()
+                < 0.0000001
+        );
+        assert!((total_price - 0.4).abs() < 0.0000001);
+    }
+
+    #[test]
+    fn get_total_price_applies_price_component_multipliers() {
+        let spot_prices = price_components_spot_prices();
+
+        // act
+        let total_price = get_total_price(
+            &spot_prices,
+            &Config {
+                price_components: vec![PriceComponent::MarketPrice, PriceComponent::EnergyTaxPrice],
+                price_component_multipliers: vec![
+                    (PriceComponent::MarketPrice, 1.21),
+                    (PriceComponent::EnergyTaxPrice, 0.0),
+                ]
+                .into_iter()
+                .collect(),
+                ..test_config()
+            },
+        );
+
+        assert!((total_price - 0.363).abs() < 0.0000001);
+    }
+
+    #[test]
+    fn are_spot_prices_stale_returns_true_if_newest_spot_price_ended_before_max_age() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now - Duration::hours(30), &[0.1, 0.2]);
+
+        // act
+        let stale = are_spot_prices_stale(&spot_prices, 24, now);
+
+        assert!(stale);
+    }
+
+    #[test]
+    fn are_spot_prices_stale_returns_false_if_newest_spot_price_is_within_max_age() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now - Duration::hours(30), &[0.1, 0.2, 0.3])
+            .into_iter()
+            .chain(hourly_spot_prices(now, &[0.4]))
+            .collect::<Vec<SpotPrice>>();
+
+        // act
+        let stale = are_spot_prices_stale(&spot_prices, 24, now);
+
+        assert!(!stale);
+    }
+
+    #[test]
+    fn remove_tap_water_overlap_from_blocking_shrinks_overlapping_blocks() {
+        let worst_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            &[0.4, 0.5, 0.4],
+        );
+        let tap_water_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 4, 15, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 5, 30, 0).unwrap(),
+        )];
+
+        // act
+        let remaining_spot_prices =
+            remove_tap_water_overlap_from_blocking(&worst_spot_prices, &tap_water_spot_prices, 0);
+
+        assert_eq!(
+            remaining_spot_prices
+                .iter()
+                .map(|sp| (
+                    sp.from.format("%H:%M").to_string(),
+                    sp.till.format("%H:%M").to_string()
+                ))
+                .collect::<Vec<(String, String)>>(),
+            vec![
+                ("03:00".to_string(), "04:00".to_string()),
+                ("04:00".to_string(), "04:15".to_string()),
+                ("05:30".to_string(), "06:00".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_tap_water_overlap_from_blocking_keeps_gap_around_tap_water_window() {
+        let worst_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            &[0.4, 0.5],
+        );
+        let tap_water_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 5, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 6, 0, 0).unwrap(),
+        )];
+
+        // act
+        let remaining_spot_prices =
+            remove_tap_water_overlap_from_blocking(&worst_spot_prices, &tap_water_spot_prices, 30);
+
+        assert_eq!(
+            remaining_spot_prices
+                .iter()
+                .map(|sp| (
+                    sp.from.format("%H:%M").to_string(),
+                    sp.till.format("%H:%M").to_string()
+                ))
+                .collect::<Vec<(String, String)>>(),
+            vec![
+                ("03:00".to_string(), "04:00".to_string()),
+                ("04:00".to_string(), "04:30".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_tap_water_overlap_from_blocking_drops_blocks_within_tap_water_window() {
+        let worst_spot_prices =
+            hourly_spot_prices(Utc.with_ymd_and_hms(2022, 4, 21, 4, 0, 0).unwrap(), &[0.4]);
+        let tap_water_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            &[0.1, 0.1, 0.1],
+        );
+
+        // act
+        let remaining_spot_prices =
+            remove_tap_water_overlap_from_blocking(&worst_spot_prices, &tap_water_spot_prices, 0);
+
+        assert!(remaining_spot_prices.is_empty());
+    }
+
+    #[test]
+    fn get_running_spot_prices_keeps_block_running_at_midday_rerun() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut planned_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            &[0.1, 0.2],
+        );
+        planned_spot_prices.extend(hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 11, 0, 0).unwrap(),
+            &[0.1, 0.2],
+        ));
+        planned_spot_prices.extend(hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 18, 0, 0).unwrap(),
+            &[0.1],
+        ));
+
+        // act
+        let running_spot_prices = get_running_spot_prices(&planned_spot_prices, now);
+
+        assert_eq!(
+            running_spot_prices
+                .iter()
+                .map(|sp| sp.from.hour())
+                .collect::<Vec<u32>>(),
+            vec![11, 12]
+        );
+    }
+
+    #[test]
+    fn get_running_spot_prices_returns_nothing_if_rerun_between_blocks() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let planned_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            &[0.1, 0.2],
+        );
+
+        // act
+        let running_spot_prices = get_running_spot_prices(&planned_spot_prices, now);
+
+        assert!(running_spot_prices.is_empty());
+    }
+
+    #[test]
+    fn merge_running_spot_prices_keeps_running_block_of_midday_rerun() {
+        let running_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 11, 0, 0).unwrap(),
+            &[0.1, 0.2],
+        );
+        let mut planned_spot_prices = hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap(),
+            &[0.3, 0.4],
+        );
+        planned_spot_prices.extend(hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 18, 0, 0).unwrap(),
+            &[0.5],
+        ));
+
+        // act
+        let merged_spot_prices =
+            merge_running_spot_prices(&running_spot_prices, &planned_spot_prices);
+
+        assert_eq!(
+            merged_spot_prices
+                .iter()
+                .map(|sp| (sp.from.hour(), sp.market_price))
+                .collect::<Vec<(u32, f64)>>(),
+            vec![(11, 0.1), (12, 0.2), (13, 0.4), (18, 0.5)]
+        );
+    }
+
+    #[test]
+    fn is_plan_unchanged_returns_true_if_planned_window_only_shifted_by_jitter() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut config = test_config();
+        config.jitter_max_minutes = 15;
+        let best_spot_prices = hourly_spot_prices(now, &[0.1, 0.2]);
+        let planned_spot_prices = hourly_spot_prices(now + Duration::minutes(10), &[0.1, 0.2]);
+
+        // act
+        let unchanged = is_plan_unchanged(&planned_spot_prices, &best_spot_prices, &config);
+
+        assert!(unchanged);
+    }
+
+    #[test]
+    fn is_plan_unchanged_returns_false_if_planned_window_moved() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut config = test_config();
+        config.jitter_max_minutes = 15;
+        let best_spot_prices = hourly_spot_prices(now, &[0.1, 0.2]);
+        let planned_spot_prices = hourly_spot_prices(now + Duration::hours(2), &[0.1, 0.2]);
+
+        // act
+        let unchanged = is_plan_unchanged(&planned_spot_prices, &best_spot_prices, &config);
+
+        assert!(!unchanged);
+    }
+
+    #[test]
+    fn round_spot_prices_to_granularity_rounds_to_nearest_multiple() {
+        let spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 14, 37, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 15, 38, 0).unwrap(),
+        )];
+
+        // act
+        let rounded_spot_prices = round_spot_prices_to_granularity(&spot_prices, 5).unwrap();
+
+        assert_eq!(
+            rounded_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 21, 14, 35, 0).unwrap()
+        );
+        assert_eq!(
+            rounded_spot_prices[0].till,
+            Utc.with_ymd_and_hms(2022, 4, 21, 15, 40, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_spot_prices_to_granularity_returns_error_if_granularity_does_not_divide_an_hour() {
+        // act
+        let result = round_spot_prices_to_granularity(&[], 7);
+
+        assert!(result.is_err());
+    }
+}
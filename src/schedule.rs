@@ -0,0 +1,683 @@
+use crate::model::{ContentItem, Item, TimeRange, WeeklySchedule};
+use crate::prices::get_contiguous_blocks;
+use chrono::{prelude::*, Duration, Utc};
+use chrono_tz::Tz;
+use jarvis_lib::model::SpotPrice;
+use tracing::{info, warn};
+
+pub(crate) fn get_tap_water_schedule_raw_values(
+    heatpump_time_zone: &Tz,
+    best_spot_prices: &[SpotPrice],
+    number_of_items: usize,
+) -> Vec<u32> {
+    let mut raw_values = vec![0; number_of_items];
+
+    if !best_spot_prices.is_empty() && number_of_items > 0 {
+        // get start time from first spot price and finish time from last spot price
+        let (from_time, till_time) = get_local_time_range(
+            heatpump_time_zone,
+            best_spot_prices.first().unwrap().from,
+            best_spot_prices.last().unwrap().till,
+        );
+
+        let from_hour = from_time.hour();
+        let from_minute = from_time.minute();
+        let till_hour = till_time.hour();
+        let till_minute = till_time.minute();
+
+        if crosses_midnight(&from_time, &till_time) {
+            // starts before midnight, finishes after
+            info!(
+                "Setting 1) to block {}:{:0>2} - {}:{:0>2}",
+                till_hour, till_minute, from_hour, from_minute
+            );
+            raw_values[0] = TimeRange::new(till_time.time(), from_time.time()).to_raw();
+        } else {
+            // start and finish on same day
+            let mut block_before = from_hour > 0 || from_minute > 0;
+            let mut block_after = till_hour > 0 || till_minute > 0;
+
+            if block_before && block_after && number_of_items == 1 {
+                // the blocks on either side of midnight need an item each, so keep the longest one
+                let minutes_before = 60 * from_hour + from_minute;
+                let minutes_after = 24 * 60 - (60 * till_hour + till_minute);
+                warn!(
+                    "A single schedule item can't block both 00:00 - {}:{:0>2} and {}:{:0>2} - 00:00, only blocking the longest",
+                    from_hour, from_minute, till_hour, till_minute
+                );
+                block_before = minutes_before > minutes_after;
+                block_after = !block_before;
+            }
+
+            if block_before {
+                info!(
+                    "Setting 1) to block 00:00 - {}:{:0>2}",
+                    from_hour, from_minute
+                );
+                raw_values[0] = TimeRange::new(NaiveTime::MIN, from_time.time()).to_raw();
+            }
+
+            if block_after {
+                info!(
+                    "Setting {}) to block {}:{:0>2} - 00:00",
+                    number_of_items, till_hour, till_minute
+                );
+                raw_values[number_of_items - 1] =
+                    TimeRange::new(till_time.time(), NaiveTime::MIN).to_raw();
+            }
+        }
+    }
+
+    raw_values
+}
+
+pub(crate) fn get_heating_schedule_raw_values(
+    heatpump_time_zone: &Tz,
+    worst_spot_prices: &[SpotPrice],
+    number_of_items: usize,
+) -> Vec<u32> {
+    let mut raw_values = vec![0; number_of_items];
+
+    if worst_spot_prices.is_empty() || number_of_items == 0 {
+        return raw_values;
+    }
+
+    // blocks starting at midnight are written from the last item backwards, all others from the first item onwards
+    let mut first_free_item = 0;
+    let mut last_free_item = number_of_items;
+
+    for block in get_contiguous_blocks(worst_spot_prices) {
+        // get start time from first spot price and finish time from last spot price in the run
+        let (from_time, till_time) = get_local_time_range(
+            heatpump_time_zone,
+            block.first().unwrap().from,
+            block.last().unwrap().till,
+        );
+
+        let from_hour = from_time.hour();
+        let from_minute = from_time.minute();
+        let till_hour = till_time.hour();
+        let till_minute = till_time.minute();
+
+        let mut block_values = vec![];
+        let mut after_midnight_value = None;
+
+        if crosses_midnight(&from_time, &till_time) {
+            // starts before midnight, finishes after
+            if from_hour > 0 || from_minute > 0 {
+                block_values.push(TimeRange::new(from_time.time(), NaiveTime::MIN));
+            }
+
+            if till_hour > 0 || till_minute > 0 {
+                after_midnight_value = Some(TimeRange::new(NaiveTime::MIN, till_time.time()));
+            }
+
+            if !block_values.is_empty() && after_midnight_value.is_some() && number_of_items == 1 {
+                // the parts on either side of midnight need an item each, so keep the longest one
+                warn!(
+                    "A single schedule item can't block {} - {} across midnight, only blocking the longest part",
+                    from_time.format("%H:%M"),
+                    till_time.format("%H:%M")
+                );
+                if 24 * 60 - (60 * from_hour + from_minute) >= 60 * till_hour + till_minute {
+                    after_midnight_value = None;
+                } else {
+                    block_values.clear();
+                }
+            }
+        } else {
+            // start and finish on same day
+            block_values.push(TimeRange::new(from_time.time(), till_time.time()));
+        }
+
+        let needed_items = block_values.len() + after_midnight_value.iter().len();
+        if last_free_item - first_free_item < needed_items {
+            warn!(
+                "Not enough schedule items left to block {} - {}, skipping it",
+                from_time.format("%H:%M"),
+                till_time.format("%H:%M")
+            );
+            continue;
+        }
+
+        for time_range in block_values {
+            info!("Setting {}) to block {}", first_free_item + 1, time_range);
+            raw_values[first_free_item] = time_range.to_raw();
+            first_free_item += 1;
+        }
+
+        if let Some(time_range) = after_midnight_value {
+            last_free_item -= 1;
+            info!("Setting {}) to block {}", last_free_item + 1, time_range);
+            raw_values[last_free_item] = time_range.to_raw();
+        }
+    }
+
+    raw_values
+}
+
+pub(crate) fn get_fixed_offset_spot_prices(
+    spot_prices: &[SpotPrice],
+    heatpump_time_zone: &Tz,
+    at: DateTime<Utc>,
+) -> Vec<SpotPrice> {
+    let offset = Duration::seconds(
+        at.with_timezone(heatpump_time_zone)
+            .offset()
+            .fix()
+            .local_minus_utc() as i64,
+    );
+
+    spot_prices
+        .iter()
+        .map(|sp| SpotPrice {
+            from: sp.from + offset,
+            till: sp.till + offset,
+            ..sp.clone()
+        })
+        .collect()
+}
+
+// converts a window to the wall clock of the heatpump, which repeats an hour when summer time ends; a window
+// overlapping that hour is widened to cover it as a whole, because the wall clock can't tell both passes apart
+fn get_local_time_range(
+    time_zone: &Tz,
+    from: DateTime<Utc>,
+    till: DateTime<Utc>,
+) -> (NaiveDateTime, NaiveDateTime) {
+    let from_local = from.with_timezone(time_zone).naive_local();
+    let till_local = till.with_timezone(time_zone).naive_local();
+
+    let get_offset_seconds =
+        |at: DateTime<Utc>| at.with_timezone(time_zone).offset().fix().local_minus_utc();
+    let from_offset_seconds = get_offset_seconds(from);
+    let till_offset_seconds = get_offset_seconds(till);
+
+    if till_offset_seconds >= from_offset_seconds {
+        return (from_local, till_local);
+    }
+
+    // find the moment the clock is set back
+    let mut before = from;
+    let mut after = till;
+    while after - before > Duration::seconds(1) {
+        let middle = before + (after - before) / 2;
+        if get_offset_seconds(middle) == from_offset_seconds {
+            before = middle;
+        } else {
+            after = middle;
+        }
+    }
+
+    let repeated_from = after.naive_utc() + Duration::seconds(till_offset_seconds as i64);
+    let repeated_till = after.naive_utc() + Duration::seconds(from_offset_seconds as i64);
+
+    (from_local.min(repeated_from), till_local.max(repeated_till))
+}
+
+fn crosses_midnight(from_time: &NaiveDateTime, till_time: &NaiveDateTime) -> bool {
+    // a window ending exactly at midnight still fits in the day it started
+    till_time.date() > from_time.date() && (till_time.hour() > 0 || till_time.minute() > 0)
+}
+
+pub(crate) fn get_externally_changed_items<'a>(
+    content: &'a ContentItem,
+    written_schedule: &[(String, i64)],
+) -> Vec<(&'a Item, i64)> {
+    content
+        .item
+        .iter()
+        .filter_map(|item| {
+            let (_, written_raw_value) = written_schedule.iter().find(|(id, _)| id == &item.id)?;
+            if item.raw.parse::<i64>().ok() == Some(*written_raw_value) {
+                return None;
+            }
+            Some((item, *written_raw_value))
+        })
+        .collect()
+}
+
+pub(crate) fn merge_written_schedule(
+    written_schedule: &[(String, i64)],
+    updated_schedule: Vec<(String, i64)>,
+) -> Vec<(String, i64)> {
+    let mut merged_schedule: Vec<(String, i64)> = written_schedule
+        .iter()
+        .filter(|(id, _)| {
+            !updated_schedule
+                .iter()
+                .any(|(updated_id, _)| updated_id == id)
+        })
+        .cloned()
+        .collect();
+    merged_schedule.extend(updated_schedule);
+
+    merged_schedule
+}
+
+pub(crate) fn schedule_needs_update(content: &ContentItem, raw_values: &[u32]) -> bool {
+    // a schedule that can't be decoded is rewritten as a whole
+    match WeeklySchedule::from_content(content) {
+        Ok(current_schedule) => current_schedule.raw_values() != raw_values,
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Content;
+    use quick_xml::de::from_str;
+
+    const TAP_WATER_SCHEDULE_RESPONSE: &str = "<Content><item><name>Maandag - Zondag</name><item id='0xa57344'><value>00:00 - 03:00</value><name>1)</name><type>timer</type><raw>11796480</raw></item><item id='0xa53c8c'><value>00:00 - 00:00</value><name>2)</name><type>timer</type><raw>0</raw></item><item id='0xa47ee4'><value>00:00 - 00:00</value><name>3)</name><type>timer</type><raw>0</raw></item><item id='0xa6630c'><value>00:00 - 00:00</value><name>4)</name><type>timer</type><raw>0</raw></item><item id='0xa68d74'><value>10:00 - 00:00</value><name>5)</name><type>timer</type><raw>600</raw></item></item></Content>";
+
+    fn spot_price(from: DateTime<Utc>, till: DateTime<Utc>) -> SpotPrice {
+        SpotPrice {
+            id: None,
+            source: None,
+            from,
+            till,
+            market_price: 0.157,
+            market_price_tax: 0.0330708,
+            sourcing_markup_price: 0.017,
+            energy_tax_price: 0.081,
+        }
+    }
+
+    fn quarter_hourly_spot_prices(from: DateTime<Utc>, market_prices: &[f64]) -> Vec<SpotPrice> {
+        market_prices
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| SpotPrice {
+                market_price: *market_price,
+                ..spot_price(
+                    from + Duration::minutes(15 * i as i64),
+                    from + Duration::minutes(15 * (i as i64 + 1)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_blocks_before_and_after_window_on_same_day() {
+        let best_spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 4, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 4, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let raw_values = get_tap_water_schedule_raw_values(&Tz::UTC, &best_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![11796480, 0, 0, 0, 600]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_blocks_around_window_of_quarter_hour_slots() {
+        let best_spot_prices = quarter_hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 15, 0).unwrap(),
+            &[0.1; 6],
+        );
+
+        // act
+        let raw_values = get_tap_water_schedule_raw_values(&Tz::UTC, &best_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![65536 * 195, 0, 0, 0, 285]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_blocks_until_start_of_window_starting_within_first_hour() {
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 30, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 2, 30, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_tap_water_schedule_raw_values(&Tz::UTC, &best_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![65536 * 30, 0, 0, 0, 150]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_blocks_after_end_of_window_ending_within_first_hour() {
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 45, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_tap_water_schedule_raw_values(&Tz::UTC, &best_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![0, 0, 0, 0, 45]);
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_blocks_window_ending_within_first_hour_after_midnight() {
+        let worst_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 23, 30, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 22, 0, 30, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![1410, 0, 0, 0, 65536 * 30]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_covers_repeated_hour_when_summer_time_ends() {
+        // 02:00 CEST - 02:00 CET on the night the clocks go back in Amsterdam
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 10, 30, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 10, 30, 1, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values =
+            get_tap_water_schedule_raw_values(&Tz::Europe__Amsterdam, &best_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![65536 * 120, 0, 0, 0, 180]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_skips_missing_hour_when_summer_time_starts() {
+        // 01:00 CET - 04:00 CEST on the night the clocks go forward in Amsterdam
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 3, 27, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 3, 27, 2, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values =
+            get_tap_water_schedule_raw_values(&Tz::Europe__Amsterdam, &best_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![65536 * 60, 0, 0, 0, 240]);
+    }
+
+    #[test]
+    fn get_fixed_offset_spot_prices_keeps_offset_of_snapshot_when_summer_time_ends() {
+        // 02:00 - 03:00 CET on the night the clocks go back in Amsterdam, with the offset snapped the day before
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 10, 30, 1, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 10, 30, 2, 0, 0).unwrap(),
+        )];
+
+        // act
+        let fixed_offset_spot_prices = get_fixed_offset_spot_prices(
+            &best_spot_prices,
+            &Tz::Europe__Amsterdam,
+            Utc.with_ymd_and_hms(2022, 10, 29, 12, 0, 0).unwrap(),
+        );
+
+        assert_eq!(
+            get_tap_water_schedule_raw_values(&Tz::UTC, &fixed_offset_spot_prices, 5),
+            vec![65536 * 180, 0, 0, 0, 240]
+        );
+        assert_eq!(
+            get_tap_water_schedule_raw_values(&Tz::Europe__Amsterdam, &best_spot_prices, 5),
+            vec![65536 * 120, 0, 0, 0, 180]
+        );
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_covers_repeated_hour_when_summer_time_ends() {
+        // 02:45 CEST - 02:15 CET on the night the clocks go back in Amsterdam
+        let worst_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 10, 30, 0, 45, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 10, 30, 1, 15, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values =
+            get_heating_schedule_raw_values(&Tz::Europe__Amsterdam, &worst_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![120 + 65536 * 180, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_blocks_window_crossing_midnight_when_summer_time_ends() {
+        // 23:00 CEST - 03:00 CET on the night the clocks go back in Amsterdam
+        let worst_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 10, 29, 21, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 10, 30, 2, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values =
+            get_heating_schedule_raw_values(&Tz::Europe__Amsterdam, &worst_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![1380, 0, 0, 0, 65536 * 180]);
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_blocks_each_contiguous_run_in_its_own_item() {
+        let worst_spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 7, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 8, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 8, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 9, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 17, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 19, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 5);
+
+        assert_eq!(
+            raw_values,
+            vec![420 + 65536 * 540, 1020 + 65536 * 1140, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_blocks_each_run_of_quarter_hour_slots() {
+        let mut worst_spot_prices = quarter_hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 7, 15, 0).unwrap(),
+            &[0.4; 2],
+        );
+        worst_spot_prices.extend(quarter_hourly_spot_prices(
+            Utc.with_ymd_and_hms(2022, 4, 21, 17, 45, 0).unwrap(),
+            &[0.4; 2],
+        ));
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 5);
+
+        assert_eq!(
+            raw_values,
+            vec![435 + 65536 * 465, 1065 + 65536 * 1095, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_splits_run_crossing_midnight_next_to_other_runs() {
+        let worst_spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 7, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 9, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 5);
+
+        assert_eq!(raw_values, vec![420 + 65536 * 540, 1320, 0, 0, 65536 * 120]);
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_skips_runs_that_do_not_fit_in_remaining_items() {
+        let worst_spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 7, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 8, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 1, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 2);
+
+        assert_eq!(raw_values, vec![420 + 65536 * 480, 0]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_blocks_first_and_last_of_three_items() {
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_tap_water_schedule_raw_values(&Tz::UTC, &best_spot_prices, 3);
+
+        assert_eq!(raw_values, vec![11796480, 0, 600]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_blocks_longest_part_in_single_item() {
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_tap_water_schedule_raw_values(&Tz::UTC, &best_spot_prices, 1);
+
+        assert_eq!(raw_values, vec![600]);
+    }
+
+    #[test]
+    fn get_tap_water_schedule_raw_values_blocks_outside_window_crossing_midnight_in_single_item() {
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_tap_water_schedule_raw_values(&Tz::UTC, &best_spot_prices, 1);
+
+        assert_eq!(raw_values, vec![120 + 65536 * 1320]);
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_splits_run_crossing_midnight_in_three_items() {
+        let worst_spot_prices = vec![
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 7, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 9, 0, 0).unwrap(),
+            ),
+            spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
+            ),
+        ];
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 3);
+
+        assert_eq!(raw_values, vec![420 + 65536 * 540, 1320, 65536 * 120]);
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_blocks_same_day_window_in_single_item() {
+        let worst_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 7, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 9, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 1);
+
+        assert_eq!(raw_values, vec![420 + 65536 * 540]);
+    }
+
+    #[test]
+    fn get_heating_schedule_raw_values_blocks_longest_part_of_run_crossing_midnight_in_single_item()
+    {
+        let worst_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 22, 1, 0, 0).unwrap(),
+        )];
+
+        // act
+        let raw_values = get_heating_schedule_raw_values(&Tz::UTC, &worst_spot_prices, 1);
+
+        assert_eq!(raw_values, vec![1320]);
+    }
+
+    #[test]
+    fn schedule_needs_update_returns_false_if_raw_values_match_programmed_schedule() {
+        let content: Content = from_str(TAP_WATER_SCHEDULE_RESPONSE).unwrap();
+
+        // act
+        let needs_update = schedule_needs_update(&content.segments[0], &[11796480, 0, 0, 0, 600]);
+
+        assert!(!needs_update);
+    }
+
+    #[test]
+    fn schedule_needs_update_returns_true_if_raw_values_differ_from_programmed_schedule() {
+        let content: Content = from_str(TAP_WATER_SCHEDULE_RESPONSE).unwrap();
+
+        // act
+        let needs_update = schedule_needs_update(&content.segments[0], &[11796480, 0, 0, 0, 660]);
+
+        assert!(needs_update);
+    }
+
+    #[test]
+    fn get_externally_changed_items_returns_items_differing_from_written_schedule() {
+        let content: Content = from_str(TAP_WATER_SCHEDULE_RESPONSE).unwrap();
+
+        // act
+        let changed_items = get_externally_changed_items(
+            &content.segments[0],
+            &[
+                ("0xa57344".to_string(), 11796480),
+                ("0xa68d74".to_string(), 660),
+            ],
+        );
+
+        assert_eq!(changed_items.len(), 1);
+        assert_eq!(changed_items[0].0.id, "0xa68d74".to_string());
+        assert_eq!(changed_items[0].1, 660);
+    }
+
+    #[test]
+    fn merge_written_schedule_replaces_items_that_were_written_again() {
+        // act
+        let written_schedule = merge_written_schedule(
+            &[
+                ("0xa57344".to_string(), 11796480),
+                ("0xa45e14".to_string(), 1080),
+            ],
+            vec![("0xa57344".to_string(), 600)],
+        );
+
+        assert_eq!(
+            written_schedule,
+            vec![
+                ("0xa45e14".to_string(), 1080),
+                ("0xa57344".to_string(), 600),
+            ]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use rand::Rng;
+use std::env;
+use std::error::Error;
+use std::future::Future;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::{interval, sleep, Duration, MissedTickBehavior};
+use tracing::{debug, error, info};
+
+pub struct SchedulerConfig {
+    run_interval_minutes: u64,
+    start_jitter_max_seconds: u64,
+}
+
+impl SchedulerConfig {
+    pub fn new(
+        run_interval_minutes: u64,
+        start_jitter_max_seconds: u64,
+    ) -> Result<Self, Box<dyn Error>> {
+        debug!(
+            "SchedulerConfig::new(run_interval_minutes: {}, start_jitter_max_seconds: {})",
+            run_interval_minutes, start_jitter_max_seconds
+        );
+
+        if run_interval_minutes == 0 {
+            return Err(Box::<dyn Error>::from(
+                "RUN_INTERVAL_MINUTES should be larger than 0",
+            ));
+        }
+
+        Ok(Self {
+            run_interval_minutes,
+            start_jitter_max_seconds,
+        })
+    }
+
+    pub fn from_env() -> Result<Option<Self>, Box<dyn Error>> {
+        let run_interval_minutes: u64 = match env::var("RUN_INTERVAL_MINUTES") {
+            Ok(minutes) => minutes.parse()?,
+            Err(_) => return Ok(None),
+        };
+        let start_jitter_max_seconds: u64 = env::var("RUN_START_JITTER_MAX_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?;
+
+        Ok(Some(Self::new(
+            run_interval_minutes,
+            start_jitter_max_seconds,
+        )?))
+    }
+}
+
+pub struct Scheduler {
+    config: SchedulerConfig,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn run_interval_minutes(&self) -> u64 {
+        self.config.run_interval_minutes
+    }
+
+    pub fn from_env() -> Result<Option<Self>, Box<dyn Error>> {
+        Ok(SchedulerConfig::from_env()?.map(Self::new))
+    }
+
+    pub async fn run<F, Fut>(&self, mut run: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(), Box<dyn Error>>>,
+    {
+        let mut sigterm = signal(SignalKind::terminate())?;
+
+        // add some jitter to the first run to prevent all planners that restart at the same time to hit the heatpump and spot price source together
+        let start_jitter_seconds = if self.config.start_jitter_max_seconds > 0 {
+            rand::thread_rng().gen_range(0..self.config.start_jitter_max_seconds)
+        } else {
+            0
+        };
+        info!(
+            "Running planner every {} minutes, starting in {} seconds",
+            self.config.run_interval_minutes, start_jitter_seconds
+        );
+
+        tokio::select! {
+            biased;
+            _ = sigterm.recv() => {
+                info!("Received SIGTERM before first run, shutting down");
+                return Ok(());
+            }
+            _ = sleep(Duration::from_secs(start_jitter_seconds)) => {}
+        }
+
+        let mut run_interval = interval(Duration::from_secs(60 * self.config.run_interval_minutes));
+        run_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            // the interval tick is immediately ready after a run that overran it, so the SIGTERM branch goes first to not start another run
+            tokio::select! {
+                biased;
+                _ = sigterm.recv() => {
+                    info!("Received SIGTERM, shutting down");
+                    return Ok(());
+                }
+                _ = run_interval.tick() => {}
+            }
+
+            // a SIGTERM received while running is picked up by the select above once the run has finished
+            info!("Starting planner run");
+            match run().await {
+                Ok(()) => info!("Finished planner run"),
+                Err(e) => error!("Planner run failed: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::error::Error;
+use std::future::Future;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::{timeout, Duration};
+use tracing::{debug, info, warn};
+
+pub struct GracefulShutdownConfig {
+    grace_period_seconds: u64,
+}
+
+impl GracefulShutdownConfig {
+    pub fn new(grace_period_seconds: u64) -> Result<Self, Box<dyn Error>> {
+        debug!(
+            "GracefulShutdownConfig::new(grace_period_seconds: {})",
+            grace_period_seconds
+        );
+
+        Ok(Self {
+            grace_period_seconds,
+        })
+    }
+
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let grace_period_seconds: u64 = env::var("SHUTDOWN_GRACE_PERIOD_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?;
+
+        Self::new(grace_period_seconds)
+    }
+}
+
+pub struct GracefulShutdown {
+    config: GracefulShutdownConfig,
+}
+
+impl GracefulShutdown {
+    pub fn new(config: GracefulShutdownConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(GracefulShutdownConfig::from_env()?))
+    }
+
+    pub async fn run<Fut>(&self, run: Fut) -> Result<(), Box<dyn Error>>
+    where
+        Fut: Future<Output = Result<(), Box<dyn Error>>>,
+    {
+        // handling SIGTERM ourselves keeps the process from being killed halfway writing a schedule
+        let mut sigterm = signal(SignalKind::terminate())?;
+
+        tokio::pin!(run);
+
+        tokio::select! {
+            result = &mut run => return result,
+            _ = sigterm.recv() => {}
+        }
+
+        info!(
+            "Received SIGTERM while planning, waiting up to {} seconds for the plan to finish",
+            self.config.grace_period_seconds
+        );
+
+        match timeout(Duration::from_secs(self.config.grace_period_seconds), run).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    "Plan did not finish within grace period of {} seconds, shutting down",
+                    self.config.grace_period_seconds
+                );
+                Err(Box::<dyn Error>::from(
+                    "Plan was interrupted by SIGTERM after grace period",
+                ))
+            }
+        }
+    }
+}
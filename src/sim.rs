@@ -0,0 +1,2095 @@
+#[cfg(test)]
+use crate::health_server::HealthState;
+#[cfg(test)]
+use crate::model::{ClockProgram, PlanningOutcome};
+use crate::model::{Config, DesinfectionMode, State, TapWaterStrategy};
+use crate::prices::{get_planned_cost, split_spot_prices_per_schedule_segment};
+#[cfg(test)]
+use crate::websocket_client::WebsocketClientError;
+use crate::websocket_client::{
+    BestSpotPricesPlanner, Clock, Connection, Connector, EffectivePricePlanner, MessageReceiver,
+    MessageSender, SystemClock, WebsocketClient, WebsocketClientConfig,
+};
+#[cfg(test)]
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use jarvis_lib::model::SpotPrice;
+#[cfg(test)]
+use jarvis_lib::model::SpotPricePlanner;
+#[cfg(test)]
+use jarvis_lib::planner_client::PlannerClient;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use websocket::OwnedMessage;
+
+const EMPTY_CONTENT_RESPONSE: &str = "<Content></Content>";
+const REPLAY_STEP_HOURS: i64 = 1;
+
+#[derive(Default)]
+struct SimulatedController {
+    navigation: String,
+    temperatures_navigation_id: String,
+    temperatures: Vec<(String, f64)>,
+    operating_modes_navigation_id: String,
+    operating_modes: Vec<(String, String)>,
+    energy_counters_navigation_id: String,
+    energy_counters: Vec<(String, f64)>,
+    settings_navigation_id: String,
+    // numeric settings by item id, with their name, value and the maximum the controller clamps a new value to
+    settings: Vec<(String, String, f64, f64)>,
+    unsaved_values: HashMap<String, f64>,
+    // clock programs by navigation id, with the raw value of each of their timer items
+    schedules: HashMap<String, Vec<(String, u32)>>,
+    unsaved_raw_values: HashMap<String, u32>,
+    responses: VecDeque<String>,
+    saves: usize,
+    // every message the controller received, in order
+    #[cfg(test)]
+    messages: Vec<String>,
+}
+
+impl SimulatedController {
+    fn handle(&mut self, message: &str) {
+        #[cfg(test)]
+        self.messages.push(message.to_string());
+
+        let parts: Vec<&str> = message.split(';').collect();
+
+        let response = match parts.as_slice() {
+            ["LOGIN", _] => Some(self.navigation.clone()),
+            ["GET", navigation_id] => Some(self.get_content(navigation_id)),
+            ["SET", item_id, value] if self.is_setting(item_id) => {
+                if let (Some(item_id), Ok(value)) =
+                    (item_id.strip_prefix("set_"), value.parse::<f64>())
+                {
+                    self.unsaved_values.insert(item_id.to_string(), value);
+                }
+                None
+            }
+            ["SET", item_id, raw_value] => {
+                if let (Some(item_id), Ok(raw_value)) =
+                    (item_id.strip_prefix("set_"), raw_value.parse::<u32>())
+                {
+                    self.unsaved_raw_values
+                        .insert(item_id.to_string(), raw_value);
+                }
+                None
+            }
+            ["SAVE", _] => {
+                self.save();
+                Some(EMPTY_CONTENT_RESPONSE.to_string())
+            }
+            // the remote control menu isn't modelled, moves are only acknowledged
+            ["MOVE", _] => Some(EMPTY_CONTENT_RESPONSE.to_string()),
+            _ => None,
+        };
+
+        if let Some(response) = response {
+            self.responses.push_back(response);
+        }
+    }
+
+    fn is_setting(&self, item_id: &str) -> bool {
+        self.settings
+            .iter()
+            .any(|(id, _, _, _)| item_id.strip_prefix("set_") == Some(id.as_str()))
+    }
+
+    fn save(&mut self) {
+        for (item_id, _, value, maximum) in self.settings.iter_mut() {
+            if let Some(unsaved_value) = self.unsaved_values.remove(item_id) {
+                *value = unsaved_value.min(*maximum);
+            }
+        }
+        for items in self.schedules.values_mut() {
+            for (item_id, raw_value) in items.iter_mut() {
+                if let Some(unsaved_raw_value) = self.unsaved_raw_values.remove(item_id) {
+                    *raw_value = unsaved_raw_value;
+                }
+            }
+        }
+        self.saves += 1;
+    }
+
+    fn get_content(&self, navigation_id: &str) -> String {
+        if navigation_id == self.temperatures_navigation_id {
+            let items: String = self
+                .temperatures
+                .iter()
+                .enumerate()
+                .map(|(i, (name, value))| {
+                    format!(
+                        "<item id='0x{:06x}'><name>{}</name><value>{:.1}°C</value></item>",
+                        i, name, value
+                    )
+                })
+                .collect();
+
+            return format!("<Content>{}<name>Temperaturen</name></Content>", items);
+        }
+
+        if navigation_id == self.operating_modes_navigation_id {
+            let items: String = self
+                .operating_modes
+                .iter()
+                .enumerate()
+                .map(|(i, (name, value))| {
+                    format!(
+                        "<item id='0x{:06x}'><name>{}</name><option value='0'>Automatisch</option><option value='2'>Party</option><option value='3'>Vakantie</option><value>{}</value></item>",
+                        i, name, value
+                    )
+                })
+                .collect();
+
+            return format!("<Content>{}<name>Bedrijfsmode</name></Content>", items);
+        }
+
+        if navigation_id == self.energy_counters_navigation_id {
+            let items: String = self
+                .energy_counters
+                .iter()
+                .enumerate()
+                .map(|(i, (name, value))| {
+                    format!(
+                        "<item id='0x{:06x}'><name>{}</name><value>{:.1} kWh</value></item>",
+                        i, name, value
+                    )
+                })
+                .collect();
+
+            return format!("<Content>{}<name>Energie</name></Content>", items);
+        }
+
+        if navigation_id == self.settings_navigation_id {
+            let items: String = self
+                .settings
+                .iter()
+                .map(|(item_id, name, value, _)| {
+                    format!(
+                        "<item id='{}'><name>{}</name><value>{:.1}°C</value></item>",
+                        item_id, name, value
+                    )
+                })
+                .collect();
+
+            return format!("<Content>{}<name>Verwarming</name></Content>", items);
+        }
+
+        match self.schedules.get(navigation_id) {
+            Some(schedule) => {
+                let items: String = schedule
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (item_id, raw_value))| {
+                        format!(
+                            "<item id='{}'><value>{}</value><name>{})</name><type>timer</type><raw>{}</raw></item>",
+                            item_id,
+                            get_timer_value(*raw_value),
+                            i + 1,
+                            raw_value
+                        )
+                    })
+                    .collect();
+
+                format!(
+                    "<Content><item><name>Maandag - Zondag</name>{}</item></Content>",
+                    items
+                )
+            }
+            None => EMPTY_CONTENT_RESPONSE.to_string(),
+        }
+    }
+}
+
+fn get_timer_value(raw_value: u32) -> String {
+    // the lower 16 bits hold the start and the upper 16 bits the end, both in minutes of the day
+    let from_minutes = raw_value % 65536;
+    let till_minutes = raw_value / 65536;
+
+    format!(
+        "{:02}:{:02} - {:02}:{:02}",
+        from_minutes / 60,
+        from_minutes % 60,
+        till_minutes / 60,
+        till_minutes % 60
+    )
+}
+
+struct SimulatedConnector {
+    controller: Arc<Mutex<SimulatedController>>,
+}
+
+impl Connector for SimulatedConnector {
+    fn connect(&self) -> Result<Connection, Box<dyn Error>> {
+        Ok((
+            Box::new(SimulatedReceiver {
+                controller: self.controller.clone(),
+            }),
+            Box::new(SimulatedSender {
+                controller: self.controller.clone(),
+            }),
+        ))
+    }
+}
+
+struct SimulatedSender {
+    controller: Arc<Mutex<SimulatedController>>,
+}
+
+impl MessageSender for SimulatedSender {
+    fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>> {
+        if let OwnedMessage::Text(text) = message {
+            self.controller.lock().unwrap().handle(text);
+        }
+
+        Ok(())
+    }
+}
+
+struct SimulatedReceiver {
+    controller: Arc<Mutex<SimulatedController>>,
+}
+
+impl MessageReceiver for SimulatedReceiver {
+    fn receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+        self.controller
+            .lock()
+            .unwrap()
+            .responses
+            .pop_front()
+            .map(|response| Ok(OwnedMessage::Text(response)))
+    }
+
+    fn try_receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+        // responses are queued as soon as a message is handled, so anything left is already buffered
+        self.receive_message()
+    }
+}
+
+// always tells the same time, so a planning run behaves the same whatever day the tests run on
+struct FixedClock {
+    now: DateTime<Utc>,
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+}
+
+#[derive(Default)]
+pub struct SimulatedHeatPumpBuilder {
+    controller: SimulatedController,
+    now: Option<DateTime<Utc>>,
+}
+
+impl SimulatedHeatPumpBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn build(self, config: WebsocketClientConfig) -> SimulatedHeatPump {
+        let controller = Arc::new(Mutex::new(self.controller));
+        let clock: Box<dyn Clock> = match self.now {
+            Some(now) => Box::new(FixedClock { now }),
+            None => Box::new(SystemClock),
+        };
+
+        SimulatedHeatPump {
+            client: WebsocketClient::with_connector_and_clock(
+                config,
+                Box::new(SimulatedConnector {
+                    controller: controller.clone(),
+                }),
+                clock,
+            ),
+            #[cfg(test)]
+            controller,
+        }
+    }
+}
+
+// the controller's menus only matter to planning runs, a replay never talks to it
+#[cfg(test)]
+impl SimulatedHeatPumpBuilder {
+    pub fn navigation(mut self, navigation: &str) -> Self {
+        self.controller.navigation = navigation.to_string();
+        self
+    }
+
+    pub fn temperatures(mut self, navigation_id: &str, temperatures: &[(&str, f64)]) -> Self {
+        self.controller.temperatures_navigation_id = navigation_id.to_string();
+        self.controller.temperatures = temperatures
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        self
+    }
+
+    pub fn operating_modes(
+        mut self,
+        navigation_id: &str,
+        operating_modes: &[(&str, &str)],
+    ) -> Self {
+        self.controller.operating_modes_navigation_id = navigation_id.to_string();
+        self.controller.operating_modes = operating_modes
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self
+    }
+
+    pub fn energy_counters(mut self, navigation_id: &str, energy_counters: &[(&str, f64)]) -> Self {
+        self.controller.energy_counters_navigation_id = navigation_id.to_string();
+        self.controller.energy_counters = energy_counters
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        self
+    }
+
+    pub fn settings(mut self, navigation_id: &str, settings: &[(&str, &str, f64, f64)]) -> Self {
+        self.controller.settings_navigation_id = navigation_id.to_string();
+        self.controller.settings = settings
+            .iter()
+            .map(|(item_id, name, value, maximum)| {
+                (item_id.to_string(), name.to_string(), *value, *maximum)
+            })
+            .collect();
+        self
+    }
+
+    pub fn schedule(mut self, navigation_id: &str, item_ids: &[&str]) -> Self {
+        self.controller.schedules.insert(
+            navigation_id.to_string(),
+            item_ids.iter().map(|id| (id.to_string(), 0)).collect(),
+        );
+        self
+    }
+
+    pub fn now(mut self, now: DateTime<Utc>) -> Self {
+        self.now = Some(now);
+        self
+    }
+}
+
+// runs the planning logic of the websocket client against an in-memory heatpump controller
+pub struct SimulatedHeatPump {
+    client: WebsocketClient,
+    #[cfg(test)]
+    controller: Arc<Mutex<SimulatedController>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl PlannerClient<Config> for SimulatedHeatPump {
+    async fn plan(
+        &self,
+        config: Config,
+        spot_price_planner: SpotPricePlanner,
+        spot_prices: Vec<SpotPrice>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.plan_with_planner(config, spot_price_planner, spot_prices)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+impl SimulatedHeatPump {
+    pub async fn plan_with_planner<P: BestSpotPricesPlanner>(
+        &self,
+        config: Config,
+        spot_price_planner: P,
+        spot_prices: Vec<SpotPrice>,
+    ) -> Result<Option<PlanningOutcome>, Box<dyn Error>> {
+        self.client
+            .plan_with_planner(config, spot_price_planner, spot_prices)
+            .await
+    }
+
+    pub fn reset_schedule(&self, program: &ClockProgram) -> Result<(), WebsocketClientError> {
+        self.client.reset_schedule(program)
+    }
+
+    pub async fn boost_tap_water(&self, config: &Config, hours: i64) -> Result<(), Box<dyn Error>> {
+        self.client.boost_tap_water(config, hours).await
+    }
+
+    pub async fn set_value(
+        &self,
+        navigation_path: &str,
+        desired_value: f64,
+        step: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        self.client
+            .set_value(navigation_path, desired_value, step)
+            .await
+    }
+
+    pub fn setting_value(&self, item_id: &str) -> Option<f64> {
+        self.controller
+            .lock()
+            .unwrap()
+            .settings
+            .iter()
+            .find(|(id, _, _, _)| id == item_id)
+            .map(|(_, _, value, _)| *value)
+    }
+
+    pub fn health_state(&self) -> HealthState {
+        self.client.health_state()
+    }
+
+    pub fn schedule_raw_values(&self, navigation_id: &str) -> Vec<u32> {
+        match self.controller.lock().unwrap().schedules.get(navigation_id) {
+            Some(schedule) => schedule.iter().map(|(_, raw_value)| *raw_value).collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn saves(&self) -> usize {
+        self.controller.lock().unwrap().saves
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.controller.lock().unwrap().messages.clone()
+    }
+}
+
+impl SimulatedHeatPump {
+    // a heatpump without any menus, enough to replay spot prices from the command line
+    pub fn for_replay() -> Result<Self, Box<dyn Error>> {
+        // the simulated controller accepts any login code
+        let config = WebsocketClientConfig::new(
+            "127.0.0.1".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            true,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )?;
+
+        Ok(SimulatedHeatPumpBuilder::new().build(config))
+    }
+
+    // steps through the spot prices hour by hour like repeated planning runs would, without touching the controller,
+    // so configurations can be compared against the same prices
+    pub fn replay<P: BestSpotPricesPlanner>(
+        &self,
+        config: &Config,
+        spot_price_planner: P,
+        spot_prices: &[SpotPrice],
+        initial_state: &State,
+    ) -> Result<ReplaySummary, Box<dyn Error>> {
+        config.validate()?;
+
+        let (replay_from, replay_till) = match (spot_prices.first(), spot_prices.last()) {
+            (Some(first), Some(last)) => (first.from, last.till),
+            _ => {
+                return Err(Box::<dyn Error>::from(
+                    "Replaying requires at least one spot price",
+                ))
+            }
+        };
+
+        let heatpump_time_zone = config.get_heatpump_time_zone()?;
+        let spot_price_planner = EffectivePricePlanner::new(spot_price_planner, config.clone());
+        let segment_spot_prices = split_spot_prices_per_schedule_segment(
+            &heatpump_time_zone,
+            &config.schedule_program,
+            spot_prices,
+        );
+
+        let mut desinfection_finished_at = initial_state
+            .desinfection_finished_at
+            .unwrap_or(replay_from - Duration::days(7));
+        let mut eco_desinfection_finished_at = initial_state.eco_desinfection_finished_at;
+        let mut desinfection_enabled = initial_state.desinfection_enabled;
+        let mut max_hours_since_last_desinfection = 0;
+        let mut decisions: Vec<ReplayDecision> = vec![];
+        let mut cumulative_cost_eur = 0.0;
+
+        let mut now = replay_from;
+        while now < replay_till {
+            let busy = matches!(decisions.last(), Some(decision) if decision.till > now);
+            if !busy {
+                let (best_spot_prices_responses, desinfection_mode, _) = self
+                    .client
+                    .get_spot_prices_for_tapwater_heating_or_desinfection_per_segment(
+                        config,
+                        &spot_price_planner,
+                        &segment_spot_prices,
+                        now,
+                        desinfection_finished_at,
+                        eco_desinfection_finished_at,
+                        desinfection_enabled,
+                    )?;
+                // the controller keeps the toggle from the previous run, the hysteresis depends on it
+                desinfection_enabled = desinfection_mode == DesinfectionMode::Full;
+
+                // a window starting after the next run can still move, so only act on the one starting before it
+                let committed_response = best_spot_prices_responses.into_iter().find(|response| {
+                    response
+                        .spot_prices
+                        .first()
+                        .is_some_and(|sp| sp.from < now + Duration::hours(REPLAY_STEP_HOURS))
+                });
+
+                // tap water only needs to heat up once a day, reruns on the same day would otherwise keep adding windows
+                let heated_today = |from: DateTime<Utc>| {
+                    decisions.iter().any(|decision| {
+                        decision
+                            .from
+                            .with_timezone(&heatpump_time_zone)
+                            .date_naive()
+                            == from.with_timezone(&heatpump_time_zone).date_naive()
+                    })
+                };
+
+                if let Some(response) = committed_response {
+                    let from = response.spot_prices.first().unwrap().from;
+                    let till = response.spot_prices.last().unwrap().till;
+
+                    if desinfection_mode == DesinfectionMode::Full || !heated_today(from) {
+                        let hours_since_last_desinfection =
+                            (from - desinfection_finished_at).num_hours();
+                        let cost_eur =
+                            get_planned_cost(&response.spot_prices, &response.load_profile, config);
+                        cumulative_cost_eur += cost_eur;
+
+                        let strategy = match desinfection_mode {
+                            DesinfectionMode::Full => {
+                                max_hours_since_last_desinfection = std::cmp::max(
+                                    max_hours_since_last_desinfection,
+                                    hours_since_last_desinfection,
+                                );
+                                desinfection_finished_at = till;
+                                TapWaterStrategy::Desinfection
+                            }
+                            DesinfectionMode::Eco => {
+                                eco_desinfection_finished_at = Some(till);
+                                TapWaterStrategy::EcoDesinfection
+                            }
+                            DesinfectionMode::None => TapWaterStrategy::TapWaterHeating,
+                        };
+
+                        decisions.push(ReplayDecision {
+                            decided_at: now,
+                            strategy,
+                            from,
+                            till,
+                            hours_since_last_desinfection,
+                            cost_eur,
+                            cumulative_cost_eur,
+                        });
+                    }
+                }
+            }
+
+            now += Duration::hours(REPLAY_STEP_HOURS);
+        }
+
+        // the time since the last desinfection keeps growing after the replay, so count it as well
+        max_hours_since_last_desinfection = std::cmp::max(
+            max_hours_since_last_desinfection,
+            (replay_till - desinfection_finished_at).num_hours(),
+        );
+
+        Ok(ReplaySummary {
+            decisions,
+            max_hours_since_last_desinfection,
+        })
+    }
+}
+
+// a window the replay committed to, because it started before the next planning run
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayDecision {
+    pub decided_at: DateTime<Utc>,
+    pub strategy: TapWaterStrategy,
+    pub from: DateTime<Utc>,
+    pub till: DateTime<Utc>,
+    pub hours_since_last_desinfection: i64,
+    pub cost_eur: f64,
+    pub cumulative_cost_eur: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaySummary {
+    pub decisions: Vec<ReplayDecision>,
+    pub max_hours_since_last_desinfection: i64,
+}
+
+impl ReplaySummary {
+    pub fn number_of_decisions(&self, strategy: &TapWaterStrategy) -> usize {
+        self.decisions
+            .iter()
+            .filter(|decision| &decision.strategy == strategy)
+            .count()
+    }
+
+    pub fn total_cost_eur(&self) -> f64 {
+        match self.decisions.last() {
+            Some(decision) => decision.cumulative_cost_eur,
+            None => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for ReplaySummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} tap water heatings, {} desinfections, ",
+            self.number_of_decisions(&TapWaterStrategy::TapWaterHeating),
+            self.number_of_decisions(&TapWaterStrategy::Desinfection),
+        )?;
+        // only mentioned when configured, so replays without eco desinfection read as before
+        let eco_desinfections = self.number_of_decisions(&TapWaterStrategy::EcoDesinfection);
+        if eco_desinfections > 0 {
+            write!(f, "{} eco desinfections, ", eco_desinfections)?;
+        }
+        write!(
+            f,
+            "at most {} hours between desinfections, total cost €{:.2}",
+            self.max_hours_since_last_desinfection,
+            self.total_cost_eur()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health_server::PlannerCounter;
+    use crate::state_client::{StateClient, StateClientConfig};
+    use chrono::{DateTime, Duration, TimeZone, Timelike, Utc};
+    use jarvis_lib::model::{
+        LoadProfile, LoadProfileSection, PlanningRequest, PlanningResponse, PlanningStrategy,
+    };
+    use std::convert::TryFrom;
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const NAVIGATION_RESPONSE: &str = "<Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item><item id='0x460cb8'><name>Energie</name></item></item><item id='0x450798'><name>Instelling</name><item id='0x460bd0'><name>Bedrijfsmode</name></item><item id='0x4a5e10'><name>Verwarming</name></item></item><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item></item><item id='0x453f30'><name>Koelen</name><readOnly>true</readOnly><item id='0x45f2a0'><name>Week</name></item></item></item><item id='0x3d9d40'><name>Afstandbediening</name></item></Navigation>";
+
+    struct FixedPlanner {
+        lowest_spot_prices: Vec<SpotPrice>,
+        highest_spot_prices: Vec<SpotPrice>,
+    }
+
+    impl BestSpotPricesPlanner for FixedPlanner {
+        fn get_best_spot_prices(
+            &self,
+            planning_request: &PlanningRequest,
+        ) -> Result<PlanningResponse, Box<dyn Error>> {
+            let spot_prices = match planning_request.planning_strategy {
+                PlanningStrategy::LowestPrice => self.lowest_spot_prices.clone(),
+                _ => self.highest_spot_prices.clone(),
+            };
+
+            Ok(PlanningResponse {
+                spot_prices,
+                load_profile: planning_request.load_profile.clone(),
+            })
+        }
+    }
+
+    fn spot_price(from: DateTime<Utc>, till: DateTime<Utc>) -> SpotPrice {
+        SpotPrice {
+            id: None,
+            source: None,
+            from,
+            till,
+            market_price: 0.157,
+            market_price_tax: 0.0330708,
+            sourcing_markup_price: 0.017,
+            energy_tax_price: 0.081,
+        }
+    }
+
+    // the simulated windows are 2 hours for tap water and desinfection alike
+    fn test_config() -> Config {
+        Config {
+            desinfection_load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 2000.0,
+                }],
+            },
+            ..crate::model::test_config()
+        }
+    }
+
+    fn simulated_heat_pump(operating_modes: &[(&str, &str)]) -> SimulatedHeatPump {
+        simulated_heat_pump_builder(operating_modes).build(websocket_client_config())
+    }
+
+    fn simulated_heat_pump_at(
+        operating_modes: &[(&str, &str)],
+        now: DateTime<Utc>,
+    ) -> SimulatedHeatPump {
+        simulated_heat_pump_builder(operating_modes)
+            .now(now)
+            .build(websocket_client_config())
+    }
+
+    fn simulated_heat_pump_builder(operating_modes: &[(&str, &str)]) -> SimulatedHeatPumpBuilder {
+        SimulatedHeatPumpBuilder::new()
+            .navigation(NAVIGATION_RESPONSE)
+            .temperatures(
+                "0x45df90",
+                &[("Tapwater gemeten", 48.2), ("Tapwater ingesteld", 50.0)],
+            )
+            .operating_modes("0x460bd0", operating_modes)
+            .energy_counters("0x460cb8", &[("Verwarming", 8765.0), ("Warmwater", 1234.5)])
+            .schedule(
+                "0x4642a8",
+                &["0xa57344", "0xa53c8c", "0xa47ee4", "0xa6630c", "0xa68d74"],
+            )
+            .schedule(
+                "0x45e118",
+                &["0xa45e14", "0xa6dbf4", "0xa4c1c4", "0xa6a0fc", "0xa51d7c"],
+            )
+            .schedule(
+                "0x45f2a0",
+                &["0xa5e2c4", "0xa4d9bc", "0xa6f1d4", "0xa47c0c", "0xa5b3e4"],
+            )
+    }
+
+    // summer afternoons are warm long before the average the controller keeps says it's summer
+    fn simulated_heat_pump_outside(
+        outside_temperature: f64,
+        average_outside_temperature: f64,
+        name: &str,
+        state: &State,
+    ) -> (SimulatedHeatPump, Arc<Mutex<Vec<String>>>) {
+        simulated_heat_pump_from_builder_with_state(
+            simulated_heat_pump_builder(&[
+                ("Verwarmen", "Automatisch"),
+                ("Warmwater", "Automatisch"),
+            ])
+            .temperatures(
+                "0x45df90",
+                &[
+                    ("Buitentemperatuur", outside_temperature),
+                    ("Gemiddelde temp.", average_outside_temperature),
+                    ("Tapwater gemeten", 48.2),
+                    ("Tapwater ingesteld", 50.0),
+                ],
+            ),
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            name,
+            state,
+        )
+    }
+
+    fn cooling_config() -> Config {
+        Config {
+            enable_cooling_scheduling: true,
+            cooling_min_outside_temperature: 22.0,
+            ..test_config()
+        }
+    }
+
+    fn websocket_client_config() -> WebsocketClientConfig {
+        websocket_client_config_with_state_client(None)
+    }
+
+    fn websocket_client_config_with_state_client(
+        state_client: Option<StateClient>,
+    ) -> WebsocketClientConfig {
+        WebsocketClientConfig::new(
+            "127.0.0.1".to_string(),
+            8214,
+            "999999".to_string(),
+            state_client,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .unwrap()
+    }
+
+    // answers the requests of the state client like the kubernetes api would, keeping their bodies to check what got
+    // stored
+    fn fake_kube_api_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let request_bodies = Arc::new(Mutex::new(vec![]));
+
+        let received_request_bodies = request_bodies.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let received_request_bodies = received_request_bodies.clone();
+                thread::spawn(move || {
+                    let mut writer = stream.try_clone().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    loop {
+                        let mut content_length = 0;
+                        let mut header = String::new();
+                        loop {
+                            header.clear();
+                            if reader.read_line(&mut header).unwrap_or(0) == 0 {
+                                return;
+                            }
+                            if header.trim().is_empty() {
+                                break;
+                            }
+                            if let Some((name, value)) = header.split_once(':') {
+                                if name.eq_ignore_ascii_case("content-length") {
+                                    content_length = value.trim().parse().unwrap();
+                                }
+                            }
+                        }
+
+                        let mut body = vec![0; content_length];
+                        reader.read_exact(&mut body).unwrap();
+                        received_request_bodies
+                            .lock()
+                            .unwrap()
+                            .push(String::from_utf8(body).unwrap());
+
+                        let response_body = r#"{"apiVersion":"v1","kind":"ConfigMap","metadata":{"name":"jarvis-alpha-innotec-planner"}}"#;
+                        write!(
+                            writer,
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                            response_body.len(),
+                            response_body
+                        )
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        (url, request_bodies)
+    }
+
+    fn simulated_heat_pump_with_state(
+        operating_modes: &[(&str, &str)],
+        now: DateTime<Utc>,
+        name: &str,
+        state: &State,
+    ) -> (SimulatedHeatPump, Arc<Mutex<Vec<String>>>) {
+        simulated_heat_pump_from_builder_with_state(
+            simulated_heat_pump_builder(operating_modes),
+            now,
+            name,
+            state,
+        )
+    }
+
+    fn simulated_heat_pump_from_builder_with_state(
+        builder: SimulatedHeatPumpBuilder,
+        now: DateTime<Utc>,
+        name: &str,
+        state: &State,
+    ) -> (SimulatedHeatPump, Arc<Mutex<Vec<String>>>) {
+        let state_file_path = env::temp_dir().join(name);
+        fs::write(&state_file_path, serde_yaml::to_string(state).unwrap()).unwrap();
+
+        let (url, request_bodies) = fake_kube_api_server();
+        let state_client = StateClient::new(
+            StateClientConfig::new(
+                kube::Client::try_from(kube::Config::new(url.parse().unwrap())).unwrap(),
+                state_file_path.to_str().unwrap().to_string(),
+                "jarvis-alpha-innotec-planner".to_string(),
+                "last-state.yaml".to_string(),
+                "jarvis".to_string(),
+                100,
+                false,
+                false,
+            )
+            .unwrap(),
+        );
+
+        (
+            builder
+                .now(now)
+                .build(websocket_client_config_with_state_client(Some(
+                    state_client,
+                ))),
+            request_bodies,
+        )
+    }
+
+    // the state a previous run leaves behind after programming the windows of the fixed planner
+    fn planned_state() -> State {
+        let planner = fixed_planner();
+        State {
+            planned_spot_prices: Some(planner.lowest_spot_prices),
+            blocked_spot_prices: Some(planner.highest_spot_prices),
+            ..initial_state()
+        }
+    }
+
+    fn fixed_planner() -> FixedPlanner {
+        FixedPlanner {
+            lowest_spot_prices: vec![
+                spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 4, 0, 0).unwrap(),
+                ),
+                spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 4, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 5, 0, 0).unwrap(),
+                ),
+            ],
+            highest_spot_prices: vec![spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 18, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 19, 0, 0).unwrap(),
+            )],
+        }
+    }
+
+    #[tokio::test]
+    async fn plan_programs_tap_water_and_heating_schedules_of_simulated_heat_pump() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![65536 * 180, 0, 0, 0, 300]
+        );
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45e118"),
+            vec![1080 + 65536 * 1140, 0, 0, 0, 0]
+        );
+        assert_eq!(heat_pump.saves(), 2);
+        assert_eq!(planning_outcome.strategy, TapWaterStrategy::TapWaterHeating);
+        assert!(planning_outcome.tap_water_schedule_written);
+        assert!(!planning_outcome.desinfection_toggled);
+        assert!(planning_outcome.heating_schedule_written);
+        assert_eq!(
+            planning_outcome.best_from,
+            Some(Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap())
+        );
+        assert_eq!(
+            planning_outcome.best_till,
+            Some(Utc.with_ymd_and_hms(2022, 4, 21, 5, 0, 0).unwrap())
+        );
+        assert_eq!(
+            planning_outcome.blocked_from,
+            Some(Utc.with_ymd_and_hms(2022, 4, 21, 18, 0, 0).unwrap())
+        );
+        assert!(heat_pump.health_state().last_successful_plan_at().is_some());
+        assert_eq!(
+            heat_pump.health_state().get_counter(PlannerCounter::Runs),
+            1
+        );
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::SuccessfulRuns),
+            1
+        );
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::HeatingBlocked),
+            1
+        );
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::ConnectionFailures),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_enables_desinfection_of_simulated_heat_pump_on_sunday_after_cooldown() {
+        // without state the last desinfection is taken to be a week ago
+        let heat_pump = simulated_heat_pump_at(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 24, 0, 0, 0).unwrap(),
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::DesinfectionEnabled),
+            1
+        );
+        assert_eq!(planning_outcome.strategy, TapWaterStrategy::Desinfection);
+        assert!(planning_outcome.desinfection_toggled);
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![65536 * 180, 0, 0, 0, 300]
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_notifies_webhook_with_desinfection_decision_of_simulated_heat_pump() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 4096];
+            let length = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..length]).to_string()
+        });
+        let heat_pump = simulated_heat_pump_at(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 24, 0, 0, 0).unwrap(),
+        );
+
+        // act
+        heat_pump
+            .plan_with_planner(
+                Config {
+                    notification_webhook_url: Some(format!("http://127.0.0.1:{}/webhook", port)),
+                    ..test_config()
+                },
+                fixed_planner(),
+                vec![],
+            )
+            .await
+            .unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.contains("\"reason\":\"desinfection is desired on Sun, today is Sun\""));
+        assert!(request.contains("\"decisions\":[{\"reason\":\"Weekday\""));
+    }
+
+    #[tokio::test]
+    async fn plan_defers_desinfection_of_simulated_heat_pump_when_best_window_is_beyond_defer_hours(
+    ) {
+        // a sunday, but the best window starts 87 hours ahead
+        let heat_pump = simulated_heat_pump_at(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 17, 12, 0, 0).unwrap(),
+        );
+
+        // act
+        heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::DesinfectionEnabled),
+            0
+        );
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![65536 * 180, 0, 0, 0, 300]
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_forces_desinfection_of_simulated_heat_pump_when_overdue_without_best_window() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 20, 12, 0, 0).unwrap();
+        let heat_pump = simulated_heat_pump_at(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            now,
+        );
+        let spot_prices: Vec<SpotPrice> = (0..12)
+            .map(|i| SpotPrice {
+                market_price: if i == 3 || i == 4 { 0.05 } else { 0.2 },
+                ..spot_price(now + Duration::hours(i), now + Duration::hours(i + 1))
+            })
+            .collect();
+
+        // act
+        heat_pump
+            .plan_with_planner(
+                Config {
+                    // without state the last desinfection is taken to be 168 hours ago
+                    max_hours_since_last_desinfection: 120,
+                    ..test_config()
+                },
+                FixedPlanner {
+                    lowest_spot_prices: vec![],
+                    highest_spot_prices: vec![],
+                },
+                spot_prices,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::DesinfectionEnabled),
+            1
+        );
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![65536 * 900, 0, 0, 0, 1020]
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_skips_heating_schedule_of_simulated_heat_pump_in_party_mode() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Party"), ("Warmwater", "Automatisch")]);
+
+        // act
+        heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![65536 * 180, 0, 0, 0, 300]
+        );
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45e118"),
+            vec![0, 0, 0, 0, 0]
+        );
+        assert_eq!(heat_pump.saves(), 1);
+    }
+
+    #[tokio::test]
+    async fn plan_only_blocks_heating_of_simulated_heat_pump_with_tap_water_scheduling_disabled() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        heat_pump
+            .plan_with_planner(
+                Config {
+                    enable_tap_water_scheduling: false,
+                    // keeps desinfection from being desired, which would still program the tap water schedule
+                    desinfection_cooldown_hours: 24 * 365,
+                    ..test_config()
+                },
+                fixed_planner(),
+                vec![],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45e118"),
+            vec![1080 + 65536 * 1140, 0, 0, 0, 0]
+        );
+        assert_eq!(heat_pump.saves(), 1);
+    }
+
+    #[tokio::test]
+    async fn plan_forces_tap_water_heating_of_simulated_heat_pump_below_minimum_temperature() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+        let next_hour = Utc
+            .timestamp_opt((Utc::now().timestamp() / 3600 + 1) * 3600, 0)
+            .unwrap();
+        let spot_prices: Vec<SpotPrice> = (0..4)
+            .map(|i| {
+                spot_price(
+                    next_hour + Duration::hours(i),
+                    next_hour + Duration::hours(i + 1),
+                )
+            })
+            .collect();
+
+        // act
+        heat_pump
+            .plan_with_planner(
+                Config {
+                    minimum_tap_water_temperature: Some(50.0),
+                    ..test_config()
+                },
+                FixedPlanner {
+                    lowest_spot_prices: vec![],
+                    highest_spot_prices: vec![],
+                },
+                spot_prices,
+            )
+            .await
+            .unwrap();
+
+        assert_ne!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![0, 0, 0, 0, 0]
+        );
+        assert_eq!(heat_pump.saves(), 1);
+    }
+
+    #[tokio::test]
+    async fn reset_schedule_clears_programmed_schedules_of_simulated_heat_pump() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+        heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap();
+
+        // act
+        for program in ClockProgram::all() {
+            heat_pump.reset_schedule(&program).unwrap();
+        }
+
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x4642a8"),
+            vec![0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45e118"),
+            vec![0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45f2a0"),
+            vec![0, 0, 0, 0, 0]
+        );
+        assert_eq!(heat_pump.saves(), 5);
+    }
+
+    #[tokio::test]
+    async fn boost_tap_water_programs_immediate_window_of_simulated_heat_pump() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+        let config = Config {
+            boost_tap_water_temperature: Some(50.0),
+            ..test_config()
+        };
+
+        // act
+        heat_pump.boost_tap_water(&config, 2).await.unwrap();
+
+        assert!(heat_pump
+            .schedule_raw_values("0x4642a8")
+            .iter()
+            .any(|raw_value| *raw_value != 0));
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45e118"),
+            vec![0, 0, 0, 0, 0]
+        );
+        assert_eq!(heat_pump.saves(), 1);
+    }
+
+    #[tokio::test]
+    async fn boost_tap_water_returns_error_for_boost_longer_than_a_day() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let result = heat_pump.boost_tap_water(&test_config(), 25).await;
+
+        assert!(result.is_err());
+        assert_eq!(heat_pump.saves(), 0);
+    }
+
+    fn simulated_heat_pump_with_settings() -> SimulatedHeatPump {
+        simulated_heat_pump_builder(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")])
+            .settings(
+                "0x4a5e10",
+                &[
+                    ("0x4a5f2c", "Temperatuur +-", -1.5, 5.0),
+                    ("0x4a60d4", "Eindpunt", 36.0, 70.0),
+                ],
+            )
+            .build(websocket_client_config())
+    }
+
+    #[tokio::test]
+    async fn set_value_stores_value_of_simulated_heat_pump_setting() {
+        let heat_pump = simulated_heat_pump_with_settings();
+
+        // act
+        heat_pump
+            .set_value("Instelling > Verwarming > Temperatuur +-", 1.0, 0.5)
+            .await
+            .unwrap();
+
+        assert_eq!(heat_pump.setting_value("0x4a5f2c"), Some(1.0));
+        assert_eq!(heat_pump.setting_value("0x4a60d4"), Some(36.0));
+        assert!(heat_pump
+            .messages()
+            .contains(&"SET;set_0x4a5f2c;1".to_string()));
+        assert_eq!(heat_pump.saves(), 1);
+    }
+
+    #[tokio::test]
+    async fn set_value_skips_simulated_heat_pump_setting_already_at_value() {
+        let heat_pump = simulated_heat_pump_with_settings();
+
+        // act
+        heat_pump
+            .set_value("Instelling > Verwarming > Eindpunt", 36.2, 0.5)
+            .await
+            .unwrap();
+
+        assert_eq!(heat_pump.setting_value("0x4a60d4"), Some(36.0));
+        assert!(!heat_pump.messages().iter().any(|m| m.starts_with("SET;")));
+        assert_eq!(heat_pump.saves(), 0);
+    }
+
+    #[tokio::test]
+    async fn set_value_restores_simulated_heat_pump_setting_when_controller_stores_other_value() {
+        let heat_pump = simulated_heat_pump_with_settings();
+
+        // act
+        let result = heat_pump
+            .set_value("Instelling > Verwarming > Temperatuur +-", 8.0, 0.5)
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(heat_pump.setting_value("0x4a5f2c"), Some(-1.5));
+        assert!(heat_pump
+            .messages()
+            .contains(&"SET;set_0x4a5f2c;-1.5".to_string()));
+        assert_eq!(heat_pump.saves(), 2);
+    }
+
+    #[tokio::test]
+    async fn plan_does_not_program_simulated_heat_pump_from_stale_spot_prices() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        heat_pump
+            .plan_with_planner(
+                Config {
+                    max_spot_price_age_hours: Some(24),
+                    ..test_config()
+                },
+                fixed_planner(),
+                fixed_planner().lowest_spot_prices,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(heat_pump.saves(), 0);
+        assert!(heat_pump.health_state().last_successful_plan_at().is_none());
+        assert_eq!(
+            heat_pump.health_state().get_counter(PlannerCounter::Runs),
+            1
+        );
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::SuccessfulRuns),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_skips_hardware_writes_of_simulated_heat_pump_when_plan_is_unchanged() {
+        let (heat_pump, request_bodies) = simulated_heat_pump_with_state(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            "plan-skips-hardware-writes-when-plan-is-unchanged",
+            &planned_state(),
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(heat_pump.saves(), 0);
+        assert!(!planning_outcome.tap_water_schedule_written);
+        assert!(!planning_outcome.heating_schedule_written);
+        assert_eq!(request_bodies.lock().unwrap().len(), 1);
+        assert_eq!(
+            heat_pump.health_state().last_successful_plan_at(),
+            Some(Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            heat_pump
+                .health_state()
+                .get_counter(PlannerCounter::SuccessfulRuns),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_stores_energy_stats_of_simulated_heat_pump_when_plan_is_unchanged() {
+        let (heat_pump, request_bodies) = simulated_heat_pump_with_state(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            "plan-stores-energy-stats-when-plan-is-unchanged",
+            &planned_state(),
+        );
+
+        // act
+        heat_pump
+            .plan_with_planner(
+                Config {
+                    read_energy_stats: true,
+                    ..test_config()
+                },
+                fixed_planner(),
+                vec![],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(heat_pump.saves(), 0);
+        let request_bodies = request_bodies.lock().unwrap();
+        assert_eq!(request_bodies.len(), 1);
+        assert!(request_bodies[0].contains("Verwarming: 8765.0"));
+        assert!(request_bodies[0].contains("Warmwater: 1234.5"));
+    }
+
+    #[tokio::test]
+    async fn plan_records_planning_history_of_simulated_heat_pump_when_plan_is_unchanged() {
+        let (heat_pump, request_bodies) = simulated_heat_pump_with_state(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            "plan-records-planning-history-when-plan-is-unchanged",
+            &planned_state(),
+        );
+
+        // act
+        heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap();
+
+        assert_eq!(heat_pump.saves(), 0);
+        let request_bodies = request_bodies.lock().unwrap();
+        assert_eq!(request_bodies.len(), 1);
+        assert!(request_bodies[0].contains("plannedFrom: 2022-04-21T03:00:00Z"));
+        assert!(request_bodies[0].contains("plannedTill: 2022-04-21T05:00:00Z"));
+    }
+
+    #[tokio::test]
+    async fn plan_writes_heating_schedule_of_simulated_heat_pump_when_only_blocking_changed() {
+        let (heat_pump, request_bodies) = simulated_heat_pump_with_state(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            "plan-writes-heating-schedule-when-only-blocking-changed",
+            &State {
+                blocked_spot_prices: Some(vec![spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 8, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 9, 0, 0).unwrap(),
+                )]),
+                ..planned_state()
+            },
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(test_config(), fixed_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(planning_outcome.heating_schedule_written);
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45e118"),
+            vec![1080 + 65536 * 1140, 0, 0, 0, 0]
+        );
+        assert!(!request_bodies.lock().unwrap().is_empty());
+    }
+
+    // a midday rerun on the sunday desinfection is desired on, after an intraday price update found a cheaper window
+    // later today while the one planned in the morning is still heating
+    fn midday_rerun_state(
+        desinfection_enabled: bool,
+        desinfection_finished_at: DateTime<Utc>,
+    ) -> State {
+        State {
+            desinfection_enabled,
+            desinfection_finished_at: Some(desinfection_finished_at),
+            planned_spot_prices: Some(vec![
+                spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 24, 11, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 24, 12, 0, 0).unwrap(),
+                ),
+                spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 24, 12, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 24, 13, 0, 0).unwrap(),
+                ),
+            ]),
+            ..initial_state()
+        }
+    }
+
+    fn midday_rerun_planner() -> FixedPlanner {
+        FixedPlanner {
+            lowest_spot_prices: vec![
+                spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 24, 20, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 24, 21, 0, 0).unwrap(),
+                ),
+                spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 24, 21, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 24, 22, 0, 0).unwrap(),
+                ),
+            ],
+            ..fixed_planner()
+        }
+    }
+
+    #[tokio::test]
+    async fn plan_keeps_running_tap_water_window_without_desinfection_when_desinfection_becomes_due(
+    ) {
+        let (heat_pump, request_bodies) = simulated_heat_pump_with_state(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 24, 12, 0, 0).unwrap(),
+            "plan-keeps-running-tap-water-window-without-desinfection",
+            &midday_rerun_state(false, Utc.with_ymd_and_hms(2022, 4, 10, 5, 0, 0).unwrap()),
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(test_config(), midday_rerun_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        let messages = heat_pump.messages();
+        assert!(messages.contains(&format!("SET;set_0xa57344;{}", 65536 * 660)));
+        assert!(messages.contains(&"SET;set_0xa68d74;780".to_string()));
+        // the remote control menu is only navigated to for toggling desinfection or changing the setpoint
+        assert!(!messages.contains(&"GET;0x3d9d40".to_string()));
+        assert_eq!(planning_outcome.strategy, TapWaterStrategy::TapWaterHeating);
+        assert!(!planning_outcome.desinfection_toggled);
+        let request_bodies = request_bodies.lock().unwrap();
+        assert!(request_bodies[0].contains("desinfectionEnabled: false"));
+        assert!(request_bodies[0].contains("desinfectionFinishedAt: 2022-04-10T05:00:00Z"));
+    }
+
+    #[tokio::test]
+    async fn plan_keeps_running_desinfection_window_enabled_when_desinfection_is_no_longer_due() {
+        let (heat_pump, request_bodies) = simulated_heat_pump_from_builder_with_state(
+            simulated_heat_pump_builder(&[
+                ("Verwarmen", "Automatisch"),
+                ("Warmwater", "Automatisch"),
+            ])
+            .temperatures(
+                "0x45df90",
+                &[("Tapwater gemeten", 52.0), ("Tapwater ingesteld", 58.0)],
+            ),
+            Utc.with_ymd_and_hms(2022, 4, 24, 12, 0, 0).unwrap(),
+            "plan-keeps-running-desinfection-window-enabled",
+            // enabling desinfection stored the end of its window as the end of the desinfection
+            &midday_rerun_state(true, Utc.with_ymd_and_hms(2022, 4, 24, 13, 0, 0).unwrap()),
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(test_config(), midday_rerun_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        let messages = heat_pump.messages();
+        assert!(messages.contains(&format!("SET;set_0xa57344;{}", 65536 * 660)));
+        assert!(messages.contains(&"SET;set_0xa68d74;780".to_string()));
+        assert!(!messages.contains(&"GET;0x3d9d40".to_string()));
+        assert_eq!(planning_outcome.strategy, TapWaterStrategy::Desinfection);
+        assert!(!planning_outcome.desinfection_toggled);
+        let request_bodies = request_bodies.lock().unwrap();
+        assert!(request_bodies[0].contains("desinfectionEnabled: true"));
+        assert!(request_bodies[0].contains("desinfectionFinishedAt: 2022-04-24T13:00:00Z"));
+    }
+
+    #[tokio::test]
+    async fn plan_writes_cooling_schedule_when_average_outside_temperature_is_above_minimum() {
+        let (heat_pump, request_bodies) = simulated_heat_pump_outside(
+            26.0,
+            24.0,
+            "plan-writes-cooling-schedule-in-cooling-season",
+            &planned_state(),
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(cooling_config(), fixed_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(planning_outcome.cooling_schedule_written);
+        assert_eq!(
+            heat_pump.schedule_raw_values("0x45f2a0"),
+            vec![65536 * 180, 0, 0, 0, 300]
+        );
+        let stored_state = stored_state(&request_bodies.lock().unwrap()[0]);
+        assert_eq!(stored_state.cooling_spot_prices.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn plan_leaves_cooling_schedule_alone_on_warm_afternoon_out_of_cooling_season() {
+        let (heat_pump, _) = simulated_heat_pump_outside(
+            26.0,
+            15.0,
+            "plan-leaves-cooling-schedule-alone-out-of-cooling-season",
+            &planned_state(),
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(cooling_config(), fixed_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!planning_outcome.cooling_schedule_written);
+        assert!(!heat_pump.messages().contains(&"GET;0x45f2a0".to_string()));
+    }
+
+    #[tokio::test]
+    async fn plan_resets_cooling_schedule_once_cooling_season_has_ended() {
+        let (heat_pump, request_bodies) = simulated_heat_pump_outside(
+            18.0,
+            15.0,
+            "plan-resets-cooling-schedule-once-cooling-season-has-ended",
+            &State {
+                cooling_spot_prices: planned_state().planned_spot_prices,
+                ..planned_state()
+            },
+        );
+
+        // act
+        let planning_outcome = heat_pump
+            .plan_with_planner(cooling_config(), fixed_planner(), vec![])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(planning_outcome.cooling_schedule_written);
+        let messages = heat_pump.messages();
+        for item_id in ["0xa5e2c4", "0xa4d9bc", "0xa6f1d4", "0xa47c0c", "0xa5b3e4"] {
+            assert!(messages.contains(&format!("SET;set_{};0", item_id)));
+        }
+        assert_eq!(heat_pump.schedule_raw_values("0x45f2a0"), vec![0; 5]);
+        let stored_state = stored_state(&request_bodies.lock().unwrap()[0]);
+        assert_eq!(stored_state.cooling_spot_prices.unwrap().len(), 0);
+        assert!(stored_state
+            .written_schedule
+            .unwrap()
+            .contains(&("0xa5e2c4".to_string(), 0)));
+    }
+
+    fn stored_state(request_body: &str) -> State {
+        let state_patch: serde_json::Value = serde_json::from_str(request_body).unwrap();
+        serde_yaml::from_str(state_patch["data"]["last-state.yaml"].as_str().unwrap()).unwrap()
+    }
+
+    fn count_messages(messages: &[String], message: &str) -> usize {
+        messages.iter().filter(|m| m.as_str() == message).count()
+    }
+
+    #[tokio::test]
+    async fn plan_lowers_setpoint_until_tap_water_window_and_restores_it_once_window_started() {
+        let config = Config {
+            enable_adaptive_setpoint: true,
+            minimum_tap_water_temperature: Some(40.0),
+            ..test_config()
+        };
+        let (heat_pump, request_bodies) = simulated_heat_pump_with_state(
+            &[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            "plan-lowers-setpoint-until-tap-water-window",
+            &initial_state(),
+        );
+
+        // act
+        heat_pump
+            .plan_with_planner(config.clone(), fixed_planner(), vec![])
+            .await
+            .unwrap();
+
+        // the window starts at 03:00, so the setpoint is lowered from 50 to 40 in decrements of 0.5
+        assert_eq!(count_messages(&heat_pump.messages(), "MOVE;1"), 20);
+        let lowered_state = stored_state(&request_bodies.lock().unwrap()[0]);
+        assert_eq!(lowered_state.tap_water_setpoint, Some(40.0));
+
+        let (heat_pump, request_bodies) = simulated_heat_pump_from_builder_with_state(
+            simulated_heat_pump_builder(&[
+                ("Verwarmen", "Automatisch"),
+                ("Warmwater", "Automatisch"),
+            ])
+            .temperatures(
+                "0x45df90",
+                &[("Tapwater gemeten", 41.0), ("Tapwater ingesteld", 40.0)],
+            ),
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 30, 0).unwrap(),
+            "plan-restores-setpoint-once-tap-water-window-started",
+            &lowered_state,
+        );
+
+        // act
+        heat_pump
+            .plan_with_planner(config, fixed_planner(), vec![])
+            .await
+            .unwrap();
+
+        // 4 moves to the setpoint in the remote control menu, then 20 increments of 0.5 back to 50
+        let messages = heat_pump.messages();
+        assert_eq!(count_messages(&messages, "MOVE;1"), 0);
+        assert_eq!(count_messages(&messages, "MOVE;0"), 24);
+        let restored_state = stored_state(&request_bodies.lock().unwrap()[0]);
+        assert_eq!(restored_state.tap_water_setpoint, Some(50.0));
+        assert_eq!(
+            restored_state
+                .planned_spot_prices
+                .unwrap()
+                .first()
+                .map(|sp| sp.from),
+            Some(Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_reads_operating_modes_of_simulated_heat_pump_before_skipping_unchanged_plan() {
+        let (heat_pump, _) = simulated_heat_pump_with_state(
+            &[("Verwarmen", "Koelen"), ("Warmwater", "Automatisch")],
+            Utc.with_ymd_and_hms(2022, 4, 21, 0, 0, 0).unwrap(),
+            "plan-reads-operating-modes-before-skipping-unchanged-plan",
+            &planned_state(),
+        );
+
+        // act
+        let result = heat_pump
+            .plan_with_planner(
+                Config {
+                    fail_on_unknown_operating_mode: true,
+                    ..test_config()
+                },
+                fixed_planner(),
+                vec![],
+            )
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Heating is in unknown operating mode Koelen".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_returns_error_for_unknown_operating_mode_if_configured() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Koelen"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let result = heat_pump
+            .plan_with_planner(
+                Config {
+                    fail_on_unknown_operating_mode: true,
+                    ..test_config()
+                },
+                fixed_planner(),
+                vec![],
+            )
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Heating is in unknown operating mode Koelen".to_string()
+        );
+        assert_eq!(heat_pump.saves(), 0);
+    }
+
+    // picks the contiguous window covering the load profile with the lowest or highest total market price
+    struct WindowPlanner {}
+
+    impl BestSpotPricesPlanner for WindowPlanner {
+        fn get_best_spot_prices(
+            &self,
+            planning_request: &PlanningRequest,
+        ) -> Result<PlanningResponse, Box<dyn Error>> {
+            let slot_seconds = planning_request
+                .spot_prices
+                .first()
+                .map(|sp| (sp.till - sp.from).num_seconds())
+                .unwrap_or(3600);
+            let slots: usize = (planning_request
+                .load_profile
+                .sections
+                .iter()
+                .map(|section| section.duration_seconds)
+                .sum::<i64>()
+                / slot_seconds) as usize;
+            let spot_prices: Vec<SpotPrice> = planning_request
+                .spot_prices
+                .iter()
+                .filter(|sp| !matches!(planning_request.after, Some(after) if sp.from < after))
+                .filter(|sp| !matches!(planning_request.before, Some(before) if sp.till > before))
+                .cloned()
+                .collect();
+
+            let mut best_window: Option<(f64, &[SpotPrice])> = None;
+            for window in spot_prices.windows(slots) {
+                let total_price: f64 = window.iter().map(|sp| sp.market_price).sum();
+                let better = match (best_window, &planning_request.planning_strategy) {
+                    (None, _) => true,
+                    (Some((best_price, _)), PlanningStrategy::LowestPrice) => {
+                        total_price < best_price
+                    }
+                    (Some((best_price, _)), _) => total_price > best_price,
+                };
+                if better {
+                    best_window = Some((total_price, window));
+                }
+            }
+
+            Ok(PlanningResponse {
+                spot_prices: best_window
+                    .map(|(_, window)| window.to_vec())
+                    .unwrap_or_default(),
+                load_profile: planning_request.load_profile.clone(),
+            })
+        }
+    }
+
+    fn week_of_spot_prices() -> Vec<SpotPrice> {
+        // monday till monday, cheap at night and expensive in the evening
+        let week_from = Utc.with_ymd_and_hms(2022, 4, 18, 0, 0, 0).unwrap();
+        (0..7 * 24)
+            .map(|hour| {
+                let from = week_from + Duration::hours(hour);
+                SpotPrice {
+                    market_price: match hour % 24 {
+                        3 | 4 => 0.05,
+                        18 | 19 => 0.4,
+                        _ => 0.2,
+                    },
+                    market_price_tax: 0.0,
+                    sourcing_markup_price: 0.0,
+                    energy_tax_price: 0.0,
+                    ..spot_price(from, from + Duration::hours(1))
+                }
+            })
+            .collect()
+    }
+
+    fn week_of_quarter_hour_spot_prices() -> Vec<SpotPrice> {
+        // the same week split in quarter hour settlement slots
+        week_of_spot_prices()
+            .iter()
+            .flat_map(|hourly_spot_price| {
+                (0..4).map(move |quarter| {
+                    let from = hourly_spot_price.from + Duration::minutes(15 * quarter);
+                    SpotPrice {
+                        from,
+                        till: from + Duration::minutes(15),
+                        ..hourly_spot_price.clone()
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn initial_state() -> State {
+        State {
+            desinfection_enabled: false,
+            desinfection_finished_at: Some(Utc.with_ymd_and_hms(2022, 4, 17, 5, 0, 0).unwrap()),
+            planned_spot_prices: None,
+            planning_history: vec![],
+            planned_cost_eur: None,
+            away_until: None,
+            written_schedule: None,
+            energy_stats_history: vec![],
+            blocked_spot_prices: None,
+            desinfection_decisions: vec![],
+            tap_water_setpoint: None,
+            do_not_disturb_until: None,
+            eco_desinfection_finished_at: None,
+            cooling_spot_prices: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_records_daily_tap_water_heating_and_sunday_desinfection() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let summary = heat_pump
+            .replay(
+                &test_config(),
+                WindowPlanner {},
+                &week_of_spot_prices(),
+                &initial_state(),
+            )
+            .unwrap();
+
+        assert_eq!(summary.decisions.len(), 7);
+        assert!(summary
+            .decisions
+            .iter()
+            .all(|decision| decision.from.hour() == 3 && decision.till.hour() == 5));
+        assert_eq!(
+            summary.decisions[6],
+            ReplayDecision {
+                decided_at: Utc.with_ymd_and_hms(2022, 4, 24, 3, 0, 0).unwrap(),
+                strategy: TapWaterStrategy::Desinfection,
+                from: Utc.with_ymd_and_hms(2022, 4, 24, 3, 0, 0).unwrap(),
+                till: Utc.with_ymd_and_hms(2022, 4, 24, 5, 0, 0).unwrap(),
+                hours_since_last_desinfection: 166,
+                cost_eur: 0.2,
+                cumulative_cost_eur: summary.decisions[6].cumulative_cost_eur,
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "6 tap water heatings, 1 desinfections, at most 166 hours between desinfections, total cost €1.40"
+                .to_string()
+        );
+        assert_eq!(heat_pump.saves(), 0);
+    }
+
+    #[tokio::test]
+    async fn replay_plans_the_same_windows_for_quarter_hour_spot_prices() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let summary = heat_pump
+            .replay(
+                &test_config(),
+                WindowPlanner {},
+                &week_of_quarter_hour_spot_prices(),
+                &initial_state(),
+            )
+            .unwrap();
+
+        assert_eq!(summary.decisions.len(), 7);
+        assert!(summary
+            .decisions
+            .iter()
+            .all(|decision| decision.from.hour() == 3 && decision.till.hour() == 5));
+        assert_eq!(
+            summary.to_string(),
+            "6 tap water heatings, 1 desinfections, at most 166 hours between desinfections, total cost €1.40"
+                .to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_summaries_differ_between_config_variants() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let summary = heat_pump
+            .replay(
+                &Config {
+                    desinfection_cooldown_hours: 8 * 24,
+                    ..test_config()
+                },
+                WindowPlanner {},
+                &week_of_spot_prices(),
+                &initial_state(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            summary.to_string(),
+            "7 tap water heatings, 0 desinfections, at most 187 hours between desinfections, total cost €1.40"
+                .to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_records_eco_desinfections_between_full_desinfections() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let summary = heat_pump
+            .replay(
+                &Config {
+                    eco_desinfection_temperature: Some(58.0),
+                    ..test_config()
+                },
+                WindowPlanner {},
+                &week_of_spot_prices(),
+                &initial_state(),
+            )
+            .unwrap();
+
+        // the sunday desinfection finished on the previous sunday, so thursday is the first day past 72 hours
+        assert_eq!(
+            summary
+                .decisions
+                .iter()
+                .map(|decision| decision.strategy.clone())
+                .collect::<Vec<TapWaterStrategy>>(),
+            vec![
+                TapWaterStrategy::TapWaterHeating,
+                TapWaterStrategy::TapWaterHeating,
+                TapWaterStrategy::TapWaterHeating,
+                TapWaterStrategy::EcoDesinfection,
+                TapWaterStrategy::TapWaterHeating,
+                TapWaterStrategy::TapWaterHeating,
+                TapWaterStrategy::Desinfection,
+            ]
+        );
+        assert_eq!(
+            summary.to_string(),
+            "5 tap water heatings, 1 desinfections, 1 eco desinfections, at most 166 hours between desinfections, total cost €1.40"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn replay_runs_on_heat_pump_without_menus() {
+        let heat_pump = SimulatedHeatPump::for_replay().unwrap();
+
+        // act
+        let summary = heat_pump
+            .replay(
+                &test_config(),
+                WindowPlanner {},
+                &week_of_spot_prices(),
+                &initial_state(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            summary.to_string(),
+            "6 tap water heatings, 1 desinfections, at most 166 hours between desinfections, total cost €1.40"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn replay_returns_error_without_spot_prices() {
+        let heat_pump =
+            simulated_heat_pump(&[("Verwarmen", "Automatisch"), ("Warmwater", "Automatisch")]);
+
+        // act
+        let result = heat_pump.replay(&test_config(), WindowPlanner {}, &[], &initial_state());
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::http_client;
+use crate::model::Config;
+use crate::spot_prices_retrier::SpotPricesRetrier;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use jarvis_lib::config_client::ConfigClient;
+use jarvis_lib::model::{SpotPrice, SpotPricePlanner, SpotPricePlannerConfig};
+use jarvis_lib::planner_client::PlannerClient;
+use jarvis_lib::spot_prices_state_client::SpotPricesStateClient;
+use std::env;
+use std::error::Error;
+use std::fs;
+use tracing::{debug, info, warn};
+
+const FETCH_TIMEOUT_SECONDS: u64 = 30;
+
+#[async_trait]
+pub trait SpotPriceSource: Send + Sync {
+    async fn fetch(&self) -> Result<Vec<SpotPrice>, Box<dyn Error>>;
+}
+
+#[async_trait]
+impl SpotPriceSource for SpotPricesStateClient {
+    async fn fetch(&self) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        Ok(match self.read_state()? {
+            Some(state) => state.future_spot_prices,
+            None => vec![],
+        })
+    }
+}
+
+pub struct HttpSpotPriceSource {
+    url: String,
+}
+
+impl HttpSpotPriceSource {
+    pub fn new(url: String) -> Self {
+        debug!("HttpSpotPriceSource::new(url: {})", url);
+
+        Self { url }
+    }
+}
+
+#[async_trait]
+impl SpotPriceSource for HttpSpotPriceSource {
+    async fn fetch(&self) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        // like the notification webhook, price sources are expected on the local network, so plain http is all that's supported
+        let response = http_client::get(&self.url, FETCH_TIMEOUT_SECONDS).await?;
+        if !response.is_success() {
+            return Err(Box::<dyn Error>::from(format!(
+                "Fetching spot prices from {} failed with response {}",
+                self.url, response.status_line
+            )));
+        }
+
+        let spot_prices: Vec<SpotPrice> = serde_json::from_str(&response.body)?;
+        info!(
+            "Fetched {} spot prices from {}",
+            spot_prices.len(),
+            self.url
+        );
+
+        Ok(spot_prices)
+    }
+}
+
+pub struct CsvSpotPriceSource {
+    path: String,
+}
+
+impl CsvSpotPriceSource {
+    pub fn new(path: String) -> Self {
+        debug!("CsvSpotPriceSource::new(path: {})", path);
+
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl SpotPriceSource for CsvSpotPriceSource {
+    async fn fetch(&self) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        let spot_prices = parse_spot_prices_csv(&fs::read_to_string(&self.path)?)?;
+        info!("Read {} spot prices from {}", spot_prices.len(), self.path);
+
+        Ok(spot_prices)
+    }
+}
+
+fn parse_spot_prices_csv(contents: &str) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+    // from,till,marketPrice,marketPriceTax,sourcingMarkupPrice,energyTaxPrice with a header line
+    contents
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            if fields.len() != 6 {
+                return Err(Box::<dyn Error>::from(format!(
+                    "Line {} of spot prices csv has {} fields, expected 6",
+                    index + 2,
+                    fields.len()
+                )));
+            }
+
+            Ok(SpotPrice {
+                id: None,
+                source: Some("csv".to_string()),
+                from: DateTime::parse_from_rfc3339(fields[0])?.with_timezone(&Utc),
+                till: DateTime::parse_from_rfc3339(fields[1])?.with_timezone(&Utc),
+                market_price: fields[2].parse()?,
+                market_price_tax: fields[3].parse()?,
+                sourcing_markup_price: fields[4].parse()?,
+                energy_tax_price: fields[5].parse()?,
+            })
+        })
+        .collect()
+}
+
+pub fn from_env() -> Result<Option<Box<dyn SpotPriceSource>>, Box<dyn Error>> {
+    // without an alternative source the spot prices are read from the jarvis spot price state
+    match env::var("SPOT_PRICE_SOURCE") {
+        Ok(source) if source == "http" => Ok(Some(Box::new(HttpSpotPriceSource::new(env::var(
+            "SPOT_PRICE_SOURCE_URL",
+        )?)))),
+        Ok(source) if source == "csv" => Ok(Some(Box::new(CsvSpotPriceSource::new(env::var(
+            "SPOT_PRICE_SOURCE_PATH",
+        )?)))),
+        Ok(source) if source == "jarvis" => Ok(None),
+        Ok(source) => Err(Box::<dyn Error>::from(format!(
+            "SPOT_PRICE_SOURCE {} is not supported, use jarvis, http or csv",
+            source
+        ))),
+        Err(_) => Ok(None),
+    }
+}
+
+pub async fn plan_from_source(
+    config_client: &ConfigClient,
+    spot_price_source: &dyn SpotPriceSource,
+    spot_prices_retrier: &SpotPricesRetrier,
+    planner_client: &dyn PlannerClient<Config>,
+) -> Result<(), Box<dyn Error>> {
+    let config: Config = config_client.read_config_from_file()?;
+    let spot_price_planner_config: SpotPricePlannerConfig =
+        config_client.read_config_from_file()?;
+
+    let spot_prices = spot_prices_retrier
+        .with_retries(config.spot_price_fetch_retries)
+        .fetch_future_spot_prices(spot_price_source, Utc::now())
+        .await?;
+    if spot_prices.is_empty() {
+        warn!("Spot prices failed to load, skipping this planning run");
+        return Ok(());
+    }
+
+    planner_client
+        .plan(
+            config,
+            SpotPricePlanner::new(spot_price_planner_config),
+            spot_prices,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn parse_spot_prices_csv_skips_header_and_empty_lines() {
+        // act
+        let spot_prices = parse_spot_prices_csv(
+            "from,till,marketPrice,marketPriceTax,sourcingMarkupPrice,energyTaxPrice\n2022-04-21T12:00:00Z,2022-04-21T13:00:00Z,0.157,0.033,0.017,0.081\n\n2022-04-21T13:00:00Z,2022-04-21T14:00:00Z,-0.010,0.0,0.017,0.081\n",
+        )
+        .unwrap();
+
+        assert_eq!(spot_prices.len(), 2);
+        assert_eq!(
+            spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap()
+        );
+        assert_eq!(spot_prices[0].market_price, 0.157);
+        assert_eq!(spot_prices[1].market_price, -0.010);
+    }
+
+    #[test]
+    fn parse_spot_prices_csv_returns_error_for_missing_fields() {
+        // act
+        let result = parse_spot_prices_csv(
+            "from,till,marketPrice,marketPriceTax,sourcingMarkupPrice,energyTaxPrice\n2022-04-21T12:00:00Z,2022-04-21T13:00:00Z,0.157\n",
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn http_spot_price_source_fetches_json_spot_prices() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 1024];
+            let _ = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n[{\"from\":\"2022-04-21T12:00:00Z\",\"till\":\"2022-04-21T13:00:00Z\",\"marketPrice\":0.157,\"marketPriceTax\":0.033,\"sourcingMarkupPrice\":0.017,\"energyTaxPrice\":0.081}]")
+                .await
+                .unwrap();
+        });
+        let source = HttpSpotPriceSource::new(format!("http://127.0.0.1:{}/prices", port));
+
+        // act
+        let spot_prices = source.fetch().await.unwrap();
+
+        assert_eq!(spot_prices.len(), 1);
+        assert_eq!(spot_prices[0].market_price, 0.157);
+    }
+}
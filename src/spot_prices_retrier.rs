@@ -0,0 +1,268 @@
+use crate::spot_price_source::SpotPriceSource;
+use chrono::{DateTime, Utc};
+use jarvis_lib::model::SpotPrice;
+use std::env;
+use std::error::Error;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, info, warn};
+
+pub struct SpotPricesRetrierConfig {
+    max_attempts: u32,
+    initial_backoff_seconds: u64,
+}
+
+impl SpotPricesRetrierConfig {
+    pub fn new(max_attempts: u32, initial_backoff_seconds: u64) -> Result<Self, Box<dyn Error>> {
+        debug!(
+            "SpotPricesRetrierConfig::new(max_attempts: {}, initial_backoff_seconds: {})",
+            max_attempts, initial_backoff_seconds
+        );
+
+        if max_attempts == 0 {
+            return Err(Box::<dyn Error>::from(
+                "SPOT_PRICES_RETRY_MAX_ATTEMPTS should be larger than 0",
+            ));
+        }
+
+        Ok(Self {
+            max_attempts,
+            initial_backoff_seconds,
+        })
+    }
+
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let max_attempts: u32 = env::var("SPOT_PRICES_RETRY_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()?;
+        let initial_backoff_seconds: u64 = env::var("SPOT_PRICES_RETRY_INITIAL_BACKOFF_SECONDS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?;
+
+        Self::new(max_attempts, initial_backoff_seconds)
+    }
+}
+
+pub struct SpotPricesRetrier {
+    config: SpotPricesRetrierConfig,
+}
+
+impl SpotPricesRetrier {
+    pub fn new(config: SpotPricesRetrierConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(SpotPricesRetrierConfig::from_env()?))
+    }
+
+    // the config's spotPriceFetchRetries takes precedence over the attempts from the environment, keeping the backoff
+    pub fn with_retries(&self, retries: Option<u32>) -> Self {
+        Self::new(SpotPricesRetrierConfig {
+            max_attempts: retries.map_or(self.config.max_attempts, |retries| retries + 1),
+            initial_backoff_seconds: self.config.initial_backoff_seconds,
+        })
+    }
+
+    // returns no spot prices when there are still none after the last attempt, and the error of the last attempt
+    // when that one failed
+    pub async fn fetch_future_spot_prices(
+        &self,
+        spot_price_source: &dyn SpotPriceSource,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        // spot prices usually become available within minutes, so retry instead of skipping planning for a whole day
+        let mut backoff_seconds = self.config.initial_backoff_seconds;
+
+        for attempt in 1..=self.config.max_attempts {
+            match spot_price_source.fetch().await {
+                Ok(spot_prices) => {
+                    // the planner only looks ahead, so leave out what already passed
+                    let future_spot_prices: Vec<SpotPrice> = spot_prices
+                        .into_iter()
+                        .filter(|spot_price| spot_price.till > now)
+                        .collect();
+                    if !future_spot_prices.is_empty() {
+                        info!(
+                            "Found {} future spot prices at attempt {}/{}",
+                            future_spot_prices.len(),
+                            attempt,
+                            self.config.max_attempts
+                        );
+                        return Ok(future_spot_prices);
+                    }
+
+                    info!(
+                        "No future spot prices available at attempt {}/{}",
+                        attempt, self.config.max_attempts
+                    );
+                }
+                Err(e) if attempt == self.config.max_attempts => return Err(e),
+                Err(e) => warn!(
+                    "Failed reading spot prices at attempt {}/{}: {}",
+                    attempt, self.config.max_attempts, e
+                ),
+            }
+
+            if attempt < self.config.max_attempts {
+                info!(
+                    "Retrying reading spot prices in {} seconds",
+                    backoff_seconds
+                );
+                sleep(Duration::from_secs(backoff_seconds)).await;
+                backoff_seconds *= 2;
+            }
+        }
+
+        warn!(
+            "No future spot prices available after {} attempts, giving up",
+            self.config.max_attempts
+        );
+
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+
+    // returns the queued fetch results in order, and no spot prices once they ran out
+    struct QueuedSpotPriceSource {
+        results: std::sync::Mutex<Vec<Result<Vec<SpotPrice>, String>>>,
+        fetches: std::sync::Mutex<usize>,
+    }
+
+    impl QueuedSpotPriceSource {
+        fn new(results: Vec<Result<Vec<SpotPrice>, String>>) -> Self {
+            Self {
+                results: std::sync::Mutex::new(results),
+                fetches: std::sync::Mutex::new(0),
+            }
+        }
+
+        fn fetches(&self) -> usize {
+            *self.fetches.lock().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl SpotPriceSource for QueuedSpotPriceSource {
+        async fn fetch(&self) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+            *self.fetches.lock().unwrap() += 1;
+            let mut results = self.results.lock().unwrap();
+            if results.is_empty() {
+                return Ok(vec![]);
+            }
+
+            results.remove(0).map_err(Box::<dyn Error>::from)
+        }
+    }
+
+    fn spot_price(from: DateTime<Utc>) -> SpotPrice {
+        SpotPrice {
+            id: None,
+            source: None,
+            from,
+            till: from + chrono::Duration::hours(1),
+            market_price: 0.157,
+            market_price_tax: 0.033,
+            sourcing_markup_price: 0.017,
+            energy_tax_price: 0.081,
+        }
+    }
+
+    fn retrier(max_attempts: u32) -> SpotPricesRetrier {
+        SpotPricesRetrier::new(SpotPricesRetrierConfig::new(max_attempts, 0).unwrap())
+    }
+
+    #[tokio::test]
+    async fn fetch_future_spot_prices_retries_until_fetch_succeeds() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let source = QueuedSpotPriceSource::new(vec![
+            Err("connection refused".to_string()),
+            Ok(vec![]),
+            Ok(vec![
+                spot_price(now),
+                spot_price(now + chrono::Duration::hours(1)),
+            ]),
+        ]);
+
+        // act
+        let spot_prices = retrier(3)
+            .fetch_future_spot_prices(&source, now)
+            .await
+            .unwrap();
+
+        assert_eq!(spot_prices.len(), 2);
+        assert_eq!(source.fetches(), 3);
+    }
+
+    #[tokio::test]
+    async fn fetch_future_spot_prices_returns_empty_after_retrying_without_future_prices() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let source = QueuedSpotPriceSource::new(vec![
+            Err("connection refused".to_string()),
+            Ok(vec![spot_price(now - chrono::Duration::hours(1))]),
+        ]);
+
+        // act
+        let spot_prices = retrier(3)
+            .fetch_future_spot_prices(&source, now)
+            .await
+            .unwrap();
+
+        assert!(spot_prices.is_empty());
+        assert_eq!(source.fetches(), 3);
+    }
+
+    #[tokio::test]
+    async fn with_retries_fetches_one_more_time_than_retries() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let source = QueuedSpotPriceSource::new(vec![]);
+
+        // act
+        let spot_prices = retrier(5)
+            .with_retries(Some(1))
+            .fetch_future_spot_prices(&source, now)
+            .await
+            .unwrap();
+
+        assert!(spot_prices.is_empty());
+        assert_eq!(source.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn with_retries_keeps_attempts_from_environment_without_retries() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let source = QueuedSpotPriceSource::new(vec![]);
+
+        // act
+        retrier(3)
+            .with_retries(None)
+            .fetch_future_spot_prices(&source, now)
+            .await
+            .unwrap();
+
+        assert_eq!(source.fetches(), 3);
+    }
+
+    #[tokio::test]
+    async fn fetch_future_spot_prices_returns_error_of_last_failed_attempt() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let source = QueuedSpotPriceSource::new(vec![
+            Err("connection refused".to_string()),
+            Err("connection reset".to_string()),
+        ]);
+
+        // act
+        let result = retrier(2).fetch_future_spot_prices(&source, now).await;
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "connection reset".to_string()
+        );
+        assert_eq!(source.fetches(), 2);
+    }
+}
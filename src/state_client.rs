@@ -1,39 +1,55 @@
 use crate::model::State;
-use k8s_openapi::api::core::v1::ConfigMap;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::{ConfigMap, Event, EventSource, ObjectReference};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
 use kube::{
-    api::{Api, PostParams},
+    api::{Api, Patch, PatchParams, PostParams},
     Client,
 };
 use std::env;
 use std::error::Error;
 use std::fs;
+use std::io::ErrorKind;
 use std::path::Path;
-use tracing::debug;
+use tracing::{debug, error};
 
 pub struct StateClientConfig {
     kube_client: kube::Client,
     state_file_path: String,
     state_file_configmap_name: String,
+    state_configmap_key: String,
     current_namespace: String,
+    max_planning_history_length: usize,
+    strict_state_parsing: bool,
+    emit_kube_events: bool,
 }
 
 impl StateClientConfig {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         kube_client: kube::Client,
         state_file_path: String,
         state_file_configmap_name: String,
+        state_configmap_key: String,
         current_namespace: String,
+        max_planning_history_length: usize,
+        strict_state_parsing: bool,
+        emit_kube_events: bool,
     ) -> Result<Self, Box<dyn Error>> {
         debug!(
-          "StateClientConfig::new(state_file_path: {}, state_file_configmap_name: {}, current_namespace: {})",
-          state_file_path, state_file_configmap_name, current_namespace
+          "StateClientConfig::new(state_file_path: {}, state_file_configmap_name: {}, state_configmap_key: {}, current_namespace: {}, max_planning_history_length: {}, strict_state_parsing: {}, emit_kube_events: {})",
+          state_file_path, state_file_configmap_name, state_configmap_key, current_namespace, max_planning_history_length, strict_state_parsing, emit_kube_events
         );
 
         Ok(Self {
             kube_client,
             state_file_path,
             state_file_configmap_name,
+            state_configmap_key,
             current_namespace,
+            max_planning_history_length,
+            strict_state_parsing,
+            emit_kube_events,
         })
     }
 
@@ -44,15 +60,35 @@ impl StateClientConfig {
             env::var("STATE_FILE_PATH").unwrap_or_else(|_| "/configs/last-state.yaml".to_string());
         let state_file_configmap_name = env::var("STATE_FILE_CONFIG_MAP_NAME")
             .unwrap_or_else(|_| "jarvis-alpha-innotec-planner".to_string());
+        let state_configmap_key =
+            get_state_configmap_key(env::var("STATE_CONFIGMAP_KEY").ok(), &state_file_path)?;
 
+        let service_account_path = env::var("KUBE_SERVICE_ACCOUNT_PATH")
+            .unwrap_or_else(|_| "/var/run/secrets/kubernetes.io/serviceaccount".to_string());
         let current_namespace =
-            fs::read_to_string("/var/run/secrets/kubernetes.io/serviceaccount/namespace")?;
+            get_current_namespace(env::var("KUBE_NAMESPACE").ok(), &service_account_path)?;
+
+        let max_planning_history_length: usize = env::var("STATE_MAX_PLANNING_HISTORY_LENGTH")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()?;
+
+        let strict_state_parsing: bool = env::var("STATE_STRICT_PARSING")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()?;
+
+        let emit_kube_events: bool = env::var("EMIT_KUBE_EVENTS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()?;
 
         Self::new(
             kube_client,
             state_file_path,
             state_file_configmap_name,
+            state_configmap_key,
             current_namespace,
+            max_planning_history_length,
+            strict_state_parsing,
+            emit_kube_events,
         )
     }
 }
@@ -71,51 +107,37 @@ impl StateClient {
     }
 
     pub fn read_state(&self) -> Result<Option<State>, Box<dyn std::error::Error>> {
-        let state_file_contents = match fs::read_to_string(&self.config.state_file_path) {
-            Ok(c) => c,
-            Err(_) => return Ok(Option::None),
-        };
+        let last_state = read_state_from_file(
+            &self.config.state_file_path,
+            self.config.strict_state_parsing,
+        )?;
 
-        let last_state: Option<State> = match serde_yaml::from_str(&state_file_contents) {
-            Ok(lm) => Some(lm),
-            Err(_) => return Ok(Option::None),
-        };
-
-        println!(
-            "Read previous state from state file at {}",
-            &self.config.state_file_path
-        );
+        if last_state.is_some() {
+            println!(
+                "Read previous state from state file at {}",
+                &self.config.state_file_path
+            );
+        }
 
         Ok(last_state)
     }
 
-    async fn get_state_configmap(&self) -> Result<ConfigMap, Box<dyn std::error::Error>> {
-        let configmaps_api: Api<ConfigMap> = Api::namespaced(
-            self.config.kube_client.clone(),
-            &self.config.current_namespace,
-        );
-
-        let config_map = configmaps_api
-            .get(&self.config.state_file_configmap_name)
-            .await?;
-
-        Ok(config_map)
-    }
-
-    async fn update_state_configmap(
+    async fn patch_state_configmap(
         &self,
-        config_map: &ConfigMap,
+        state_patch: &serde_json::Value,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let configmaps_api: Api<ConfigMap> = Api::namespaced(
             self.config.kube_client.clone(),
             &self.config.current_namespace,
         );
 
+        // a merge patch only touches the keys in it, so other tools writing their own key to the configmap at the
+        // same time keep their data, where replacing the whole object would overwrite it
         configmaps_api
-            .replace(
+            .patch(
                 &self.config.state_file_configmap_name,
-                &PostParams::default(),
-                config_map,
+                &PatchParams::default(),
+                &Patch::Merge(state_patch),
             )
             .await?;
 
@@ -123,39 +145,448 @@ impl StateClient {
     }
 
     pub async fn store_state(&self, state: &State) -> Result<(), Box<dyn std::error::Error>> {
-        // retrieve configmap
-        let mut config_map = self.get_state_configmap().await?;
+        // only keep the most recent planning history to keep the configmap small
+        let mut state = state.clone();
+        cap_history(
+            &mut state.planning_history,
+            self.config.max_planning_history_length,
+        );
+        cap_history(
+            &mut state.energy_stats_history,
+            self.config.max_planning_history_length,
+        );
 
         // marshal state to yaml
-        let yaml_data = match serde_yaml::to_string(state) {
+        let yaml_data = match serde_yaml::to_string(&state) {
             Ok(yd) => yd,
             Err(e) => return Err(Box::new(e)),
         };
 
-        // extract filename from config file path
-        let state_file_path = Path::new(&self.config.state_file_path);
-        let state_file_name = match state_file_path.file_name() {
-            Some(filename) => match filename.to_str() {
-                Some(filename) => String::from(filename),
-                None => return Err(Box::<dyn Error>::from("No filename found in path")),
-            },
-            None => return Err(Box::<dyn Error>::from("No filename found in path")),
-        };
-
-        // update data in configmap
-        let mut data: std::collections::BTreeMap<String, String> =
-            config_map.data.unwrap_or_default();
-        data.insert(state_file_name, yaml_data);
-        config_map.data = Some(data);
-
         // update configmap to have measurement available when the application runs the next time and for other applications
-        self.update_state_configmap(&config_map).await?;
+        self.patch_state_configmap(&build_state_patch(
+            &self.config.state_configmap_key,
+            &yaml_data,
+        ))
+        .await?;
 
         println!(
-            "Stored last state in configmap {}",
-            &self.config.state_file_configmap_name
+            "Stored last state under key {} in configmap {}",
+            &self.config.state_configmap_key, &self.config.state_file_configmap_name
         );
 
         Ok(())
     }
+
+    pub async fn emit_event(&self, reason: &str, message: &str) -> Result<(), Box<dyn Error>> {
+        if !self.config.emit_kube_events {
+            return Ok(());
+        }
+
+        let events_api: Api<Event> = Api::namespaced(
+            self.config.kube_client.clone(),
+            &self.config.current_namespace,
+        );
+
+        events_api
+            .create(
+                &PostParams::default(),
+                &build_event(
+                    &self.config.current_namespace,
+                    &self.config.state_file_configmap_name,
+                    reason,
+                    message,
+                    Utc::now(),
+                ),
+            )
+            .await?;
+
+        debug!("Emitted event {}: {}", reason, message);
+
+        Ok(())
+    }
+}
+
+fn build_state_patch(state_file_name: &str, yaml_data: &str) -> serde_json::Value {
+    // without a resource version the patch isn't rejected when the configmap changed since it was read
+    serde_json::json!({
+        "data": {
+            state_file_name: yaml_data,
+        }
+    })
+}
+
+fn build_event(
+    namespace: &str,
+    configmap_name: &str,
+    reason: &str,
+    message: &str,
+    now: DateTime<Utc>,
+) -> Event {
+    // the state configmap is the one object every run touches, so the events show up when describing it
+    Event {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-", configmap_name)),
+            namespace: Some(namespace.to_string()),
+            ..ObjectMeta::default()
+        },
+        involved_object: ObjectReference {
+            api_version: Some("v1".to_string()),
+            kind: Some("ConfigMap".to_string()),
+            name: Some(configmap_name.to_string()),
+            namespace: Some(namespace.to_string()),
+            ..ObjectReference::default()
+        },
+        reason: Some(reason.to_string()),
+        message: Some(message.to_string()),
+        type_: Some("Normal".to_string()),
+        count: Some(1),
+        first_timestamp: Some(Time(now)),
+        last_timestamp: Some(Time(now)),
+        source: Some(EventSource {
+            component: Some("jarvis-alpha-innotec-planner".to_string()),
+            ..EventSource::default()
+        }),
+        reporting_component: Some("jarvis-alpha-innotec-planner".to_string()),
+        ..Event::default()
+    }
+}
+
+fn read_state_from_file(
+    state_file_path: &str,
+    strict_state_parsing: bool,
+) -> Result<Option<State>, Box<dyn Error>> {
+    // a missing state file is expected before the first run, an unreadable or corrupt one is not
+    let state_file_contents = match fs::read_to_string(state_file_path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return handle_invalid_state_file(
+                format!("Failed reading state file at {}: {}", state_file_path, e),
+                strict_state_parsing,
+            )
+        }
+    };
+
+    match serde_yaml::from_str(&state_file_contents) {
+        Ok(state) => Ok(Some(state)),
+        Err(e) => handle_invalid_state_file(
+            format!("Failed parsing state file at {}: {}", state_file_path, e),
+            strict_state_parsing,
+        ),
+    }
+}
+
+fn handle_invalid_state_file(
+    message: String,
+    strict_state_parsing: bool,
+) -> Result<Option<State>, Box<dyn Error>> {
+    if strict_state_parsing {
+        Err(Box::<dyn Error>::from(message))
+    } else {
+        error!("{}, continuing without previous state", message);
+        Ok(None)
+    }
+}
+
+fn get_current_namespace(
+    namespace: Option<String>,
+    service_account_path: &str,
+) -> Result<String, Box<dyn Error>> {
+    // outside the cluster, with an injected kubeconfig, there's no service account to read the namespace from
+    if let Some(namespace) = namespace.filter(|namespace| !namespace.is_empty()) {
+        return Ok(namespace);
+    }
+
+    let namespace_file_path = Path::new(service_account_path).join("namespace");
+    let namespace = fs::read_to_string(&namespace_file_path).map_err(|e| {
+        format!(
+            "Failed reading namespace from {}, set KUBE_NAMESPACE when not running in-cluster: {}",
+            namespace_file_path.display(),
+            e
+        )
+    })?;
+
+    Ok(namespace.trim().to_string())
+}
+
+fn get_state_configmap_key(
+    state_configmap_key: Option<String>,
+    state_file_path: &str,
+) -> Result<String, Box<dyn Error>> {
+    // the configmap can be mounted at a path that doesn't end in its key, only defaulting to the file name keeps
+    // existing deployments reading back what they store
+    let state_configmap_key = match state_configmap_key.filter(|key| !key.is_empty()) {
+        Some(state_configmap_key) => state_configmap_key,
+        None => match Path::new(state_file_path)
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+        {
+            Some(file_name) => file_name.to_string(),
+            None => {
+                return Err(Box::<dyn Error>::from(format!(
+                    "No filename found in state file path {}, set STATE_CONFIGMAP_KEY",
+                    state_file_path
+                )))
+            }
+        },
+    };
+
+    if !state_configmap_key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err(Box::<dyn Error>::from(format!(
+            "State configmap key {} should only contain alphanumeric characters, '-', '_' or '.'",
+            state_configmap_key
+        )));
+    }
+
+    Ok(state_configmap_key)
+}
+
+fn cap_history<T>(history: &mut Vec<T>, max_length: usize) {
+    if history.len() > max_length {
+        let excess_length = history.len() - max_length;
+        history.drain(..excess_length);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{PlanRecord, TapWaterStrategy};
+    use chrono::{TimeZone, Utc};
+
+    fn plan_record(hour: u32) -> PlanRecord {
+        PlanRecord {
+            planned_at: Utc.with_ymd_and_hms(2022, 4, 21, hour, 0, 0).unwrap(),
+            strategy: TapWaterStrategy::TapWaterHeating,
+            desinfection_desired: false,
+            planned_from: None,
+            planned_till: None,
+        }
+    }
+
+    #[test]
+    fn cap_planning_history_drops_oldest_records() {
+        let mut planning_history = vec![plan_record(1), plan_record(2), plan_record(3)];
+
+        // act
+        cap_history(&mut planning_history, 2);
+
+        assert_eq!(planning_history, vec![plan_record(2), plan_record(3)]);
+    }
+
+    #[test]
+    fn cap_planning_history_keeps_history_shorter_than_max_length() {
+        let mut planning_history = vec![plan_record(1), plan_record(2)];
+
+        // act
+        cap_history(&mut planning_history, 5);
+
+        assert_eq!(planning_history, vec![plan_record(1), plan_record(2)]);
+    }
+
+    fn apply_merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+        // json merge patch as the api server applies it, see rfc 7386
+        match (target.as_object_mut(), patch.as_object()) {
+            (Some(target), Some(patch)) => {
+                for (key, value) in patch {
+                    if value.is_null() {
+                        target.remove(key);
+                    } else {
+                        apply_merge_patch(
+                            target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                            value,
+                        );
+                    }
+                }
+            }
+            _ => *target = patch.clone(),
+        }
+    }
+
+    #[test]
+    fn build_state_patch_keeps_unrelated_configmap_keys() {
+        let mut config_map = serde_json::to_value(ConfigMap {
+            metadata: ObjectMeta {
+                name: Some("jarvis-alpha-innotec-planner".to_string()),
+                ..ObjectMeta::default()
+            },
+            data: Some(
+                vec![
+                    ("last-state.yaml".to_string(), "previous".to_string()),
+                    ("other-tool.yaml".to_string(), "theirs".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..ConfigMap::default()
+        })
+        .unwrap();
+
+        // act
+        let state_patch = build_state_patch("last-state.yaml", "desinfectionEnabled: false\n");
+
+        apply_merge_patch(&mut config_map, &state_patch);
+        let config_map: ConfigMap = serde_json::from_value(config_map).unwrap();
+        let data = config_map.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data["last-state.yaml"], "desinfectionEnabled: false\n");
+        assert_eq!(data["other-tool.yaml"], "theirs");
+        assert_eq!(
+            config_map.metadata.name,
+            Some("jarvis-alpha-innotec-planner".to_string())
+        );
+    }
+
+    #[test]
+    fn build_state_patch_only_contains_state_key() {
+        // act
+        let state_patch = build_state_patch("last-state.yaml", "desinfectionEnabled: false\n");
+
+        assert_eq!(
+            state_patch,
+            serde_json::json!({"data": {"last-state.yaml": "desinfectionEnabled: false\n"}})
+        );
+    }
+
+    fn write_state_file(name: &str, contents: &str) -> String {
+        let state_file_path = env::temp_dir().join(name);
+        fs::write(&state_file_path, contents).unwrap();
+
+        state_file_path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn build_event_refers_to_state_configmap() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        // act
+        let event = build_event(
+            "jarvis",
+            "jarvis-alpha-innotec-planner",
+            "TapWaterScheduled",
+            "Scheduled tap water heating",
+            now,
+        );
+
+        assert_eq!(event.reason, Some("TapWaterScheduled".to_string()));
+        assert_eq!(
+            event.message,
+            Some("Scheduled tap water heating".to_string())
+        );
+        assert_eq!(event.involved_object.kind, Some("ConfigMap".to_string()));
+        assert_eq!(
+            event.involved_object.name,
+            Some("jarvis-alpha-innotec-planner".to_string())
+        );
+        assert_eq!(event.metadata.namespace, Some("jarvis".to_string()));
+        assert_eq!(event.last_timestamp, Some(Time(now)));
+    }
+
+    #[test]
+    fn get_current_namespace_prefers_namespace_from_env() {
+        // act
+        let namespace =
+            get_current_namespace(Some("jarvis".to_string()), "/nonexistent/serviceaccount")
+                .unwrap();
+
+        assert_eq!(namespace, "jarvis".to_string());
+    }
+
+    #[test]
+    fn get_current_namespace_reads_namespace_from_service_account_path() {
+        let service_account_path = env::temp_dir().join("get-current-namespace-serviceaccount");
+        fs::create_dir_all(&service_account_path).unwrap();
+        fs::write(service_account_path.join("namespace"), "jarvis\n").unwrap();
+
+        // act
+        let namespace =
+            get_current_namespace(None, service_account_path.to_str().unwrap()).unwrap();
+
+        assert_eq!(namespace, "jarvis".to_string());
+    }
+
+    #[test]
+    fn get_current_namespace_returns_error_without_env_and_service_account() {
+        // act
+        let result = get_current_namespace(None, "/nonexistent/serviceaccount");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_state_configmap_key_defaults_to_state_file_name() {
+        // act
+        let state_configmap_key =
+            get_state_configmap_key(None, "/configs/last-state.yaml").unwrap();
+
+        assert_eq!(state_configmap_key, "last-state.yaml".to_string());
+    }
+
+    #[test]
+    fn get_state_configmap_key_prefers_key_from_env() {
+        // act
+        let state_configmap_key =
+            get_state_configmap_key(Some("state.yaml".to_string()), "/state/last-state.yaml")
+                .unwrap();
+
+        assert_eq!(state_configmap_key, "state.yaml".to_string());
+    }
+
+    #[test]
+    fn get_state_configmap_key_returns_error_for_invalid_key() {
+        // act
+        let result = get_state_configmap_key(Some("state/last.yaml".to_string()), "/state");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_state_from_file_returns_none_for_missing_file() {
+        // act
+        let state = read_state_from_file("/nonexistent/last-state.yaml", true).unwrap();
+
+        assert!(state.is_none());
+    }
+
+    #[test]
+    fn read_state_from_file_returns_state_for_valid_file() {
+        let state_file_path = write_state_file(
+            "read-state-from-file-valid.yaml",
+            "desinfectionEnabled: true\ndesinfectionFinishedAt: 2022-04-21T15:00:00Z\nplannedSpotPrices: []\n",
+        );
+
+        // act
+        let state = read_state_from_file(&state_file_path, true).unwrap();
+
+        assert!(state.unwrap().desinfection_enabled);
+    }
+
+    #[test]
+    fn read_state_from_file_returns_error_for_corrupt_file_if_strict() {
+        let state_file_path = write_state_file(
+            "read-state-from-file-corrupt-strict.yaml",
+            "desinfectionEnabled: [",
+        );
+
+        // act
+        let result = read_state_from_file(&state_file_path, true);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_state_from_file_returns_none_for_corrupt_file_if_tolerant() {
+        let state_file_path = write_state_file(
+            "read-state-from-file-corrupt-tolerant.yaml",
+            "desinfectionEnabled: [",
+        );
+
+        // act
+        let state = read_state_from_file(&state_file_path, false).unwrap();
+
+        assert!(state.is_none());
+    }
 }
@@ -0,0 +1,94 @@
+use crate::http_client;
+use crate::model::DesinfectionDecision;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::error::Error;
+use tracing::{debug, info};
+
+const NOTIFICATION_TIMEOUT_SECONDS: u64 = 10;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DesinfectionNotification {
+    pub previous_desinfection_enabled: bool,
+    pub desinfection_enabled: bool,
+    pub desinfection_finished_at: DateTime<Utc>,
+    pub reason: String,
+    // what got decided for every segment of the clock program, the reason describes them in words
+    pub decisions: Vec<DesinfectionDecision>,
+}
+
+pub struct WebhookNotifier {
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        debug!("WebhookNotifier::new(url: {})", url);
+
+        Self { url }
+    }
+
+    pub async fn notify<T: Serialize>(&self, payload: &T) -> Result<(), Box<dyn Error>> {
+        let body = serde_json::to_string(payload)?;
+
+        // home automation webhooks usually live on the local network, so plain http is all that's supported
+        let response =
+            http_client::post_json(&self.url, &body, NOTIFICATION_TIMEOUT_SECONDS).await?;
+        if !response.is_success() {
+            return Err(Box::<dyn Error>::from(format!(
+                "Posting notification to {} failed with response {}",
+                self.url, response.status_line
+            )));
+        }
+
+        info!("Posted notification to {}", self.url);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn notify_posts_json_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 1024];
+            let length = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..length]).to_string()
+        });
+        let notifier = WebhookNotifier::new(format!("http://127.0.0.1:{}/webhook", port));
+
+        // act
+        let result = notifier
+            .notify(&DesinfectionNotification {
+                previous_desinfection_enabled: false,
+                desinfection_enabled: true,
+                desinfection_finished_at: Utc.with_ymd_and_hms(2022, 4, 21, 6, 0, 0).unwrap(),
+                reason: "desinfection is overdue (250 > 240 hours), forced in the cheapest available window".to_string(),
+                decisions: vec![DesinfectionDecision::Overdue {
+                    hours_since_last_desinfection: 250,
+                    max_hours_since_last_desinfection: 240,
+                }],
+            })
+            .await;
+
+        assert!(result.is_ok());
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /webhook HTTP/1.0\r\n"));
+        assert!(request.ends_with(
+            "{\"previousDesinfectionEnabled\":false,\"desinfectionEnabled\":true,\"desinfectionFinishedAt\":\"2022-04-21T06:00:00Z\",\"reason\":\"desinfection is overdue (250 > 240 hours), forced in the cheapest available window\",\"decisions\":[{\"reason\":\"Overdue\",\"hoursSinceLastDesinfection\":250,\"maxHoursSinceLastDesinfection\":240}]}"
+        ));
+    }
+}
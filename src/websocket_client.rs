@@ -1,1261 +1,7587 @@
-use crate::model::{Config, Content, State};
+use crate::health_server::{HealthState, PlannerCounter};
+use crate::model::{
+    BlockingStrategy, ClockProgram, Config, Content, ContentItem, DesinfectionDecision,
+    DesinfectionMode, EnergyStats, Fault, FirmwareVersion, OperatingMode, PlanAction, PlanRecord,
+    PlanningOutcome, ScheduleProgram, State, TapWaterStrategy, TemperatureUnit, ValuesContent,
+    WeeklySchedule,
+};
+use crate::prices::{
+    apply_past_slot_policy, are_spot_prices_stale, cap_spot_prices_to_duration,
+    clamp_jitter_shift_minutes, filter_spot_prices_above_price, filter_spot_prices_on_weekdays,
+    get_average_effective_price_per_hour, get_cheapest_contiguous_spot_prices,
+    get_contiguous_blocks, get_earliest_contiguous_spot_prices, get_effective_spot_price,
+    get_planned_cost, get_price_percentile, get_running_spot_prices,
+    get_schedule_segment_spot_prices, get_spot_prices_above_price, get_spot_prices_within_day,
+    get_total_price, is_plan_unchanged, merge_running_spot_prices,
+    remove_protected_windows_from_blocking, remove_tap_water_overlap_from_blocking,
+    round_spot_prices_to_granularity, split_spot_prices_per_schedule_segment,
+};
+use crate::schedule::{
+    get_externally_changed_items, get_fixed_offset_spot_prices, get_heating_schedule_raw_values,
+    get_tap_water_schedule_raw_values, merge_written_schedule, schedule_needs_update,
+};
 use crate::state_client::StateClient;
+use crate::webhook_notifier::{DesinfectionNotification, WebhookNotifier};
 use async_trait::async_trait;
 use chrono::{prelude::*, Duration, Utc};
+use chrono_tz::Tz;
 use jarvis_lib::model::{
     LoadProfile, LoadProfileSection, PlanningRequest, PlanningResponse, PlanningStrategy,
     SpotPrice, SpotPricePlanner,
 };
 use jarvis_lib::planner_client::PlannerClient;
 use quick_xml::de::from_str;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
 use regex::Regex;
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::error::Error;
-use tracing::{debug, info};
+use std::fmt;
+use std::fs;
+use std::net::Ipv6Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use tracing::{debug, error, info, warn};
 use websocket::client::ClientBuilder;
 use websocket::OwnedMessage;
 
 const MAXIMUM_TAP_WATER_TEMPERATURE: f64 = 58.0;
+const MAXIMUM_SESSION_ATTEMPTS: u32 = 3;
+const RESPONSE_TIMEOUT_SECONDS: u64 = 30;
+const SESSION_TIMEOUT_SECONDS: u64 = 600;
+const RECENT_FAULT_HOURS: i64 = 24;
+
+#[derive(Debug)]
+pub enum WebsocketClientError {
+    Connect(String),
+    Auth {
+        response: String,
+    },
+    ConnectionClosed {
+        message_in_flight: String,
+    },
+    NavigationNotFound {
+        item: String,
+        closest_item: Option<String>,
+        available_items: Vec<String>,
+    },
+    ReadOnly {
+        item_path: String,
+    },
+    Timeout {
+        message_in_flight: String,
+    },
+    SaveRejected {
+        response: String,
+    },
+    NavigationDiverged {
+        expected: String,
+        actual: String,
+    },
+    Parse(String),
+    Config(String),
+    SessionTimeout {
+        timeout: std::time::Duration,
+    },
+}
+
+impl fmt::Display for WebsocketClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WebsocketClientError::Connect(message) => {
+                write!(f, "Failed communicating with heatpump: {}", message)
+            }
+            WebsocketClientError::Auth { response } => write!(
+                f,
+                "Login rejected by heatpump, check WEBSOCKET_LOGIN_CODE; response: {}",
+                response
+            ),
+            WebsocketClientError::ConnectionClosed { message_in_flight } => write!(
+                f,
+                "Connection closed by heatpump while awaiting response for message {}",
+                message_in_flight
+            ),
+            WebsocketClientError::NavigationNotFound {
+                item,
+                closest_item,
+                available_items,
+            } => match (closest_item, available_items.is_empty()) {
+                (_, true) => write!(f, "Item {} does not exist", item),
+                (Some(closest_item), false) => write!(
+                    f,
+                    "Item {} does not exist, did you mean {}? Available items: {}",
+                    item,
+                    closest_item,
+                    available_items.join(", ")
+                ),
+                (None, false) => write!(
+                    f,
+                    "Item {} does not exist. Available items: {}",
+                    item,
+                    available_items.join(", ")
+                ),
+            },
+            WebsocketClientError::ReadOnly { item_path } => write!(
+                f,
+                "Item {} is read-only, the clock program is probably locked by the installer",
+                item_path
+            ),
+            WebsocketClientError::Timeout { message_in_flight } => {
+                write!(f, "No response received for message {}", message_in_flight)
+            }
+            WebsocketClientError::SaveRejected { response } => write!(
+                f,
+                "Heatpump did not accept saving the changes; response: {}",
+                response
+            ),
+            WebsocketClientError::NavigationDiverged { expected, actual } => write!(
+                f,
+                "Remote control menu ended up at {} instead of {}, aborting to avoid toggling the wrong setting",
+                actual, expected
+            ),
+            WebsocketClientError::Parse(message) => write!(f, "{}", message),
+            WebsocketClientError::Config(message) => write!(f, "{}", message),
+            WebsocketClientError::SessionTimeout { timeout } => write!(
+                f,
+                "Session with heatpump did not finish within {:?}, abandoning it",
+                timeout
+            ),
+        }
+    }
+}
+
+impl Error for WebsocketClientError {}
 
 pub struct WebsocketClientConfig {
     host_address: String,
     host_port: u32,
     login_code: String,
     state_client: Option<StateClient>,
+    dry_run: bool,
+    websocket_origin: Option<String>,
+    websocket_protocol: String,
+    navigation_step_delay_ms: u64,
+    keepalive_interval_seconds: Option<u64>,
 }
 
 impl WebsocketClientConfig {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         host_address: String,
         host_port: u32,
         login_code: String,
         state_client: Option<StateClient>,
+        dry_run: bool,
+        websocket_origin: Option<String>,
+        websocket_protocol: String,
+        navigation_step_delay_ms: u64,
+        keepalive_interval_seconds: Option<u64>,
     ) -> Result<Self, Box<dyn Error>> {
+        // allow ipv6 addresses to be passed with or without brackets
+        let host_address = host_address
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+
+        if host_address.is_empty() {
+            return Err(Box::<dyn Error>::from("Host address should not be empty"));
+        }
+
+        if !(1..=65535).contains(&host_port) {
+            return Err(Box::<dyn Error>::from(format!(
+                "Host port {} is not between 1 and 65535",
+                host_port
+            )));
+        }
+
+        if websocket_protocol.is_empty() {
+            return Err(Box::<dyn Error>::from(
+                "Websocket protocol should not be empty",
+            ));
+        }
+
+        // catch a mistyped code locally instead of as an opaque failure after connecting
+        if login_code.is_empty() {
+            return Err(Box::<dyn Error>::from(
+                "Login code should not be empty, check WEBSOCKET_LOGIN_CODE",
+            ));
+        }
+
+        if login_code.trim() != login_code {
+            return Err(Box::<dyn Error>::from(
+                "Login code should not start or end with whitespace, check WEBSOCKET_LOGIN_CODE",
+            ));
+        }
+
+        if !login_code.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Box::<dyn Error>::from(format!(
+                "Login code of {} characters should only contain digits or hexadecimal characters, check WEBSOCKET_LOGIN_CODE",
+                login_code.chars().count()
+            )));
+        }
+
+        if keepalive_interval_seconds == Some(0) {
+            return Err(Box::<dyn Error>::from(
+                "Keepalive interval should be larger than 0 seconds, check KEEPALIVE_INTERVAL_SECONDS",
+            ));
+        }
+
         let config = Self {
             host_address,
             host_port,
             login_code,
             state_client,
+            dry_run,
+            websocket_origin,
+            websocket_protocol,
+            navigation_step_delay_ms,
+            keepalive_interval_seconds,
         };
 
         Ok(config)
     }
 
-    pub fn from_env(state_client: Option<StateClient>) -> Result<Self, Box<dyn Error>> {
+    pub fn from_env(
+        state_client: Option<StateClient>,
+        dry_run: bool,
+    ) -> Result<Self, Box<dyn Error>> {
         let host_address =
             env::var("WEBSOCKET_HOST_IP").unwrap_or_else(|_| "127.0.0.1".to_string());
         let host_port: u32 = env::var("WEBSOCKET_HOST_PORT")
             .unwrap_or_else(|_| "8214".to_string())
             .parse()?;
-        let login_code = env::var("WEBSOCKET_LOGIN_CODE")?;
+        let login_code = get_login_code(
+            env::var("WEBSOCKET_LOGIN_CODE").ok(),
+            env::var("WEBSOCKET_LOGIN_CODE_FILE").ok(),
+        )?;
+        let websocket_origin = env::var("WEBSOCKET_ORIGIN").ok();
+        let websocket_protocol =
+            env::var("WEBSOCKET_PROTOCOL").unwrap_or_else(|_| "Lux_WS".to_string());
+        let navigation_step_delay_ms: u64 = env::var("NAVIGATION_STEP_DELAY_MS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse()?;
+        let keepalive_interval_seconds: Option<u64> = match env::var("KEEPALIVE_INTERVAL_SECONDS") {
+            Ok(keepalive_interval_seconds) => Some(keepalive_interval_seconds.parse()?),
+            Err(_) => None,
+        };
 
-        Self::new(host_address, host_port, login_code, state_client)
+        Self::new(
+            host_address,
+            host_port,
+            login_code,
+            state_client,
+            dry_run,
+            websocket_origin,
+            websocket_protocol,
+            navigation_step_delay_ms,
+            keepalive_interval_seconds,
+        )
     }
-}
 
-pub struct WebsocketClient {
-    config: WebsocketClientConfig,
-}
+    fn get_host(&self) -> String {
+        match self.host_address.parse::<Ipv6Addr>() {
+            Ok(_) => format!("[{}]", self.host_address),
+            Err(_) => self.host_address.clone(),
+        }
+    }
 
-#[async_trait]
-impl PlannerClient<Config> for WebsocketClient {
-    async fn plan(
-        &self,
-        config: Config,
-        spot_price_planner: SpotPricePlanner,
-        spot_prices: Vec<SpotPrice>,
-    ) -> Result<(), Box<dyn Error>> {
-        info!("Planning best time to heat tap water for alpha innotec heatpump...");
+    pub fn get_websocket_url(&self) -> String {
+        format!("ws://{}:{}", self.get_host(), self.host_port)
+    }
 
-        let now = Utc::now();
+    pub fn get_origin(&self) -> String {
+        // proxied setups can require the origin of the proxy instead of the heatpump itself
+        match &self.websocket_origin {
+            Some(websocket_origin) => websocket_origin.clone(),
+            None => format!("http://{}", self.get_host()),
+        }
+    }
+}
 
-        let state = if let Some(state_client) = &self.config.state_client {
-            state_client.read_state()?
-        } else {
-            None
-        };
+fn get_login_code(
+    login_code: Option<String>,
+    login_code_file_path: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    // a mounted secret keeps the code out of the pod spec, so it wins over the env var; never log the code itself
+    if let Some(login_code_file_path) = login_code_file_path.filter(|path| !path.is_empty()) {
+        debug!("Reading login code from {}", login_code_file_path);
+        let login_code = fs::read_to_string(&login_code_file_path).map_err(|e| {
+            format!(
+                "Failed reading login code from {}: {}",
+                login_code_file_path, e
+            )
+        })?;
 
-        debug!("state: {:?}", state);
+        return Ok(login_code.trim().to_string());
+    }
 
-        let current_desinfection_enabled = match &state {
-            Some(st) => st.desinfection_enabled,
-            None => false,
-        };
+    match login_code {
+        Some(login_code) => Ok(login_code),
+        None => Err(Box::<dyn Error>::from(
+            "Set WEBSOCKET_LOGIN_CODE or WEBSOCKET_LOGIN_CODE_FILE to log in to the heatpump",
+        )),
+    }
+}
 
-        let desinfection_finished_at = match state {
-            Some(st) => match st.desinfection_finished_at {
-                Some(fa) => fa,
-                None => now - Duration::days(7),
-            },
-            None => now - Duration::days(7),
-        };
+pub(crate) trait MessageSender: Send {
+    fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>>;
+}
 
-        let (best_spot_prices_response, desinfection_desired) = self
-            .get_spot_prices_for_tapwater_heating_or_desinfection(
-                &config,
-                &spot_price_planner,
-                &spot_prices,
-                now,
-                desinfection_finished_at,
-            )?;
-        let best_spot_prices = best_spot_prices_response.spot_prices;
+pub(crate) trait MessageReceiver: Send {
+    fn receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>>;
 
-        if !best_spot_prices.is_empty() {
-            info!(
-                "Found block of {} spot price slots to use for planning heating of tap water:\n{:?}",
-                best_spot_prices.len(),
-                best_spot_prices
-            );
+    // returns a message only if one already arrived, without waiting for the controller
+    fn try_receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+        None
+    }
+}
 
-            let connection = ClientBuilder::new(&format!(
-                "ws://{}:{}",
-                self.config.host_address, self.config.host_port
-            ))?
-            .origin(format!("http://{}", self.config.host_address))
-            .add_protocol("Lux_WS")
-            .connect_insecure()?;
+impl<T: MessageSender + ?Sized> MessageSender for Box<T> {
+    fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>> {
+        (**self).send_message(message)
+    }
+}
 
-            let (mut receiver, mut sender) = connection.split()?;
+impl<T: MessageReceiver + ?Sized> MessageReceiver for Box<T> {
+    fn receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+        (**self).receive_message()
+    }
 
-            let navigation = self.login(&mut receiver, &mut sender)?;
+    fn try_receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+        (**self).try_receive_message()
+    }
+}
 
-            // add some jitter to start time to prevent all alpha innotec planner controlled heat pumps to start at the exact same time
-            let best_spot_prices = Self::add_jitter_to_spot_prices(&config, &best_spot_prices);
+pub(crate) type Connection = (Box<dyn MessageReceiver>, Box<dyn MessageSender>);
 
-            self.set_tap_water_schedule_from_best_spot_prices(
-                &mut receiver,
-                &mut sender,
-                &navigation,
-                &config,
-                &best_spot_prices,
-            )?;
+// pings the controller from a background thread whenever nothing was sent for an interval, so idle periods in long
+// navigation sequences don't exceed the controller's timeout; the thread stops when the sender is dropped
+struct KeepaliveSender {
+    sender: Arc<Mutex<Box<dyn MessageSender>>>,
+    last_sent_at: Arc<Mutex<std::time::Instant>>,
+    stop: Option<mpsc::Sender<()>>,
+    keepalive: Option<thread::JoinHandle<()>>,
+}
 
-            if desinfection_desired && !current_desinfection_enabled {
-                info!("Enabling desinfection mode");
-                self.toggle_continuous_desinfection(&mut receiver, &mut sender, &navigation)?;
-            } else if !desinfection_desired && current_desinfection_enabled {
-                info!("Disabling desinfection mode");
-                self.toggle_continuous_desinfection(&mut receiver, &mut sender, &navigation)?;
-            } else if desinfection_desired {
-                info!("No need to update desinfection mode, it's already enabled");
-            } else {
-                info!("No need to update desinfection mode, it's already disabled");
-            }
+impl KeepaliveSender {
+    fn new(sender: Box<dyn MessageSender>, interval: std::time::Duration) -> Self {
+        let sender = Arc::new(Mutex::new(sender));
+        let last_sent_at = Arc::new(Mutex::new(std::time::Instant::now()));
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let keepalive = {
+            let sender = Arc::clone(&sender);
+            let last_sent_at = Arc::clone(&last_sent_at);
+            thread::spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if last_sent_at.lock().unwrap().elapsed() < interval {
+                        continue;
+                    }
+
+                    debug!("Sending keepalive ping");
+                    if let Err(e) = sender
+                        .lock()
+                        .unwrap()
+                        .send_message(&OwnedMessage::Ping(vec![]))
+                    {
+                        // the session itself runs into the broken connection on its next send
+                        warn!("Failed sending keepalive ping: {}", e);
+                        return;
+                    }
+                    *last_sent_at.lock().unwrap() = std::time::Instant::now();
+                }
+            })
+        };
 
-            let desired_tap_water_temperature = if desinfection_desired {
-                MAXIMUM_TAP_WATER_TEMPERATURE
-            } else {
-                config.desired_tap_water_temperature
-            };
+        Self {
+            sender,
+            last_sent_at,
+            stop: Some(stop),
+            keepalive: Some(keepalive),
+        }
+    }
+}
 
-            self.set_tap_water_temperature(
-                &mut receiver,
-                &mut sender,
-                &navigation,
-                desired_tap_water_temperature,
-            )?;
+impl MessageSender for KeepaliveSender {
+    fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>> {
+        self.sender.lock().unwrap().send_message(message)?;
+        *self.last_sent_at.lock().unwrap() = std::time::Instant::now();
 
-            let mut desinfection_finished_at = desinfection_finished_at;
-            if desinfection_desired {
-                desinfection_finished_at = best_spot_prices.last().unwrap().till;
-            }
+        Ok(())
+    }
+}
 
-            if let Some(state_client) = &self.config.state_client {
-                state_client
-                    .store_state(&State {
-                        desinfection_enabled: desinfection_desired,
-                        desinfection_finished_at: Some(desinfection_finished_at),
-                        planned_spot_prices: Some(best_spot_prices),
-                    })
-                    .await?;
-            }
-        } else {
-            info!("No available best spot prices, not updating heatpump tap water schedule.");
+impl Drop for KeepaliveSender {
+    fn drop(&mut self) {
+        // dropping the stop sender wakes up the keepalive thread
+        self.stop.take();
+        if let Some(keepalive) = self.keepalive.take() {
+            let _ = keepalive.join();
         }
+    }
+}
 
-        info!("Blocking worst time for heating for alpha innotec heatpump...");
+// refuses to send once the session it belongs to was abandoned, so a hung session doesn't carry on writing to the
+// heatpump after the planner gave up on it
+struct CancellableSender {
+    sender: Box<dyn MessageSender>,
+    cancelled: Arc<AtomicBool>,
+}
 
-        let worst_spot_prices_response = self.get_worst_spot_prices_for_blocking_heating(
-            &spot_price_planner,
-            &spot_prices,
-            now,
-        )?;
-        let worst_spot_prices = worst_spot_prices_response.spot_prices;
+impl MessageSender for CancellableSender {
+    fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return Err(Box::<dyn Error>::from(
+                "Session was abandoned, not sending any more messages",
+            ));
+        }
 
-        if !worst_spot_prices.is_empty() {
-            info!(
-                "Found block of {} spot price slots to use for blocking heating:\n{:?}",
-                worst_spot_prices.len(),
-                worst_spot_prices
-            );
+        self.sender.send_message(message)
+    }
+}
 
-            let connection = ClientBuilder::new(&format!(
-                "ws://{}:{}",
-                self.config.host_address, self.config.host_port
-            ))?
-            .origin(format!("http://{}", self.config.host_address))
-            .add_protocol("Lux_WS")
-            .connect_insecure()?;
+// opens a connection to the heatpump controller; allows swapping the websocket for a simulated controller
+pub(crate) trait Connector: Send + Sync {
+    fn connect(&self) -> Result<Connection, Box<dyn Error>>;
+}
 
-            let (mut receiver, mut sender) = connection.split()?;
+// tells the current time; allows pinning the time when testing a full planning run
+pub(crate) trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
 
-            let navigation = self.login(&mut receiver, &mut sender)?;
+pub(crate) struct SystemClock;
 
-            // add some jitter to start time to prevent all alpha innotec planner controlled heat pumps to start/stop at the exact same time
-            let worst_spot_prices = Self::add_jitter_to_spot_prices(&config, &worst_spot_prices);
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
 
-            if config.enable_blocking_worst_heating_times {
-                self.set_heating_schedule_from_worst_spot_prices(
-                    &mut receiver,
-                    &mut sender,
-                    &navigation,
-                    &config,
-                    &worst_spot_prices,
-                )?;
-            }
-        } else {
-            info!("No available worst spot prices, not updating heatpump heating schedule.");
-        }
+// allows planning against fixed planning responses without a configured spot price planner
+pub(crate) trait BestSpotPricesPlanner: Send {
+    fn get_best_spot_prices(
+        &self,
+        planning_request: &PlanningRequest,
+    ) -> Result<PlanningResponse, Box<dyn Error>>;
+}
 
-        Ok(())
+impl BestSpotPricesPlanner for SpotPricePlanner {
+    fn get_best_spot_prices(
+        &self,
+        planning_request: &PlanningRequest,
+    ) -> Result<PlanningResponse, Box<dyn Error>> {
+        SpotPricePlanner::get_best_spot_prices(self, planning_request)
     }
 }
 
-impl WebsocketClient {
-    pub fn new(config: WebsocketClientConfig) -> Self {
-        Self { config }
+impl MessageSender for websocket::sender::Writer<std::net::TcpStream> {
+    fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>> {
+        websocket::sender::Writer::send_message(self, message)?;
+
+        Ok(())
     }
+}
 
-    pub fn from_env(state_client: Option<StateClient>) -> Result<Self, Box<dyn Error>> {
-        Ok(Self::new(WebsocketClientConfig::from_env(state_client)?))
+impl MessageReceiver for websocket::receiver::Reader<std::net::TcpStream> {
+    fn receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+        match self.incoming_messages().next() {
+            // the read timed out, which is reported the same as a connection that ended without a response
+            Some(Err(websocket::WebSocketError::IoError(e)))
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                None
+            }
+            message => message.map(|message| message.map_err(|e| Box::new(e) as Box<dyn Error>)),
+        }
     }
 
-    fn login(
-        &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-    ) -> Result<Navigation, Box<dyn Error>> {
-        let response_message = self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text(format!("LOGIN;{}", self.config.login_code)),
-        )?;
-        debug!("Retrieved response for login:\n{}", response_message);
+    fn try_receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+        // only read once a frame started arriving, so reading the rest of it never waits on the controller
+        if self.stream.get_buf().is_empty() {
+            let tcp_stream = self.stream.get_ref();
+            if let Err(e) = tcp_stream.set_nonblocking(true) {
+                return Some(Err(Box::new(e)));
+            }
+            let peeked = tcp_stream.peek(&mut [0; 1]);
+            if let Err(e) = tcp_stream.set_nonblocking(false) {
+                return Some(Err(Box::new(e)));
+            }
 
-        let navigation = self.get_navigation_from_response(response_message)?;
+            match peeked {
+                // a closed connection is left to the next regular receive
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return None,
+                Err(e) => return Some(Err(Box::new(e))),
+            }
+        }
 
-        Ok(navigation)
+        self.receive_message()
     }
+}
 
-    fn toggle_continuous_desinfection(
-        &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        navigation: &Navigation,
-    ) -> Result<(), Box<dyn Error>> {
-        info!("Toggling continuous desinfection");
-
-        debug!("To Afstandbediening");
-        self.navigate_to(receiver, sender, navigation, "Afstandbediening")?;
+// lets the planner optimize against the effective price, by replacing each price component with its effective value
+pub(crate) struct EffectivePricePlanner<P> {
+    planner: P,
+    config: Config,
+}
 
-        // to menu
-        debug!("To menu");
-        self.click(receiver, sender)?;
+impl<P: BestSpotPricesPlanner> EffectivePricePlanner<P> {
+    pub(crate) fn new(planner: P, config: Config) -> Self {
+        Self { planner, config }
+    }
+}
 
-        // to warmwater
-        debug!("To warmwater");
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.click(receiver, sender)?;
+impl<P: BestSpotPricesPlanner> BestSpotPricesPlanner for EffectivePricePlanner<P> {
+    fn get_best_spot_prices(
+        &self,
+        planning_request: &PlanningRequest,
+    ) -> Result<PlanningResponse, Box<dyn Error>> {
+        let planning_response = self.planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: planning_request
+                .spot_prices
+                .iter()
+                .map(|sp| get_effective_spot_price(sp, &self.config))
+                .collect(),
+            ..planning_request.clone()
+        })?;
+
+        // return the original spot prices so schedules, costs and state keep the full prices
+        Ok(PlanningResponse {
+            spot_prices: planning_response
+                .spot_prices
+                .iter()
+                .map(|sp| {
+                    planning_request
+                        .spot_prices
+                        .iter()
+                        .find(|original| original.from == sp.from)
+                        .unwrap_or(sp)
+                        .clone()
+                })
+                .collect(),
+            ..planning_response
+        })
+    }
+}
 
-        // to onderhoudsprogramma
-        debug!("To onderhoudsprogramma");
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.click(receiver, sender)?;
+struct WebsocketConnector {
+    websocket_url: String,
+    origin: String,
+    protocol: String,
+}
 
-        // to thermische desinfectie
-        debug!("To thermische desinfectie");
-        self.click(receiver, sender)?;
+impl Connector for WebsocketConnector {
+    fn connect(&self) -> Result<Connection, Box<dyn Error>> {
+        let connection = ClientBuilder::new(&self.websocket_url)?
+            .origin(self.origin.clone())
+            .add_protocol(self.protocol.clone())
+            .connect_insecure()?;
 
-        // to continu
-        debug!("To continu");
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
-        self.move_right(receiver, sender)?;
+        // a controller that stops responding would otherwise leave a read hanging forever, and with it the graceful
+        // shutdown waiting for the plan to finish
+        let response_timeout = Some(std::time::Duration::from_secs(RESPONSE_TIMEOUT_SECONDS));
+        connection.stream_ref().set_read_timeout(response_timeout)?;
+        connection
+            .stream_ref()
+            .set_write_timeout(response_timeout)?;
 
-        // check/uncheck continu
-        debug!("Toggle continu checkbox");
-        self.click(receiver, sender)?;
+        let (receiver, sender) = connection.split()?;
 
-        // apply
-        debug!("Apply changes");
-        self.move_right(receiver, sender)?;
-        self.click(receiver, sender)?;
+        Ok((Box::new(receiver), Box::new(sender)))
+    }
+}
 
-        // back to home
-        debug!("To home");
-        self.click(receiver, sender)?;
-        self.click(receiver, sender)?;
-        self.click(receiver, sender)?;
-        self.click(receiver, sender)?;
+// the login response the navigation was parsed from, to detect the controller sending a different tree
+type CachedNavigation = Option<(String, Arc<Navigation>)>;
+
+// cheap to clone, clones share their connection settings and caches so a session can run on the blocking pool
+// what earlier runs left in the state, with what a first run starts from
+struct PreviousPlan {
+    desinfection_enabled: bool,
+    desinfection_finished_at: DateTime<Utc>,
+    stored_desinfection_finished_at: Option<DateTime<Utc>>,
+    eco_desinfection_finished_at: Option<DateTime<Utc>>,
+    desinfection_decisions: Vec<DesinfectionDecision>,
+    tap_water_setpoint: Option<f64>,
+    away_until: Option<DateTime<Utc>>,
+    do_not_disturb_until: Option<DateTime<Utc>>,
+    planning_history: Vec<PlanRecord>,
+    // what was written in previous runs, to detect the schedule being changed on the heatpump itself
+    written_schedule: Vec<(String, i64)>,
+    energy_stats_history: Vec<EnergyStats>,
+    planned_spot_prices: Vec<SpotPrice>,
+    planned_cost_eur: Option<f64>,
+    blocked_spot_prices: Vec<SpotPrice>,
+    cooling_spot_prices: Vec<SpotPrice>,
+}
 
-        Ok(())
+impl PreviousPlan {
+    fn from_state(state: Option<State>, now: DateTime<Utc>) -> Self {
+        match state {
+            Some(st) => Self {
+                desinfection_enabled: st.desinfection_enabled,
+                desinfection_finished_at: st
+                    .desinfection_finished_at
+                    .unwrap_or(now - Duration::days(7)),
+                stored_desinfection_finished_at: st.desinfection_finished_at,
+                eco_desinfection_finished_at: st.eco_desinfection_finished_at,
+                desinfection_decisions: st.desinfection_decisions,
+                tap_water_setpoint: st.tap_water_setpoint,
+                away_until: st.away_until,
+                do_not_disturb_until: st.do_not_disturb_until,
+                planning_history: st.planning_history,
+                written_schedule: st.written_schedule.unwrap_or_default(),
+                energy_stats_history: st.energy_stats_history,
+                planned_spot_prices: st.planned_spot_prices.unwrap_or_default(),
+                planned_cost_eur: st.planned_cost_eur,
+                blocked_spot_prices: st.blocked_spot_prices.unwrap_or_default(),
+                cooling_spot_prices: st.cooling_spot_prices.unwrap_or_default(),
+            },
+            None => Self {
+                desinfection_enabled: false,
+                desinfection_finished_at: now - Duration::days(7),
+                stored_desinfection_finished_at: None,
+                eco_desinfection_finished_at: None,
+                desinfection_decisions: vec![],
+                tap_water_setpoint: None,
+                away_until: None,
+                do_not_disturb_until: None,
+                planning_history: vec![],
+                written_schedule: vec![],
+                energy_stats_history: vec![],
+                planned_spot_prices: vec![],
+                planned_cost_eur: None,
+                blocked_spot_prices: vec![],
+                cooling_spot_prices: vec![],
+            },
+        }
     }
 
-    fn set_tap_water_temperature(
+    // the state as it is when nothing gets written, the writes of a run update the parts they wrote
+    fn to_next_state(
         &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        navigation: &Navigation,
-        desired_tap_water_temperature: f64,
-    ) -> Result<(), Box<dyn Error>> {
-        // get current set tap water temperature
-        let response_message =
-            self.navigate_to(receiver, sender, navigation, "Informatie > Temperaturen")?;
+        heatpump_readings: &HeatpumpReadings,
+        plan_decision: &PlanDecision,
+        away_until: Option<DateTime<Utc>>,
+        do_not_disturb_until: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> State {
+        let mut energy_stats_history = self.energy_stats_history.clone();
+        if let Some(energy_counters) = &heatpump_readings.energy_counters {
+            energy_stats_history.push(EnergyStats {
+                read_at: now,
+                counters_kwh: energy_counters.clone(),
+            });
+        }
 
-        let value = self.get_item_from_response("Tapwater ingesteld", &response_message)?;
-        if value != desired_tap_water_temperature {
-            debug!("To Afstandbediening");
-            self.navigate_to(receiver, sender, navigation, "Afstandbediening")?;
+        State {
+            desinfection_enabled: heatpump_readings.desinfection_enabled,
+            desinfection_finished_at: self.stored_desinfection_finished_at,
+            planned_spot_prices: Some(self.planned_spot_prices.clone()),
+            planning_history: self.planning_history.clone(),
+            planned_cost_eur: self.planned_cost_eur,
+            away_until,
+            written_schedule: Some(self.written_schedule.clone()),
+            energy_stats_history,
+            blocked_spot_prices: Some(self.blocked_spot_prices.clone()),
+            desinfection_decisions: plan_decision.tap_water.desinfection_decisions.clone(),
+            tap_water_setpoint: self.tap_water_setpoint,
+            do_not_disturb_until,
+            eco_desinfection_finished_at: self.eco_desinfection_finished_at,
+            cooling_spot_prices: Some(self.cooling_spot_prices.clone()),
+        }
+    }
+}
 
-            // to menu
-            debug!("To menu");
-            self.click(receiver, sender)?;
+// what a planning run reads from the heatpump before deciding what to write to it
+struct HeatpumpReadings {
+    desinfection_enabled: bool,
+    energy_counters: Option<BTreeMap<String, f64>>,
+    measured_tap_water_temperature: Option<f64>,
+    tap_water_clock_program_honored: bool,
+    heating_clock_program_honored: bool,
+}
 
-            // to warmwater
-            debug!("To warmwater");
-            self.move_right(receiver, sender)?;
-            self.move_right(receiver, sender)?;
-            self.move_right(receiver, sender)?;
-            self.click(receiver, sender)?;
+struct TapWaterDecision {
+    best_spot_prices_responses: Vec<PlanningResponse>,
+    desinfection_mode: DesinfectionMode,
+    desinfection_decisions: Vec<DesinfectionDecision>,
+    // the window of an earlier run that's heating right now
+    running_spot_prices: Vec<SpotPrice>,
+}
 
-            // to temperatuur
-            debug!("To temperatuur");
-            self.move_right(receiver, sender)?;
-            self.click(receiver, sender)?;
+impl TapWaterDecision {
+    fn best_spot_prices(&self) -> Vec<SpotPrice> {
+        self.best_spot_prices_responses
+            .iter()
+            .flat_map(|response| response.spot_prices.iter().cloned())
+            .collect()
+    }
 
-            // to gewenste waarde
-            debug!("To gewenste waarde");
-            self.click(receiver, sender)?;
+    // only a full cycle enables the continuous desinfection mode of the controller
+    fn desinfection_desired(&self) -> bool {
+        self.desinfection_mode == DesinfectionMode::Full
+    }
+}
 
-            // raise / lower temperature
-            let desired_tap_water_temperature_diff = desired_tap_water_temperature - value;
-            if desired_tap_water_temperature_diff > 0.0 {
-                let temperature_increments = (desired_tap_water_temperature_diff / 0.5) as i64;
-                debug!(
-                    "Raising temperature by {} increments of 0.5°C",
-                    temperature_increments
-                );
-                for _n in 0..temperature_increments {
-                    self.move_right(receiver, sender)?;
-                }
-                self.click(receiver, sender)?;
-            } else {
-                let temperature_decrements =
-                    (-1.0 * desired_tap_water_temperature_diff / 0.5) as i64;
-                debug!(
-                    "Lowering temperature by {} decrements of 0.5°C",
-                    temperature_decrements
-                );
-                for _n in 0..temperature_decrements {
-                    self.move_left(receiver, sender)?;
-                }
-                self.click(receiver, sender)?;
-            }
+struct PlanDecision {
+    tap_water: TapWaterDecision,
+    worst_spot_prices: Vec<SpotPrice>,
+    cooling_season: bool,
+    cooling_spot_prices: Vec<SpotPrice>,
+    // nothing differs from what earlier runs programmed, so there's nothing to write
+    unchanged: bool,
+}
 
-            // apply
-            debug!("Apply changes");
-            self.click(receiver, sender)?;
+#[derive(Clone)]
+pub struct WebsocketClient {
+    config: Arc<WebsocketClientConfig>,
+    connector: Arc<dyn Connector>,
+    navigation: Arc<Mutex<CachedNavigation>>,
+    firmware_version: Arc<Mutex<Option<FirmwareVersion>>>,
+    health_state: HealthState,
+    clock: Arc<dyn Clock>,
+    session_timeout: std::time::Duration,
+}
 
-            // back to home
-            debug!("To home");
-            self.click(receiver, sender)?;
-            self.click(receiver, sender)?;
-            self.click(receiver, sender)?;
+#[async_trait]
+impl PlannerClient<Config> for WebsocketClient {
+    async fn plan(
+        &self,
+        config: Config,
+        spot_price_planner: SpotPricePlanner,
+        spot_prices: Vec<SpotPrice>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut config = config;
+        config.apply_env_overrides()?;
 
-            info!(
-                "Finished updating tap water temperature to {}°C",
-                desired_tap_water_temperature
-            )
-        } else {
-            info!(
-                "Set tap water temperature is already at {}°C, no need to update it",
-                value
-            )
-        }
+        // the trait has no room for the outcome, it's already logged by the planning run itself
+        self.plan_with_planner(config, spot_price_planner, spot_prices)
+            .await
+            .map(|_| ())
+    }
+}
 
-        Ok(())
+impl WebsocketClient {
+    pub fn new(config: WebsocketClientConfig) -> Self {
+        let connector = Box::new(WebsocketConnector {
+            websocket_url: config.get_websocket_url(),
+            origin: config.get_origin(),
+            protocol: config.websocket_protocol.clone(),
+        });
+
+        Self::with_connector(config, connector)
     }
 
-    fn set_tap_water_schedule_from_best_spot_prices(
-        &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        navigation: &Navigation,
-        config: &Config,
-        best_spot_prices: &[SpotPrice],
-    ) -> Result<(), Box<dyn Error>> {
-        info!("Updating tap water heating schedule from best spot prices");
-        let response_message = self.navigate_to(
-            receiver,
-            sender,
-            navigation,
-            "Klokprogramma > Warmwater > Week",
-        )?;
-        let content: Content = from_str(&response_message).unwrap();
-        debug!("Deserialized response:\n{:?}", content);
+    pub(crate) fn with_connector(
+        config: WebsocketClientConfig,
+        connector: Box<dyn Connector>,
+    ) -> Self {
+        Self::with_connector_and_clock(config, connector, Box::new(SystemClock))
+    }
 
-        // set all items to 0
-        info!("Resetting schedule");
-        for item in &content.item.item {
-            debug!("Setting {} to 00:00 - 00:00", item.name);
-            self.send(
-                sender,
-                websocket::OwnedMessage::Text(format!("SET;set_{};{}", item.id, 0)),
-            )?;
+    pub(crate) fn with_connector_and_clock(
+        config: WebsocketClientConfig,
+        connector: Box<dyn Connector>,
+        clock: Box<dyn Clock>,
+    ) -> Self {
+        Self {
+            config: Arc::new(config),
+            connector: Arc::from(connector),
+            navigation: Arc::new(Mutex::new(None)),
+            firmware_version: Arc::new(Mutex::new(None)),
+            health_state: HealthState::default(),
+            clock: Arc::from(clock),
+            session_timeout: std::time::Duration::from_secs(SESSION_TIMEOUT_SECONDS),
         }
+    }
 
-        if !best_spot_prices.is_empty() && content.item.item.len() > 1 {
-            let heatpump_time_zone = config.get_heatpump_time_zone()?;
+    pub fn from_env(
+        state_client: Option<StateClient>,
+        dry_run: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(WebsocketClientConfig::from_env(
+            state_client,
+            dry_run,
+        )?))
+    }
 
-            // get start time from first spot price
-            let from_time = best_spot_prices
-                .first()
-                .unwrap()
-                .from
-                .with_timezone(&heatpump_time_zone);
-            let from_hour = from_time.hour();
-            let from_minute = from_time.minute();
+    pub fn health_state(&self) -> HealthState {
+        self.health_state.clone()
+    }
 
-            // get finish time from last spot price
-            let till_time = best_spot_prices
-                .last()
-                .unwrap()
-                .till
-                .with_timezone(&heatpump_time_zone);
+    // returns no outcome when the run stopped before deciding what to plan
+    pub(crate) async fn plan_with_planner<P: BestSpotPricesPlanner>(
+        &self,
+        config: Config,
+        spot_price_planner: P,
+        spot_prices: Vec<SpotPrice>,
+    ) -> Result<Option<PlanningOutcome>, Box<dyn Error>> {
+        info!("Planning best time to heat tap water for alpha innotec heatpump...");
+        self.health_state.increment(PlannerCounter::Runs);
 
-            let till_hour = till_time.hour();
-            let till_minute = till_time.minute();
+        config.validate()?;
 
-            if from_hour > till_hour {
-                // starts before midnight, finishes after
-                let first_item_id = content.item.item.first().unwrap().id.clone();
-                info!(
-                    "Setting 1) to block {}:{:0>2} - {}:{:0>2}",
-                    till_hour, till_minute, from_hour, from_minute
-                );
-                self.send(
-                    sender,
-                    websocket::OwnedMessage::Text(format!(
-                        "SET;set_{};{}",
-                        first_item_id,
-                        60 * till_hour + till_minute + 65536 * (60 * from_hour + from_minute)
-                    )),
-                )?;
-            } else {
-                // start and finish on same day
-                if from_hour > 0 {
-                    let first_item_id = content.item.item.first().unwrap().id.clone();
-                    info!(
-                        "Setting 1) to block 00:00 - {}:{:0>2}",
-                        from_hour, from_minute
-                    );
-                    self.send(
-                        sender,
-                        websocket::OwnedMessage::Text(format!(
-                            "SET;set_{};{}",
-                            first_item_id,
-                            65536 * (60 * from_hour + from_minute)
-                        )),
-                    )?;
-                }
+        let now = self.clock.now();
 
-                if till_hour > 0 {
-                    let last_item_id = content.item.item.last().unwrap().id.clone();
-                    info!(
-                        "Setting 5) to block {}:{:0>2} - 00:00",
-                        till_hour, till_minute
-                    );
-                    self.send(
-                        sender,
-                        websocket::OwnedMessage::Text(format!(
-                            "SET;set_{};{}",
-                            last_item_id,
-                            60 * till_hour + till_minute
-                        )),
-                    )?;
-                }
+        // programming a schedule from prices that weren't refreshed does more harm than keeping the current one
+        if let Some(max_spot_price_age_hours) = config.max_spot_price_age_hours {
+            if are_spot_prices_stale(&spot_prices, max_spot_price_age_hours, now) {
+                error!(
+                    "Newest spot price ended more than {} hours ago, spot prices are stale; not updating heatpump schedules",
+                    max_spot_price_age_hours
+                );
+                return Ok(None);
             }
         }
 
-        info!("Saving changes");
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("SAVE;1".to_string()),
-        )?;
+        // without any prices there's no window to find at all, which is a loading problem rather than no cheap window
+        if spot_prices.is_empty() {
+            warn!("No spot prices to plan with, they probably failed to load rather than there being no cheap window");
+        }
 
-        Ok(())
-    }
+        let state = if let Some(state_client) = &self.config.state_client {
+            state_client.read_state()?
+        } else {
+            None
+        };
 
-    fn set_heating_schedule_from_worst_spot_prices(
-        &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        navigation: &Navigation,
-        config: &Config,
-        worst_spot_prices: &[SpotPrice],
-    ) -> Result<(), Box<dyn Error>> {
-        info!("Updating heating schedule to block worst spot prices");
-        let response_message = self.navigate_to(
-            receiver,
-            sender,
-            navigation,
-            "Klokprogramma > Verwarmen > Week",
-        )?;
-        let content: Content = from_str(&response_message).unwrap();
-        debug!("Deserialized response:\n{:?}", content);
+        debug!("state: {:?}", state);
 
-        // set all items to 0
-        info!("Resetting schedule");
-        for item in &content.item.item {
-            debug!("Setting {} to 00:00 - 00:00", item.name);
-            self.send(
-                sender,
-                websocket::OwnedMessage::Text(format!("SET;set_{};{}", item.id, 0)),
-            )?;
-        }
+        let previous_plan = PreviousPlan::from_state(state, now);
 
-        if !worst_spot_prices.is_empty() && content.item.item.len() > 1 {
-            let heatpump_time_zone = config.get_heatpump_time_zone()?;
+        let away_until = get_active_away_until(config.away_until, previous_plan.away_until, now);
+        let (config, outside_temperatures) = self.prepare_config(config, away_until).await?;
 
-            // get start time from first spot price
-            let from_time = worst_spot_prices
-                .first()
-                .unwrap()
-                .from
-                .with_timezone(&heatpump_time_zone);
+        let spot_price_planner = EffectivePricePlanner::new(spot_price_planner, config.clone());
 
-            let from_hour = from_time.hour();
-            let from_minute = from_time.minute();
+        let do_not_disturb_until =
+            get_active_do_not_disturb_until(previous_plan.do_not_disturb_until, now);
 
-            // get finish time from last spot price
-            let till_time = worst_spot_prices
-                .last()
-                .unwrap()
-                .till
-                .with_timezone(&heatpump_time_zone);
-
-            let till_hour = till_time.hour();
-            let till_minute = till_time.minute();
-
-            if from_hour > till_hour {
-                // starts before midnight, finishes after
-                if from_hour > 0 {
-                    let first_item_id = content.item.item.first().unwrap().id.clone();
-                    info!(
-                        "Setting 1) to block {}:{:0>2} - 00:00",
-                        from_hour, from_minute
-                    );
+        // every segment of the clock program gets its own window, planned from the spot prices for its own days
+        let segment_spot_prices = split_spot_prices_per_schedule_segment(
+            &config.get_heatpump_time_zone()?,
+            &config.schedule_program,
+            &spot_prices,
+        );
 
-                    self.send(
-                        sender,
-                        websocket::OwnedMessage::Text(format!(
-                            "SET;set_{};{}",
-                            first_item_id,
-                            60 * from_hour + from_minute
-                        )),
-                    )?;
-                }
+        let tap_water_decision = self.decide_tap_water(
+            &config,
+            &spot_price_planner,
+            &segment_spot_prices,
+            &previous_plan,
+            now,
+        )?;
 
-                if till_hour > 0 {
-                    let last_item_id = content.item.item.last().unwrap().id.clone();
-                    info!(
-                        "Setting 5) to block 00:00 - {}:{:0>2}",
-                        till_hour, till_minute
-                    );
-                    self.send(
-                        sender,
-                        websocket::OwnedMessage::Text(format!(
-                            "SET;set_{};{}",
-                            last_item_id,
-                            65536 * (60 * till_hour + till_minute)
-                        )),
-                    )?;
-                }
-            } else {
-                // start and finish on same day
-                let first_item_id = content.item.item.first().unwrap().id.clone();
-                info!(
-                    "Setting 1) to block {}:{:0>2} - {}:{:0>2}",
-                    from_hour, from_minute, till_hour, till_minute
-                );
-                self.send(
-                    sender,
-                    websocket::OwnedMessage::Text(format!(
-                        "SET;set_{};{}",
-                        first_item_id,
-                        60 * from_hour + from_minute + 65536 * (60 * till_hour + till_minute)
-                    )),
-                )?;
-            }
+        let mut planning_outcome = PlanningOutcome {
+            strategy: match tap_water_decision.desinfection_mode {
+                DesinfectionMode::Full => TapWaterStrategy::Desinfection,
+                DesinfectionMode::Eco => TapWaterStrategy::EcoDesinfection,
+                DesinfectionMode::None => TapWaterStrategy::TapWaterHeating,
+            },
+            best_from: None,
+            best_till: None,
+            total_price: None,
+            planned_cost_eur: None,
+            away_mode_active: away_until.is_some(),
+            desinfection_desired: tap_water_decision.desinfection_desired(),
+            hours_since_last_desinfection: (now - previous_plan.desinfection_finished_at)
+                .num_hours(),
+            tap_water_schedule_written: false,
+            desinfection_toggled: false,
+            blocked_from: None,
+            blocked_till: None,
+            heating_schedule_written: false,
+            cooling_schedule_written: false,
+        };
+
+        // another tool can have the planner stand down for a while, it then only logs what it would have written
+        if let Some(do_not_disturb_until) = do_not_disturb_until {
+            info!(
+                "Do not disturb is active until {}, skipping hardware writes",
+                do_not_disturb_until
+            );
         }
 
-        info!("Saving changes");
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("SAVE;1".to_string()),
-        )?;
+        if self.config.dry_run || do_not_disturb_until.is_some() {
+            return self.show_plan_without_writing(
+                &config,
+                &spot_price_planner,
+                &spot_prices,
+                &segment_spot_prices,
+                &tap_water_decision,
+                planning_outcome,
+                now,
+            );
+        }
 
-        Ok(())
-    }
+        let heatpump_readings = self
+            .read_heatpump_for_planning(&config, previous_plan.desinfection_enabled, now)
+            .await?;
 
-    fn add_jitter_to_spot_prices(config: &Config, spot_prices: &[SpotPrice]) -> Vec<SpotPrice> {
-        if spot_prices.is_empty() || config.jitter_max_minutes == 0 {
-            spot_prices.to_vec()
-        } else {
-            let mut rng = rand::thread_rng();
-            let shift_minutes =
-                rng.gen_range(0..2 * config.jitter_max_minutes) - config.jitter_max_minutes;
+        let plan_decision = self.decide_plan(
+            &config,
+            &spot_price_planner,
+            &spot_prices,
+            &segment_spot_prices,
+            &previous_plan,
+            tap_water_decision,
+            &heatpump_readings,
+            outside_temperatures,
+            now,
+        )?;
 
-            let mut updated_spot_prices: Vec<SpotPrice> = vec![];
+        // what this run read from the heatpump is kept as well when it turns out nothing needs to be written, the
+        // schedule writes only update the parts they wrote
+        let mut next_state = previous_plan.to_next_state(
+            &heatpump_readings,
+            &plan_decision,
+            away_until,
+            do_not_disturb_until,
+            now,
+        );
 
-            for spot_price in spot_prices.iter() {
-                updated_spot_prices.push(SpotPrice {
-                    from: spot_price.from + Duration::minutes(shift_minutes),
-                    till: spot_price.till + Duration::minutes(shift_minutes),
-                    ..spot_price.clone()
-                })
-            }
+        if plan_decision.unchanged {
+            info!("No change since last run, skipping hardware writes.");
+            // the previously planned window stays programmed, so that's what this run planned as well
+            planning_outcome.best_from =
+                previous_plan.planned_spot_prices.first().map(|sp| sp.from);
+            planning_outcome.best_till = previous_plan.planned_spot_prices.last().map(|sp| sp.till);
+        } else {
+            self.write_plan(
+                &config,
+                &previous_plan,
+                &heatpump_readings,
+                plan_decision,
+                &mut planning_outcome,
+                &mut next_state,
+                now,
+            )
+            .await?;
+        }
 
-            updated_spot_prices
+        next_state
+            .planning_history
+            .push(get_plan_record(&planning_outcome, now));
+        if let Some(state_client) = &self.config.state_client {
+            state_client.store_state(&next_state).await?;
         }
-    }
 
-    fn navigate_to(
-        &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        navigation: &Navigation,
-        nav: &str,
-    ) -> Result<String, Box<dyn Error>> {
-        debug!("Navigate to '{}'", nav);
-        let navigation_id = navigation.get_navigation_item_id(nav)?;
-        let response_message = self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text(format!("GET;{}", navigation_id)),
-        )?;
-        debug!(
-            "Retrieved response from '{}' ({}):\n{}",
-            &nav, navigation_id, response_message
+        info!(
+            planning_outcome = %serde_json::to_string(&planning_outcome)?,
+            "Finished planning"
         );
 
-        Ok(response_message)
+        self.health_state.mark_plan_succeeded(self.clock.now());
+        self.health_state.increment(PlannerCounter::SuccessfulRuns);
+
+        Ok(Some(planning_outcome))
     }
 
-    fn get_navigation_from_response(
+    // applies away mode and what the heatpump reads to the config, returns the outside temperatures it read as well
+    async fn prepare_config(
         &self,
-        response_message: String,
-    ) -> Result<Navigation, Box<dyn Error>> {
-        let navigation: Navigation = from_str(&response_message)?;
+        config: Config,
+        away_until: Option<DateTime<Utc>>,
+    ) -> Result<(Config, Option<(f64, f64)>), Box<dyn Error>> {
+        let mut config = config;
+        if let Some(away_until) = away_until {
+            // the house can coast while away, but tap water still needs desinfection when it's overdue
+            info!(
+                "Away mode is active until {}, not blocking heating and only desinfecting when overdue",
+                away_until
+            );
+            config.enable_blocking_worst_heating_times = false;
+            config.min_hours_since_last_desinfection = config.max_hours_since_last_desinfection;
+        }
 
-        Ok(navigation)
+        // desinfecting takes more energy when it's cold outside, and cooling is only needed when it's hot
+        let outside_temperatures = if config.conditional_desinfection_load_profiles.is_empty()
+            && !config.enable_cooling_scheduling
+        {
+            None
+        } else if self.config.dry_run {
+            info!("Not reading outside temperature in dry-run mode, using default desinfection load profile and leaving cooling as is");
+            None
+        } else {
+            Some(
+                self.run_session(|client, receiver, sender, navigation| {
+                    Ok(client.read_outside_temperatures(receiver, sender, navigation)?)
+                })
+                .await?,
+            )
+        };
+
+        if !config.conditional_desinfection_load_profiles.is_empty() {
+            config.desinfection_load_profile = config
+                .get_desinfection_load_profile(
+                    outside_temperatures.map(|(outside_temperature, _)| outside_temperature),
+                )
+                .clone();
+        }
+
+        // size the heating window to what the tap water actually needs instead of the static load profile
+        if let Some(heating_rate_celsius_per_hour) = config.heating_rate_celsius_per_hour {
+            if self.config.dry_run {
+                info!(
+                    "Not reading tap water temperatures in dry-run mode, using static load profile"
+                );
+            } else {
+                let (measured_tap_water_temperature, set_tap_water_temperature) = self
+                    .run_session(|client, receiver, sender, navigation| {
+                        Ok(client.read_tap_water_temperatures(receiver, sender, navigation)?)
+                    })
+                    .await?;
+
+                config.load_profile = config.get_load_profile_for_temperature_gap(
+                    heating_rate_celsius_per_hour,
+                    measured_tap_water_temperature,
+                    set_tap_water_temperature,
+                );
+                info!(
+                    "Heating tap water from {}{} to {}{} takes {} seconds at {}°C/hour",
+                    measured_tap_water_temperature,
+                    config.temperature_unit.symbol(),
+                    set_tap_water_temperature,
+                    config.temperature_unit.symbol(),
+                    config.load_profile.sections[0].duration_seconds,
+                    heating_rate_celsius_per_hour
+                );
+            }
+        }
+
+        Ok((config, outside_temperatures))
     }
 
-    fn move_right(
+    fn decide_tap_water(
         &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-    ) -> Result<(), Box<dyn Error>> {
-        debug!("Move right/down");
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("MOVE;0".to_string()),
-        )?;
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("MOVE;6".to_string()),
-        )?;
-        Ok(())
-    }
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        segment_spot_prices: &[Vec<SpotPrice>],
+        previous_plan: &PreviousPlan,
+        now: DateTime<Utc>,
+    ) -> Result<TapWaterDecision, Box<dyn Error>> {
+        let (best_spot_prices_responses, desinfection_mode, desinfection_decisions) = self
+            .get_spot_prices_for_tapwater_heating_or_desinfection_per_segment(
+                config,
+                spot_price_planner,
+                segment_spot_prices,
+                now,
+                previous_plan.desinfection_finished_at,
+                previous_plan.eco_desinfection_finished_at,
+                previous_plan.desinfection_enabled,
+            )?;
+        // a tap water window that's heating right now is kept until it has finished, and so is the mode it was planned
+        // in: toggling desinfection halfway would heat a short window to the desinfection temperature and record it as a
+        // full cycle, or abort a cycle that's running
+        let running_spot_prices = get_running_spot_prices(&previous_plan.planned_spot_prices, now);
+        let (desinfection_mode, desinfection_decisions) = if running_spot_prices.is_empty() {
+            (desinfection_mode, desinfection_decisions)
+        } else {
+            let running_desinfection_mode = get_running_desinfection_mode(
+                previous_plan.desinfection_enabled,
+                previous_plan.eco_desinfection_finished_at,
+                &running_spot_prices,
+            );
+            if running_desinfection_mode != desinfection_mode {
+                info!(
+                    "Tap water window {} - {} is still running, keeping its desinfection mode {:?} instead of {:?}",
+                    running_spot_prices.first().unwrap().from,
+                    running_spot_prices.last().unwrap().till,
+                    running_desinfection_mode,
+                    desinfection_mode
+                );
+            }
+            (
+                running_desinfection_mode,
+                previous_plan.desinfection_decisions.clone(),
+            )
+        };
+        for desinfection_decision in &desinfection_decisions {
+            info!("Desinfection decision: {}", desinfection_decision);
+        }
 
-    fn move_left(
-        &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-    ) -> Result<(), Box<dyn Error>> {
-        debug!("Move left/up");
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("MOVE;1".to_string()),
-        )?;
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("MOVE;6".to_string()),
-        )?;
-        Ok(())
+        Ok(TapWaterDecision {
+            best_spot_prices_responses,
+            desinfection_mode,
+            desinfection_decisions,
+            running_spot_prices,
+        })
     }
 
-    fn click(
+    // logs the plan in do not disturb mode and prints it in dry-run mode, without writing anything to the heatpump
+    #[allow(clippy::too_many_arguments)]
+    fn show_plan_without_writing(
         &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-    ) -> Result<(), Box<dyn Error>> {
-        debug!("Click");
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("MOVE;2".to_string()),
-        )?;
-        self.send_and_await(
-            receiver,
-            sender,
-            websocket::OwnedMessage::Text("MOVE;6".to_string()),
-        )?;
-        Ok(())
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        spot_prices: &[SpotPrice],
+        segment_spot_prices: &[Vec<SpotPrice>],
+        tap_water_decision: &TapWaterDecision,
+        planning_outcome: PlanningOutcome,
+        now: DateTime<Utc>,
+    ) -> Result<Option<PlanningOutcome>, Box<dyn Error>> {
+        let mut planning_outcome = planning_outcome;
+
+        let best_spot_prices =
+            Self::add_jitter_to_spot_prices(config, &tap_water_decision.best_spot_prices(), now)?;
+        let worst_spot_prices = remove_tap_water_overlap_from_blocking(
+            &Self::add_jitter_to_spot_prices(
+                config,
+                &self.get_worst_spot_prices_for_blocking_heating_per_segment(
+                    config,
+                    spot_price_planner,
+                    segment_spot_prices,
+                    now,
+                )?,
+                now,
+            )?,
+            &best_spot_prices,
+            config.tap_water_blocking_gap_minutes,
+        );
+
+        planning_outcome.best_from = best_spot_prices.first().map(|sp| sp.from);
+        planning_outcome.best_till = best_spot_prices.last().map(|sp| sp.till);
+        planning_outcome.blocked_from = worst_spot_prices.first().map(|sp| sp.from);
+        planning_outcome.blocked_till = worst_spot_prices.last().map(|sp| sp.till);
+
+        let plan_table = format_plan_table(
+            config,
+            &config.get_local_time_zone()?,
+            &planning_outcome.strategy,
+            &best_spot_prices,
+            &worst_spot_prices,
+        );
+
+        if !self.config.dry_run {
+            info!("Would have programmed the heatpump as:\n{}", plan_table);
+            self.health_state.mark_plan_succeeded(self.clock.now());
+            self.health_state.increment(PlannerCounter::SuccessfulRuns);
+            return Ok(Some(planning_outcome));
+        }
+
+        println!("{}", plan_table);
+
+        println!(
+            "{}",
+            format_preview_table(
+                config,
+                &config.get_local_time_zone()?,
+                &self.preview_next_24_hours(config, spot_price_planner, spot_prices, now)?,
+            )
+        );
+
+        Ok(Some(planning_outcome))
     }
 
-    fn get_item_from_response(
+    // reads what decides whether and what the run can write, and stops the run when the heatpump isn't fit for it
+    async fn read_heatpump_for_planning(
         &self,
-        item: &str,
-        response_message: &str,
-    ) -> Result<f64, Box<dyn Error>> {
-        // <Content><item id='0x4816ac'><name>Aanvoer</name><value>22.0°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>5.0°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>
+        config: &Config,
+        stored_desinfection_enabled: bool,
+        now: DateTime<Utc>,
+    ) -> Result<HeatpumpReadings, Box<dyn Error>> {
+        // someone can toggle desinfection on the heatpump itself, toggling based on the stored state would then flip it
+        // the wrong way
+        let desinfection_enabled = if config.read_desinfection_enabled {
+            let device_desinfection_enabled = self
+                .run_session(|client, receiver, sender, navigation| {
+                    Ok(client.read_desinfection_enabled(receiver, sender, navigation)?)
+                })
+                .await?;
+            reconcile_desinfection_enabled(stored_desinfection_enabled, device_desinfection_enabled)
+        } else {
+            stored_desinfection_enabled
+        };
 
-        let re = Regex::new(&format!(
-            r"<item id='[^']*'><name>{}</name><value>(-?[0-9.]+|---)[^<]*</value></item>",
-            item
-        ))?;
-        let matches = match re.captures(response_message) {
-            Some(m) => m,
-            None => {
-                return Err(Box::<dyn Error>::from(format!(
-                    "No match for item {}",
-                    item
-                )));
-            }
+        let (
+            firmware_version,
+            faults,
+            energy_counters,
+            tap_water_operating_mode,
+            heating_operating_mode,
+            measured_tap_water_temperature,
+            controller_time,
+        ) = {
+            let config = config.clone();
+            self.run_session(move |client, receiver, sender, navigation| {
+                let measured_tap_water_temperature = match config.minimum_tap_water_temperature {
+                    Some(_) => Some(
+                        client.read_measured_tap_water_temperature(receiver, sender, navigation)?,
+                    ),
+                    None => None,
+                };
+
+                // both circuits are listed in the same menu
+                let operating_modes = client.read_operating_modes(receiver, sender, navigation)?;
+
+                Ok((
+                    client.read_firmware_version(receiver, sender, navigation)?,
+                    if config.read_fault_buffer || config.abort_on_fault {
+                        client.read_fault_buffer(receiver, sender, navigation)?
+                    } else {
+                        vec![]
+                    },
+                    if config.read_energy_stats {
+                        client.read_energy_stats(receiver, sender, navigation)?
+                    } else {
+                        None
+                    },
+                    get_operating_mode(&operating_modes, "Warmwater")?,
+                    get_operating_mode(&operating_modes, "Verwarmen")?,
+                    measured_tap_water_temperature,
+                    match config.max_clock_skew_minutes {
+                        Some(_) => client.read_controller_time(receiver, sender, navigation)?,
+                        None => None,
+                    },
+                ))
+            })
+            .await?
         };
+        match firmware_version {
+            Some(firmware_version) if firmware_version.is_known_good() => {
+                info!("Heatpump runs firmware {}", firmware_version)
+            }
+            Some(firmware_version) => warn!(
+                "Heatpump runs firmware {}, which the navigation sequences haven't been verified against",
+                firmware_version
+            ),
+            None => warn!("Could not determine the firmware version of the heatpump"),
+        }
+        *self.firmware_version.lock().unwrap() = firmware_version;
+
+        if let Some(energy_counters) = &energy_counters {
+            info!("Heatpump energy counters in kWh: {:?}", energy_counters);
+            self.health_state
+                .set_energy_counters(energy_counters.clone());
+        }
 
-        if matches.len() != 2 {
+        let recent_faults = get_recent_faults(&faults, &config.get_heatpump_time_zone()?, now);
+        for fault in recent_faults.iter() {
+            warn!(
+                "Heatpump reported fault {} at {}: {}",
+                fault.code, fault.timestamp, fault.description
+            );
+        }
+        if config.abort_on_fault && !recent_faults.is_empty() {
             return Err(Box::<dyn Error>::from(format!(
-                "No match for item {}",
-                item
+                "Heatpump reported {} faults in the last {} hours, not updating heatpump schedules",
+                recent_faults.len(),
+                RECENT_FAULT_HOURS
             )));
         }
 
-        return match matches.get(1) {
-            None => Ok(0.0),
-            Some(m) => {
-                let value = m.as_str();
-                if value == "---" {
-                    return Ok(0.0);
+        // after a power loss the controller clock can be off, running every window written below at the wrong time
+        if let Some(max_clock_skew_minutes) = config.max_clock_skew_minutes {
+            match controller_time {
+                Some(controller_time) => {
+                    let clock_skew_minutes = get_clock_skew_minutes(
+                        &controller_time,
+                        &config.get_heatpump_time_zone()?,
+                        now,
+                    );
+                    if clock_skew_minutes.abs() > max_clock_skew_minutes {
+                        warn!(
+                            "Heatpump clock reads {}, which is {} minutes off from {} in {}",
+                            controller_time,
+                            clock_skew_minutes,
+                            now.with_timezone(&config.get_heatpump_time_zone()?)
+                                .naive_local(),
+                            config.heatpump_time_zone
+                        );
+                        if config.abort_on_clock_skew {
+                            return Err(Box::<dyn Error>::from(format!(
+                                "Heatpump clock is {} minutes off, more than the maximum of {} minutes, not updating heatpump schedules",
+                                clock_skew_minutes, max_clock_skew_minutes
+                            )));
+                        }
+                    } else {
+                        debug!(
+                            "Heatpump clock reads {}, {} minutes off",
+                            controller_time, clock_skew_minutes
+                        );
+                    }
                 }
-                Ok(value.parse()?)
+                None => warn!("Could not determine the time of the heatpump clock"),
             }
-        };
-    }
-
-    fn send(
-        &self,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        message: websocket::OwnedMessage,
-    ) -> Result<(), Box<dyn Error>> {
-        sender.send_message(&message)?;
+        }
 
-        Ok(())
+        Ok(HeatpumpReadings {
+            desinfection_enabled,
+            energy_counters,
+            measured_tap_water_temperature,
+            tap_water_clock_program_honored: is_clock_program_honored(
+                config,
+                "Tap water",
+                &tap_water_operating_mode,
+            )?,
+            heating_clock_program_honored: is_clock_program_honored(
+                config,
+                "Heating",
+                &heating_operating_mode,
+            )?,
+        })
     }
 
-    fn send_and_await(
+    // decides every window of the run and whether any of them differs from what's programmed already
+    #[allow(clippy::too_many_arguments)]
+    fn decide_plan(
         &self,
-        receiver: &mut websocket::receiver::Reader<std::net::TcpStream>,
-        sender: &mut websocket::sender::Writer<std::net::TcpStream>,
-        message: websocket::OwnedMessage,
-    ) -> Result<String, Box<dyn Error>> {
-        sender.send_message(&message)?;
-
-        for message in receiver.incoming_messages() {
-            match message? {
-                OwnedMessage::Text(text) => {
-                    return Ok(text);
-                }
-                OwnedMessage::Close(_) => {
-                    // return a close
-                    sender.send_message(&OwnedMessage::Close(None))?;
-                }
-                OwnedMessage::Ping(data) => {
-                    // return a pong
-                    sender.send_message(&OwnedMessage::Pong(data))?;
-                }
-                OwnedMessage::Pong(_) => {}
-                OwnedMessage::Binary(_) => {}
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        spot_prices: &[SpotPrice],
+        segment_spot_prices: &[Vec<SpotPrice>],
+        previous_plan: &PreviousPlan,
+        tap_water_decision: TapWaterDecision,
+        heatpump_readings: &HeatpumpReadings,
+        outside_temperatures: Option<(f64, f64)>,
+        now: DateTime<Utc>,
+    ) -> Result<PlanDecision, Box<dyn Error>> {
+        let mut tap_water_decision = tap_water_decision;
+
+        // the floor is a safety net for when no best window is found, so it heats regardless of price
+        if let (true, Some(minimum_tap_water_temperature), Some(measured_tap_water_temperature)) = (
+            tap_water_decision.best_spot_prices().is_empty(),
+            config.minimum_tap_water_temperature,
+            heatpump_readings.measured_tap_water_temperature,
+        ) {
+            if measured_tap_water_temperature < minimum_tap_water_temperature {
+                let duration_seconds: i64 = config
+                    .load_profile
+                    .sections
+                    .iter()
+                    .map(|section| section.duration_seconds)
+                    .sum();
+
+                let best_spot_prices =
+                    get_earliest_contiguous_spot_prices(spot_prices, duration_seconds, now);
+                info!(
+                    "Measured tap water temperature {}{} is below minimum of {}{}, forcing heating in first available window of {} spot price slots",
+                    measured_tap_water_temperature,
+                    config.temperature_unit.symbol(),
+                    minimum_tap_water_temperature,
+                    config.temperature_unit.symbol(),
+                    best_spot_prices.len()
+                );
+                tap_water_decision.best_spot_prices_responses = vec![PlanningResponse {
+                    spot_prices: best_spot_prices,
+                    load_profile: config.load_profile.clone(),
+                }];
             }
         }
+        let best_spot_prices = tap_water_decision.best_spot_prices();
 
-        Err(Box::<dyn Error>::from(
-            "No response received for login message",
-        ))
+        let worst_spot_prices = self.get_worst_spot_prices_for_blocking_heating_per_segment(
+            config,
+            spot_price_planner,
+            segment_spot_prices,
+            now,
+        )?;
+        // the average the controller keeps of the outside temperature, a single warm afternoon isn't summer yet
+        let cooling_season = config.is_cooling_season(
+            outside_temperatures
+                .map(|(_, average_outside_temperature)| average_outside_temperature),
+        );
+        let cooling_spot_prices = if cooling_season {
+            self.get_best_spot_prices_for_cooling_per_segment(
+                config,
+                spot_price_planner,
+                segment_spot_prices,
+                now,
+            )?
+        } else {
+            vec![]
+        };
+
+        // rerunning with the same outcome would only wear out the controller's flash with identical writes
+        // with an adaptive setpoint an unchanged plan still needs the setpoint restored once its window starts
+        // an eco heat-up only differs from regular heating by its setpoint, so that has to match as well
+        let desinfection_mode = tap_water_decision.desinfection_mode;
+        let unchanged_setpoint =
+            if config.enable_adaptive_setpoint || desinfection_mode == DesinfectionMode::Eco {
+                previous_plan.tap_water_setpoint
+                    == Some(get_desired_tap_water_temperature(
+                        config,
+                        desinfection_mode,
+                        &best_spot_prices,
+                        now,
+                    ))
+            } else {
+                true
+            };
+        // the heating and cooling schedules only get written when there's something to write, so only then they can
+        // have changed
+        let unchanged_blocking = !(config.enable_blocking_worst_heating_times
+            && heatpump_readings.heating_clock_program_honored
+            && !worst_spot_prices.is_empty())
+            || is_plan_unchanged(
+                &previous_plan.blocked_spot_prices,
+                &merge_running_spot_prices(
+                    &get_running_spot_prices(&previous_plan.blocked_spot_prices, now),
+                    &remove_tap_water_overlap_from_blocking(
+                        &worst_spot_prices,
+                        &best_spot_prices,
+                        config.tap_water_blocking_gap_minutes,
+                    ),
+                ),
+                config,
+            );
+        // out of season a cooling window stored before still has to be reset
+        let unchanged_cooling = if cooling_season {
+            cooling_spot_prices.is_empty()
+                || is_plan_unchanged(
+                    &previous_plan.cooling_spot_prices,
+                    &cooling_spot_prices,
+                    config,
+                )
+        } else {
+            previous_plan.cooling_spot_prices.is_empty()
+        };
+
+        let unchanged = tap_water_decision.desinfection_desired()
+            == heatpump_readings.desinfection_enabled
+            && is_plan_unchanged(
+                &previous_plan.planned_spot_prices,
+                &best_spot_prices,
+                config,
+            )
+            && unchanged_setpoint
+            && unchanged_blocking
+            && unchanged_cooling;
+
+        Ok(PlanDecision {
+            tap_water: tap_water_decision,
+            worst_spot_prices,
+            cooling_season,
+            cooling_spot_prices,
+            unchanged,
+        })
     }
 
-    fn get_spot_prices_for_tapwater_heating_or_desinfection(
+    // writes the decided windows to the heatpump, one schedule after the other, and records what got written
+    #[allow(clippy::too_many_arguments)]
+    async fn write_plan(
         &self,
         config: &Config,
-        spot_price_planner: &SpotPricePlanner,
-        spot_prices: &[SpotPrice],
+        previous_plan: &PreviousPlan,
+        heatpump_readings: &HeatpumpReadings,
+        plan_decision: PlanDecision,
+        planning_outcome: &mut PlanningOutcome,
+        next_state: &mut State,
         now: DateTime<Utc>,
-        desinfection_finished_at: DateTime<Utc>,
-    ) -> Result<(PlanningResponse, bool), Box<dyn Error>> {
-        let lowest_price_tapwater_heating_response =
-            spot_price_planner.get_best_spot_prices(&PlanningRequest {
-                spot_prices: spot_prices.to_owned(),
-                load_profile: config.load_profile.clone(),
-                planning_strategy: PlanningStrategy::LowestPrice,
-                after: Some(now),
-                before: Some(now + Duration::hours(12)),
-            })?;
+    ) -> Result<(), Box<dyn Error>> {
+        // what got written to the tap water schedule, to keep blocking heating clear of it
+        let tap_water_spot_prices = if !heatpump_readings.tap_water_clock_program_honored {
+            info!("Not updating heatpump tap water schedule in its current operating mode.");
+            vec![]
+        } else if !plan_decision.tap_water.best_spot_prices().is_empty() {
+            self.write_tap_water_plan(
+                config,
+                previous_plan,
+                heatpump_readings.desinfection_enabled,
+                plan_decision.tap_water,
+                planning_outcome,
+                next_state,
+                now,
+            )
+            .await?
+        } else {
+            info!("No available best spot prices, not updating heatpump tap water schedule.");
+            vec![]
+        };
 
-        let lowest_price_desinfection_response =
-            spot_price_planner.get_best_spot_prices(&PlanningRequest {
-                spot_prices: spot_prices.to_owned(),
-                load_profile: config.desinfection_load_profile.clone(),
-                planning_strategy: PlanningStrategy::LowestPrice,
-                after: Some(now),
-                before: Some(now + Duration::hours(24)),
-            })?;
+        info!("Blocking worst time for heating for alpha innotec heatpump...");
 
-        // if lowest price desinfection spot prices start after next 12 hours we don't want desinfection to run now
-        if lowest_price_desinfection_response.spot_prices.is_empty()
-            || lowest_price_desinfection_response
-                .spot_prices
-                .first()
-                .unwrap()
-                .from
-                > now + Duration::hours(12)
-        {
-            info!("Optimal spot prices for desinfection are more than 12 hours ahead, skip using those for now and will check again next run");
-            Ok((lowest_price_tapwater_heating_response, false))
+        if !plan_decision.worst_spot_prices.is_empty() {
+            self.write_heating_plan(
+                config,
+                previous_plan,
+                heatpump_readings.heating_clock_program_honored,
+                &plan_decision.worst_spot_prices,
+                &tap_water_spot_prices,
+                planning_outcome,
+                next_state,
+                now,
+            )
+            .await?;
         } else {
-            let highest_price_desinfection_response =
-                spot_price_planner.get_best_spot_prices(&PlanningRequest {
-                    spot_prices: spot_prices.to_owned(),
-                    load_profile: config.desinfection_load_profile.clone(),
-                    planning_strategy: PlanningStrategy::HighestPrice,
-                    after: Some(now),
-                    before: Some(now + Duration::hours(24)),
-                })?;
-
-            info!("Checking if desinfection is needed");
-            let desinfection_desired = is_desinfection_desired(
-                config.min_hours_since_last_desinfection,
-                config.max_hours_since_last_desinfection,
-                &desinfection_finished_at,
-                &lowest_price_desinfection_response,
-                &highest_price_desinfection_response,
-            )?;
+            info!("No available worst spot prices, not updating heatpump heating schedule.");
+        }
 
-            if desinfection_desired {
-                Ok((lowest_price_desinfection_response, desinfection_desired))
-            } else {
-                Ok((lowest_price_tapwater_heating_response, false))
-            }
+        if plan_decision.cooling_season {
+            self.write_cooling_plan(
+                config,
+                &plan_decision.cooling_spot_prices,
+                planning_outcome,
+                next_state,
+                now,
+            )
+            .await?;
+        } else if !previous_plan.cooling_spot_prices.is_empty() {
+            // the window would otherwise keep blocking cooling outside of it until the next season's first plan
+            info!("Cooling season has ended, resetting the cooling schedule");
+
+            let cooling_written_schedule = self
+                .run_session(|client, receiver, sender, navigation| {
+                    Ok(client.reset_schedule_program(
+                        receiver,
+                        sender,
+                        navigation,
+                        &ClockProgram::Cooling,
+                    )?)
+                })
+                .await?;
+            next_state.written_schedule = Some(merge_written_schedule(
+                &next_state.written_schedule.clone().unwrap_or_default(),
+                cooling_written_schedule,
+            ));
+            next_state.cooling_spot_prices = Some(vec![]);
+
+            planning_outcome.cooling_schedule_written = true;
         }
+
+        Ok(())
     }
 
-    fn get_worst_spot_prices_for_blocking_heating(
+    // returns the windows that got written to the tap water schedule
+    #[allow(clippy::too_many_arguments)]
+    async fn write_tap_water_plan(
         &self,
-        spot_price_planner: &SpotPricePlanner,
-        spot_prices: &[SpotPrice],
+        config: &Config,
+        previous_plan: &PreviousPlan,
+        current_desinfection_enabled: bool,
+        tap_water_decision: TapWaterDecision,
+        planning_outcome: &mut PlanningOutcome,
+        next_state: &mut State,
         now: DateTime<Utc>,
-    ) -> Result<PlanningResponse, Box<dyn Error>> {
-        let highest_price_desinfection_response =
-            spot_price_planner.get_best_spot_prices(&PlanningRequest {
-                spot_prices: spot_prices.to_owned(),
-                load_profile: LoadProfile {
-                    sections: vec![LoadProfileSection {
-                        duration_seconds: 3600,
-                        power_draw_watt: 1000.0,
-                    }],
-                },
-                planning_strategy: PlanningStrategy::HighestPrice,
-                after: Some(now),
-                before: Some(now + Duration::hours(24)),
-            })?;
-
-        Ok(highest_price_desinfection_response)
-    }
-}
-
-fn is_desinfection_desired(
-    _min_hours_since_last_desinfection: i64,
-    _max_hours_since_last_desinfection: i64,
-    _desinfection_finished_at: &DateTime<Utc>,
-    _lowest_price_desinfection_response: &PlanningResponse,
-    _highest_price_desinfection_response: &PlanningResponse,
-) -> Result<bool, Box<dyn Error>> {
-    // if max_hours_since_last_desinfection <= min_hours_since_last_desinfection {
-    //     return Err(Box::<dyn Error>::from(format!("max_hours_since_last_desinfection ({}) is less or equal to min_hours_since_last_desinfection ({}) which is not allowed", max_hours_since_last_desinfection, min_hours_since_last_desinfection)));
-    // }
-
-    // // make likelihood of desinfection when max of best spot prices < fraction of max of all spot prices
-    // // where fraction is an exponential curve between 5 and 10 days after previous round
-    // if lowest_price_desinfection_response.spot_prices.is_empty() {
-    //     info!("No best spot prices, desinfection is not desired");
-    //     Ok(false)
-    // } else {
-    //     let planned_finished_at = lowest_price_desinfection_response
-    //         .spot_prices
-    //         .last()
-    //         .unwrap()
-    //         .till;
-    //     let hours_since_last_desinfection =
-    //         (planned_finished_at - *desinfection_finished_at).num_hours();
-
-    //     let lowest_total_price = lowest_price_desinfection_response.total_price(Some(|sp| {
-    //         sp.market_price + sp.market_price_tax + sp.sourcing_markup_price + sp.energy_tax_price
-    //     }));
-
-    //     if lowest_total_price <= 0.0 {
-    //         info!(
-    //             "Lowest prices is less then equal to zero ({}), desinfection IS desired",
-    //             lowest_total_price
-    //         );
-    //         Ok(true)
-    //     } else if hours_since_last_desinfection < min_hours_since_last_desinfection {
-    //         info!("Hours since last desinfection less than min configured hours ({} < {}), desinfection is not desired", hours_since_last_desinfection, min_hours_since_last_desinfection);
-    //         Ok(false)
-    //     } else if hours_since_last_desinfection > max_hours_since_last_desinfection {
-    //         info!("Hours since last desinfection greater than min configured hours ({} > {}), desinfection IS desired", hours_since_last_desinfection, max_hours_since_last_desinfection);
-    //         Ok(true)
-    //     } else {
-    //         let hours_since_min = hours_since_last_desinfection - min_hours_since_last_desinfection;
-    //         let hours_between_min_and_max: i64 =
-    //             max_hours_since_last_desinfection - min_hours_since_last_desinfection;
-
-    //         assert!(max_hours_since_last_desinfection > min_hours_since_last_desinfection);
-
-    //         let fraction_of_max_price = (hours_since_min * hours_since_min) as f64
-    //             / (hours_between_min_and_max * hours_between_min_and_max) as f64;
-    //         debug!(
-    //             "fraction_of_max_price = {}^2 / {}^2 = {}",
-    //             hours_since_min, hours_between_min_and_max, fraction_of_max_price
-    //         );
-
-    //         let highest_total_price =
-    //             highest_price_desinfection_response.total_price(Some(|sp| sp.market_price));
-    //         let lowest_total_price =
-    //             lowest_price_desinfection_response.total_price(Some(|sp| sp.market_price));
-
-    //         let is_desired = lowest_total_price < (fraction_of_max_price * highest_total_price);
-    //         info!(
-    //             "is_desired = {} < ({} * {}) = {}",
-    //             lowest_total_price, fraction_of_max_price, highest_total_price, is_desired
-    //         );
-
-    //         Ok(is_desired)
-    //     }
-    // }
-
-    Ok(Utc::now().weekday() == Weekday::Sun)
-}
+    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        let desinfection_mode = tap_water_decision.desinfection_mode;
+        let desinfection_desired = tap_water_decision.desinfection_desired();
+        let desinfection_decisions = tap_water_decision.desinfection_decisions.clone();
+        let running_spot_prices = tap_water_decision.running_spot_prices.clone();
+
+        info!(
+            "Found block of {} spot price slots to use for planning heating of tap water:\n{:?}",
+            tap_water_decision.best_spot_prices().len(),
+            tap_water_decision.best_spot_prices()
+        );
 
-#[derive(Debug, Deserialize)]
-struct Navigation {
-    // id: String, // `xml:"id,attr"`
-    #[serde(rename = "item", default)]
-    items: Vec<NavigationItem>, // `xml:"item"`
-}
+        // add some jitter to start time to prevent all alpha innotec planner controlled heat pumps to start at the exact same time
+        let best_spot_prices_responses = tap_water_decision
+            .best_spot_prices_responses
+            .into_iter()
+            .map(|response| {
+                Ok(PlanningResponse {
+                    spot_prices: Self::add_jitter_to_spot_prices(
+                        config,
+                        &response.spot_prices,
+                        now,
+                    )?,
+                    load_profile: response.load_profile,
+                })
+            })
+            .collect::<Result<Vec<PlanningResponse>, Box<dyn Error>>>()?;
 
-#[derive(Debug, Deserialize)]
-struct NavigationItem {
-    id: String,   //           `xml:"id,attr"`
-    name: String, //           `xml:"name"`
-    #[serde(rename = "item", default)]
-    items: Vec<NavigationItem>, // `xml:"item"`
-}
+        // when rerunning during the day the window that's heating right now can't be planned again, and
+        // the clock program only holds a single window, so keep it until it has finished
+        let best_spot_prices_responses = if running_spot_prices.is_empty() {
+            best_spot_prices_responses
+        } else {
+            info!(
+                "Tap water window {} - {} is still running, keeping it instead of the newly planned window",
+                running_spot_prices.first().unwrap().from,
+                running_spot_prices.last().unwrap().till
+            );
+            vec![PlanningResponse {
+                spot_prices: running_spot_prices,
+                load_profile: config.load_profile.clone(),
+            }]
+        };
+        let best_spot_prices: Vec<SpotPrice> = best_spot_prices_responses
+            .iter()
+            .flat_map(|response| response.spot_prices.iter().cloned())
+            .collect();
+
+        let desired_tap_water_temperature =
+            get_desired_tap_water_temperature(config, desinfection_mode, &best_spot_prices, now);
+
+        // toggling isn't idempotent, so don't toggle again when the session is retried after a dropped connection
+        let mut desinfection_mode_updated = false;
+
+        // desinfection still needs its window in the tap water schedule when tap water is managed manually
+        let tap_water_scheduled =
+            config.enable_tap_water_scheduling || desinfection_mode != DesinfectionMode::None;
+        if !tap_water_scheduled {
+            info!("Tap water scheduling is disabled, leaving the tap water schedule untouched");
+        }
 
-impl Navigation {
-    fn get_navigation_item_id(&self, item_path: &str) -> Result<String, Box<dyn Error>> {
-        let item_path_parts: Vec<&str> = item_path.split(" > ").collect();
+        let written_schedule = next_state.written_schedule.clone().unwrap_or_default();
+        let tap_water_written_schedule = {
+            let config = config.clone();
+            let best_spot_prices = best_spot_prices.clone();
+            let written_schedule = written_schedule.clone();
+            self.run_session(move |client, receiver, sender, navigation| {
+                let tap_water_written_schedule = if tap_water_scheduled {
+                    client.set_tap_water_schedule_from_best_spot_prices(
+                        receiver,
+                        sender,
+                        navigation,
+                        &config,
+                        &best_spot_prices,
+                        &written_schedule,
+                    )?
+                } else {
+                    vec![]
+                };
+
+                if !desinfection_mode_updated {
+                    if desinfection_desired && !current_desinfection_enabled {
+                        info!("Enabling desinfection mode");
+                        client.toggle_continuous_desinfection(receiver, sender, navigation)?;
+                        client
+                            .health_state
+                            .increment(PlannerCounter::DesinfectionEnabled);
+                    } else if !desinfection_desired && current_desinfection_enabled {
+                        info!("Disabling desinfection mode");
+                        client.toggle_continuous_desinfection(receiver, sender, navigation)?;
+                    } else if desinfection_desired {
+                        info!("No need to update desinfection mode, it's already enabled");
+                    } else {
+                        info!("No need to update desinfection mode, it's already disabled");
+                    }
+                    desinfection_mode_updated = true;
+                }
 
-        let mut navigation_id: String = "".to_string();
-        let mut items = &self.items;
+                client.set_tap_water_temperature(
+                    receiver,
+                    sender,
+                    navigation,
+                    desired_tap_water_temperature,
+                    config.temperature_unit,
+                )?;
 
-        for part in item_path_parts.iter() {
-            let mut exists = false;
-            for item in items.iter() {
-                if *part == item.name {
-                    exists = true;
+                Ok(tap_water_written_schedule)
+            })
+            .await?
+        };
 
-                    navigation_id = item.id.clone();
-                    items = &item.items;
+        planning_outcome.best_from = best_spot_prices.first().map(|sp| sp.from);
+        planning_outcome.best_till = best_spot_prices.last().map(|sp| sp.till);
+        planning_outcome.total_price = Some(get_total_price(&best_spot_prices, config));
+
+        // each segment's window has its own load profile, so spread the costs per window
+        let planned_cost_eur: f64 = best_spot_prices_responses
+            .iter()
+            .map(|response| get_planned_cost(&response.spot_prices, &response.load_profile, config))
+            .sum();
+        info!(
+            "Planned tap water heating session will cost ≈ €{:.2}",
+            planned_cost_eur
+        );
+        planning_outcome.planned_cost_eur = Some(planned_cost_eur);
+        planning_outcome.tap_water_schedule_written = tap_water_scheduled;
+        planning_outcome.desinfection_toggled =
+            desinfection_desired != current_desinfection_enabled;
+
+        let mut desinfection_finished_at = previous_plan.desinfection_finished_at;
+        let mut eco_desinfection_finished_at = previous_plan.eco_desinfection_finished_at;
+        match desinfection_mode {
+            DesinfectionMode::Full => {
+                desinfection_finished_at = best_spot_prices.last().unwrap().till
+            }
+            DesinfectionMode::Eco => {
+                eco_desinfection_finished_at = Some(best_spot_prices.last().unwrap().till)
+            }
+            DesinfectionMode::None => {}
+        }
 
-                    break;
-                }
+        next_state.desinfection_enabled = desinfection_desired;
+        next_state.desinfection_finished_at = Some(desinfection_finished_at);
+        next_state.eco_desinfection_finished_at = eco_desinfection_finished_at;
+        next_state.planned_spot_prices = Some(best_spot_prices.clone());
+        next_state.planned_cost_eur = Some(planned_cost_eur);
+        next_state.written_schedule = Some(merge_written_schedule(
+            &written_schedule,
+            tap_water_written_schedule,
+        ));
+        next_state.tap_water_setpoint = Some(desired_tap_water_temperature);
+
+        if let Some(state_client) = &self.config.state_client {
+            let (reason, message) = match desinfection_mode {
+                DesinfectionMode::Full => ("DesinfectionEnabled", "Scheduled desinfection"),
+                DesinfectionMode::Eco => ("EcoDesinfectionScheduled", "Scheduled eco desinfection"),
+                DesinfectionMode::None => ("TapWaterScheduled", "Scheduled tap water heating"),
+            };
+            if let Err(e) = state_client
+                .emit_event(
+                    reason,
+                    &format!(
+                        "{} from {} till {} for ≈ €{:.2}",
+                        message,
+                        planning_outcome.best_from.unwrap(),
+                        planning_outcome.best_till.unwrap(),
+                        planned_cost_eur
+                    ),
+                )
+                .await
+            {
+                warn!("Failed emitting event: {}", e);
             }
+        }
 
-            if !exists {
-                return Err(Box::<dyn Error>::from(format!(
-                    "Item {} does not exist",
-                    part
-                )));
+        // toggling desinfection is the most impactful change, so let the user know about it
+        if desinfection_desired != current_desinfection_enabled {
+            if let Some(notification_webhook_url) = &config.notification_webhook_url {
+                if let Err(e) = WebhookNotifier::new(notification_webhook_url.clone())
+                    .notify(&DesinfectionNotification {
+                        previous_desinfection_enabled: current_desinfection_enabled,
+                        desinfection_enabled: desinfection_desired,
+                        desinfection_finished_at,
+                        reason: desinfection_decisions
+                            .iter()
+                            .map(|desinfection_decision| desinfection_decision.to_string())
+                            .collect::<Vec<String>>()
+                            .join("; "),
+                        decisions: desinfection_decisions.clone(),
+                    })
+                    .await
+                {
+                    warn!("Failed notifying about desinfection change: {}", e);
+                }
             }
         }
 
-        Ok(navigation_id)
+        Ok(if tap_water_scheduled {
+            best_spot_prices
+        } else {
+            vec![]
+        })
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use jarvis_lib::model::{LoadProfile, LoadProfileSection, SpotPrice};
-
-    #[test]
-    fn deserialize_navigation_xml() {
-        let xml_string = "<Navigation id=\"0x45cd88\"><item id=\"0x45df90\"><name>Informatie</name><item id=\"0x45df90\"><name>Temperaturen</name></item><item id=\"0x455968\"><name>Ingangen</name></item></item><item id=\"0x450798\"><name>Instelling</name></item><item id=\"0x3dc420\"><name>Klokprogramma</name></item><item id=\"0x45c7b0\"><name>Toegang: Gebruiker</name></item></Navigation>";
 
-        // act
-        let navigation: Navigation = from_str(xml_string).unwrap();
+    #[allow(clippy::too_many_arguments)]
+    async fn write_heating_plan(
+        &self,
+        config: &Config,
+        previous_plan: &PreviousPlan,
+        heating_clock_program_honored: bool,
+        worst_spot_prices: &[SpotPrice],
+        tap_water_spot_prices: &[SpotPrice],
+        planning_outcome: &mut PlanningOutcome,
+        next_state: &mut State,
+        now: DateTime<Utc>,
+    ) -> Result<(), Box<dyn Error>> {
+        info!(
+            "Found block of {} spot price slots to use for blocking heating:\n{:?}",
+            worst_spot_prices.len(),
+            worst_spot_prices
+        );
 
-        assert_eq!(navigation.items.len(), 4);
-        assert_eq!(navigation.items[0].name, "Informatie".to_string());
-        assert_eq!(navigation.items[0].items.len(), 2);
-        assert_eq!(
-            navigation.items[0].items[0].name,
-            "Temperaturen".to_string()
+        // add some jitter to start time to prevent all alpha innotec planner controlled heat pumps to start/stop at the exact same time
+        let worst_spot_prices = Self::add_jitter_to_spot_prices(config, worst_spot_prices, now)?;
+
+        // blocks that are running since an earlier run today can't be planned again, so keep them instead of unblocking
+        let worst_spot_prices = merge_running_spot_prices(
+            &get_running_spot_prices(&previous_plan.blocked_spot_prices, now),
+            &remove_tap_water_overlap_from_blocking(
+                &worst_spot_prices,
+                tap_water_spot_prices,
+                config.tap_water_blocking_gap_minutes,
+            ),
         );
-        assert_eq!(navigation.items[0].items[0].id, "0x45df90".to_string());
-        assert_eq!(navigation.items[0].items[1].name, "Ingangen".to_string());
-        assert_eq!(navigation.items[0].items[1].id, "0x455968".to_string());
-    }
 
-    #[test]
-    fn get_navigation_item_id_returns_id_if_it_exists() {
-        // <Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item><item id='0x455968'><name>Ingangen</name></item><item id='0x455760'><name>Uitgangen</name></item><item id='0x45bf10'><name>Aflooptijden</name></item><item id='0x456f08'><name>Bedrijfsuren</name></item><item id='0x4643a8'><name>Storingsbuffer</name></item><item id='0x3ddfa8'><name>Afschakelingen</name></item><item id='0x45d840'><name>Installatiestatus</name></item><item id='0x460cb8'><name>Energie</name></item><item id='0x4586a8'><name>GBS</name></item></item><item id='0x450798'><name>Instelling</name><item id='0x460bd0'><name>Bedrijfsmode</name></item><item id='0x461170'><name>Temperaturen</name></item><item id='0x462988'><name>Systeeminstelling</name></item></item><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item><item id='0x45df00'><name>5+2</name></item><item id='0x45c200'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item><item id='0x463940'><name>5+2</name></item><item id='0x463b68'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x3dcc00'><name>Zwembad</name><readOnly>true</readOnly><item id='0x455580'><name>Week</name></item><item id='0x463f78'><name>5+2</name></item><item id='0x462690'><name>Dagen (Ma, Di,...)</name></item></item></item><item id='0x45c7b0'><name>Toegang: Gebruiker</name></item></Navigation>
+        if !config.enable_blocking_worst_heating_times || !heating_clock_program_honored {
+            return Ok(());
+        }
 
-        let navigation = Navigation {
-            // id: "0x45cd88".to_string(),
-            items: vec![
-                NavigationItem {
-                    id: "0x45df90".to_string(),
-                    name: "Informatie".to_string(),
-                    items: vec![
-                        NavigationItem {
-                            id: "0x45df90".to_string(),
-                            name: "Temperaturen".to_string(),
-                            items: vec![],
-                        },
-                        NavigationItem {
-                            id: "0x455968".to_string(),
-                            name: "Ingangen".to_string(),
-                            items: vec![],
-                        },
-                    ],
-                },
-                NavigationItem {
-                    id: "0x450798".to_string(),
-                    name: "Instelling".to_string(),
-                    items: vec![],
-                },
-                NavigationItem {
-                    id: "0x3dc420".to_string(),
-                    name: "Klokprogramma".to_string(),
-                    items: vec![],
-                },
-                NavigationItem {
-                    id: "0x45c7b0".to_string(),
-                    name: "Toegang: Gebruiker".to_string(),
-                    items: vec![],
-                },
-            ],
+        let written_schedule = next_state.written_schedule.clone().unwrap_or_default();
+        let heating_written_schedule = {
+            let config = config.clone();
+            let worst_spot_prices = worst_spot_prices.clone();
+            let written_schedule = written_schedule.clone();
+            self.run_session(move |client, receiver, sender, navigation| {
+                Ok(client.set_heating_schedule_from_worst_spot_prices(
+                    receiver,
+                    sender,
+                    navigation,
+                    &config,
+                    &worst_spot_prices,
+                    &written_schedule,
+                )?)
+            })
+            .await?
         };
 
-        let item_id = navigation
-            .get_navigation_item_id(&"Informatie".to_string())
-            .unwrap();
+        next_state.written_schedule = Some(merge_written_schedule(
+            &written_schedule,
+            heating_written_schedule,
+        ));
+        next_state.blocked_spot_prices = Some(worst_spot_prices.clone());
 
-        assert_eq!(item_id, "0x45df90".to_string());
+        planning_outcome.heating_schedule_written = true;
+        planning_outcome.blocked_from = worst_spot_prices.first().map(|sp| sp.from);
+        planning_outcome.blocked_till = worst_spot_prices.last().map(|sp| sp.till);
+        if !worst_spot_prices.is_empty() {
+            self.health_state.increment(PlannerCounter::HeatingBlocked);
+        }
+
+        Ok(())
     }
 
-    #[test]
-    fn get_navigation_item_id_returns_id_if_it_exists_as_nested_item_inside_top_level_item() {
-        // <Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item><item id='0x455968'><name>Ingangen</name></item><item id='0x455760'><name>Uitgangen</name></item><item id='0x45bf10'><name>Aflooptijden</name></item><item id='0x456f08'><name>Bedrijfsuren</name></item><item id='0x4643a8'><name>Storingsbuffer</name></item><item id='0x3ddfa8'><name>Afschakelingen</name></item><item id='0x45d840'><name>Installatiestatus</name></item><item id='0x460cb8'><name>Energie</name></item><item id='0x4586a8'><name>GBS</name></item></item><item id='0x450798'><name>Instelling</name><item id='0x460bd0'><name>Bedrijfsmode</name></item><item id='0x461170'><name>Temperaturen</name></item><item id='0x462988'><name>Systeeminstelling</name></item></item><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item><item id='0x45df00'><name>5+2</name></item><item id='0x45c200'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item><item id='0x463940'><name>5+2</name></item><item id='0x463b68'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x3dcc00'><name>Zwembad</name><readOnly>true</readOnly><item id='0x455580'><name>Week</name></item><item id='0x463f78'><name>5+2</name></item><item id='0x462690'><name>Dagen (Ma, Di,...)</name></item></item></item><item id='0x45c7b0'><name>Toegang: Gebruiker</name></item></Navigation>
+    async fn write_cooling_plan(
+        &self,
+        config: &Config,
+        cooling_spot_prices: &[SpotPrice],
+        planning_outcome: &mut PlanningOutcome,
+        next_state: &mut State,
+        now: DateTime<Utc>,
+    ) -> Result<(), Box<dyn Error>> {
+        info!("Planning best time for cooling for alpha innotec heatpump...");
 
-        let navigation = Navigation {
-            // id: "0x45cd88".to_string(),
-            items: vec![
-                NavigationItem {
-                    id: "0x45df90".to_string(),
-                    name: "Informatie".to_string(),
-                    items: vec![
-                        NavigationItem {
-                            id: "0x45df90".to_string(),
-                            name: "Temperaturen".to_string(),
-                            items: vec![],
-                        },
-                        NavigationItem {
-                            id: "0x455968".to_string(),
-                            name: "Ingangen".to_string(),
-                            items: vec![],
-                        },
-                    ],
-                },
-                NavigationItem {
-                    id: "0x450798".to_string(),
-                    name: "Instelling".to_string(),
-                    items: vec![],
-                },
-                NavigationItem {
-                    id: "0x3dc420".to_string(),
-                    name: "Klokprogramma".to_string(),
-                    items: vec![],
-                },
-                NavigationItem {
-                    id: "0x45c7b0".to_string(),
-                    name: "Toegang: Gebruiker".to_string(),
-                    items: vec![],
-                },
-            ],
+        let cooling_spot_prices =
+            Self::add_jitter_to_spot_prices(config, cooling_spot_prices, now)?;
+
+        if cooling_spot_prices.is_empty() {
+            info!("No available best spot prices, not updating heatpump cooling schedule.");
+            return Ok(());
+        }
+
+        info!(
+            "Found block of {} spot price slots to use for cooling:\n{:?}",
+            cooling_spot_prices.len(),
+            cooling_spot_prices
+        );
+
+        let written_schedule = next_state.written_schedule.clone().unwrap_or_default();
+        let cooling_written_schedule = {
+            let config = config.clone();
+            let cooling_spot_prices = cooling_spot_prices.clone();
+            let written_schedule = written_schedule.clone();
+            self.run_session(move |client, receiver, sender, navigation| {
+                Ok(client.set_cooling_schedule_from_best_spot_prices(
+                    receiver,
+                    sender,
+                    navigation,
+                    &config,
+                    &cooling_spot_prices,
+                    &written_schedule,
+                )?)
+            })
+            .await?
         };
 
-        let item_id = navigation
-            .get_navigation_item_id(&"Informatie > Ingangen".to_string())
-            .unwrap();
+        next_state.written_schedule = Some(merge_written_schedule(
+            &written_schedule,
+            cooling_written_schedule,
+        ));
+        next_state.cooling_spot_prices = Some(cooling_spot_prices);
 
-        assert_eq!(item_id, "0x455968".to_string());
+        planning_outcome.cooling_schedule_written = true;
+
+        Ok(())
     }
 
-    #[tokio::test]
-    #[ignore]
-    async fn update_schedule() -> Result<(), Box<dyn Error>> {
-        let websocket_host_ip = env::var("WEBSOCKET_HOST_IP")?;
-        let client = WebsocketClient::new(WebsocketClientConfig::from_env(None)?);
+    // returns the action for every spot price in the next 24 hours, without connecting to the heatpump
+    pub(crate) fn preview_next_24_hours(
+        &self,
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        spot_prices: &[SpotPrice],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(SpotPrice, PlanAction)>, Box<dyn Error>> {
+        let state = if let Some(state_client) = &self.config.state_client {
+            state_client.read_state()?
+        } else {
+            None
+        };
 
-        let connection = ClientBuilder::new(&format!("ws://{}:{}", websocket_host_ip, 8214))?
-            .origin(format!("http://{}", websocket_host_ip))
-            .add_protocol("Lux_WS")
-            .connect_insecure()?;
+        let mut config = config.clone();
+        if get_active_away_until(
+            config.away_until,
+            state.as_ref().and_then(|st| st.away_until),
+            now,
+        )
+        .is_some()
+        {
+            config.enable_blocking_worst_heating_times = false;
+            config.min_hours_since_last_desinfection = config.max_hours_since_last_desinfection;
+        }
 
-        let (mut receiver, mut sender) = connection.split()?;
+        let desinfection_finished_at = state
+            .as_ref()
+            .and_then(|st| st.desinfection_finished_at)
+            .unwrap_or(now - Duration::days(7));
+        let current_desinfection_enabled = state
+            .as_ref()
+            .map(|st| st.desinfection_enabled)
+            .unwrap_or(false);
+        let eco_desinfection_finished_at = state.and_then(|st| st.eco_desinfection_finished_at);
+
+        let segment_spot_prices = split_spot_prices_per_schedule_segment(
+            &config.get_heatpump_time_zone()?,
+            &config.schedule_program,
+            spot_prices,
+        );
 
-        let navigation = client.login(&mut receiver, &mut sender)?;
+        // jitter is left out, it's random per run and would make the preview differ from the actual plan anyway
+        let (best_spot_prices_responses, desinfection_mode, _) = self
+            .get_spot_prices_for_tapwater_heating_or_desinfection_per_segment(
+                &config,
+                spot_price_planner,
+                &segment_spot_prices,
+                now,
+                desinfection_finished_at,
+                eco_desinfection_finished_at,
+                current_desinfection_enabled,
+            )?;
+        let worst_spot_prices = if config.enable_blocking_worst_heating_times {
+            self.get_worst_spot_prices_for_blocking_heating_per_segment(
+                &config,
+                spot_price_planner,
+                &segment_spot_prices,
+                now,
+            )?
+        } else {
+            vec![]
+        };
 
-        client.set_tap_water_schedule_from_best_spot_prices(
-            &mut receiver,
-            &mut sender,
-            &navigation,
-            &Config {
-                local_time_zone: "Europe/Amsterdam".to_string(),
-                heatpump_time_zone: "Europe/Amsterdam".to_string(),
-                desired_tap_water_temperature: 50.0,
-                min_hours_since_last_desinfection: 96,
-                max_hours_since_last_desinfection: 240,
-                load_profile: LoadProfile {
-                    sections: vec![LoadProfileSection {
-                        duration_seconds: 7200,
-                        power_draw_watt: 2000.0,
-                    }],
-                },
-                desinfection_load_profile: LoadProfile {
-                    sections: vec![
-                        LoadProfileSection {
-                            duration_seconds: 7200,
-                            power_draw_watt: 2000.0,
-                        },
-                        LoadProfileSection {
-                            duration_seconds: 1800,
-                            power_draw_watt: 8000.0,
-                        },
-                    ],
-                },
-                jitter_max_minutes: 15,
-                enable_blocking_worst_heating_times: true,
-            },
-            &vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 21, 14, 0, 0).unwrap(),
-                    market_price: 0.157,
-                    market_price_tax: 0.0330708,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 21, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 21, 15, 0, 0).unwrap(),
-                    market_price: 0.164,
-                    market_price_tax: 0.0344316,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-            ],
-        )?;
+        let tap_water_action = match desinfection_mode {
+            DesinfectionMode::Full => Some(PlanAction::Desinfection),
+            DesinfectionMode::Eco => Some(PlanAction::EcoDesinfection),
+            DesinfectionMode::None if config.enable_tap_water_scheduling => {
+                Some(PlanAction::HeatTapWater)
+            }
+            DesinfectionMode::None => None,
+        };
 
-        Ok(())
+        Ok(spot_prices
+            .iter()
+            .filter(|sp| sp.till > now && sp.from < now + Duration::hours(24))
+            .map(|sp| {
+                let action = match &tap_water_action {
+                    Some(tap_water_action)
+                        if best_spot_prices_responses
+                            .iter()
+                            .flat_map(|response| response.spot_prices.iter())
+                            .any(|best| best.from == sp.from) =>
+                    {
+                        tap_water_action.clone()
+                    }
+                    _ if worst_spot_prices.iter().any(|worst| worst.from == sp.from) => {
+                        PlanAction::BlockHeating
+                    }
+                    _ => PlanAction::Idle,
+                };
+
+                (sp.clone(), action)
+            })
+            .collect())
     }
 
-    #[tokio::test]
-    #[ignore]
-    async fn set_tap_water_temperature() -> Result<(), Box<dyn Error>> {
-        let websocket_host_ip = env::var("WEBSOCKET_HOST_IP")?;
-        let client = WebsocketClient::new(WebsocketClientConfig::from_env(None)?);
+    fn connect(&self) -> Result<Connection, WebsocketClientError> {
+        self.connector
+            .connect()
+            .map_err(|e| WebsocketClientError::Connect(e.to_string()))
+    }
 
-        let connection = ClientBuilder::new(&format!("ws://{}:{}", websocket_host_ip, 8214))?
-            .origin(format!("http://{}", websocket_host_ip))
-            .add_protocol("Lux_WS")
-            .connect_insecure()?;
+    async fn run_session<T, F>(&self, session: F) -> Result<T, Box<dyn Error>>
+    where
+        T: Send + 'static,
+        F: FnMut(
+                &WebsocketClient,
+                &mut dyn MessageReceiver,
+                &mut dyn MessageSender,
+                &Navigation,
+            ) -> Result<T, Box<dyn Error>>
+            + Send
+            + 'static,
+    {
+        // the websocket reads block, so run the session on the blocking pool to keep the runtime's workers free for
+        // other tasks, like the health server; a session that hangs is abandoned and stops at its next send
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let session_handle = {
+            let client = self.clone();
+            let cancelled = Arc::clone(&cancelled);
+            tokio::task::spawn_blocking(move || {
+                // the error has to cross back from the blocking thread, which a plain boxed error can't
+                client
+                    .run_session_attempts(session, &cancelled)
+                    .map_err(|e| e.to_string())
+            })
+        };
 
-        let (mut receiver, mut sender) = connection.split()?;
+        match tokio::time::timeout(self.session_timeout, session_handle).await {
+            Ok(Ok(result)) => result.map_err(Box::<dyn Error>::from),
+            Ok(Err(e)) => Err(Box::new(e)),
+            Err(_) => {
+                cancelled.store(true, Ordering::SeqCst);
+                Err(Box::new(WebsocketClientError::SessionTimeout {
+                    timeout: self.session_timeout,
+                }))
+            }
+        }
+    }
 
-        let navigation = client.login(&mut receiver, &mut sender)?;
+    fn run_session_attempts<T, F>(
+        &self,
+        mut session: F,
+        cancelled: &Arc<AtomicBool>,
+    ) -> Result<T, Box<dyn Error>>
+    where
+        F: FnMut(
+            &WebsocketClient,
+            &mut dyn MessageReceiver,
+            &mut dyn MessageSender,
+            &Navigation,
+        ) -> Result<T, Box<dyn Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let connection = self.connect();
+            if connection.is_err() {
+                self.health_state
+                    .increment(PlannerCounter::ConnectionFailures);
+            }
+            let (mut receiver, sender) = connection?;
+            let sender: Box<dyn MessageSender> = match self.config.keepalive_interval_seconds {
+                Some(keepalive_interval_seconds) => Box::new(KeepaliveSender::new(
+                    sender,
+                    std::time::Duration::from_secs(keepalive_interval_seconds),
+                )),
+                None => sender,
+            };
+            let mut sender = CancellableSender {
+                sender,
+                cancelled: Arc::clone(cancelled),
+            };
 
-        client.set_tap_water_temperature(&mut receiver, &mut sender, &navigation, 50.0)?;
+            let result = match self.login(&mut receiver, &mut sender) {
+                Ok(navigation) => session(self, &mut receiver, &mut sender, &navigation),
+                Err(e) => Err(e.into()),
+            };
 
-        Ok(())
-    }
+            let connection_closed = matches!(
+                &result,
+                Err(e) if matches!(
+                    e.downcast_ref::<WebsocketClientError>(),
+                    Some(WebsocketClientError::ConnectionClosed { .. })
+                )
+            );
+            if connection_closed {
+                self.health_state
+                    .increment(PlannerCounter::ConnectionFailures);
+            }
 
-    #[tokio::test]
-    #[ignore]
-    async fn toggle_continuous_desinfection() -> Result<(), Box<dyn Error>> {
-        let websocket_host_ip = env::var("WEBSOCKET_HOST_IP")?;
-        let client = WebsocketClient::new(WebsocketClientConfig::from_env(None)?);
+            // the controller sometimes drops the connection halfway, in that case start over with a fresh session
+            match result {
+                Err(e) if connection_closed && attempt < MAXIMUM_SESSION_ATTEMPTS => {
+                    warn!(
+                        "{}, retrying session (attempt {}/{})",
+                        e,
+                        attempt + 1,
+                        MAXIMUM_SESSION_ATTEMPTS
+                    );
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
 
-        let connection = ClientBuilder::new(&format!("ws://{}:{}", websocket_host_ip, 8214))?
-            .origin(format!("http://{}", websocket_host_ip))
-            .add_protocol("Lux_WS")
-            .connect_insecure()?;
+    fn login(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+    ) -> Result<Arc<Navigation>, WebsocketClientError> {
+        let response_message = self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text(format!("LOGIN;{}", self.config.login_code)),
+        )?;
+        debug!(
+            "Retrieved response for login:\n{}",
+            redact_login_code(&response_message, &self.config.login_code)
+        );
 
-        let (mut receiver, mut sender) = connection.split()?;
+        // a wrong login code doesn't get the navigation tree, so don't mistake it for a corrupt one
+        if !response_message.trim_start().starts_with("<Navigation") {
+            return Err(WebsocketClientError::Auth {
+                response: redact_login_code(&response_message, &self.config.login_code),
+            });
+        }
 
-        let navigation = client.login(&mut receiver, &mut sender)?;
+        // reuse the navigation tree parsed at a previous login, so its item id cache is kept across connections, but
+        // only while the controller sends the same tree, after a reboot or firmware update its ids can differ
+        let mut cached_navigation = self.navigation.lock().unwrap();
+        if let Some((cached_response_message, navigation)) = cached_navigation.as_ref() {
+            if *cached_response_message == response_message {
+                debug!("Using cached navigation");
+                return Ok(navigation.clone());
+            }
+            info!("Navigation changed since the previous login, rebuilding it");
+        }
 
-        client.toggle_continuous_desinfection(&mut receiver, &mut sender, &navigation)?;
+        let navigation = Arc::new(self.get_navigation_from_response(response_message.clone())?);
+        *cached_navigation = Some((response_message, navigation.clone()));
 
-        Ok(())
+        Ok(navigation)
     }
 
-    #[test]
+    pub fn get_temperatures(&self) -> Result<HashMap<String, f64>, WebsocketClientError> {
+        let (mut receiver, mut sender) = self.connect()?;
+
+        let navigation = self.login(&mut receiver, &mut sender)?;
+
+        let response_message = self.navigate_to(
+            &mut receiver,
+            &mut sender,
+            &navigation,
+            "Informatie > Temperaturen",
+        )?;
+
+        self.parse_all_items(&response_message)
+    }
+
+    fn navigate_to_thermal_desinfection(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<String, WebsocketClientError> {
+        // the remote control menu is navigated blindly, so its layout has to match the firmware
+        if let Some(firmware_version) = *self.firmware_version.lock().unwrap() {
+            if !firmware_version.is_known_good() {
+                warn!(
+                    "Navigating to thermal desinfection with a navigation sequence that hasn't been verified against firmware {}",
+                    firmware_version
+                );
+            }
+        }
+
+        debug!("To Afstandbediening");
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Afstandbediening")?;
+        confirm_screen(&response_message, "Afstandbediening")?;
+
+        // to menu
+        debug!("To menu");
+        let response_message = self.click(receiver, sender)?;
+        confirm_screen(&response_message, "Menu")?;
+
+        // to warmwater
+        debug!("To warmwater");
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        let response_message = self.click(receiver, sender)?;
+        confirm_screen(&response_message, "Warmwater")?;
+
+        // to onderhoudsprogramma
+        debug!("To onderhoudsprogramma");
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        let response_message = self.click(receiver, sender)?;
+        confirm_screen(&response_message, "Onderhoudsprogramma")?;
+
+        // to thermische desinfectie
+        debug!("To thermische desinfectie");
+        let response_message = self.click(receiver, sender)?;
+        confirm_screen(&response_message, "Thermische desinfectie")?;
+
+        Ok(response_message)
+    }
+
+    fn read_desinfection_enabled(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<Option<bool>, WebsocketClientError> {
+        // nothing gets applied, so the next navigation leaves the remote control menu as it was
+        let response_message =
+            self.navigate_to_thermal_desinfection(receiver, sender, navigation)?;
+
+        get_desinfection_enabled_from_response(&response_message)
+    }
+
+    fn toggle_continuous_desinfection(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<(), WebsocketClientError> {
+        info!("Toggling continuous desinfection");
+
+        self.navigate_to_thermal_desinfection(receiver, sender, navigation)?;
+
+        // to continu
+        debug!("To continu");
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+        self.move_right(receiver, sender)?;
+
+        // check/uncheck continu
+        debug!("Toggle continu checkbox");
+        self.click(receiver, sender)?;
+
+        // apply
+        debug!("Apply changes");
+        self.move_right(receiver, sender)?;
+        self.click(receiver, sender)?;
+
+        // back to home
+        debug!("To home");
+        self.click(receiver, sender)?;
+        self.click(receiver, sender)?;
+        self.click(receiver, sender)?;
+        self.click(receiver, sender)?;
+
+        Ok(())
+    }
+
+    fn read_measured_tap_water_temperature(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<f64, WebsocketClientError> {
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Informatie > Temperaturen")?;
+
+        self.get_item_from_response("Tapwater gemeten", &response_message)
+    }
+
+    fn read_tap_water_setpoint(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<f64, WebsocketClientError> {
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Informatie > Temperaturen")?;
+
+        self.get_item_from_response("Tapwater ingesteld", &response_message)
+    }
+
+    fn read_tap_water_temperatures(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<(f64, f64), WebsocketClientError> {
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Informatie > Temperaturen")?;
+
+        Ok((
+            self.get_item_from_response("Tapwater gemeten", &response_message)?,
+            self.get_item_from_response("Tapwater ingesteld", &response_message)?,
+        ))
+    }
+
+    fn read_outside_temperatures(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<(f64, f64), WebsocketClientError> {
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Informatie > Temperaturen")?;
+
+        let outside_temperature =
+            self.get_item_from_response("Buitentemperatuur", &response_message)?;
+        let average_outside_temperature =
+            self.get_item_from_response("Gemiddelde temp.", &response_message)?;
+        info!(
+            "Outside temperature is {}°C, {}°C on average",
+            outside_temperature, average_outside_temperature
+        );
+
+        Ok((outside_temperature, average_outside_temperature))
+    }
+
+    fn read_firmware_version(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<Option<FirmwareVersion>, WebsocketClientError> {
+        // older controllers don't list their installation status
+        if navigation
+            .get_navigation_item_id("Informatie > Installatiestatus")
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        let response_message = self.navigate_to(
+            receiver,
+            sender,
+            navigation,
+            "Informatie > Installatiestatus",
+        )?;
+
+        Ok(get_firmware_version_from_response(&response_message))
+    }
+
+    fn read_controller_time(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<Option<NaiveDateTime>, WebsocketClientError> {
+        // older controllers don't list their installation status
+        if navigation
+            .get_navigation_item_id("Informatie > Installatiestatus")
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        let response_message = self.navigate_to(
+            receiver,
+            sender,
+            navigation,
+            "Informatie > Installatiestatus",
+        )?;
+
+        Ok(get_controller_time_from_response(&response_message))
+    }
+
+    fn read_energy_stats(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<Option<BTreeMap<String, f64>>, WebsocketClientError> {
+        // not every controller has an energy monitor
+        if navigation
+            .get_navigation_item_id("Informatie > Energie")
+            .is_err()
+        {
+            warn!("Heatpump has no Informatie > Energie menu, not reading energy stats");
+            return Ok(None);
+        }
+
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Informatie > Energie")?;
+
+        Ok(Some(get_energy_counters_from_response(&response_message)?))
+    }
+
+    fn read_fault_buffer(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<Vec<Fault>, WebsocketClientError> {
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Informatie > Storingsbuffer")?;
+
+        get_faults_from_response(&response_message)
+    }
+
+    fn read_operating_modes(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+    ) -> Result<HashMap<String, OperatingMode>, WebsocketClientError> {
+        let response_message =
+            self.navigate_to(receiver, sender, navigation, "Instelling > Bedrijfsmode")?;
+
+        let operating_modes = get_operating_modes_from_response(&response_message)?;
+        debug!("Operating modes: {:?}", operating_modes);
+
+        Ok(operating_modes)
+    }
+
+    fn set_tap_water_temperature(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        desired_tap_water_temperature: f64,
+        temperature_unit: TemperatureUnit,
+    ) -> Result<(), WebsocketClientError> {
+        let value = self.read_tap_water_setpoint(receiver, sender, navigation)?;
+        let temperature_steps = get_value_steps(
+            value,
+            desired_tap_water_temperature,
+            temperature_unit.step(),
+        );
+        if temperature_steps != 0 {
+            debug!("To Afstandbediening");
+            self.navigate_to(receiver, sender, navigation, "Afstandbediening")?;
+
+            // to menu
+            debug!("To menu");
+            self.click(receiver, sender)?;
+
+            // to warmwater
+            debug!("To warmwater");
+            self.move_right(receiver, sender)?;
+            self.move_right(receiver, sender)?;
+            self.move_right(receiver, sender)?;
+            self.click(receiver, sender)?;
+
+            // to temperatuur
+            debug!("To temperatuur");
+            self.move_right(receiver, sender)?;
+            self.click(receiver, sender)?;
+
+            // to gewenste waarde
+            debug!("To gewenste waarde");
+            self.click(receiver, sender)?;
+
+            // raise / lower temperature
+            if temperature_steps > 0 {
+                let temperature_increments = temperature_steps;
+                debug!(
+                    "Raising temperature by {} increments of {}{}",
+                    temperature_increments,
+                    temperature_unit.step(),
+                    temperature_unit.symbol()
+                );
+                for _n in 0..temperature_increments {
+                    self.move_right(receiver, sender)?;
+                }
+                self.click(receiver, sender)?;
+            } else {
+                let temperature_decrements = -temperature_steps;
+                debug!(
+                    "Lowering temperature by {} decrements of {}{}",
+                    temperature_decrements,
+                    temperature_unit.step(),
+                    temperature_unit.symbol()
+                );
+                for _n in 0..temperature_decrements {
+                    self.move_left(receiver, sender)?;
+                }
+                self.click(receiver, sender)?;
+            }
+
+            // apply
+            debug!("Apply changes");
+            self.click(receiver, sender)?;
+
+            // back to home
+            debug!("To home");
+            self.click(receiver, sender)?;
+            self.click(receiver, sender)?;
+            self.click(receiver, sender)?;
+
+            info!(
+                "Finished updating tap water temperature to {}{}",
+                desired_tap_water_temperature,
+                temperature_unit.symbol()
+            )
+        } else {
+            info!(
+                "Set tap water temperature is already at {}{}, no need to update it",
+                value,
+                temperature_unit.symbol()
+            )
+        }
+
+        Ok(())
+    }
+
+    fn set_tap_water_schedule_from_best_spot_prices(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        config: &Config,
+        best_spot_prices: &[SpotPrice],
+        written_schedule: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        info!("Updating tap water heating schedule from best spot prices");
+        self.set_schedule_program(
+            receiver,
+            sender,
+            navigation,
+            config,
+            &ClockProgram::TapWater,
+            best_spot_prices,
+            get_tap_water_schedule_raw_values,
+            written_schedule,
+        )
+    }
+
+    fn set_heating_schedule_from_worst_spot_prices(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        config: &Config,
+        worst_spot_prices: &[SpotPrice],
+        written_schedule: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        info!("Updating heating schedule to block worst spot prices");
+        let local_time_zone = config
+            .get_local_time_zone()
+            .map_err(|e| WebsocketClientError::Config(e.to_string()))?;
+        let worst_spot_prices = remove_protected_windows_from_blocking(
+            worst_spot_prices,
+            &config.protected_windows,
+            &local_time_zone,
+        );
+
+        self.set_schedule_program(
+            receiver,
+            sender,
+            navigation,
+            config,
+            &ClockProgram::Heating,
+            &worst_spot_prices,
+            get_heating_schedule_raw_values,
+            written_schedule,
+        )
+    }
+
+    fn set_cooling_schedule_from_best_spot_prices(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        config: &Config,
+        best_spot_prices: &[SpotPrice],
+        written_schedule: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        // heatpumps that can't cool don't have a cooling clock program
+        let path = format!(
+            "{} > {}",
+            ClockProgram::Cooling.menu(),
+            config.schedule_program.navigation_name()
+        );
+        if navigation.get_navigation_item_id(&path).is_err() {
+            warn!(
+                "Heatpump has no {} menu, not updating cooling schedule",
+                path
+            );
+            return Ok(vec![]);
+        }
+
+        // like tap water, cooling is only allowed inside the cheapest window and blocked outside of it
+        info!("Updating cooling schedule from best spot prices");
+        self.set_schedule_program(
+            receiver,
+            sender,
+            navigation,
+            config,
+            &ClockProgram::Cooling,
+            best_spot_prices,
+            get_tap_water_schedule_raw_values,
+            written_schedule,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn set_schedule_program(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        config: &Config,
+        program: &ClockProgram,
+        spot_prices: &[SpotPrice],
+        get_raw_values: fn(&Tz, &[SpotPrice], usize) -> Vec<u32>,
+        written_schedule: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        let mut programs_written_schedule = self.set_schedule_program_segments(
+            receiver,
+            sender,
+            navigation,
+            config,
+            program,
+            &config.schedule_program,
+            spot_prices,
+            get_raw_values,
+            written_schedule,
+        )?;
+
+        if !config.mirror_schedule_to_all_programs {
+            return Ok(programs_written_schedule);
+        }
+
+        // some firmware gives a stale program priority over the configured one; week and per day together cover every
+        // day, so those get the same schedule and the others are cleared to keep them from interfering
+        for schedule_program in ScheduleProgram::all() {
+            if schedule_program == config.schedule_program {
+                continue;
+            }
+
+            let path = format!(
+                "{} > {}",
+                program.menu(),
+                schedule_program.navigation_name()
+            );
+            if navigation.get_navigation_item_id(&path).is_err() {
+                debug!("Item {} does not exist, no need to mirror to it", path);
+                continue;
+            }
+
+            if schedule_program == ScheduleProgram::Week
+                || schedule_program == ScheduleProgram::PerDay
+            {
+                info!("Mirroring schedule to {}", path);
+                programs_written_schedule.extend(self.set_schedule_program_segments(
+                    receiver,
+                    sender,
+                    navigation,
+                    config,
+                    program,
+                    &schedule_program,
+                    spot_prices,
+                    get_raw_values,
+                    written_schedule,
+                )?);
+            } else {
+                info!("Clearing {} so it can't take priority", path);
+                programs_written_schedule
+                    .extend(self.clear_schedule_program(receiver, sender, navigation, &path)?);
+            }
+        }
+
+        Ok(programs_written_schedule)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn set_schedule_program_segments(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        config: &Config,
+        program: &ClockProgram,
+        schedule_program: &ScheduleProgram,
+        spot_prices: &[SpotPrice],
+        get_raw_values: fn(&Tz, &[SpotPrice], usize) -> Vec<u32>,
+        written_schedule: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        let path = format!(
+            "{} > {}",
+            program.menu(),
+            schedule_program.navigation_name()
+        );
+        navigation.ensure_navigation_item_is_writable(&path)?;
+        let content = self.get_content(receiver, sender, navigation, &path)?;
+
+        if content.segments.len() != schedule_program.number_of_segments() {
+            return Err(WebsocketClientError::Parse(format!(
+                "Item {} has {} segments, expected {}",
+                path,
+                content.segments.len(),
+                schedule_program.number_of_segments()
+            )));
+        }
+
+        let heatpump_time_zone = config
+            .get_heatpump_time_zone()
+            .map_err(|e| WebsocketClientError::Config(e.to_string()))?;
+
+        // with a snapshot of the current offset the windows are shifted up front and encoded as is, so they're never
+        // widened or skipped around summer time transitions, at the cost of being an hour off on those days
+        let (heatpump_time_zone, spot_prices) = if config.use_fixed_offset_for_schedule {
+            (
+                Tz::UTC,
+                get_fixed_offset_spot_prices(spot_prices, &heatpump_time_zone, self.clock.now()),
+            )
+        } else {
+            (heatpump_time_zone, spot_prices.to_vec())
+        };
+        let spot_prices = spot_prices.as_slice();
+
+        let mut segments_written_schedule = vec![];
+
+        // every segment is saved separately, so the per day program takes seven writes
+        for (segment_index, segment) in content.segments.iter().enumerate() {
+            let segment_spot_prices = get_schedule_segment_spot_prices(
+                &heatpump_time_zone,
+                schedule_program,
+                spot_prices,
+                segment_index,
+            );
+
+            // days without prices yet are planned in a later run, until then keep what's programmed
+            if segment_spot_prices.is_empty() && *schedule_program != ScheduleProgram::Week {
+                info!(
+                    "No planned spot prices for {}, leaving it as is",
+                    segment.name
+                );
+                continue;
+            }
+
+            info!("Updating schedule for {}", segment.name);
+            let raw_values = get_raw_values(
+                &heatpump_time_zone,
+                &segment_spot_prices,
+                segment.item.len(),
+            );
+
+            segments_written_schedule.extend(self.set_schedule(
+                receiver,
+                sender,
+                segment,
+                &raw_values,
+                written_schedule,
+            )?);
+        }
+
+        Ok(segments_written_schedule)
+    }
+
+    fn clear_schedule_program(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        path: &str,
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        navigation.ensure_navigation_item_is_writable(path)?;
+        let content = self.get_content(receiver, sender, navigation, path)?;
+
+        let cleared_schedule: Vec<(String, i64)> = content
+            .segments
+            .iter()
+            .flat_map(|segment| segment.item.iter())
+            .map(|item| (item.id.clone(), 0))
+            .collect();
+
+        if content
+            .segments
+            .iter()
+            .flat_map(|segment| segment.item.iter())
+            .all(|item| item.raw == "0")
+        {
+            info!(
+                "Schedule of {} is already cleared, no need to update it",
+                path
+            );
+            return Ok(cleared_schedule);
+        }
+
+        for segment in &content.segments {
+            self.reset_schedule_items(sender, segment)?;
+        }
+        self.save(receiver, sender)?;
+
+        Ok(cleared_schedule)
+    }
+
+    fn set_schedule(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        content: &ContentItem,
+        raw_values: &[u32],
+        written_schedule: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        for item in &content.item {
+            if let Ok(Some((from, till))) = item.parse_time_range() {
+                debug!(
+                    "Currently programmed {} block {} - {}",
+                    item.name,
+                    from.format("%H:%M"),
+                    till.format("%H:%M")
+                );
+            }
+        }
+
+        for (item, written_raw_value) in get_externally_changed_items(content, written_schedule) {
+            warn!(
+                "Item {} of {} was changed on the heatpump to {} (raw {}) since it was written as raw {}, overwriting it",
+                item.name, content.name, item.value, item.raw, written_raw_value
+            );
+        }
+
+        let schedule = WeeklySchedule::from_raw_values(content, raw_values)
+            .map_err(|e| WebsocketClientError::Parse(e.to_string()))?;
+        let desired_schedule: Vec<(String, i64)> = schedule
+            .item_ids
+            .iter()
+            .cloned()
+            .zip(schedule.raw_values().into_iter().map(i64::from))
+            .collect();
+
+        if !schedule_needs_update(content, raw_values) {
+            info!("Schedule is already programmed as desired, no need to update it");
+            return Ok(desired_schedule);
+        }
+
+        for (item_id, time_range) in schedule.item_ids.iter().zip(schedule.slots.iter()) {
+            debug!("Programming {} as {}", item_id, time_range);
+        }
+        for set_command in schedule.to_set_commands() {
+            self.send(sender, websocket::OwnedMessage::Text(set_command))?;
+        }
+
+        self.save(receiver, sender)?;
+
+        Ok(desired_schedule)
+    }
+
+    pub fn reset_schedule(&self, program: &ClockProgram) -> Result<(), WebsocketClientError> {
+        let (mut receiver, mut sender) = self.connect()?;
+
+        let navigation = self.login(&mut receiver, &mut sender)?;
+
+        self.reset_schedule_program(&mut receiver, &mut sender, &navigation, program)?;
+
+        Ok(())
+    }
+
+    fn reset_schedule_program(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        program: &ClockProgram,
+    ) -> Result<Vec<(String, i64)>, WebsocketClientError> {
+        // what got reset, so the written schedule can tell the reset apart from a change on the heatpump itself
+        let mut reset_schedule = vec![];
+
+        for schedule_program in ScheduleProgram::all() {
+            let path = format!(
+                "{} > {}",
+                program.menu(),
+                schedule_program.navigation_name()
+            );
+
+            // not every controller offers every program, only reset the ones it has
+            if navigation.get_navigation_item_id(&path).is_err() {
+                debug!("Item {} does not exist, no need to reset it", path);
+                continue;
+            }
+            navigation.ensure_navigation_item_is_writable(&path)?;
+
+            let content = self.get_content(receiver, sender, navigation, &path)?;
+            for segment in &content.segments {
+                info!("Resetting schedule for {} of {}", segment.name, path);
+                self.reset_schedule_items(sender, segment)?;
+                reset_schedule.extend(segment.item.iter().map(|item| (item.id.clone(), 0)));
+            }
+            self.save(receiver, sender)?;
+
+            // read the program back, so a reset the controller didn't store isn't reported as done
+            let response_message = self.navigate_to(receiver, sender, navigation, &path)?;
+            let content = get_content_from_response(&path, &response_message)?;
+            if content
+                .segments
+                .iter()
+                .flat_map(|segment| segment.item.iter())
+                .any(|item| item.raw != "0")
+            {
+                return Err(WebsocketClientError::SaveRejected {
+                    response: response_message,
+                });
+            }
+
+            info!("Finished resetting schedule of {}", path);
+        }
+
+        Ok(reset_schedule)
+    }
+
+    pub async fn set_value(
+        &self,
+        navigation_path: &str,
+        desired_value: f64,
+        step: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        if step <= 0.0 {
+            return Err(Box::new(WebsocketClientError::Config(format!(
+                "Step {} should be larger than 0",
+                step
+            ))));
+        }
+
+        // the last part of the path is the item on the page the rest of the path leads to
+        let (page_path, item_name) = match navigation_path.rsplit_once(" > ") {
+            Some((page_path, item_name)) => (page_path.to_string(), item_name.to_string()),
+            None => {
+                return Err(Box::new(WebsocketClientError::Config(format!(
+                    "Path {} should end with the item to set, like Instelling > Verwarming > Temperatuur +-",
+                    navigation_path
+                ))))
+            }
+        };
+        let navigation_path = navigation_path.to_string();
+
+        self.run_session(move |client, receiver, sender, navigation| {
+            navigation.ensure_navigation_item_is_writable(&page_path)?;
+
+            let response_message = client.navigate_to(receiver, sender, navigation, &page_path)?;
+            let (item_id, value) = get_numeric_item_from_response(&item_name, &response_message)?;
+
+            let value_steps = get_value_steps(value, desired_value, step);
+            if value_steps == 0 {
+                info!(
+                    "Item {} is already at {}, no need to update it",
+                    navigation_path, value
+                );
+                return Ok(());
+            }
+
+            let updated_value = value + value_steps as f64 * step;
+            if (updated_value - desired_value).abs() > step / 1000.0 {
+                warn!(
+                    "Value {} is not reachable in steps of {} from {}, setting {} instead",
+                    desired_value, step, value, updated_value
+                );
+            }
+
+            // unlike the remote control, which steps a value up or down from wherever its cursor is, a page of the web
+            // interface takes the new value of an item as shown on that page, in the same SET message that writes the
+            // clock programs; the value is kept to whole steps from the current one, so it's a value the item can hold,
+            // and only the value read back after saving counts as set
+            info!(
+                "Changing item {} from {} to {} in {} steps of {}",
+                navigation_path, value, updated_value, value_steps, step
+            );
+            client.set_item_value(receiver, sender, &item_id, updated_value)?;
+
+            let response_message = client.navigate_to(receiver, sender, navigation, &page_path)?;
+            let (_, stored_value) = get_numeric_item_from_response(&item_name, &response_message)?;
+            if get_value_steps(stored_value, updated_value, step) != 0 {
+                // don't leave the item at whatever the controller made of the value, put the original one back
+                warn!(
+                    "Item {} reads {} instead of {} after saving, restoring it to {}",
+                    navigation_path, stored_value, updated_value, value
+                );
+                client.set_item_value(receiver, sender, &item_id, value)?;
+
+                return Err(Box::new(WebsocketClientError::SaveRejected {
+                    response: response_message,
+                }));
+            }
+
+            info!(
+                "Finished updating item {} to {}",
+                navigation_path, stored_value
+            );
+
+            Ok(())
+        })
+        .await
+    }
+
+    fn set_item_value(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        item_id: &str,
+        value: f64,
+    ) -> Result<(), WebsocketClientError> {
+        self.send(
+            sender,
+            websocket::OwnedMessage::Text(format!("SET;set_{};{}", item_id, value)),
+        )?;
+        self.save(receiver, sender)
+    }
+
+    pub async fn boost_tap_water(&self, config: &Config, hours: i64) -> Result<(), Box<dyn Error>> {
+        // a day program can't hold a window longer than a day
+        if !(1..=24).contains(&hours) {
+            return Err(Box::<dyn Error>::from(format!(
+                "Boost of {} hours should be between 1 and 24 hours",
+                hours
+            )));
+        }
+
+        let now = self.clock.now();
+        let boost_spot_prices = round_spot_prices_to_granularity(
+            &[SpotPrice {
+                id: None,
+                source: None,
+                from: now,
+                till: now + Duration::hours(hours),
+                market_price: 0.0,
+                market_price_tax: 0.0,
+                sourcing_markup_price: 0.0,
+                energy_tax_price: 0.0,
+            }],
+            config.schedule_granularity_minutes,
+        )?;
+        let maximum_tap_water_temperature = config
+            .temperature_unit
+            .convert_from_celsius(MAXIMUM_TAP_WATER_TEMPERATURE);
+        let boost_tap_water_temperature = config
+            .boost_tap_water_temperature
+            .unwrap_or(maximum_tap_water_temperature)
+            .min(maximum_tap_water_temperature);
+
+        let state = if let Some(state_client) = &self.config.state_client {
+            state_client.read_state()?
+        } else {
+            None
+        };
+        let written_schedule = match &state {
+            Some(st) => st.written_schedule.clone().unwrap_or_default(),
+            None => vec![],
+        };
+
+        info!(
+            "Boosting tap water to {}{} for {} hours from {}",
+            boost_tap_water_temperature,
+            config.temperature_unit.symbol(),
+            hours,
+            now
+        );
+        let tap_water_written_schedule = {
+            let config = config.clone();
+            let boost_spot_prices = boost_spot_prices.clone();
+            let written_schedule = written_schedule.clone();
+            self.run_session(move |client, receiver, sender, navigation| {
+                let tap_water_written_schedule = client
+                    .set_tap_water_schedule_from_best_spot_prices(
+                        receiver,
+                        sender,
+                        navigation,
+                        &config,
+                        &boost_spot_prices,
+                        &written_schedule,
+                    )?;
+
+                client.set_tap_water_temperature(
+                    receiver,
+                    sender,
+                    navigation,
+                    boost_tap_water_temperature,
+                    config.temperature_unit,
+                )?;
+
+                Ok(tap_water_written_schedule)
+            })
+            .await?
+        };
+
+        // forget the planned window, so the next run writes the planned schedule back instead of skipping it as unchanged
+        if let (Some(state_client), Some(mut state)) = (&self.config.state_client, state) {
+            state.planned_spot_prices = None;
+            state.written_schedule = Some(merge_written_schedule(
+                &written_schedule,
+                tap_water_written_schedule,
+            ));
+            state_client.store_state(&state).await?;
+        }
+
+        Ok(())
+    }
+
+    fn reset_schedule_items(
+        &self,
+        sender: &mut dyn MessageSender,
+        content: &ContentItem,
+    ) -> Result<(), WebsocketClientError> {
+        // set all items to 0
+        info!("Resetting schedule");
+        for item in &content.item {
+            debug!("Setting {} to 00:00 - 00:00", item.name);
+            self.send(
+                sender,
+                websocket::OwnedMessage::Text(format!("SET;set_{};{}", item.id, 0)),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn save(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+    ) -> Result<(), WebsocketClientError> {
+        info!("Saving changes");
+        let response_message = self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text("SAVE;1".to_string()),
+        )?;
+
+        // an accepted save is answered with the page content, anything else means the changes weren't stored
+        if !response_message.trim_start().starts_with("<Content") {
+            return Err(WebsocketClientError::SaveRejected {
+                response: response_message,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn get_content(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        path: &str,
+    ) -> Result<Content, WebsocketClientError> {
+        let response_message = self.navigate_to(receiver, sender, navigation, path)?;
+
+        get_content_from_response(path, &response_message)
+    }
+
+    fn add_jitter_to_spot_prices(
+        config: &Config,
+        spot_prices: &[SpotPrice],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        let jittered_spot_prices = if spot_prices.is_empty() || config.jitter_max_minutes == 0 {
+            spot_prices.to_vec()
+        } else {
+            let heatpump_time_zone = config.get_heatpump_time_zone()?;
+            // a pinned seed makes the shift reproducible when debugging a schedule
+            let mut rng: Box<dyn RngCore> = match config.jitter_seed {
+                Some(jitter_seed) => Box::new(StdRng::seed_from_u64(jitter_seed)),
+                None => Box::new(rand::thread_rng()),
+            };
+
+            let mut updated_spot_prices: Vec<SpotPrice> = vec![];
+
+            // jitter each contiguous block independently so multiple blocks don't all move together
+            for block in get_contiguous_blocks(spot_prices) {
+                let shift_minutes =
+                    rng.gen_range(0..2 * config.jitter_max_minutes) - config.jitter_max_minutes;
+
+                let shift_minutes = clamp_jitter_shift_minutes(
+                    &heatpump_time_zone,
+                    block,
+                    now,
+                    shift_minutes,
+                    config.past_slot_policy,
+                );
+
+                for spot_price in block.iter() {
+                    updated_spot_prices.push(SpotPrice {
+                        from: spot_price.from + Duration::minutes(shift_minutes),
+                        till: spot_price.till + Duration::minutes(shift_minutes),
+                        ..spot_price.clone()
+                    })
+                }
+            }
+
+            updated_spot_prices
+        };
+
+        // round explicitly to what the clock program can represent instead of having the controller truncate it
+        let rounded_spot_prices = round_spot_prices_to_granularity(
+            &jittered_spot_prices,
+            config.schedule_granularity_minutes,
+        )?;
+
+        Ok(apply_past_slot_policy(
+            spot_prices,
+            &rounded_spot_prices,
+            now,
+            config.past_slot_policy,
+            config.schedule_granularity_minutes,
+        ))
+    }
+
+    fn navigate_to(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        navigation: &Navigation,
+        nav: &str,
+    ) -> Result<String, WebsocketClientError> {
+        debug!("Navigate to '{}'", nav);
+        let navigation_id = navigation.get_navigation_item_id(nav)?;
+        let response_message = self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text(format!("GET;{}", navigation_id)),
+        )?;
+        debug!(
+            "Retrieved response from '{}' ({}):\n{}",
+            &nav, navigation_id, response_message
+        );
+
+        Ok(response_message)
+    }
+
+    fn get_navigation_from_response(
+        &self,
+        response_message: String,
+    ) -> Result<Navigation, WebsocketClientError> {
+        let mut navigation: Navigation = from_str(&response_message).map_err(|e| {
+            WebsocketClientError::Parse(format!("Failed parsing navigation: {}", e))
+        })?;
+        navigation.build_item_id_index();
+
+        Ok(navigation)
+    }
+
+    fn move_right(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+    ) -> Result<(), WebsocketClientError> {
+        debug!("Move right/down");
+        self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text("MOVE;0".to_string()),
+        )?;
+        self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text("MOVE;6".to_string()),
+        )?;
+        self.wait_for_navigation_step();
+        Ok(())
+    }
+
+    fn move_left(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+    ) -> Result<(), WebsocketClientError> {
+        debug!("Move left/up");
+        self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text("MOVE;1".to_string()),
+        )?;
+        self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text("MOVE;6".to_string()),
+        )?;
+        self.wait_for_navigation_step();
+        Ok(())
+    }
+
+    fn click(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+    ) -> Result<String, WebsocketClientError> {
+        debug!("Click");
+        self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text("MOVE;2".to_string()),
+        )?;
+        // the refresh returns the screen the click landed on
+        let response_message = self.send_and_await(
+            receiver,
+            sender,
+            websocket::OwnedMessage::Text("MOVE;6".to_string()),
+        )?;
+        self.wait_for_navigation_step();
+        Ok(response_message)
+    }
+
+    fn wait_for_navigation_step(&self) {
+        // slow controllers need time to redraw their ui, otherwise the next move can desync the cursor
+        if self.config.navigation_step_delay_ms > 0 {
+            thread::sleep(std::time::Duration::from_millis(
+                self.config.navigation_step_delay_ms,
+            ));
+        }
+    }
+
+    fn get_item_from_response(
+        &self,
+        item: &str,
+        response_message: &str,
+    ) -> Result<f64, WebsocketClientError> {
+        let items = self.parse_all_items(response_message)?;
+
+        match items.get(item) {
+            Some(value) => Ok(*value),
+            None => Err(WebsocketClientError::Parse(format!(
+                "No match for item {}",
+                item
+            ))),
+        }
+    }
+
+    fn parse_all_items(
+        &self,
+        response_message: &str,
+    ) -> Result<HashMap<String, f64>, WebsocketClientError> {
+        // <Content><item id='0x4816ac'><name>Aanvoer</name><value>22.0°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>5.0°C</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>
+
+        let content: ValuesContent = from_str(response_message).map_err(|e| {
+            WebsocketClientError::Parse(format!("Failed parsing items from response: {}", e))
+        })?;
+
+        let mut items: HashMap<String, f64> = HashMap::new();
+        for item in content.items {
+            if let Some(value) = parse_item_value(&item.name, &item.value)? {
+                items.insert(item.name, value);
+            }
+        }
+
+        Ok(items)
+    }
+
+    fn send(
+        &self,
+        sender: &mut dyn MessageSender,
+        message: websocket::OwnedMessage,
+    ) -> Result<(), WebsocketClientError> {
+        debug!("Sending {}", describe_message(&message));
+        sender
+            .send_message(&message)
+            .map_err(|e| WebsocketClientError::Connect(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn drain_pending_messages(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        message: &websocket::OwnedMessage,
+    ) -> Result<(), WebsocketClientError> {
+        // the controller doesn't answer SET, but may push a refresh of the current page after it, and REFRESH
+        // keeps pushing the page last navigated to; left unread such a frame would be taken as the next response
+        let connect_error = |e: Box<dyn Error>| WebsocketClientError::Connect(e.to_string());
+
+        while let Some(pending) = receiver.try_receive_message() {
+            match pending.map_err(connect_error)? {
+                OwnedMessage::Text(text) => {
+                    debug!(
+                        "Discarding unsolicited message before sending {}: {}",
+                        describe_message(message),
+                        text
+                    );
+                }
+                OwnedMessage::Close(_) => {
+                    sender
+                        .send_message(&OwnedMessage::Close(None))
+                        .map_err(connect_error)?;
+
+                    return Err(WebsocketClientError::ConnectionClosed {
+                        message_in_flight: describe_message(message),
+                    });
+                }
+                OwnedMessage::Ping(data) => {
+                    sender
+                        .send_message(&OwnedMessage::Pong(data))
+                        .map_err(connect_error)?;
+                }
+                OwnedMessage::Pong(_) => {}
+                OwnedMessage::Binary(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send_and_await(
+        &self,
+        receiver: &mut dyn MessageReceiver,
+        sender: &mut dyn MessageSender,
+        message: websocket::OwnedMessage,
+    ) -> Result<String, WebsocketClientError> {
+        let connect_error = |e: Box<dyn Error>| WebsocketClientError::Connect(e.to_string());
+
+        self.drain_pending_messages(receiver, sender, &message)?;
+        sender.send_message(&message).map_err(connect_error)?;
+
+        // large navigation and content payloads can arrive spread over several text frames
+        let mut response_message = String::new();
+
+        while let Some(response) = receiver.receive_message() {
+            match response.map_err(connect_error)? {
+                OwnedMessage::Text(text) => {
+                    response_message.push_str(&text);
+                    if is_complete_response(&response_message) {
+                        return Ok(response_message);
+                    }
+                    debug!(
+                        "Received partial response for message {}, awaiting more frames",
+                        describe_message(&message)
+                    );
+                }
+                OwnedMessage::Close(_) => {
+                    // return a close
+                    sender
+                        .send_message(&OwnedMessage::Close(None))
+                        .map_err(connect_error)?;
+
+                    return Err(WebsocketClientError::ConnectionClosed {
+                        message_in_flight: describe_message(&message),
+                    });
+                }
+                OwnedMessage::Ping(data) => {
+                    // return a pong
+                    sender
+                        .send_message(&OwnedMessage::Pong(data))
+                        .map_err(connect_error)?;
+                }
+                OwnedMessage::Pong(_) => {}
+                OwnedMessage::Binary(_) => {}
+            }
+        }
+
+        if !response_message.is_empty() {
+            return Err(WebsocketClientError::Parse(format!(
+                "Incomplete response for message {}: {}",
+                describe_message(&message),
+                response_message
+            )));
+        }
+
+        Err(WebsocketClientError::Timeout {
+            message_in_flight: describe_message(&message),
+        })
+    }
+
+    fn get_spot_prices_for_tapwater_heating_or_desinfection(
+        &self,
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        spot_prices: &[SpotPrice],
+        now: DateTime<Utc>,
+        desinfection_finished_at: DateTime<Utc>,
+        current_desinfection_enabled: bool,
+    ) -> Result<(PlanningResponse, bool, DesinfectionDecision), Box<dyn Error>> {
+        let lowest_price_tapwater_heating_response =
+            spot_price_planner.get_best_spot_prices(&PlanningRequest {
+                spot_prices: spot_prices.to_owned(),
+                load_profile: config.load_profile.clone(),
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: Some(now),
+                before: Some(now + Duration::hours(12)),
+            })?;
+
+        // desinfection may be restricted to days someone's home, the overdue override below still uses all prices
+        let desinfection_spot_prices = filter_spot_prices_on_weekdays(
+            spot_prices,
+            &config.desinfection_allowed_weekdays,
+            &config.get_heatpump_time_zone()?,
+        );
+
+        let lowest_price_desinfection_response =
+            spot_price_planner.get_best_spot_prices(&PlanningRequest {
+                spot_prices: desinfection_spot_prices.clone(),
+                load_profile: config.desinfection_load_profile.clone(),
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: Some(now),
+                before: Some(now + Duration::hours(config.desinfection_lookahead_hours)),
+            })?;
+
+        // for legionella safety don't skip an overdue desinfection just because the planner found no best window
+        let hours_since_last_desinfection = (now - desinfection_finished_at).num_hours();
+        if lowest_price_desinfection_response.spot_prices.is_empty()
+            && config.force_desinfection_when_overdue
+            && hours_since_last_desinfection > config.max_hours_since_last_desinfection
+        {
+            let desinfection_duration_seconds: i64 = config
+                .desinfection_load_profile
+                .sections
+                .iter()
+                .map(|section| section.duration_seconds)
+                .sum();
+
+            let cheapest_spot_prices = get_cheapest_contiguous_spot_prices(
+                spot_prices,
+                config,
+                desinfection_duration_seconds,
+                now,
+            );
+
+            if !cheapest_spot_prices.is_empty() {
+                info!(
+                    "Desinfection is overdue ({} > {} hours) and no best spot prices are available, forcing desinfection in cheapest available window",
+                    hours_since_last_desinfection, config.max_hours_since_last_desinfection
+                );
+                return Ok((
+                    PlanningResponse {
+                        spot_prices: cheapest_spot_prices,
+                        load_profile: config.desinfection_load_profile.clone(),
+                    },
+                    true,
+                    DesinfectionDecision::Overdue {
+                        hours_since_last_desinfection,
+                        max_hours_since_last_desinfection: config.max_hours_since_last_desinfection,
+                    },
+                ));
+            }
+
+            info!("Desinfection is overdue, but no contiguous window of spot prices is long enough to force it");
+        }
+
+        // if lowest price desinfection spot prices start after the defer horizon we don't want desinfection to run now
+        if is_desinfection_deferred(
+            &lowest_price_desinfection_response.spot_prices,
+            config.desinfection_defer_hours,
+            now,
+        ) {
+            info!(
+                "Optimal spot prices for desinfection are more than {} hours ahead, skip using those for now and will check again next run",
+                config.desinfection_defer_hours
+            );
+            Ok((
+                lowest_price_tapwater_heating_response,
+                false,
+                DesinfectionDecision::Deferred {
+                    defer_hours: config.desinfection_defer_hours,
+                },
+            ))
+        } else {
+            let highest_price_desinfection_response =
+                spot_price_planner.get_best_spot_prices(&PlanningRequest {
+                    spot_prices: desinfection_spot_prices,
+                    load_profile: config.desinfection_load_profile.clone(),
+                    planning_strategy: PlanningStrategy::HighestPrice,
+                    after: Some(now),
+                    before: Some(now + Duration::hours(config.desinfection_lookahead_hours)),
+                })?;
+
+            info!("Checking if desinfection is needed");
+            let (desinfection_desired, desinfection_decision) = is_desinfection_desired(
+                config,
+                current_desinfection_enabled,
+                &desinfection_finished_at,
+                &lowest_price_desinfection_response,
+                &highest_price_desinfection_response,
+                now,
+            )?;
+
+            if desinfection_desired {
+                Ok((
+                    lowest_price_desinfection_response,
+                    desinfection_desired,
+                    desinfection_decision,
+                ))
+            } else {
+                Ok((
+                    lowest_price_tapwater_heating_response,
+                    false,
+                    desinfection_decision,
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn get_spot_prices_for_desinfection_mode(
+        &self,
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        spot_prices: &[SpotPrice],
+        now: DateTime<Utc>,
+        desinfection_finished_at: DateTime<Utc>,
+        eco_desinfection_finished_at: Option<DateTime<Utc>>,
+        current_desinfection_enabled: bool,
+    ) -> Result<(PlanningResponse, DesinfectionMode, DesinfectionDecision), Box<dyn Error>> {
+        let (best_spot_prices_response, desinfection_desired, desinfection_decision) = self
+            .get_spot_prices_for_tapwater_heating_or_desinfection(
+                config,
+                spot_price_planner,
+                spot_prices,
+                now,
+                desinfection_finished_at,
+                current_desinfection_enabled,
+            )?;
+
+        if desinfection_desired {
+            return Ok((
+                best_spot_prices_response,
+                DesinfectionMode::Full,
+                desinfection_decision,
+            ));
+        }
+
+        if config.eco_desinfection_temperature.is_none() {
+            return Ok((
+                best_spot_prices_response,
+                DesinfectionMode::None,
+                desinfection_decision,
+            ));
+        }
+
+        let lowest_price_eco_desinfection_response =
+            spot_price_planner.get_best_spot_prices(&PlanningRequest {
+                spot_prices: spot_prices.to_owned(),
+                load_profile: config.get_eco_desinfection_load_profile().clone(),
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: Some(now),
+                before: Some(now + Duration::hours(12)),
+            })?;
+
+        // either cycle heats the tank above the normal setpoint, so the most recent of both counts
+        let last_heat_up_at = match eco_desinfection_finished_at {
+            Some(eco_desinfection_finished_at) => {
+                std::cmp::max(desinfection_finished_at, eco_desinfection_finished_at)
+            }
+            None => desinfection_finished_at,
+        };
+
+        match is_eco_desinfection_desired(
+            config,
+            last_heat_up_at,
+            &lowest_price_eco_desinfection_response,
+        ) {
+            Some(eco_desinfection_decision) => Ok((
+                lowest_price_eco_desinfection_response,
+                DesinfectionMode::Eco,
+                eco_desinfection_decision,
+            )),
+            None => Ok((
+                best_spot_prices_response,
+                DesinfectionMode::None,
+                desinfection_decision,
+            )),
+        }
+    }
+
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub(crate) fn get_spot_prices_for_tapwater_heating_or_desinfection_per_segment(
+        &self,
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        segment_spot_prices: &[Vec<SpotPrice>],
+        now: DateTime<Utc>,
+        desinfection_finished_at: DateTime<Utc>,
+        eco_desinfection_finished_at: Option<DateTime<Utc>>,
+        current_desinfection_enabled: bool,
+    ) -> Result<
+        (
+            Vec<PlanningResponse>,
+            DesinfectionMode,
+            Vec<DesinfectionDecision>,
+        ),
+        Box<dyn Error>,
+    > {
+        let mut best_spot_prices_responses: Vec<PlanningResponse> = vec![];
+        let mut desinfection_mode = DesinfectionMode::None;
+        let mut desinfection_decisions: Vec<DesinfectionDecision> = vec![];
+        for spot_prices in segment_spot_prices.iter() {
+            let (
+                best_spot_prices_response,
+                segment_desinfection_mode,
+                segment_desinfection_decision,
+            ) = self.get_spot_prices_for_desinfection_mode(
+                config,
+                spot_price_planner,
+                spot_prices,
+                now,
+                desinfection_finished_at,
+                eco_desinfection_finished_at,
+                current_desinfection_enabled,
+            )?;
+            let segment_desinfection_desired = segment_desinfection_mode != DesinfectionMode::None;
+
+            // flat prices can make the planner pick a long window, while tap water only needs a limited time to heat up
+            let best_spot_prices_response = match config.max_tap_water_heating_hours {
+                Some(max_tap_water_heating_hours) if !segment_desinfection_desired => {
+                    PlanningResponse {
+                        spot_prices: cap_spot_prices_to_duration(
+                            &best_spot_prices_response.spot_prices,
+                            config,
+                            max_tap_water_heating_hours * 3600,
+                        ),
+                        ..best_spot_prices_response
+                    }
+                }
+                _ => best_spot_prices_response,
+            };
+
+            // on expensive days it's better to not heat at all and leave it to the minimum temperature floor
+            let best_spot_prices_response = match config.max_acceptable_price {
+                Some(max_acceptable_price) if !segment_desinfection_desired => PlanningResponse {
+                    spot_prices: filter_spot_prices_above_price(
+                        &best_spot_prices_response.spot_prices,
+                        config,
+                        max_acceptable_price,
+                    ),
+                    ..best_spot_prices_response
+                },
+                _ => best_spot_prices_response,
+            };
+            best_spot_prices_responses.push(best_spot_prices_response);
+            desinfection_mode = std::cmp::max(desinfection_mode, segment_desinfection_mode);
+            desinfection_decisions.push(segment_desinfection_decision);
+        }
+
+        Ok((
+            best_spot_prices_responses,
+            desinfection_mode,
+            desinfection_decisions,
+        ))
+    }
+
+    fn get_best_spot_prices_for_cooling_per_segment(
+        &self,
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        segment_spot_prices: &[Vec<SpotPrice>],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        let mut best_spot_prices: Vec<SpotPrice> = vec![];
+        for spot_prices in segment_spot_prices.iter() {
+            let lowest_price_cooling_response =
+                spot_price_planner.get_best_spot_prices(&PlanningRequest {
+                    spot_prices: spot_prices.clone(),
+                    load_profile: config.get_cooling_load_profile().clone(),
+                    planning_strategy: PlanningStrategy::LowestPrice,
+                    after: Some(now),
+                    before: Some(now + Duration::hours(12)),
+                })?;
+            best_spot_prices.extend(lowest_price_cooling_response.spot_prices);
+        }
+
+        Ok(best_spot_prices)
+    }
+
+    fn get_worst_spot_prices_for_blocking_heating(
+        &self,
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        spot_prices: &[SpotPrice],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        match config.blocking_strategy {
+            BlockingStrategy::SingleWorstHour => {
+                let highest_price_desinfection_response =
+                    spot_price_planner.get_best_spot_prices(&PlanningRequest {
+                        spot_prices: spot_prices.to_owned(),
+                        load_profile: LoadProfile {
+                            sections: vec![LoadProfileSection {
+                                duration_seconds: 3600,
+                                power_draw_watt: 1000.0,
+                            }],
+                        },
+                        planning_strategy: PlanningStrategy::HighestPrice,
+                        after: Some(now),
+                        before: Some(now + Duration::hours(24)),
+                    })?;
+
+                Ok(highest_price_desinfection_response.spot_prices)
+            }
+            BlockingStrategy::AbovePrice(blocking_price) => Ok(get_spot_prices_above_price(
+                &get_spot_prices_within_day(spot_prices, now),
+                config,
+                blocking_price,
+            )),
+            BlockingStrategy::AbovePercentile(percentile) => {
+                let spot_prices = get_spot_prices_within_day(spot_prices, now);
+                match get_price_percentile(&spot_prices, config, percentile) {
+                    Some(blocking_price) => {
+                        debug!(
+                            "Blocking heating above {:.4}, the {}th percentile of the day's prices",
+                            blocking_price, percentile
+                        );
+                        Ok(get_spot_prices_above_price(
+                            &spot_prices,
+                            config,
+                            blocking_price,
+                        ))
+                    }
+                    None => Ok(vec![]),
+                }
+            }
+        }
+    }
+
+    fn get_worst_spot_prices_for_blocking_heating_per_segment(
+        &self,
+        config: &Config,
+        spot_price_planner: &dyn BestSpotPricesPlanner,
+        segment_spot_prices: &[Vec<SpotPrice>],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        let mut worst_spot_prices = vec![];
+        for spot_prices in segment_spot_prices.iter() {
+            worst_spot_prices.extend(self.get_worst_spot_prices_for_blocking_heating(
+                config,
+                spot_price_planner,
+                spot_prices,
+                now,
+            )?);
+        }
+
+        Ok(worst_spot_prices)
+    }
+}
+
+fn is_clock_program_honored(
+    config: &Config,
+    circuit: &str,
+    operating_mode: &OperatingMode,
+) -> Result<bool, Box<dyn Error>> {
+    match operating_mode {
+        OperatingMode::Automatic => Ok(true),
+        OperatingMode::Unknown(value) => {
+            if config.fail_on_unknown_operating_mode {
+                return Err(Box::<dyn Error>::from(format!(
+                    "{} is in unknown operating mode {}",
+                    circuit, value
+                )));
+            }
+
+            warn!(
+                "{} is in unknown operating mode {}, scheduling as if it's automatic",
+                circuit, value
+            );
+            Ok(true)
+        }
+        // party, holiday and the like ignore the clock program, so writing it has no effect until they end
+        operating_mode => {
+            info!(
+                "{} is in operating mode {:?}, which doesn't follow the clock program",
+                circuit, operating_mode
+            );
+            Ok(false)
+        }
+    }
+}
+
+fn get_active_away_until(
+    config_away_until: Option<DateTime<Utc>>,
+    state_away_until: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match config_away_until.or(state_away_until) {
+        Some(away_until) if away_until > now => Some(away_until),
+        Some(away_until) => {
+            info!("Away mode ended at {}, clearing it", away_until);
+            None
+        }
+        None => None,
+    }
+}
+
+// every successful run gets a record, also the ones that skip writing an unchanged plan
+fn get_plan_record(planning_outcome: &PlanningOutcome, now: DateTime<Utc>) -> PlanRecord {
+    PlanRecord {
+        planned_at: now,
+        strategy: planning_outcome.strategy.clone(),
+        desinfection_desired: planning_outcome.desinfection_desired,
+        planned_from: planning_outcome.best_from,
+        planned_till: planning_outcome.best_till,
+    }
+}
+
+fn get_active_do_not_disturb_until(
+    state_do_not_disturb_until: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match state_do_not_disturb_until {
+        Some(do_not_disturb_until) if do_not_disturb_until > now => Some(do_not_disturb_until),
+        Some(do_not_disturb_until) => {
+            info!(
+                "Do not disturb ended at {}, clearing it",
+                do_not_disturb_until
+            );
+            None
+        }
+        None => None,
+    }
+}
+
+fn is_desinfection_deferred(
+    desinfection_spot_prices: &[SpotPrice],
+    desinfection_defer_hours: i64,
+    now: DateTime<Utc>,
+) -> bool {
+    match desinfection_spot_prices.first() {
+        Some(first) => first.from > now + Duration::hours(desinfection_defer_hours),
+        None => true,
+    }
+}
+
+// these logs get shipped to centralized logging, so the login code shouldn't end up there even if the controller echoes it
+fn redact_login_code(text: &str, login_code: &str) -> String {
+    if login_code.is_empty() {
+        return text.to_string();
+    }
+
+    text.replace(login_code, "***")
+}
+
+fn describe_message(message: &OwnedMessage) -> String {
+    match message {
+        // keep the login code out of the logs
+        OwnedMessage::Text(text) if text.starts_with("LOGIN;") => "LOGIN;***".to_string(),
+        OwnedMessage::Text(text) => text.clone(),
+        _ => format!("{:?}", message),
+    }
+}
+
+fn get_desired_tap_water_temperature(
+    config: &Config,
+    desinfection_mode: DesinfectionMode,
+    best_spot_prices: &[SpotPrice],
+    now: DateTime<Utc>,
+) -> f64 {
+    match (desinfection_mode, config.eco_desinfection_temperature) {
+        (DesinfectionMode::Full, _) => config
+            .temperature_unit
+            .convert_from_celsius(MAXIMUM_TAP_WATER_TEMPERATURE),
+        (DesinfectionMode::Eco, Some(eco_desinfection_temperature)) => eco_desinfection_temperature,
+        _ => get_adaptive_tap_water_temperature(config, best_spot_prices, now),
+    }
+}
+
+fn get_adaptive_tap_water_temperature(
+    config: &Config,
+    best_spot_prices: &[SpotPrice],
+    now: DateTime<Utc>,
+) -> f64 {
+    // keeping the tank hot until a cheap window that heats it anyway only adds standby losses, so hold it at the
+    // floor until then; a run once the window has started restores the desired temperature
+    let next_window_from = best_spot_prices
+        .iter()
+        .filter(|sp| sp.till > now)
+        .map(|sp| sp.from)
+        .min();
+
+    match (
+        config.enable_adaptive_setpoint,
+        config.minimum_tap_water_temperature,
+        next_window_from,
+    ) {
+        (true, Some(minimum_tap_water_temperature), Some(next_window_from))
+            if next_window_from > now
+                && next_window_from <= now + Duration::hours(config.adaptive_setpoint_hours) =>
+        {
+            info!(
+                "Next tap water window starts at {}, lowering setpoint to {} until then",
+                next_window_from, minimum_tap_water_temperature
+            );
+            minimum_tap_water_temperature
+        }
+        _ => config.desired_tap_water_temperature,
+    }
+}
+
+fn get_running_desinfection_mode(
+    desinfection_enabled: bool,
+    eco_desinfection_finished_at: Option<DateTime<Utc>>,
+    running_spot_prices: &[SpotPrice],
+) -> DesinfectionMode {
+    // desinfection stays enabled until the run after its window, an eco heat-up is only known by the end of its window
+    if desinfection_enabled {
+        DesinfectionMode::Full
+    } else if eco_desinfection_finished_at.is_some()
+        && eco_desinfection_finished_at == running_spot_prices.last().map(|sp| sp.till)
+    {
+        DesinfectionMode::Eco
+    } else {
+        DesinfectionMode::None
+    }
+}
+
+fn get_firmware_version_from_response(response_message: &str) -> Option<FirmwareVersion> {
+    // <item id='0x44c6cc'><name>Softwareversie</name><value>V3.88.2</value></item>
+    static FIRMWARE_VERSION_REGEX: OnceLock<Regex> = OnceLock::new();
+    let re = FIRMWARE_VERSION_REGEX.get_or_init(|| {
+        Regex::new(r"<name>Software[^<]*</name>\s*<value>([^<]*)</value>").unwrap()
+    });
+
+    re.captures(response_message)
+        .and_then(|captures| FirmwareVersion::from_value(&captures[1]))
+}
+
+fn get_controller_time_from_response(response_message: &str) -> Option<NaiveDateTime> {
+    // <item id='0x45e4d4'><name>Datum/tijd</name><value>21.04.22 10:22:13</value></item>, in heatpump local time
+    let content: ValuesContent = from_str(response_message).ok()?;
+
+    content.items.iter().find_map(|item| {
+        [
+            "%d.%m.%y %H:%M:%S",
+            "%d.%m.%y %H:%M",
+            "%d.%m.%Y %H:%M:%S",
+            "%d.%m.%Y %H:%M",
+        ]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(item.value.trim(), format).ok())
+    })
+}
+
+fn get_clock_skew_minutes(
+    controller_time: &NaiveDateTime,
+    heatpump_time_zone: &Tz,
+    now: DateTime<Utc>,
+) -> i64 {
+    // positive when the heatpump clock runs ahead
+    (*controller_time - now.with_timezone(heatpump_time_zone).naive_local()).num_minutes()
+}
+
+fn is_complete_response(response_message: &str) -> bool {
+    // only xml gets split over frames, so anything else is complete as is
+    let response_message = response_message.trim();
+    let root_tag = match response_message.strip_prefix('<') {
+        Some(rest) => rest
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or_default(),
+        None => return true,
+    };
+    if root_tag.is_empty() || root_tag.starts_with(['?', '!']) {
+        return true;
+    }
+
+    response_message.ends_with(&format!("</{}>", root_tag))
+        || (response_message.ends_with("/>") && response_message.matches('<').count() == 1)
+}
+
+fn get_screen_label_from_response(response_message: &str) -> Option<String> {
+    // <Content><item id='0x3d9dd8'><name>Continu</name><value>0</value></item><name>Thermische desinfectie</name></Content>
+    from_str::<ValuesContent>(response_message)
+        .ok()
+        .and_then(|content| content.name)
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn confirm_screen(response_message: &str, expected: &str) -> Result<(), WebsocketClientError> {
+    match get_screen_label_from_response(response_message) {
+        Some(actual) if actual.eq_ignore_ascii_case(expected) => Ok(()),
+        Some(actual) => Err(WebsocketClientError::NavigationDiverged {
+            expected: expected.to_string(),
+            actual,
+        }),
+        None => {
+            // some firmwares don't label remote control screens, there's nothing to check against then
+            warn!(
+                "Can't confirm remote control menu reached {}, proceeding",
+                expected
+            );
+            Ok(())
+        }
+    }
+}
+
+fn get_desinfection_enabled_from_response(
+    response_message: &str,
+) -> Result<Option<bool>, WebsocketClientError> {
+    // <Content><item id='0x3d9dd8'><name>Continu</name><value>0</value></item><name>Thermische desinfectie</name></Content>
+    let content: ValuesContent = from_str(response_message).map_err(|e| {
+        WebsocketClientError::Parse(format!(
+            "Failed parsing thermal desinfection from response: {}",
+            e
+        ))
+    })?;
+
+    // some firmwares don't list the checkbox on the remote control screen, the stored state is all there is then
+    match content
+        .items
+        .iter()
+        .find(|item| item.name.trim() == "Continu")
+    {
+        Some(item) => match item.value.trim() {
+            "1" => Ok(Some(true)),
+            "0" => Ok(Some(false)),
+            value => Err(WebsocketClientError::Parse(format!(
+                "Failed parsing thermal desinfection checkbox value {}",
+                value
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn reconcile_desinfection_enabled(
+    stored_desinfection_enabled: bool,
+    device_desinfection_enabled: Option<bool>,
+) -> bool {
+    match device_desinfection_enabled {
+        Some(device_desinfection_enabled) => {
+            if device_desinfection_enabled != stored_desinfection_enabled {
+                warn!(
+                    "Heatpump has desinfection {}, while the stored state has it {}; it was probably toggled on the heatpump itself",
+                    if device_desinfection_enabled { "enabled" } else { "disabled" },
+                    if stored_desinfection_enabled { "enabled" } else { "disabled" }
+                );
+            }
+            device_desinfection_enabled
+        }
+        None => {
+            warn!("Could not read desinfection from the heatpump, relying on the stored state");
+            stored_desinfection_enabled
+        }
+    }
+}
+
+fn get_energy_counters_from_response(
+    response_message: &str,
+) -> Result<BTreeMap<String, f64>, WebsocketClientError> {
+    // <Content><item id='0x4a5e3c'><name>Verwarming</name><value>8765.0 kWh</value></item><item id='0x4a5f2c'><name>Warmwater</name><value>---</value></item><name>Energie</name></Content>
+    let content: ValuesContent = from_str(response_message).map_err(|e| {
+        WebsocketClientError::Parse(format!("Failed parsing energy stats from response: {}", e))
+    })?;
+
+    // counters that aren't available read as --- and are left out, reporting them as 0 would break deltas between runs
+    let mut counters = BTreeMap::new();
+    for item in content.items {
+        if let Some(value) = item.value.trim().strip_suffix("kWh") {
+            let value = value.trim().replace(',', ".").parse::<f64>().map_err(|e| {
+                WebsocketClientError::Parse(format!(
+                    "Failed parsing energy counter {} value {}: {}",
+                    item.name, item.value, e
+                ))
+            })?;
+            counters.insert(item.name, value);
+        }
+    }
+
+    Ok(counters)
+}
+
+fn get_operating_modes_from_response(
+    response_message: &str,
+) -> Result<HashMap<String, OperatingMode>, WebsocketClientError> {
+    // <Content><item id='0x4e6a2c'><name>Verwarmen</name><option value='0'>Automatisch</option><option value='1'>Zw. WEZ</option><option value='2'>Party</option><option value='3'>Vakantie</option><option value='4'>Uit</option><value>Automatisch</value></item><name>Bedrijfsmode</name></Content>
+    let content: ValuesContent = from_str(response_message).map_err(|e| {
+        WebsocketClientError::Parse(format!(
+            "Failed parsing operating modes from response: {}",
+            e
+        ))
+    })?;
+
+    Ok(content
+        .items
+        .into_iter()
+        .map(|item| {
+            let operating_mode = OperatingMode::from_value(&item.value);
+            (item.name, operating_mode)
+        })
+        .collect())
+}
+
+fn get_operating_mode(
+    operating_modes: &HashMap<String, OperatingMode>,
+    circuit: &str,
+) -> Result<OperatingMode, WebsocketClientError> {
+    operating_modes.get(circuit).cloned().ok_or_else(|| {
+        WebsocketClientError::Parse(format!(
+            "No operating mode for {} in Instelling > Bedrijfsmode",
+            circuit
+        ))
+    })
+}
+
+fn get_faults_from_response(response_message: &str) -> Result<Vec<Fault>, WebsocketClientError> {
+    let content: ValuesContent = from_str(response_message).map_err(|e| {
+        WebsocketClientError::Parse(format!("Failed parsing fault buffer from response: {}", e))
+    })?;
+
+    // unused slots of the buffer don't parse into a fault and are skipped
+    Ok(content
+        .items
+        .iter()
+        .filter_map(|item| Fault::from_item(&item.name, &item.value))
+        .collect())
+}
+
+fn get_recent_faults(faults: &[Fault], heatpump_time_zone: &Tz, now: DateTime<Utc>) -> Vec<Fault> {
+    // the buffer keeps old faults around as history, only recent ones are worth acting upon
+    let recent_after = (now - Duration::hours(RECENT_FAULT_HOURS))
+        .with_timezone(heatpump_time_zone)
+        .naive_local();
+
+    faults
+        .iter()
+        .filter(|fault| fault.timestamp > recent_after)
+        .cloned()
+        .collect()
+}
+
+fn parse_item_value(name: &str, value: &str) -> Result<Option<f64>, WebsocketClientError> {
+    // values carry their unit like 54.2°C or 4.8 K, disconnected sensors show ---
+    let value = value.trim();
+    if value.starts_with("---") {
+        return Ok(Some(0.0));
+    }
+
+    // depending on its locale the controller renders 57,0°C instead of 57.0°C
+    let number_length = value
+        .char_indices()
+        .find(|(index, c)| {
+            !(c.is_ascii_digit() || *c == '.' || *c == ',' || (*index == 0 && *c == '-'))
+        })
+        .map(|(index, _)| index)
+        .unwrap_or(value.len());
+    let number = value[..number_length].replace(',', ".");
+    if number.is_empty() || number == "-" {
+        return Ok(None);
+    }
+
+    number.parse::<f64>().map(Some).map_err(|e| {
+        WebsocketClientError::Parse(format!(
+            "Failed parsing value {} of item {}: {}",
+            value, name, e
+        ))
+    })
+}
+
+fn get_numeric_item_from_response(
+    item_name: &str,
+    response_message: &str,
+) -> Result<(String, f64), WebsocketClientError> {
+    let content: ValuesContent = from_str(response_message).map_err(|e| {
+        WebsocketClientError::Parse(format!("Failed parsing items from response: {}", e))
+    })?;
+
+    let item = match content
+        .items
+        .into_iter()
+        .find(|item| item.name == item_name)
+    {
+        Some(item) => item,
+        None => {
+            return Err(WebsocketClientError::Parse(format!(
+                "No match for item {}",
+                item_name
+            )))
+        }
+    };
+
+    // selections like Automatisch or Uit can't be stepped, neither can unset values showing ---
+    let value = if item.value.trim().starts_with("---") {
+        None
+    } else {
+        parse_item_value(&item.name, &item.value)?
+    };
+
+    match (item.id, value) {
+        (Some(id), Some(value)) => Ok((id, value)),
+        (None, _) => Err(WebsocketClientError::Parse(format!(
+            "Item {} has no id to set it by",
+            item_name
+        ))),
+        (_, None) => Err(WebsocketClientError::Parse(format!(
+            "Item {} has value {}, which is not numeric",
+            item_name, item.value
+        ))),
+    }
+}
+
+fn get_value_steps(value: f64, desired_value: f64, step: f64) -> i64 {
+    ((desired_value - value) / step).round() as i64
+}
+
+fn get_content_from_response(
+    path: &str,
+    response_message: &str,
+) -> Result<Content, WebsocketClientError> {
+    let content: Content = from_str(response_message).map_err(|e| {
+        WebsocketClientError::Parse(format!("Failed parsing content of {}: {}", path, e))
+    })?;
+    debug!("Deserialized response:\n{:?}", content);
+
+    Ok(content)
+}
+
+fn format_plan_table(
+    config: &Config,
+    time_zone: &Tz,
+    strategy: &TapWaterStrategy,
+    best_spot_prices: &[SpotPrice],
+    worst_spot_prices: &[SpotPrice],
+) -> String {
+    let mut table = format!(
+        "{:<16} {:<17} {:<17} {:>10}\n",
+        "Action", "From", "Till", "Price"
+    );
+
+    let rows = best_spot_prices
+        .iter()
+        .map(|sp| (format!("{:?}", strategy), sp))
+        .chain(
+            worst_spot_prices
+                .iter()
+                .map(|sp| ("BlockHeating".to_string(), sp)),
+        );
+
+    for (action, spot_price) in rows {
+        table.push_str(&format!(
+            "{:<16} {:<17} {:<17} {:>10.4}\n",
+            action,
+            spot_price
+                .from
+                .with_timezone(time_zone)
+                .format("%Y-%m-%d %H:%M"),
+            spot_price
+                .till
+                .with_timezone(time_zone)
+                .format("%Y-%m-%d %H:%M"),
+            config.effective_price(spot_price)
+        ));
+    }
+
+    table
+}
+
+fn format_preview_table(
+    config: &Config,
+    time_zone: &Tz,
+    preview: &[(SpotPrice, PlanAction)],
+) -> String {
+    let mut table = format!("{:<16} {:<17} {:>10}\n", "Action", "From", "Price");
+
+    for (spot_price, action) in preview {
+        table.push_str(&format!(
+            "{:<16} {:<17} {:>10.4}\n",
+            format!("{:?}", action),
+            spot_price
+                .from
+                .with_timezone(time_zone)
+                .format("%Y-%m-%d %H:%M"),
+            config.effective_price(spot_price)
+        ));
+    }
+
+    table
+}
+
+fn is_eco_desinfection_desired(
+    config: &Config,
+    last_heat_up_at: DateTime<Utc>,
+    lowest_price_eco_desinfection_response: &PlanningResponse,
+) -> Option<DesinfectionDecision> {
+    let planned_spot_prices = &lowest_price_eco_desinfection_response.spot_prices;
+    let planned_from = planned_spot_prices.first()?.from;
+    let planned_till = planned_spot_prices.last()?.till;
+
+    let hours_since_last_heat_up = (planned_from - last_heat_up_at).num_hours();
+    if hours_since_last_heat_up < config.min_hours_since_last_eco_desinfection {
+        info!(
+            "Hours since last heat-up less than minimum ({} < {}), eco desinfection is not desired",
+            hours_since_last_heat_up, config.min_hours_since_last_eco_desinfection
+        );
+        return None;
+    }
+
+    // a partial heat-up is a nice to have, so only take it when the window is cheap enough
+    if let Some(eco_desinfection_max_price) = config.eco_desinfection_max_price {
+        let window_hours = (planned_till - planned_from).num_seconds() as f64 / 3600.0;
+        let average_price = get_total_price(planned_spot_prices, config) / window_hours;
+        if average_price > eco_desinfection_max_price {
+            info!(
+                "Eco desinfection window averages {:.4} per hour, above maximum of {:.4}, eco desinfection is not desired",
+                average_price, eco_desinfection_max_price
+            );
+            return None;
+        }
+    }
+
+    Some(DesinfectionDecision::EcoHeatUp {
+        hours_since_last_heat_up,
+        min_hours_since_last_heat_up: config.min_hours_since_last_eco_desinfection,
+    })
+}
+
+fn is_desinfection_desired(
+    config: &Config,
+    current_desinfection_enabled: bool,
+    desinfection_finished_at: &DateTime<Utc>,
+    lowest_price_desinfection_response: &PlanningResponse,
+    highest_price_desinfection_response: &PlanningResponse,
+    now: DateTime<Utc>,
+) -> Result<(bool, DesinfectionDecision), Box<dyn Error>> {
+    // a cycle right after the previous one only stresses the compressor, however cheap or negative the prices are
+    if let Some(planned_from) = lowest_price_desinfection_response
+        .spot_prices
+        .first()
+        .map(|sp| sp.from)
+    {
+        let hours_since_last_desinfection = (planned_from - *desinfection_finished_at).num_hours();
+        if hours_since_last_desinfection < config.desinfection_cooldown_hours {
+            info!(
+                "Hours since last desinfection less than cooldown ({} < {}), desinfection is not desired",
+                hours_since_last_desinfection, config.desinfection_cooldown_hours
+            );
+            return Ok((
+                false,
+                DesinfectionDecision::WithinCooldown {
+                    hours_since_last_desinfection,
+                    cooldown_hours: config.desinfection_cooldown_hours,
+                },
+            ));
+        }
+    }
+
+    // the planner can return a different number of slots for both responses, so totals aren't comparable; an average
+    // price per hour is, whatever the slot count or slot length
+    if let (true, Some(lowest_price_per_hour), Some(highest_price_per_hour)) = (
+        config.enable_price_based_desinfection,
+        get_average_effective_price_per_hour(
+            config,
+            &lowest_price_desinfection_response.spot_prices,
+        ),
+        get_average_effective_price_per_hour(
+            config,
+            &highest_price_desinfection_response.spot_prices,
+        ),
+    ) {
+        // the threshold grows quadratically from nothing at the minimum to the highest price at the maximum hours, so
+        // desinfection gets more likely the longer it has been
+        let planned_finished_at = lowest_price_desinfection_response
+            .spot_prices
+            .last()
+            .unwrap()
+            .till;
+        let hours_since_last_desinfection =
+            (planned_finished_at - *desinfection_finished_at).num_hours();
+        let fraction_of_max_hours =
+            ((hours_since_last_desinfection - config.min_hours_since_last_desinfection) as f64
+                / (config.max_hours_since_last_desinfection
+                    - config.min_hours_since_last_desinfection) as f64)
+                .clamp(0.0, 1.0);
+        let threshold_price_per_hour =
+            fraction_of_max_hours * fraction_of_max_hours * highest_price_per_hour;
+
+        // prices wobble between runs, so once desinfection is enabled it stays enabled until it's clearly not needed
+        // anymore and the other way around, instead of toggling the controller on every small change
+        let hysteresis_price_per_hour =
+            config.desinfection_hysteresis_margin * highest_price_per_hour.abs();
+        let threshold_price_per_hour = if current_desinfection_enabled {
+            threshold_price_per_hour + hysteresis_price_per_hour
+        } else {
+            threshold_price_per_hour - hysteresis_price_per_hour
+        };
+
+        return Ok((
+            lowest_price_per_hour < threshold_price_per_hour,
+            DesinfectionDecision::PriceThreshold {
+                hours_since_last_desinfection,
+                lowest_price_per_hour,
+                threshold_price_per_hour,
+            },
+        ));
+    }
+
+    // a decision by weekday doesn't flip between runs on the same day, so it needs no hysteresis
+    let desinfection_decision = DesinfectionDecision::Weekday {
+        weekday: now.weekday(),
+        desired_weekday: Weekday::Sun,
+    };
+    Ok((now.weekday() == Weekday::Sun, desinfection_decision))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Navigation {
+    // id: String, // `xml:"id,attr"`
+    #[serde(rename = "item", default)]
+    items: Vec<NavigationItem>, // `xml:"item"`
+    #[serde(skip)]
+    item_id_cache: Mutex<HashMap<String, String>>,
+    #[serde(skip)]
+    item_id_index: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NavigationItem {
+    id: String,   //           `xml:"id,attr"`
+    name: String, //           `xml:"name"`
+    #[serde(rename = "readOnly", default)]
+    read_only: bool, //        `xml:"readOnly"`
+    #[serde(rename = "item", default)]
+    items: Vec<NavigationItem>, // `xml:"item"`
+}
+
+impl Navigation {
+    fn build_item_id_index(&mut self) {
+        let mut item_id_index = HashMap::new();
+        add_navigation_items_to_index(&mut item_id_index, "", &self.items);
+
+        self.item_id_index = item_id_index;
+    }
+
+    fn get_navigation_item_id(&self, item_path: &str) -> Result<String, WebsocketClientError> {
+        if let Some(navigation_id) = self.item_id_index.get(item_path) {
+            return Ok(navigation_id.clone());
+        }
+
+        if let Some(navigation_id) = self.item_id_cache.lock().unwrap().get(item_path) {
+            return Ok(navigation_id.clone());
+        }
+
+        let navigation_id = self.get_navigation_item(item_path)?.id.clone();
+
+        self.item_id_cache
+            .lock()
+            .unwrap()
+            .insert(item_path.to_string(), navigation_id.clone());
+
+        Ok(navigation_id)
+    }
+
+    fn ensure_navigation_item_is_writable(
+        &self,
+        item_path: &str,
+    ) -> Result<(), WebsocketClientError> {
+        // parent menus are always flagged read-only, only the item itself tells whether it's locked
+        if self.get_navigation_item(item_path)?.read_only {
+            return Err(WebsocketClientError::ReadOnly {
+                item_path: item_path.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn get_navigation_item(
+        &self,
+        item_path: &str,
+    ) -> Result<&NavigationItem, WebsocketClientError> {
+        let item_path_parts: Vec<&str> = item_path.split(" > ").collect();
+
+        let mut navigation_item: Option<&NavigationItem> = None;
+        let mut items = &self.items;
+
+        for part in item_path_parts.iter() {
+            let mut exists = false;
+            for item in items.iter() {
+                if *part == item.name {
+                    exists = true;
+
+                    navigation_item = Some(item);
+                    items = &item.items;
+
+                    break;
+                }
+            }
+
+            if !exists {
+                let available_names: Vec<&str> = items.iter().map(|i| i.name.as_str()).collect();
+
+                return Err(WebsocketClientError::NavigationNotFound {
+                    item: part.to_string(),
+                    closest_item: get_closest_navigation_item_name(part, &available_names)
+                        .map(|closest_name| closest_name.to_string()),
+                    available_items: available_names.iter().map(|n| n.to_string()).collect(),
+                });
+            }
+        }
+
+        match navigation_item {
+            Some(navigation_item) => Ok(navigation_item),
+            None => Err(WebsocketClientError::NavigationNotFound {
+                item: item_path.to_string(),
+                closest_item: None,
+                available_items: vec![],
+            }),
+        }
+    }
+}
+
+fn add_navigation_items_to_index(
+    item_id_index: &mut HashMap<String, String>,
+    parent_path: &str,
+    items: &[NavigationItem],
+) {
+    for item in items.iter() {
+        let item_path = if parent_path.is_empty() {
+            item.name.clone()
+        } else {
+            format!("{} > {}", parent_path, item.name)
+        };
+
+        add_navigation_items_to_index(item_id_index, &item_path, &item.items);
+
+        // the linear lookup takes the first item with a matching name, keep doing so for duplicate names
+        item_id_index
+            .entry(item_path)
+            .or_insert_with(|| item.id.clone());
+    }
+}
+
+fn get_closest_navigation_item_name<'a>(
+    name: &str,
+    available_names: &[&'a str],
+) -> Option<&'a str> {
+    // only suggest names that are reasonably close, to avoid pointing at an unrelated menu item
+    let max_distance = std::cmp::max(2, name.chars().count() / 3);
+
+    available_names
+        .iter()
+        .map(|available_name| {
+            (
+                *available_name,
+                get_levenshtein_distance(name, available_name),
+            )
+        })
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(available_name, _)| available_name)
+}
+
+fn get_levenshtein_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current_row = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution_cost = if a_char == b_char { 0 } else { 1 };
+            current_row[j + 1] = std::cmp::min(
+                std::cmp::min(previous_row[j + 1] + 1, current_row[j] + 1),
+                previous_row[j] + substitution_cost,
+            );
+        }
+        previous_row = current_row;
+    }
+
+    previous_row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{test_config, PriceComponent, TimeSlot};
+    use jarvis_lib::model::{LoadProfile, LoadProfileSection, SpotPrice};
+
+    #[test]
+    fn deserialize_navigation_xml() {
+        let xml_string = "<Navigation id=\"0x45cd88\"><item id=\"0x45df90\"><name>Informatie</name><item id=\"0x45df90\"><name>Temperaturen</name></item><item id=\"0x455968\"><name>Ingangen</name></item></item><item id=\"0x450798\"><name>Instelling</name></item><item id=\"0x3dc420\"><name>Klokprogramma</name></item><item id=\"0x45c7b0\"><name>Toegang: Gebruiker</name></item></Navigation>";
+
+        // act
+        let navigation: Navigation = from_str(xml_string).unwrap();
+
+        assert_eq!(navigation.items.len(), 4);
+        assert_eq!(navigation.items[0].name, "Informatie".to_string());
+        assert_eq!(navigation.items[0].items.len(), 2);
+        assert_eq!(
+            navigation.items[0].items[0].name,
+            "Temperaturen".to_string()
+        );
+        assert_eq!(navigation.items[0].items[0].id, "0x45df90".to_string());
+        assert_eq!(navigation.items[0].items[1].name, "Ingangen".to_string());
+        assert_eq!(navigation.items[0].items[1].id, "0x455968".to_string());
+    }
+
+    #[test]
+    fn get_navigation_item_id_returns_id_if_it_exists() {
+        // <Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item><item id='0x455968'><name>Ingangen</name></item><item id='0x455760'><name>Uitgangen</name></item><item id='0x45bf10'><name>Aflooptijden</name></item><item id='0x456f08'><name>Bedrijfsuren</name></item><item id='0x4643a8'><name>Storingsbuffer</name></item><item id='0x3ddfa8'><name>Afschakelingen</name></item><item id='0x45d840'><name>Installatiestatus</name></item><item id='0x460cb8'><name>Energie</name></item><item id='0x4586a8'><name>GBS</name></item></item><item id='0x450798'><name>Instelling</name><item id='0x460bd0'><name>Bedrijfsmode</name></item><item id='0x461170'><name>Temperaturen</name></item><item id='0x462988'><name>Systeeminstelling</name></item></item><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item><item id='0x45df00'><name>5+2</name></item><item id='0x45c200'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item><item id='0x463940'><name>5+2</name></item><item id='0x463b68'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x3dcc00'><name>Zwembad</name><readOnly>true</readOnly><item id='0x455580'><name>Week</name></item><item id='0x463f78'><name>5+2</name></item><item id='0x462690'><name>Dagen (Ma, Di,...)</name></item></item></item><item id='0x45c7b0'><name>Toegang: Gebruiker</name></item></Navigation>
+
+        let navigation = Navigation {
+            // id: "0x45cd88".to_string(),
+            items: vec![
+                NavigationItem {
+                    id: "0x45df90".to_string(),
+                    name: "Informatie".to_string(),
+                    read_only: false,
+                    items: vec![
+                        NavigationItem {
+                            id: "0x45df90".to_string(),
+                            name: "Temperaturen".to_string(),
+                            read_only: false,
+                            items: vec![],
+                        },
+                        NavigationItem {
+                            id: "0x455968".to_string(),
+                            name: "Ingangen".to_string(),
+                            read_only: false,
+                            items: vec![],
+                        },
+                    ],
+                },
+                NavigationItem {
+                    id: "0x450798".to_string(),
+                    name: "Instelling".to_string(),
+                    read_only: false,
+                    items: vec![],
+                },
+                NavigationItem {
+                    id: "0x3dc420".to_string(),
+                    name: "Klokprogramma".to_string(),
+                    read_only: false,
+                    items: vec![],
+                },
+                NavigationItem {
+                    id: "0x45c7b0".to_string(),
+                    name: "Toegang: Gebruiker".to_string(),
+                    read_only: false,
+                    items: vec![],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let item_id = navigation
+            .get_navigation_item_id(&"Informatie".to_string())
+            .unwrap();
+
+        assert_eq!(item_id, "0x45df90".to_string());
+    }
+
+    #[test]
+    fn get_navigation_item_id_returns_id_if_it_exists_as_nested_item_inside_top_level_item() {
+        // <Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item><item id='0x455968'><name>Ingangen</name></item><item id='0x455760'><name>Uitgangen</name></item><item id='0x45bf10'><name>Aflooptijden</name></item><item id='0x456f08'><name>Bedrijfsuren</name></item><item id='0x4643a8'><name>Storingsbuffer</name></item><item id='0x3ddfa8'><name>Afschakelingen</name></item><item id='0x45d840'><name>Installatiestatus</name></item><item id='0x460cb8'><name>Energie</name></item><item id='0x4586a8'><name>GBS</name></item></item><item id='0x450798'><name>Instelling</name><item id='0x460bd0'><name>Bedrijfsmode</name></item><item id='0x461170'><name>Temperaturen</name></item><item id='0x462988'><name>Systeeminstelling</name></item></item><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item><item id='0x45df00'><name>5+2</name></item><item id='0x45c200'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item><item id='0x463940'><name>5+2</name></item><item id='0x463b68'><name>Dagen (Ma, Di,...)</name></item></item><item id='0x3dcc00'><name>Zwembad</name><readOnly>true</readOnly><item id='0x455580'><name>Week</name></item><item id='0x463f78'><name>5+2</name></item><item id='0x462690'><name>Dagen (Ma, Di,...)</name></item></item></item><item id='0x45c7b0'><name>Toegang: Gebruiker</name></item></Navigation>
+
+        let navigation = Navigation {
+            // id: "0x45cd88".to_string(),
+            items: vec![
+                NavigationItem {
+                    id: "0x45df90".to_string(),
+                    name: "Informatie".to_string(),
+                    read_only: false,
+                    items: vec![
+                        NavigationItem {
+                            id: "0x45df90".to_string(),
+                            name: "Temperaturen".to_string(),
+                            read_only: false,
+                            items: vec![],
+                        },
+                        NavigationItem {
+                            id: "0x455968".to_string(),
+                            name: "Ingangen".to_string(),
+                            read_only: false,
+                            items: vec![],
+                        },
+                    ],
+                },
+                NavigationItem {
+                    id: "0x450798".to_string(),
+                    name: "Instelling".to_string(),
+                    read_only: false,
+                    items: vec![],
+                },
+                NavigationItem {
+                    id: "0x3dc420".to_string(),
+                    name: "Klokprogramma".to_string(),
+                    read_only: false,
+                    items: vec![],
+                },
+                NavigationItem {
+                    id: "0x45c7b0".to_string(),
+                    name: "Toegang: Gebruiker".to_string(),
+                    read_only: false,
+                    items: vec![],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let item_id = navigation
+            .get_navigation_item_id(&"Informatie > Ingangen".to_string())
+            .unwrap();
+
+        assert_eq!(item_id, "0x455968".to_string());
+    }
+
+    #[test]
+    fn get_navigation_item_id_returns_cached_id_on_second_lookup() {
+        let xml_string = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item></item></item></Navigation>";
+        let mut navigation: Navigation = from_str(xml_string).unwrap();
+
+        let item_id = navigation
+            .get_navigation_item_id("Klokprogramma > Warmwater > Week")
+            .unwrap();
+        assert_eq!(item_id, "0x4642a8".to_string());
+
+        // clear the tree so the second lookup can only succeed from the cache
+        navigation.items.clear();
+
+        // act
+        let item_id = navigation
+            .get_navigation_item_id("Klokprogramma > Warmwater > Week")
+            .unwrap();
+
+        assert_eq!(item_id, "0x4642a8".to_string());
+        assert!(navigation
+            .get_navigation_item_id("Klokprogramma > Verwarmen > Week")
+            .is_err());
+    }
+
+    fn synthetic_navigation(width: usize) -> Navigation {
+        Navigation {
+            items: (0..width)
+                .map(|i| NavigationItem {
+                    id: format!("0x{}", i),
+                    name: format!("Menu {}", i),
+                    read_only: true,
+                    items: (0..width)
+                        .map(|j| NavigationItem {
+                            id: format!("0x{}_{}", i, j),
+                            name: format!("Submenu {}", j),
+                            read_only: true,
+                            items: (0..width)
+                                .map(|k| NavigationItem {
+                                    id: format!("0x{}_{}_{}", i, j, k),
+                                    name: format!("Item {}", k),
+                                    read_only: false,
+                                    items: vec![],
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_item_id_index_indexes_every_path_of_large_tree() {
+        let mut navigation = synthetic_navigation(20);
+
+        // act
+        navigation.build_item_id_index();
+
+        assert_eq!(navigation.item_id_index.len(), 20 + 20 * 20 + 20 * 20 * 20);
+        assert_eq!(
+            navigation
+                .item_id_index
+                .get("Menu 19 > Submenu 7 > Item 13"),
+            Some(&"0x19_7_13".to_string())
+        );
+        assert_eq!(
+            navigation.item_id_index.get("Menu 3"),
+            Some(&"0x3".to_string())
+        );
+    }
+
+    #[test]
+    fn build_item_id_index_keeps_first_item_for_duplicate_names() {
+        let xml_string = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><item id='0x4642a8'><name>Week</name></item><item id='0x455580'><name>Week</name></item></item></Navigation>";
+        let mut navigation: Navigation = from_str(xml_string).unwrap();
+
+        // act
+        navigation.build_item_id_index();
+
+        assert_eq!(
+            navigation.item_id_index.get("Klokprogramma > Week"),
+            Some(&"0x4642a8".to_string())
+        );
+    }
+
+    #[test]
+    fn get_navigation_item_id_returns_id_from_index_without_walking_tree() {
+        let mut navigation = synthetic_navigation(5);
+        navigation.build_item_id_index();
+
+        // clear the tree so the lookup can only succeed from the index
+        navigation.items.clear();
+
+        // act
+        let item_id = navigation
+            .get_navigation_item_id("Menu 4 > Submenu 2 > Item 0")
+            .unwrap();
+
+        assert_eq!(item_id, "0x4_2_0".to_string());
+        assert!(navigation
+            .get_navigation_item_id("Menu 4 > Submenu 2 > Item 5")
+            .is_err());
+    }
+
+    #[test]
+    fn get_navigation_from_response_builds_item_id_index() {
+        let client = test_client();
+        let xml_string = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><item id='0x43e8e8'><name>Warmwater</name><item id='0x4642a8'><name>Week</name></item></item></item></Navigation>";
+
+        // act
+        let navigation = client
+            .get_navigation_from_response(xml_string.to_string())
+            .unwrap();
+
+        assert_eq!(
+            navigation
+                .item_id_index
+                .get("Klokprogramma > Warmwater > Week"),
+            Some(&"0x4642a8".to_string())
+        );
+    }
+
+    #[test]
+    fn get_navigation_item_id_returns_error_with_available_items_and_closest_match() {
+        let xml_string = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item></item><item id='0x43e8e8'><name>Warm water</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item></item><item id='0x3dcc00'><name>Zwembad</name><readOnly>true</readOnly></item></item></Navigation>";
+        let navigation: Navigation = from_str(xml_string).unwrap();
+
+        // act
+        let result = navigation.get_navigation_item_id("Klokprogramma > Warmwater > Week");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Item Warmwater does not exist, did you mean Warm water? Available items: Verwarmen, Warm water, Zwembad".to_string()
+        );
+    }
+
+    #[test]
+    fn deserialize_navigation_xml_parses_read_only_flag() {
+        let xml_string = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name><readOnly>true</readOnly></item></item></item></Navigation>";
+
+        // act
+        let navigation: Navigation = from_str(xml_string).unwrap();
+
+        assert!(navigation.items[0].read_only);
+        assert!(navigation.items[0].items[0].items[0].read_only);
+    }
+
+    #[test]
+    fn ensure_navigation_item_is_writable_returns_error_for_read_only_program() {
+        let xml_string = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name><readOnly>true</readOnly></item></item></item></Navigation>";
+        let navigation: Navigation = from_str(xml_string).unwrap();
+
+        // act
+        let result =
+            navigation.ensure_navigation_item_is_writable("Klokprogramma > Warmwater > Week");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Item Klokprogramma > Warmwater > Week is read-only, the clock program is probably locked by the installer".to_string()
+        );
+    }
+
+    #[test]
+    fn ensure_navigation_item_is_writable_ignores_read_only_parent_menus() {
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+
+        // act
+        let result =
+            navigation.ensure_navigation_item_is_writable("Klokprogramma > Warmwater > Week");
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn get_closest_navigation_item_name_returns_none_if_no_name_is_close() {
+        let available_names = vec!["Verwarmen", "Warmwater", "Zwembad"];
+
+        // act
+        let closest_name = get_closest_navigation_item_name("Temperaturen", &available_names);
+
+        assert_eq!(closest_name, None);
+    }
+
+    #[test]
+    fn get_levenshtein_distance_returns_number_of_edits() {
+        assert_eq!(get_levenshtein_distance("Warmwater", "Warm water"), 1);
+        assert_eq!(get_levenshtein_distance("Warmwater", "warmwater"), 0);
+        assert_eq!(get_levenshtein_distance("Week", "5+2"), 4);
+        assert_eq!(get_levenshtein_distance("", "Week"), 4);
+    }
+
+    const TAP_WATER_SCHEDULE_RESPONSE: &str = "<Content><item><name>Maandag - Zondag</name><item id='0xa57344'><value>00:00 - 03:00</value><name>1)</name><type>timer</type><raw>11796480</raw></item><item id='0xa53c8c'><value>00:00 - 00:00</value><name>2)</name><type>timer</type><raw>0</raw></item><item id='0xa47ee4'><value>00:00 - 00:00</value><name>3)</name><type>timer</type><raw>0</raw></item><item id='0xa6630c'><value>00:00 - 00:00</value><name>4)</name><type>timer</type><raw>0</raw></item><item id='0xa68d74'><value>10:00 - 00:00</value><name>5)</name><type>timer</type><raw>600</raw></item></item></Content>";
+
+    fn spot_price(from: DateTime<Utc>, till: DateTime<Utc>) -> SpotPrice {
+        SpotPrice {
+            id: None,
+            source: None,
+            from,
+            till,
+            market_price: 0.157,
+            market_price_tax: 0.0330708,
+            sourcing_markup_price: 0.017,
+            energy_tax_price: 0.081,
+        }
+    }
+
+    #[test]
+    fn add_jitter_to_spot_prices_returns_identical_windows_for_same_seed() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 6, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now + Duration::hours(4), &[0.1, 0.2]);
+        let config = Config {
+            jitter_max_minutes: 30,
+            jitter_seed: Some(42),
+            ..test_config()
+        };
+
+        // act
+        let first = WebsocketClient::add_jitter_to_spot_prices(&config, &spot_prices, now).unwrap();
+        let second =
+            WebsocketClient::add_jitter_to_spot_prices(&config, &spot_prices, now).unwrap();
+
+        assert_eq!(first.len(), second.len());
+        for (first, second) in first.iter().zip(second.iter()) {
+            assert_eq!(first.from, second.from);
+            assert_eq!(first.till, second.till);
+        }
+    }
+
+    const EMPTY_SCHEDULE_RESPONSE: &str = "<Content><item><name>Maandag - Zondag</name><item id='0xa57344'><value>00:00 - 00:00</value><name>1)</name><type>timer</type><raw>0</raw></item><item id='0xa53c8c'><value>00:00 - 00:00</value><name>2)</name><type>timer</type><raw>0</raw></item><item id='0xa47ee4'><value>00:00 - 00:00</value><name>3)</name><type>timer</type><raw>0</raw></item><item id='0xa6630c'><value>00:00 - 00:00</value><name>4)</name><type>timer</type><raw>0</raw></item><item id='0xa68d74'><value>00:00 - 00:00</value><name>5)</name><type>timer</type><raw>0</raw></item></item></Content>";
+
+    const SAVED_RESPONSE: &str = "<Content></Content>";
+
+    const SCHEDULE_NAVIGATION_RESPONSE: &str = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item></item></item></Navigation>";
+
+    #[derive(Default)]
+    struct MockSender {
+        messages: Vec<String>,
+    }
+
+    impl MessageSender for MockSender {
+        fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>> {
+            if let OwnedMessage::Text(text) = message {
+                self.messages.push(text.clone());
+            }
+
+            Ok(())
+        }
+    }
+
+    struct MockReceiver {
+        responses: std::collections::VecDeque<OwnedMessage>,
+        pending: std::collections::VecDeque<OwnedMessage>,
+    }
+
+    impl MockReceiver {
+        fn new(responses: Vec<&str>) -> Self {
+            Self::from_messages(
+                responses
+                    .into_iter()
+                    .map(|r| OwnedMessage::Text(r.to_string()))
+                    .collect(),
+            )
+        }
+
+        fn from_messages(responses: Vec<OwnedMessage>) -> Self {
+            Self {
+                responses: responses.into_iter().collect(),
+                pending: std::collections::VecDeque::new(),
+            }
+        }
+
+        // messages that already arrived before the next request gets sent
+        fn with_pending(mut self, pending: Vec<&str>) -> Self {
+            self.pending = pending
+                .into_iter()
+                .map(|p| OwnedMessage::Text(p.to_string()))
+                .collect();
+            self
+        }
+    }
+
+    impl MessageReceiver for MockReceiver {
+        fn receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+            self.responses.pop_front().map(Ok)
+        }
+
+        fn try_receive_message(&mut self) -> Option<Result<OwnedMessage, Box<dyn Error>>> {
+            self.pending.pop_front().map(Ok)
+        }
+    }
+
+    struct MockConnector {
+        connections: Mutex<std::collections::VecDeque<Vec<OwnedMessage>>>,
+    }
+
+    impl Connector for MockConnector {
+        fn connect(&self) -> Result<Connection, Box<dyn Error>> {
+            match self.connections.lock().unwrap().pop_front() {
+                Some(responses) => Ok((
+                    Box::new(MockReceiver::from_messages(responses)),
+                    Box::new(MockSender::default()),
+                )),
+                None => Err(Box::<dyn Error>::from("No more connections")),
+            }
+        }
+    }
+
+    fn test_client() -> WebsocketClient {
+        WebsocketClient::new(
+            WebsocketClientConfig::new(
+                "127.0.0.1".to_string(),
+                8214,
+                "999999".to_string(),
+                None,
+                false,
+                None,
+                "Lux_WS".to_string(),
+                0,
+                None,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn reset_messages() -> Vec<String> {
+        vec![
+            "SET;set_0xa57344;0".to_string(),
+            "SET;set_0xa53c8c;0".to_string(),
+            "SET;set_0xa47ee4;0".to_string(),
+            "SET;set_0xa6630c;0".to_string(),
+            "SET;set_0xa68d74;0".to_string(),
+        ]
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_blocks_before_and_after_same_day_window() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec!["GET;0x4642a8".to_string()];
+        expected_messages.extend(reset_messages());
+        expected_messages.extend(vec![
+            "SET;set_0xa57344;11796480".to_string(),
+            "SET;set_0xa68d74;600".to_string(),
+            "SAVE;1".to_string(),
+        ]);
+        assert_eq!(sender.messages, expected_messages);
+    }
+
+    const COOLING_SCHEDULE_NAVIGATION_RESPONSE: &str = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453ee0'><name>Koelen</name><readOnly>true</readOnly><item id='0x46a1c0'><name>Week</name></item></item></item></Navigation>";
+
+    #[test]
+    fn set_cooling_schedule_from_best_spot_prices_encodes_window_like_tap_water() {
+        let client = test_client();
+        let navigation: Navigation = from_str(COOLING_SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_cooling_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec!["GET;0x46a1c0".to_string()];
+        expected_messages.extend(reset_messages());
+        expected_messages.extend(vec![
+            "SET;set_0xa57344;11796480".to_string(),
+            "SET;set_0xa68d74;600".to_string(),
+            "SAVE;1".to_string(),
+        ]);
+        assert_eq!(sender.messages, expected_messages);
+    }
+
+    #[test]
+    fn set_cooling_schedule_from_best_spot_prices_skips_heatpump_without_cooling_program() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![]);
+        let mut sender = MockSender::default();
+
+        // act
+        let written_schedule = client
+            .set_cooling_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        assert!(written_schedule.is_empty());
+        assert!(sender.messages.is_empty());
+    }
+
+    #[test]
+    fn get_best_spot_prices_for_cooling_per_segment_plans_cooling_load_profile_per_segment() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let segment_spot_prices = vec![
+            hourly_spot_prices(now, &[0.2; 12]),
+            hourly_spot_prices(now + Duration::hours(12), &[0.2; 12]),
+        ];
+
+        // act
+        let best_spot_prices = client
+            .get_best_spot_prices_for_cooling_per_segment(
+                &Config {
+                    cooling_load_profile: Some(LoadProfile {
+                        sections: vec![LoadProfileSection {
+                            duration_seconds: 10800,
+                            power_draw_watt: 2000.0,
+                        }],
+                    }),
+                    ..test_config()
+                },
+                &FirstWindowPlanner {},
+                &segment_spot_prices,
+                now,
+            )
+            .unwrap();
+
+        assert_eq!(best_spot_prices.len(), 6);
+        assert_eq!(best_spot_prices[3].from, now + Duration::hours(12));
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_blocks_outside_window_crossing_midnight() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[
+                    spot_price(
+                        Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+                        Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap(),
+                    ),
+                    spot_price(
+                        Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap(),
+                        Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
+                    ),
+                ],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec!["GET;0x4642a8".to_string()];
+        expected_messages.extend(reset_messages());
+        expected_messages.extend(vec![
+            format!("SET;set_0xa57344;{}", 120 + 65536 * 1320),
+            "SAVE;1".to_string(),
+        ]);
+        assert_eq!(sender.messages, expected_messages);
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_resets_schedule_for_empty_spot_prices() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![TAP_WATER_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec!["GET;0x4642a8".to_string()];
+        expected_messages.extend(reset_messages());
+        expected_messages.push("SAVE;1".to_string());
+        assert_eq!(sender.messages, expected_messages);
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_does_not_write_unchanged_schedule() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![TAP_WATER_SCHEDULE_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        assert_eq!(sender.messages, vec!["GET;0x4642a8".to_string()]);
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_returns_written_raw_values_per_item() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        let written_schedule = client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        assert_eq!(
+            written_schedule,
+            vec![
+                ("0xa57344".to_string(), 11796480),
+                ("0xa53c8c".to_string(), 0),
+                ("0xa47ee4".to_string(), 0),
+                ("0xa6630c".to_string(), 0),
+                ("0xa68d74".to_string(), 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_heating_schedule_from_worst_spot_prices_blocks_same_day_window() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_heating_schedule_from_worst_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 17, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 18, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec!["GET;0x45e118".to_string()];
+        expected_messages.extend(reset_messages());
+        expected_messages.extend(vec![
+            format!("SET;set_0xa57344;{}", 1020 + 65536 * 1080),
+            "SAVE;1".to_string(),
+        ]);
+        assert_eq!(sender.messages, expected_messages);
+    }
+
+    #[test]
+    fn set_heating_schedule_from_worst_spot_prices_does_not_block_worst_hour_in_protected_window() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_heating_schedule_from_worst_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &Config {
+                    // 17:00 - 18:00 utc is 19:00 - 20:00 in amsterdam
+                    protected_windows: vec![TimeSlot {
+                        from: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+                        till: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                        if_price_below: None,
+                    }],
+                    ..test_config()
+                },
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 17, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 18, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        assert_eq!(sender.messages, vec!["GET;0x45e118".to_string()]);
+    }
+
+    fn adaptive_setpoint_config() -> Config {
+        Config {
+            enable_adaptive_setpoint: true,
+            minimum_tap_water_temperature: Some(40.0),
+            adaptive_setpoint_hours: 8,
+            ..test_config()
+        }
+    }
+
+    #[test]
+    fn get_adaptive_tap_water_temperature_lowers_then_restores_setpoint_around_window() {
+        let config = adaptive_setpoint_config();
+        let window_from = Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap();
+        let best_spot_prices = hourly_spot_prices(window_from, &[0.1, 0.1]);
+
+        // act
+        let setpoints: Vec<f64> = [
+            window_from - Duration::hours(12),
+            window_from - Duration::hours(6),
+            window_from + Duration::minutes(30),
+            window_from + Duration::hours(3),
+        ]
+        .iter()
+        .map(|now| get_adaptive_tap_water_temperature(&config, &best_spot_prices, *now))
+        .collect();
+
+        // too far ahead, lowered until the window, restored while running and once it finished
+        assert_eq!(setpoints, vec![50.0, 40.0, 50.0, 50.0]);
+    }
+
+    #[test]
+    fn get_adaptive_tap_water_temperature_keeps_desired_setpoint_if_disabled() {
+        let config = Config {
+            enable_adaptive_setpoint: false,
+            ..adaptive_setpoint_config()
+        };
+        let window_from = Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap();
+        let best_spot_prices = hourly_spot_prices(window_from, &[0.1, 0.1]);
+
+        // act
+        let setpoint = get_adaptive_tap_water_temperature(
+            &config,
+            &best_spot_prices,
+            window_from - Duration::hours(6),
+        );
+
+        assert_eq!(setpoint, 50.0);
+    }
+
+    #[test]
+    fn get_adaptive_tap_water_temperature_keeps_desired_setpoint_without_window() {
+        // act
+        let setpoint = get_adaptive_tap_water_temperature(
+            &adaptive_setpoint_config(),
+            &[],
+            Utc.with_ymd_and_hms(2022, 4, 21, 20, 0, 0).unwrap(),
+        );
+
+        assert_eq!(setpoint, 50.0);
+    }
+
+    #[test]
+    fn set_heating_schedule_from_worst_spot_prices_blocks_window_crossing_midnight() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_heating_schedule_from_worst_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 23, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 22, 1, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec!["GET;0x45e118".to_string()];
+        expected_messages.extend(reset_messages());
+        expected_messages.extend(vec![
+            "SET;set_0xa57344;1380".to_string(),
+            format!("SET;set_0xa68d74;{}", 65536 * 60),
+            "SAVE;1".to_string(),
+        ]);
+        assert_eq!(sender.messages, expected_messages);
+    }
+
+    const FIVE_PLUS_TWO_NAVIGATION_RESPONSE: &str = "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4642a8'><name>Week</name></item><item id='0x463940'><name>5+2</name></item></item></item></Navigation>";
+
+    const FIVE_PLUS_TWO_SCHEDULE_RESPONSE: &str = "<Content><item><name>Maandag - Vrijdag</name><item id='0xa57344'><value>00:00 - 00:00</value><name>1)</name><type>timer</type><raw>0</raw></item><item id='0xa53c8c'><value>00:00 - 00:00</value><name>2)</name><type>timer</type><raw>0</raw></item></item><item><name>Zaterdag - Zondag</name><item id='0xa47ee4'><value>00:00 - 00:00</value><name>1)</name><type>timer</type><raw>0</raw></item><item id='0xa6630c'><value>00:00 - 00:00</value><name>2)</name><type>timer</type><raw>0</raw></item></item></Content>";
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_only_writes_segments_with_planned_spot_prices()
+    {
+        let client = test_client();
+        let navigation: Navigation = from_str(FIVE_PLUS_TWO_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![FIVE_PLUS_TWO_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &Config {
+                    schedule_program: ScheduleProgram::FivePlusTwo,
+                    ..test_config()
+                },
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 23, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 23, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        assert_eq!(
+            sender.messages,
+            vec![
+                "GET;0x463940".to_string(),
+                "SET;set_0xa47ee4;0".to_string(),
+                "SET;set_0xa6630c;0".to_string(),
+                format!("SET;set_0xa47ee4;{}", 65536 * 180),
+                "SET;set_0xa6630c;600".to_string(),
+                "SAVE;1".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_returns_error_if_segments_do_not_match_program()
+    {
+        let client = test_client();
+        let navigation: Navigation = from_str(FIVE_PLUS_TWO_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.set_tap_water_schedule_from_best_spot_prices(
+            &mut receiver,
+            &mut sender,
+            &navigation,
+            &Config {
+                schedule_program: ScheduleProgram::FivePlusTwo,
+                ..test_config()
+            },
+            &[],
+            &[],
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Item Klokprogramma > Warmwater > 5+2 has 1 segments, expected 2".to_string()
+        );
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_mirrors_schedule_to_week_program() {
+        let client = test_client();
+        let navigation: Navigation = from_str(FIVE_PLUS_TWO_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![
+            FIVE_PLUS_TWO_SCHEDULE_RESPONSE,
+            SAVED_RESPONSE,
+            EMPTY_SCHEDULE_RESPONSE,
+            SAVED_RESPONSE,
+        ]);
+        let mut sender = MockSender::default();
+
+        // act
+        let written_schedule = client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &Config {
+                    schedule_program: ScheduleProgram::FivePlusTwo,
+                    mirror_schedule_to_all_programs: true,
+                    ..test_config()
+                },
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 23, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 23, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec![
+            "GET;0x463940".to_string(),
+            "SET;set_0xa47ee4;0".to_string(),
+            "SET;set_0xa6630c;0".to_string(),
+            format!("SET;set_0xa47ee4;{}", 65536 * 180),
+            "SET;set_0xa6630c;600".to_string(),
+            "SAVE;1".to_string(),
+            "GET;0x4642a8".to_string(),
+        ];
+        expected_messages.extend(reset_messages());
+        expected_messages.extend(vec![
+            "SET;set_0xa57344;11796480".to_string(),
+            "SET;set_0xa68d74;600".to_string(),
+            "SAVE;1".to_string(),
+        ]);
+        assert_eq!(sender.messages, expected_messages);
+        assert!(written_schedule.contains(&("0xa68d74".to_string(), 600)));
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_clears_programs_that_are_not_mirrored() {
+        let client = test_client();
+        let navigation: Navigation = from_str(FIVE_PLUS_TWO_NAVIGATION_RESPONSE).unwrap();
+        let programmed_five_plus_two_response =
+            FIVE_PLUS_TWO_SCHEDULE_RESPONSE.replacen("<raw>0</raw>", "<raw>600</raw>", 1);
+        let mut receiver = MockReceiver::new(vec![
+            EMPTY_SCHEDULE_RESPONSE,
+            SAVED_RESPONSE,
+            &programmed_five_plus_two_response,
+            SAVED_RESPONSE,
+        ]);
+        let mut sender = MockSender::default();
+
+        // act
+        let written_schedule = client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &Config {
+                    mirror_schedule_to_all_programs: true,
+                    ..test_config()
+                },
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        assert_eq!(
+            sender.messages[sender.messages.len() - 6..],
+            vec![
+                "GET;0x463940".to_string(),
+                "SET;set_0xa57344;0".to_string(),
+                "SET;set_0xa53c8c;0".to_string(),
+                "SET;set_0xa47ee4;0".to_string(),
+                "SET;set_0xa6630c;0".to_string(),
+                "SAVE;1".to_string(),
+            ]
+        );
+        assert!(written_schedule.contains(&("0xa6630c".to_string(), 0)));
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_does_not_mirror_to_read_only_program() {
+        let client = test_client();
+        let navigation: Navigation = from_str(&FIVE_PLUS_TWO_NAVIGATION_RESPONSE.replace(
+            "<name>5+2</name>",
+            "<name>5+2</name><readOnly>true</readOnly>",
+        ))
+        .unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.set_tap_water_schedule_from_best_spot_prices(
+            &mut receiver,
+            &mut sender,
+            &navigation,
+            &Config {
+                mirror_schedule_to_all_programs: true,
+                ..test_config()
+            },
+            &[],
+            &[],
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Item Klokprogramma > Warmwater > 5+2 is read-only, the clock program is probably locked by the installer"
+        );
+        assert!(!sender.messages.contains(&"GET;0x463940".to_string()));
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_returns_save_rejected_error_if_save_is_not_accepted(
+    ) {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![EMPTY_SCHEDULE_RESPONSE, "error"]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.set_tap_water_schedule_from_best_spot_prices(
+            &mut receiver,
+            &mut sender,
+            &navigation,
+            &test_config(),
+            &[spot_price(
+                Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+            )],
+            &[],
+        );
+
+        assert!(matches!(
+            result.unwrap_err(),
+            WebsocketClientError::SaveRejected { .. }
+        ));
+    }
+
+    #[test]
+    fn websocket_client_config_new_wraps_ipv6_host_in_brackets() {
+        let config = WebsocketClientConfig::new(
+            "fe80::1".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get_websocket_url(),
+            "ws://[fe80::1]:8214".to_string()
+        );
+        assert_eq!(config.get_origin(), "http://[fe80::1]".to_string());
+    }
+
+    #[test]
+    fn websocket_client_config_get_origin_returns_configured_origin() {
+        let config = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            false,
+            Some("http://heatpump.local".to_string()),
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .unwrap();
+
+        // act
+        let origin = config.get_origin();
+
+        assert_eq!(origin, "http://heatpump.local".to_string());
+    }
+
+    #[test]
+    fn websocket_client_config_new_rejects_empty_protocol() {
+        // act
+        let result = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "".to_string(),
+            0,
+            None,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn websocket_client_config_new_rejects_zero_keepalive_interval() {
+        // act
+        let result = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            Some(0),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedSender {
+        messages: Arc<Mutex<Vec<OwnedMessage>>>,
+    }
+
+    impl MessageSender for SharedSender {
+        fn send_message(&mut self, message: &OwnedMessage) -> Result<(), Box<dyn Error>> {
+            self.messages.lock().unwrap().push(message.clone());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keepalive_sender_pings_while_idle_and_stops_when_dropped() {
+        let shared_sender = SharedSender::default();
+        let sender = KeepaliveSender::new(
+            Box::new(shared_sender.clone()),
+            std::time::Duration::from_millis(10),
+        );
+
+        // act
+        thread::sleep(std::time::Duration::from_millis(100));
+        drop(sender);
+
+        let pings = shared_sender.messages.lock().unwrap().len();
+        assert!(pings > 0);
+        assert!(shared_sender
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|message| matches!(message, OwnedMessage::Ping(_))));
+        thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(shared_sender.messages.lock().unwrap().len(), pings);
+    }
+
+    #[test]
+    fn keepalive_sender_forwards_messages() {
+        let shared_sender = SharedSender::default();
+        let mut sender = KeepaliveSender::new(
+            Box::new(shared_sender.clone()),
+            std::time::Duration::from_secs(60),
+        );
+
+        // act
+        sender
+            .send_message(&OwnedMessage::Text("SET;set_0xa57344;480".to_string()))
+            .unwrap();
+        drop(sender);
+
+        assert_eq!(
+            *shared_sender.messages.lock().unwrap(),
+            vec![OwnedMessage::Text("SET;set_0xa57344;480".to_string())]
+        );
+    }
+
+    #[test]
+    fn websocket_client_config_new_rejects_empty_login_code() {
+        // act
+        let result = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            "".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        );
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Login code should not be empty, check WEBSOCKET_LOGIN_CODE".to_string()
+        );
+    }
+
+    #[test]
+    fn websocket_client_config_new_rejects_whitespace_padded_login_code() {
+        // act
+        let result = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            " 999999\n".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        );
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Login code should not start or end with whitespace, check WEBSOCKET_LOGIN_CODE"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn websocket_client_config_new_rejects_login_code_with_non_hexadecimal_characters() {
+        // act
+        let result = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            "999 999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn websocket_client_config_new_accepts_hexadecimal_login_code() {
+        // act
+        let result = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            "00FF9a".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn websocket_client_config_new_accepts_bracketed_ipv6_host() {
+        let config = WebsocketClientConfig::new(
+            "[::1]".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(config.get_websocket_url(), "ws://[::1]:8214".to_string());
+    }
+
+    #[test]
+    fn websocket_client_config_new_does_not_wrap_ipv4_host() {
+        let config = WebsocketClientConfig::new(
+            "192.168.1.10".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get_websocket_url(),
+            "ws://192.168.1.10:8214".to_string()
+        );
+    }
+
+    #[test]
+    fn websocket_client_config_new_returns_error_for_out_of_range_port() {
+        assert!(WebsocketClientConfig::new(
+            "127.0.0.1".to_string(),
+            0,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .is_err());
+        assert!(WebsocketClientConfig::new(
+            "127.0.0.1".to_string(),
+            65536,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn websocket_client_config_new_returns_error_for_empty_host() {
+        assert!(WebsocketClientConfig::new(
+            " ".to_string(),
+            8214,
+            "999999".to_string(),
+            None,
+            false,
+            None,
+            "Lux_WS".to_string(),
+            0,
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn format_plan_table_returns_row_per_spot_price_in_local_time() {
+        let best_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 4, 0, 0).unwrap(),
+        )];
+        let worst_spot_prices = vec![spot_price(
+            Utc.with_ymd_and_hms(2022, 4, 21, 17, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 4, 21, 18, 0, 0).unwrap(),
+        )];
+
+        // act
+        let table = format_plan_table(
+            &test_config(),
+            &Tz::Europe__Amsterdam,
+            &TapWaterStrategy::TapWaterHeating,
+            &best_spot_prices,
+            &worst_spot_prices,
+        );
+
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "TapWaterHeating  2022-04-21 05:00  2022-04-21 06:00      0.2881"
+        );
+        assert_eq!(
+            lines[2],
+            "BlockHeating     2022-04-21 19:00  2022-04-21 20:00      0.2881"
+        );
+    }
+
+    const TEMPERATURES_RESPONSE: &str = "<Content><item id='0x4816ac'><name>Aanvoer</name><value>22.0°C</value></item><item id='0x44fdcc'><name>Retour</name><value>22.0°C</value></item><item id='0x4807dc'><name>Retour berekend</name><value>23.0°C</value></item><item id='0x45e1bc'><name>Heetgas</name><value>38.0°C</value></item><item id='0x448894'><name>Buitentemperatuur</name><value>11.6°C</value></item><item id='0x48047c'><name>Gemiddelde temp.</name><value>13.1°C</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57.0°C</value></item><item id='0x45a41c'><name>Bron-in</name><value>10.5°C</value></item><item id='0x480204'><name>Bron-uit</name><value>10.3°C</value></item><item id='0x4803cc'><name>Menggroep2-aanvoer</name><value>22.0°C</value></item><item id='0x4609cc'><name>Menggr2-aanv.ingest.</name><value>19.0°C</value></item><item id='0x45a514'><name>Zonnecollector</name><value>5.0°C</value></item><item id='0x461ecc'><name>Zonneboiler</name><value>150.0°C</value></item><item id='0x4817a4'><name>Externe energiebron</name><value>---</value></item><item id='0x4646b4'><name>Aanvoer max.</name><value>66.0°C</value></item><item id='0x45e76c'><name>Zuiggasleiding comp.</name><value>19.4°C</value></item><item id='0x4607d4'><name>Comp. verwarming</name><value>37.7°C</value></item><item id='0x43e60c'><name>Oververhitting</name><value>4.8 K</value></item><name>Temperaturen</name></Content>";
+
+    #[test]
+    fn get_controller_time_from_response_returns_date_time_value() {
+        // act
+        let controller_time = get_controller_time_from_response("<Content><item id='0x44c6cc'><name>Type warmtepomp</name><value>LWD</value></item><item id='0x44c704'><name>Softwareversie</name><value>V3.88.2</value></item><item id='0x45e4d4'><name>Datum/tijd</name><value>21.04.22 10:22:13</value></item><name>Installatiestatus</name></Content>");
+
+        assert_eq!(
+            controller_time,
+            Some(
+                NaiveDate::from_ymd_opt(2022, 4, 21)
+                    .unwrap()
+                    .and_hms_opt(10, 22, 13)
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn get_controller_time_from_response_returns_none_without_date_time_value() {
+        // act
+        let controller_time = get_controller_time_from_response("<Content><item id='0x44c6cc'><name>Type warmtepomp</name><value>LWD</value></item><item id='0x44c704'><name>Softwareversie</name><value>V3.88.2</value></item><name>Installatiestatus</name></Content>");
+
+        assert_eq!(controller_time, None);
+    }
+
+    #[test]
+    fn get_clock_skew_minutes_compares_in_heatpump_time_zone() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 8, 0, 0).unwrap();
+        let controller_time = NaiveDate::from_ymd_opt(2022, 4, 21)
+            .unwrap()
+            .and_hms_opt(10, 25, 0)
+            .unwrap();
+
+        // act
+        let clock_skew_minutes =
+            get_clock_skew_minutes(&controller_time, &Tz::Europe__Amsterdam, now);
+
+        assert_eq!(clock_skew_minutes, 25);
+    }
+
+    #[test]
+    fn get_clock_skew_minutes_is_negative_when_heatpump_clock_lags_behind() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 8, 0, 0).unwrap();
+        let controller_time = NaiveDate::from_ymd_opt(2022, 4, 21)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+
+        // act
+        let clock_skew_minutes =
+            get_clock_skew_minutes(&controller_time, &Tz::Europe__Amsterdam, now);
+
+        assert_eq!(clock_skew_minutes, -60);
+    }
+
+    #[test]
+    fn get_firmware_version_from_response_returns_software_version() {
+        // act
+        let firmware_version = get_firmware_version_from_response("<Content><item id='0x44c6cc'><name>Type warmtepomp</name><value>LWD</value></item><item id='0x44c704'><name>Softwareversie</name><value>V3.88.2</value></item><name>Installatiestatus</name></Content>");
+
+        assert_eq!(
+            firmware_version,
+            Some(FirmwareVersion {
+                major: 3,
+                minor: 88,
+                patch: 2
+            })
+        );
+    }
+
+    #[test]
+    fn is_complete_response_returns_false_until_root_tag_is_closed() {
+        assert!(!is_complete_response(
+            "<Content><item><name>Maandag - Zondag</name>"
+        ));
+        assert!(is_complete_response(
+            "<Content><item><name>Maandag - Zondag</name></item></Content>"
+        ));
+        assert!(is_complete_response("<Content/>"));
+        assert!(is_complete_response("error"));
+    }
+
+    #[test]
+    fn set_tap_water_schedule_from_best_spot_prices_accumulates_content_sent_in_two_frames() {
+        let client = test_client();
+        let navigation: Navigation = from_str(SCHEDULE_NAVIGATION_RESPONSE).unwrap();
+        let (first_frame, second_frame) = EMPTY_SCHEDULE_RESPONSE.split_at(200);
+        let mut receiver = MockReceiver::new(vec![first_frame, second_frame, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_schedule_from_best_spot_prices(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                &test_config(),
+                &[spot_price(
+                    Utc.with_ymd_and_hms(2022, 4, 21, 3, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 21, 10, 0, 0).unwrap(),
+                )],
+                &[],
+            )
+            .unwrap();
+
+        let mut expected_messages = vec!["GET;0x4642a8".to_string()];
+        expected_messages.extend(reset_messages());
+        expected_messages.extend(vec![
+            "SET;set_0xa57344;11796480".to_string(),
+            "SET;set_0xa68d74;600".to_string(),
+            "SAVE;1".to_string(),
+        ]);
+        assert_eq!(sender.messages, expected_messages);
+    }
+
+    #[test]
+    fn get_numeric_item_from_response_returns_id_and_value() {
+        // act
+        let (id, value) = get_numeric_item_from_response(
+            "Temperatuur +-",
+            "<Content><item id='0x4a5f2c'><name>Temperatuur +-</name><value>-1.5°C</value></item><item id='0x4a60d4'><name>Modus</name><value>Automatisch</value></item><name>Verwarming</name></Content>",
+        )
+        .unwrap();
+
+        assert_eq!(id, "0x4a5f2c".to_string());
+        assert_eq!(value, -1.5);
+    }
+
+    #[test]
+    fn get_numeric_item_from_response_returns_error_for_non_numeric_item() {
+        // act
+        let result = get_numeric_item_from_response(
+            "Modus",
+            "<Content><item id='0x4a5f2c'><name>Temperatuur +-</name><value>-1.5°C</value></item><item id='0x4a60d4'><name>Modus</name><value>Automatisch</value></item><name>Verwarming</name></Content>",
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Item Modus has value Automatisch, which is not numeric".to_string()
+        );
+    }
+
+    #[test]
+    fn get_value_steps_rounds_to_nearest_step() {
+        assert_eq!(get_value_steps(-1.5, 1.0, 0.5), 5);
+        assert_eq!(get_value_steps(57.0, 50.0, 0.5), -14);
+        assert_eq!(get_value_steps(2.0, 2.2, 0.5), 0);
+        assert_eq!(get_value_steps(2.0, 2.3, 0.5), 1);
+    }
+
+    #[test]
+    fn get_screen_label_from_response_returns_content_name() {
+        // act
+        let label = get_screen_label_from_response("<Content><item id='0x3d9dd8'><name>Continu</name><value>0</value></item><name>Thermische desinfectie</name></Content>");
+
+        assert_eq!(label, Some("Thermische desinfectie".to_string()));
+    }
+
+    #[test]
+    fn confirm_screen_proceeds_when_screen_is_unlabeled() {
+        // act
+        let result = confirm_screen("<Content></Content>", "Menu");
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn toggle_continuous_desinfection_aborts_when_menu_path_diverges() {
+        let client = test_client();
+        let navigation: Navigation = from_str("<Navigation id='0x45cd88'><item id='0x3d9d40'><name>Afstandbediening</name></item></Navigation>").unwrap();
+        let mut receiver = MockReceiver::new(vec![
+            "<Content><name>Afstandbediening</name></Content>",
+            "<Content></Content>",
+            "<Content><name>Menu</name></Content>",
+            "<Content></Content>",
+            "<Content></Content>",
+            "<Content></Content>",
+            "<Content></Content>",
+            "<Content></Content>",
+            "<Content></Content>",
+            "<Content></Content>",
+            "<Content><name>Verwarmen</name></Content>",
+        ]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.toggle_continuous_desinfection(&mut receiver, &mut sender, &navigation);
+
+        match result {
+            Err(WebsocketClientError::NavigationDiverged { expected, actual }) => {
+                assert_eq!(expected, "Warmwater".to_string());
+                assert_eq!(actual, "Verwarmen".to_string());
+            }
+            _ => panic!("expected a diverged navigation error"),
+        }
+        // nothing got clicked past the diverged screen
+        assert_eq!(sender.messages.len(), 11);
+    }
+
+    #[test]
+    fn get_desinfection_enabled_from_response_parses_continu_checkbox() {
+        // act
+        let enabled = get_desinfection_enabled_from_response("<Content><item id='0x3d9dd8'><name>Continu</name><value>1</value></item><name>Thermische desinfectie</name></Content>").unwrap();
+        let disabled = get_desinfection_enabled_from_response("<Content><item id='0x3d9dd8'><name>Continu</name><value>0</value></item><name>Thermische desinfectie</name></Content>").unwrap();
+
+        assert_eq!(enabled, Some(true));
+        assert_eq!(disabled, Some(false));
+    }
+
+    #[test]
+    fn get_desinfection_enabled_from_response_returns_none_without_continu_checkbox() {
+        // act
+        let desinfection_enabled = get_desinfection_enabled_from_response(
+            "<Content><name>Thermische desinfectie</name></Content>",
+        )
+        .unwrap();
+
+        assert_eq!(desinfection_enabled, None);
+    }
+
+    #[test]
+    fn get_desinfection_enabled_from_response_returns_error_for_unknown_value() {
+        // act
+        let result = get_desinfection_enabled_from_response("<Content><item id='0x3d9dd8'><name>Continu</name><value>---</value></item><name>Thermische desinfectie</name></Content>");
+
+        assert!(matches!(result, Err(WebsocketClientError::Parse(_))));
+    }
+
+    #[test]
+    fn reconcile_desinfection_enabled_prefers_heatpump_over_stored_state() {
+        // act
+        let reconciled = [
+            reconcile_desinfection_enabled(false, Some(true)),
+            reconcile_desinfection_enabled(true, Some(false)),
+            reconcile_desinfection_enabled(true, None),
+        ];
+
+        assert_eq!(reconciled, [true, false, true]);
+    }
+
+    #[test]
+    fn read_desinfection_enabled_reads_checkbox_from_thermal_desinfection_screen() {
+        let client = test_client();
+        let navigation: Navigation = from_str("<Navigation id='0x45cd88'><item id='0x3d9d40'><name>Afstandbediening</name></item></Navigation>").unwrap();
+        let mut responses = vec![
+            "<Content><name>Afstandbediening</name></Content>",
+            "<Content></Content>",
+            "<Content><name>Menu</name></Content>",
+        ];
+        responses.extend(vec!["<Content></Content>"; 7]);
+        responses.push("<Content><name>Warmwater</name></Content>");
+        responses.extend(vec!["<Content></Content>"; 9]);
+        responses.push("<Content><name>Onderhoudsprogramma</name></Content>");
+        responses.push("<Content></Content>");
+        responses.push("<Content><item id='0x3d9dd8'><name>Continu</name><value>1</value></item><name>Thermische desinfectie</name></Content>");
+        let mut receiver = MockReceiver::new(responses);
+        let mut sender = MockSender::default();
+
+        // act
+        let desinfection_enabled = client
+            .read_desinfection_enabled(&mut receiver, &mut sender, &navigation)
+            .unwrap();
+
+        assert_eq!(desinfection_enabled, Some(true));
+        // only navigated, the checkbox didn't get clicked
+        assert_eq!(sender.messages.len(), 23);
+    }
+
+    #[test]
+    fn get_energy_counters_from_response_skips_unavailable_counters() {
+        // act
+        let counters = get_energy_counters_from_response("<Content><item id='0x4a5e3c'><name>Verwarming</name><value>8765.0 kWh</value></item><item id='0x4a5f2c'><name>Warmwater</name><value>1234.5 kWh</value></item><item id='0x4a601c'><name>Zwembad</name><value>---</value></item><name>Energie</name></Content>").unwrap();
+
+        assert_eq!(counters.len(), 2);
+        assert_eq!(counters.get("Verwarming"), Some(&8765.0));
+        assert_eq!(counters.get("Warmwater"), Some(&1234.5));
+    }
+
+    #[test]
+    fn get_operating_modes_from_response_returns_selected_value_of_each_circuit() {
+        // act
+        let operating_modes = get_operating_modes_from_response("<Content><item id='0x4e6a2c'><name>Verwarmen</name><option value='0'>Automatisch</option><option value='1'>Zw. WEZ</option><option value='2'>Party</option><option value='3'>Vakantie</option><option value='4'>Uit</option><value>Vakantie</value></item><item id='0x4e6b1c'><name>Warmwater</name><option value='0'>Automatisch</option><option value='4'>Uit</option><value>Automatisch</value></item><name>Bedrijfsmode</name></Content>").unwrap();
+
+        assert_eq!(operating_modes.len(), 2);
+        assert_eq!(
+            get_operating_mode(&operating_modes, "Verwarmen").unwrap(),
+            OperatingMode::Holiday
+        );
+        assert_eq!(
+            get_operating_mode(&operating_modes, "Warmwater").unwrap(),
+            OperatingMode::Automatic
+        );
+    }
+
+    #[test]
+    fn get_operating_mode_returns_error_if_circuit_is_missing() {
+        let operating_modes = get_operating_modes_from_response("<Content><item id='0x4e6b1c'><name>Warmwater</name><option value='0'>Automatisch</option><value>Automatisch</value></item><name>Bedrijfsmode</name></Content>").unwrap();
+
+        // act
+        let result = get_operating_mode(&operating_modes, "Verwarmen");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No operating mode for Verwarmen in Instelling > Bedrijfsmode".to_string()
+        );
+    }
+
+    #[test]
+    fn get_faults_from_response_skips_unused_slots() {
+        // act
+        let faults = get_faults_from_response("<Content><item id='0x4b3e14'><name>21.04.22 10:22:13</name><value>717 Flow monitoring</value></item><item id='0x4b3f04'><name>19.04.22 02:05:41</name><value>714 Hot gas lockout</value></item><item id='0x4b3ff4'><name>---</name><value>---</value></item><name>Storingsbuffer</name></Content>").unwrap();
+
+        assert_eq!(faults.len(), 2);
+        assert_eq!(faults[0].code, 717);
+        assert_eq!(faults[0].description, "Flow monitoring".to_string());
+        assert_eq!(faults[1].code, 714);
+    }
+
+    #[test]
+    fn get_recent_faults_returns_faults_from_last_day_in_heatpump_local_time() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let faults = get_faults_from_response("<Content><item id='0x4b3e14'><name>21.04.22 10:22:13</name><value>717 Flow monitoring</value></item><item id='0x4b3f04'><name>19.04.22 02:05:41</name><value>714 Hot gas lockout</value></item></Content>").unwrap();
+
+        // act
+        let recent_faults = get_recent_faults(&faults, &chrono_tz::Europe::Amsterdam, now);
+
+        assert_eq!(recent_faults.len(), 1);
+        assert_eq!(recent_faults[0].code, 717);
+    }
+
+    #[test]
+    fn parse_all_items_returns_all_items_from_temperatures_response() {
+        let client = test_client();
+
+        // act
+        let items = client.parse_all_items(TEMPERATURES_RESPONSE).unwrap();
+
+        assert_eq!(items.len(), 19);
+        assert_eq!(items.get("Tapwater gemeten"), Some(&54.2));
+        assert_eq!(items.get("Tapwater ingesteld"), Some(&57.0));
+        assert_eq!(items.get("Externe energiebron"), Some(&0.0));
+        assert_eq!(items.get("Oververhitting"), Some(&4.8));
+    }
+
+    #[test]
+    fn parse_all_items_handles_double_quotes_extra_attributes_and_whitespace() {
+        let client = test_client();
+
+        // act
+        let items = client
+            .parse_all_items(
+                "<Content>\n  <item id=\"0x457724\" readOnly=\"true\" >\n    <name>Tapwater gemeten</name>\n    <value>54.2°C</value>\n  </item>\n  <item class=\"temp\" id=\"0x448894\"><value>-3.5°C</value><name>Buitentemperatuur</name></item>\n  <item id=\"0x4817a4\"><name>Externe energiebron</name><value>---</value></item>\n  <name>Temperaturen</name>\n</Content>",
+            )
+            .unwrap();
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items.get("Tapwater gemeten"), Some(&54.2));
+        assert_eq!(items.get("Buitentemperatuur"), Some(&-3.5));
+        assert_eq!(items.get("Externe energiebron"), Some(&0.0));
+    }
+
+    #[test]
+    fn parse_all_items_skips_items_without_numeric_value() {
+        let client = test_client();
+
+        // act
+        let items = client
+            .parse_all_items("<Content><item id='0x44c6cc'><name>Type warmtepomp</name><value>LWD</value></item><item id='0x457724'><name>Tapwater gemeten</name><value>54.2°C</value></item></Content>")
+            .unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items.get("Tapwater gemeten"), Some(&54.2));
+    }
+
+    #[test]
+    fn get_item_from_response_parses_comma_decimal_separator() {
+        let client = test_client();
+
+        // act
+        let value = client
+            .get_item_from_response("Tapwater ingesteld", "<Content><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57,5°C</value></item><name>Temperaturen</name></Content>")
+            .unwrap();
+
+        assert_eq!(value, 57.5);
+    }
+
+    #[test]
+    fn parse_item_value_accepts_comma_and_dot_decimal_separator() {
+        assert_eq!(
+            parse_item_value("Tapwater ingesteld", "57,0°C").unwrap(),
+            Some(57.0)
+        );
+        assert_eq!(
+            parse_item_value("Tapwater ingesteld", "57.0°C").unwrap(),
+            Some(57.0)
+        );
+        assert_eq!(
+            parse_item_value("Tapwater gemeten", "-4,8°C").unwrap(),
+            Some(-4.8)
+        );
+    }
+
+    #[test]
+    fn read_tap_water_setpoint_reads_setpoint_from_temperatures() {
+        let client = test_client();
+        let navigation: Navigation = from_str(REMOTE_CONTROL_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec![TEMPERATURES_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        let setpoint = client
+            .read_tap_water_setpoint(&mut receiver, &mut sender, &navigation)
+            .unwrap();
+
+        assert_eq!(setpoint, 57.0);
+        assert_eq!(sender.messages, vec!["GET;0x45df90".to_string()]);
+    }
+
+    #[test]
+    fn set_tap_water_temperature_skips_update_for_equal_comma_separated_value() {
+        let client = test_client();
+        let navigation: Navigation = from_str(REMOTE_CONTROL_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec!["<Content><item id='0x45e97c'><name>Tapwater ingesteld</name><value>57,5°C</value></item><name>Temperaturen</name></Content>"]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_temperature(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                57.5,
+                TemperatureUnit::Celsius,
+            )
+            .unwrap();
+
+        assert_eq!(sender.messages, vec!["GET;0x45df90".to_string()]);
+    }
+
+    #[test]
+    fn get_energy_counters_from_response_parses_comma_decimal_separator() {
+        // act
+        let counters = get_energy_counters_from_response("<Content><item id='0x4a5e3c'><name>Verwarming</name><value>8765,5 kWh</value></item><name>Energie</name></Content>").unwrap();
+
+        assert_eq!(counters.get("Verwarming"), Some(&8765.5));
+    }
+
+    #[test]
+    fn get_item_from_response_returns_error_if_item_does_not_exist() {
+        let client = test_client();
+
+        // act
+        let result = client.get_item_from_response("Tapwater", TEMPERATURES_RESPONSE);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_all_items_returns_same_values_as_repeated_get_item_from_response() {
+        let client = test_client();
+        let names = vec![
+            "Aanvoer",
+            "Retour",
+            "Retour berekend",
+            "Heetgas",
+            "Buitentemperatuur",
+            "Gemiddelde temp.",
+            "Tapwater gemeten",
+            "Tapwater ingesteld",
+            "Bron-in",
+            "Bron-uit",
+        ];
+
+        // act
+        let items = client.parse_all_items(TEMPERATURES_RESPONSE).unwrap();
+
+        for name in &names {
+            assert_eq!(
+                items.get(*name).copied(),
+                Some(
+                    client
+                        .get_item_from_response(name, TEMPERATURES_RESPONSE)
+                        .unwrap()
+                )
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn update_schedule() -> Result<(), Box<dyn Error>> {
+        let client = WebsocketClient::new(WebsocketClientConfig::from_env(None, false)?);
+
+        let (mut receiver, mut sender) = client.connect()?;
+
+        let navigation = client.login(&mut receiver, &mut sender)?;
+
+        client.set_tap_water_schedule_from_best_spot_prices(
+            &mut receiver,
+            &mut sender,
+            &navigation,
+            &Config {
+                heatpump_time_zone: "Europe/Amsterdam".to_string(),
+                desinfection_load_profile: LoadProfile {
+                    sections: vec![
+                        LoadProfileSection {
+                            duration_seconds: 7200,
+                            power_draw_watt: 2000.0,
+                        },
+                        LoadProfileSection {
+                            duration_seconds: 1800,
+                            power_draw_watt: 8000.0,
+                        },
+                    ],
+                },
+                jitter_max_minutes: 15,
+                ..test_config()
+            },
+            &vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 4, 21, 14, 0, 0).unwrap(),
+                    market_price: 0.157,
+                    market_price_tax: 0.0330708,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.081,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 4, 21, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 4, 21, 15, 0, 0).unwrap(),
+                    market_price: 0.164,
+                    market_price_tax: 0.0344316,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.081,
+                },
+            ],
+            &[],
+        )?;
+
+        Ok(())
+    }
+
+    const REMOTE_CONTROL_NAVIGATION_RESPONSE: &str = "<Navigation id='0x45cd88'><item id='0x45e068'><name>Informatie</name><item id='0x45df90'><name>Temperaturen</name></item></item><item id='0x3d9d40'><name>Afstandbediening</name></item></Navigation>";
+
+    fn count_messages(messages: &[String], message: &str) -> usize {
+        messages.iter().filter(|m| m.as_str() == message).count()
+    }
+
+    #[test]
+    fn set_tap_water_temperature_raises_fahrenheit_target_by_whole_degrees() {
+        let client = test_client();
+        let navigation: Navigation = from_str(REMOTE_CONTROL_NAVIGATION_RESPONSE).unwrap();
+        let mut responses = vec!["<Content><item id='0x457724'><name>Tapwater gemeten</name><value>118.4°F</value></item><item id='0x45e97c'><name>Tapwater ingesteld</name><value>122.0°F</value></item><name>Temperaturen</name></Content>"];
+        responses.extend(vec![SAVED_RESPONSE; 100]);
+        let mut receiver = MockReceiver::new(responses);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_temperature(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                131.0,
+                TemperatureUnit::Fahrenheit,
+            )
+            .unwrap();
+
+        // 3 moves to warmwater and 1 to temperatuur, followed by a press per degree fahrenheit
+        assert_eq!(count_messages(&sender.messages, "MOVE;0"), 4 + 9);
+        assert_eq!(count_messages(&sender.messages, "MOVE;1"), 0);
+    }
+
+    #[test]
+    fn set_tap_water_temperature_lowers_celsius_target_by_half_degrees() {
+        let client = test_client();
+        let navigation: Navigation = from_str(REMOTE_CONTROL_NAVIGATION_RESPONSE).unwrap();
+        let mut responses = vec![TEMPERATURES_RESPONSE];
+        responses.extend(vec![SAVED_RESPONSE; 100]);
+        let mut receiver = MockReceiver::new(responses);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_temperature(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                55.0,
+                TemperatureUnit::Celsius,
+            )
+            .unwrap();
+
+        assert_eq!(count_messages(&sender.messages, "MOVE;0"), 4);
+        assert_eq!(count_messages(&sender.messages, "MOVE;1"), 4);
+    }
+
+    #[test]
+    fn set_tap_water_temperature_skips_fahrenheit_target_within_half_a_step() {
+        let client = test_client();
+        let navigation: Navigation = from_str(REMOTE_CONTROL_NAVIGATION_RESPONSE).unwrap();
+        let mut receiver = MockReceiver::new(vec!["<Content><item id='0x45e97c'><name>Tapwater ingesteld</name><value>122.0°F</value></item><name>Temperaturen</name></Content>"]);
+        let mut sender = MockSender::default();
+
+        // act
+        client
+            .set_tap_water_temperature(
+                &mut receiver,
+                &mut sender,
+                &navigation,
+                122.4,
+                TemperatureUnit::Fahrenheit,
+            )
+            .unwrap();
+
+        assert_eq!(sender.messages, vec!["GET;0x45df90".to_string()]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn set_tap_water_temperature() -> Result<(), Box<dyn Error>> {
+        let client = WebsocketClient::new(WebsocketClientConfig::from_env(None, false)?);
+
+        let (mut receiver, mut sender) = client.connect()?;
+
+        let navigation = client.login(&mut receiver, &mut sender)?;
+
+        client.set_tap_water_temperature(
+            &mut receiver,
+            &mut sender,
+            &navigation,
+            50.0,
+            TemperatureUnit::Celsius,
+        )?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn toggle_continuous_desinfection() -> Result<(), Box<dyn Error>> {
+        let client = WebsocketClient::new(WebsocketClientConfig::from_env(None, false)?);
+
+        let (mut receiver, mut sender) = client.connect()?;
+
+        let navigation = client.login(&mut receiver, &mut sender)?;
+
+        client.toggle_continuous_desinfection(&mut receiver, &mut sender, &navigation)?;
+
+        Ok(())
+    }
+
+    fn price_based_desinfection_desired(
+        lowest_spot_prices: Vec<SpotPrice>,
+        highest_spot_prices: Vec<SpotPrice>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, Box<dyn Error>> {
+        // the cheapest window finishes 168 hours after the last desinfection, halfway between min and max hours, so the
+        // threshold is a quarter of the highest price per hour
+        let config = Config {
+            min_hours_since_last_desinfection: 96,
+            max_hours_since_last_desinfection: 240,
+            desinfection_cooldown_hours: 24,
+            enable_price_based_desinfection: true,
+            desinfection_hysteresis_margin: 0.0,
+            price_components: vec![PriceComponent::MarketPrice],
+            ..test_config()
+        };
+        let (desinfection_desired, _) = is_desinfection_desired(
+            &config,
+            false,
+            &(now - Duration::hours(165)),
+            &PlanningResponse {
+                spot_prices: lowest_spot_prices,
+                load_profile: config.desinfection_load_profile.clone(),
+            },
+            &PlanningResponse {
+                spot_prices: highest_spot_prices,
+                load_profile: config.desinfection_load_profile.clone(),
+            },
+            now,
+        )?;
+
+        Ok(desinfection_desired)
+    }
+
+    #[test]
+    fn is_desinfection_desired_compares_hourly_windows_with_different_slot_counts(
+    ) -> Result<(), Box<dyn Error>> {
+        let now = Utc.with_ymd_and_hms(2022, 5, 10, 10, 0, 0).unwrap();
+
+        // act
+        let desinfection_desired = price_based_desinfection_desired(
+            hourly_spot_prices(now + Duration::hours(1), &[0.1, 0.1, 0.1, 0.1]),
+            hourly_spot_prices(now + Duration::hours(8), &[0.5, 0.5]),
+            now,
+        )?;
+
+        // the 0.4 total of the cheapest window is over a quarter of the 1.0 total of the most expensive one, its 0.1
+        // per hour isn't over a quarter of 0.5 per hour
+        assert!(desinfection_desired);
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_compares_quarter_hourly_with_hourly_window(
+    ) -> Result<(), Box<dyn Error>> {
+        let now = Utc.with_ymd_and_hms(2022, 5, 10, 10, 0, 0).unwrap();
+
+        // act
+        let desinfection_desired = price_based_desinfection_desired(
+            quarter_hourly_spot_prices(now + Duration::hours(1), &[0.1; 8]),
+            hourly_spot_prices(now + Duration::hours(8), &[0.5, 0.5]),
+            now,
+        )?;
+
+        // 8 quarter hours add up to 0.8, over a quarter of the 1.0 total of the most expensive window, but average 0.1
+        // per hour
+        assert!(desinfection_desired);
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_returns_false_when_average_per_hour_is_above_threshold(
+    ) -> Result<(), Box<dyn Error>> {
+        let now = Utc.with_ymd_and_hms(2022, 5, 10, 10, 0, 0).unwrap();
+
+        // act
+        let desinfection_desired = price_based_desinfection_desired(
+            quarter_hourly_spot_prices(now + Duration::hours(1), &[0.15, 0.15]),
+            hourly_spot_prices(now + Duration::hours(8), &[0.5, 0.5, 0.5, 0.5]),
+            now,
+        )?;
+
+        // the 0.3 total of the cheapest window is under a quarter of the 2.0 total of the most expensive one, its 0.15
+        // per hour is over a quarter of 0.5 per hour
+        assert!(!desinfection_desired);
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_returns_false_within_cooldown_despite_negative_prices(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 15, 10, 0, 0).unwrap();
+        let mut negative_spot_price = spot_price(
+            desinfection_finished_at + Duration::hours(1),
+            desinfection_finished_at + Duration::hours(2),
+        );
+        negative_spot_price.market_price = -0.5;
+        let planning_response = PlanningResponse {
+            spot_prices: vec![negative_spot_price],
+            load_profile: test_config().desinfection_load_profile,
+        };
+
+        // act
+        let (desinfection_desired, desinfection_decision) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection: 96,
+                max_hours_since_last_desinfection: 240,
+                desinfection_cooldown_hours: 24,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &planning_response,
+            &planning_response,
+            Utc::now(),
+        )?;
+
+        assert!(!desinfection_desired);
+        assert_eq!(
+            desinfection_decision,
+            DesinfectionDecision::WithinCooldown {
+                hours_since_last_desinfection: 1,
+                cooldown_hours: 24,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_returns_false_within_cooldown_for_quarter_hour_slots(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 14, 10, 0, 0).unwrap();
+        let planning_response = PlanningResponse {
+            spot_prices: quarter_hourly_spot_prices(
+                desinfection_finished_at + Duration::hours(23) + Duration::minutes(45),
+                &[0.1; 8],
+            ),
+            load_profile: test_config().desinfection_load_profile,
+        };
+
+        // act
+        let (desinfection_desired, desinfection_decision) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection: 96,
+                max_hours_since_last_desinfection: 240,
+                desinfection_cooldown_hours: 24,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &planning_response,
+            &planning_response,
+            Utc.with_ymd_and_hms(2022, 5, 15, 9, 0, 0).unwrap(),
+        )?;
+
+        assert!(!desinfection_desired);
+        assert_eq!(
+            desinfection_decision,
+            DesinfectionDecision::WithinCooldown {
+                hours_since_last_desinfection: 23,
+                cooldown_hours: 24,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_returns_true_on_sunday_after_cooldown() -> Result<(), Box<dyn Error>>
+    {
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 8, 10, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2022, 5, 15, 10, 0, 0).unwrap();
+        let planning_response = PlanningResponse {
+            spot_prices: vec![spot_price(
+                now + Duration::hours(1),
+                now + Duration::hours(2),
+            )],
+            load_profile: test_config().desinfection_load_profile,
+        };
+
+        // act
+        let (desinfection_desired, desinfection_decision) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection: 96,
+                max_hours_since_last_desinfection: 240,
+                desinfection_cooldown_hours: 24,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &planning_response,
+            &planning_response,
+            now,
+        )?;
+
+        assert!(desinfection_desired);
+        assert_eq!(
+            desinfection_decision,
+            DesinfectionDecision::Weekday {
+                weekday: Weekday::Sun,
+                desired_weekday: Weekday::Sun,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_returns_false_on_other_weekdays_after_cooldown(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 8, 10, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2022, 5, 14, 10, 0, 0).unwrap();
+        let planning_response = PlanningResponse {
+            spot_prices: vec![spot_price(
+                now + Duration::hours(1),
+                now + Duration::hours(2),
+            )],
+            load_profile: test_config().desinfection_load_profile,
+        };
+
+        // act
+        let (desinfection_desired, desinfection_decision) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection: 96,
+                max_hours_since_last_desinfection: 240,
+                desinfection_cooldown_hours: 24,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &planning_response,
+            &planning_response,
+            now,
+        )?;
+
+        assert!(!desinfection_desired);
+        assert_eq!(
+            desinfection_decision.to_string(),
+            "desinfection is desired on Sun, today is Sat".to_string()
+        );
+
+        Ok(())
+    }
+
+    fn price_based_desinfection_decisions(
+        desinfection_hysteresis_margin: f64,
+        lowest_market_prices: &[f64],
+    ) -> Result<Vec<bool>, Box<dyn Error>> {
+        // a tuesday, 15 days after the last desinfection, so the threshold is the full highest price
+        let now = Utc.with_ymd_and_hms(2022, 5, 10, 10, 0, 0).unwrap();
+        let desinfection_finished_at = now - Duration::days(15);
+        let config = Config {
+            min_hours_since_last_desinfection: 96,
+            max_hours_since_last_desinfection: 240,
+            desinfection_cooldown_hours: 24,
+            enable_price_based_desinfection: true,
+            desinfection_hysteresis_margin,
+            price_components: vec![PriceComponent::MarketPrice],
+            ..test_config()
+        };
+        let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: hourly_spot_prices(now + Duration::hours(6), &[0.3, 0.3]),
+            load_profile: config.desinfection_load_profile.clone(),
+        };
+
+        // every run feeds the toggle it left the controller in to the next one
+        let mut desinfection_enabled = false;
+        let mut decisions = vec![];
+        for lowest_market_price in lowest_market_prices {
+            let lowest_price_desinfection_response = PlanningResponse {
+                spot_prices: hourly_spot_prices(
+                    now + Duration::hours(1),
+                    &[*lowest_market_price, *lowest_market_price],
+                ),
+                load_profile: config.desinfection_load_profile.clone(),
+            };
+            let (desinfection_desired, _) = is_desinfection_desired(
+                &config,
+                desinfection_enabled,
+                &desinfection_finished_at,
+                &lowest_price_desinfection_response,
+                &highest_price_desinfection_response,
+                now,
+            )?;
+            desinfection_enabled = desinfection_desired;
+            decisions.push(desinfection_desired);
+        }
+
+        Ok(decisions)
+    }
+
+    #[test]
+    fn is_desinfection_desired_stays_stable_when_prices_flip_around_threshold(
+    ) -> Result<(), Box<dyn Error>> {
+        // act
+        let decisions = price_based_desinfection_decisions(
+            0.1,
+            &[0.29, 0.31, 0.29, 0.31, 0.2, 0.31, 0.29, 0.31, 0.4, 0.29],
+        )?;
+
+        assert_eq!(
+            decisions,
+            vec![false, false, false, false, true, true, true, true, false, false]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_flips_with_prices_around_threshold_without_hysteresis(
+    ) -> Result<(), Box<dyn Error>> {
+        // act
+        let decisions = price_based_desinfection_decisions(0.0, &[0.29, 0.31, 0.29, 0.31])?;
+
+        assert_eq!(decisions, vec![true, false, true, false]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_desinfection_desired_returns_price_threshold_decision_when_price_based(
+    ) -> Result<(), Box<dyn Error>> {
+        let now = Utc.with_ymd_and_hms(2022, 5, 10, 10, 0, 0).unwrap();
+        let desinfection_finished_at = now - Duration::days(15);
+        let config = Config {
+            enable_price_based_desinfection: true,
+            desinfection_hysteresis_margin: 0.1,
+            price_components: vec![PriceComponent::MarketPrice],
+            ..test_config()
+        };
+        let lowest_price_desinfection_response = PlanningResponse {
+            spot_prices: hourly_spot_prices(now + Duration::hours(1), &[0.2, 0.2]),
+            load_profile: config.desinfection_load_profile.clone(),
+        };
+        let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: hourly_spot_prices(now + Duration::hours(6), &[0.3, 0.3]),
+            load_profile: config.desinfection_load_profile.clone(),
+        };
+
+        // act
+        let (desinfection_desired, desinfection_decision) = is_desinfection_desired(
+            &config,
+            true,
+            &desinfection_finished_at,
+            &lowest_price_desinfection_response,
+            &highest_price_desinfection_response,
+            now,
+        )?;
+
+        assert!(desinfection_desired);
+        match desinfection_decision {
+            DesinfectionDecision::PriceThreshold {
+                hours_since_last_desinfection,
+                lowest_price_per_hour,
+                threshold_price_per_hour,
+            } => {
+                assert_eq!(hours_since_last_desinfection, 363);
+                assert!((lowest_price_per_hour - 0.2).abs() < 1e-9);
+                assert!((threshold_price_per_hour - 0.33).abs() < 1e-9);
+            }
+            other => panic!("expected a price threshold decision, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn is_desinfection_desired_returns_false_when_best_spot_prices_is_empty(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 5400,
+                    power_draw_watt: 2000.0,
+                },
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 8000.0,
+                },
+            ],
+        };
+        let min_hours_since_last_desinfection: i64 = 96;
+        let max_hours_since_last_desinfection: i64 = 240;
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 4, 30, 9, 37, 0).unwrap();
+        let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    market_price: 0.33,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile.clone(),
+        };
+        let lowest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![],
+            load_profile: desinfection_load_profile,
+        };
+
+        let (desinfection_desired, _) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection,
+                max_hours_since_last_desinfection,
+                desinfection_cooldown_hours: 0,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &lowest_price_desinfection_response,
+            &highest_price_desinfection_response,
+            Utc::now(),
+        )?;
+
+        assert_eq!(desinfection_desired, false);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn is_desinfection_desired_returns_false_when_hours_since_last_desinfection_are_less_than_min_hours(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 5400,
+                    power_draw_watt: 2000.0,
+                },
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 8000.0,
+                },
+            ],
+        };
+
+        let min_hours_since_last_desinfection: i64 = 96;
+        let max_hours_since_last_desinfection: i64 = 240;
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 11, 9, 37, 0).unwrap();
+        let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    market_price: 0.33,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile.clone(),
+        };
+        let lowest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile,
+        };
+
+        let (desinfection_desired, _) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection,
+                max_hours_since_last_desinfection,
+                desinfection_cooldown_hours: 0,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &lowest_price_desinfection_response,
+            &highest_price_desinfection_response,
+            Utc::now(),
+        )?;
+
+        assert_eq!(desinfection_desired, false);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn is_desinfection_desired_returns_true_when_hours_since_last_desinfection_are_less_than_min_hours_but_total_price_for_desinfection_is_negative(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 5400,
+                    power_draw_watt: 2000.0,
+                },
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 8000.0,
+                },
+            ],
+        };
+
+        let min_hours_since_last_desinfection: i64 = 96;
+        let max_hours_since_last_desinfection: i64 = 240;
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 11, 9, 37, 0).unwrap();
+        let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    market_price: 0.33,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile.clone(),
+        };
+        let lowest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: -0.40,
+                    market_price_tax: -0.10,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: -0.40,
+                    market_price_tax: -0.10,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile,
+        };
+
+        let (desinfection_desired, _) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection,
+                max_hours_since_last_desinfection,
+                desinfection_cooldown_hours: 0,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &lowest_price_desinfection_response,
+            &highest_price_desinfection_response,
+            Utc::now(),
+        )?;
+
+        assert_eq!(desinfection_desired, true);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn is_desinfection_desired_returns_true_when_hours_since_last_desinfection_are_greater_than_max_hours(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 5400,
+                    power_draw_watt: 2000.0,
+                },
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 8000.0,
+                },
+            ],
+        };
+
+        let min_hours_since_last_desinfection: i64 = 96;
+        let max_hours_since_last_desinfection: i64 = 240;
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 4, 30, 9, 37, 0).unwrap();
+        let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    market_price: 0.33,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile.clone(),
+        };
+        let lowest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile,
+        };
+
+        let (desinfection_desired, _) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection,
+                max_hours_since_last_desinfection,
+                desinfection_cooldown_hours: 0,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &lowest_price_desinfection_response,
+            &highest_price_desinfection_response,
+            Utc::now(),
+        )?;
+
+        assert_eq!(desinfection_desired, true);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn is_desinfection_desired_returns_true_when_hours_since_last_desinfection_between_min_and_max_hours_and_max_prices_of_best_spot_prices_is_less_than_calculated_percentage_of_max_of_all_spot_prices(
+    ) -> Result<(), Box<dyn Error>> {
+        let desinfection_load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 5400,
+                    power_draw_watt: 2000.0,
+                },
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 8000.0,
+                },
+            ],
+        };
+
+        let min_hours_since_last_desinfection: i64 = 96;
+        let max_hours_since_last_desinfection: i64 = 240;
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 5, 9, 37, 0).unwrap();
+        let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 18, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
+                    market_price: 0.33,
+                    market_price_tax: 0.06,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
+                    market_price: 0.28,
+                    market_price_tax: 0.05,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 21, 0, 0).unwrap(),
+                    market_price: 0.25,
+                    market_price_tax: 0.04,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile.clone(),
+        };
+        let lowest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    market_price: 0.06,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile,
+        };
+
+        let (desinfection_desired, _) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection,
+                max_hours_since_last_desinfection,
+                desinfection_cooldown_hours: 0,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &lowest_price_desinfection_response,
+            &highest_price_desinfection_response,
+            Utc::now(),
+        )?;
+
+        assert_eq!(desinfection_desired, true);
+
+        Ok(())
+    }
+
+    #[test]
     #[ignore]
-    fn is_desinfection_desired_returns_false_when_best_spot_prices_is_empty(
+    fn is_desinfection_desired_returns_false_when_hours_since_last_desinfection_between_min_and_max_hours_and_max_prices_of_best_spot_prices_is_greater_than_or_equal_to_calculated_percentage_of_max_of_all_spot_prices(
     ) -> Result<(), Box<dyn Error>> {
         let desinfection_load_profile = LoadProfile {
             sections: vec![
@@ -1269,17 +7595,53 @@ mod tests {
                 },
             ],
         };
+
         let min_hours_since_last_desinfection: i64 = 96;
         let max_hours_since_last_desinfection: i64 = 240;
-        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 4, 30, 9, 37, 0).unwrap();
+        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 6, 9, 37, 0).unwrap();
         let highest_price_desinfection_response = PlanningResponse {
+            spot_prices: vec![
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 18, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
+                    market_price: 0.33,
+                    market_price_tax: 0.06,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
+                    market_price: 0.08,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 21, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile.clone(),
+        };
+        let lowest_price_desinfection_response = PlanningResponse {
             spot_prices: vec![
                 SpotPrice {
                     id: None,
                     source: None,
                     from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
                     till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    market_price: 0.33,
+                    market_price: 0.08,
                     market_price_tax: 0.02,
                     sourcing_markup_price: 0.017,
                     energy_tax_price: 0.08,
@@ -1294,524 +7656,1383 @@ mod tests {
                     sourcing_markup_price: 0.017,
                     energy_tax_price: 0.08,
                 },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
+                SpotPrice {
+                    id: None,
+                    source: None,
+                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
+                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
+                    market_price: 0.09,
+                    market_price_tax: 0.02,
+                    sourcing_markup_price: 0.017,
+                    energy_tax_price: 0.08,
+                },
+            ],
+            load_profile: desinfection_load_profile,
+        };
+
+        let (desinfection_desired, _) = is_desinfection_desired(
+            &Config {
+                min_hours_since_last_desinfection,
+                max_hours_since_last_desinfection,
+                desinfection_cooldown_hours: 0,
+                ..test_config()
+            },
+            false,
+            &desinfection_finished_at,
+            &lowest_price_desinfection_response,
+            &highest_price_desinfection_response,
+            Utc::now(),
+        )?;
+
+        assert_eq!(desinfection_desired, false);
+
+        Ok(())
+    }
+
+    struct EmptyPlanner {}
+
+    impl BestSpotPricesPlanner for EmptyPlanner {
+        fn get_best_spot_prices(
+            &self,
+            planning_request: &PlanningRequest,
+        ) -> Result<PlanningResponse, Box<dyn Error>> {
+            Ok(PlanningResponse {
+                spot_prices: vec![],
+                load_profile: planning_request.load_profile.clone(),
+            })
+        }
+    }
+
+    fn hourly_spot_prices(from: DateTime<Utc>, market_prices: &[f64]) -> Vec<SpotPrice> {
+        market_prices
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| SpotPrice {
+                market_price: *market_price,
+                ..spot_price(
+                    from + Duration::hours(i as i64),
+                    from + Duration::hours(i as i64 + 1),
+                )
+            })
+            .collect()
+    }
+
+    fn quarter_hourly_spot_prices(from: DateTime<Utc>, market_prices: &[f64]) -> Vec<SpotPrice> {
+        market_prices
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| SpotPrice {
+                market_price: *market_price,
+                ..spot_price(
+                    from + Duration::minutes(15 * i as i64),
+                    from + Duration::minutes(15 * (i as i64 + 1)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_spot_prices_for_tapwater_heating_or_desinfection_forces_desinfection_if_overdue_and_best_response_is_empty(
+    ) {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.3, 0.1, 0.2, 0.05, 0.4]);
+
+        // act
+        let (response, desinfection_desired, desinfection_decision) = client
+            .get_spot_prices_for_tapwater_heating_or_desinfection(
+                &test_config(),
+                &EmptyPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(241),
+                false,
+            )
+            .unwrap();
+
+        assert!(desinfection_desired);
+        assert_eq!(
+            desinfection_decision,
+            DesinfectionDecision::Overdue {
+                hours_since_last_desinfection: 241,
+                max_hours_since_last_desinfection: 240,
+            }
+        );
+        assert_eq!(response.spot_prices.len(), 3);
+        assert_eq!(
+            response.spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 21, 13, 0, 0).unwrap()
+        );
+    }
+
+    struct RecordingPlanner {
+        desinfection_requests: Mutex<Vec<PlanningRequest>>,
+    }
+
+    impl BestSpotPricesPlanner for RecordingPlanner {
+        fn get_best_spot_prices(
+            &self,
+            planning_request: &PlanningRequest,
+        ) -> Result<PlanningResponse, Box<dyn Error>> {
+            // the desinfection load profile of the test config is the only one of 9000 seconds
+            if planning_request.load_profile.sections[0].duration_seconds == 9000 {
+                self.desinfection_requests
+                    .lock()
+                    .unwrap()
+                    .push(planning_request.clone());
+            }
+
+            EmptyPlanner {}.get_best_spot_prices(planning_request)
+        }
+    }
+
+    #[test]
+    fn get_spot_prices_for_tapwater_heating_or_desinfection_only_plans_desinfection_on_allowed_weekdays(
+    ) {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 18, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 7 * 24]);
+        let planner = RecordingPlanner {
+            desinfection_requests: Mutex::new(vec![]),
+        };
+
+        // act
+        client
+            .get_spot_prices_for_tapwater_heating_or_desinfection(
+                &Config {
+                    desinfection_allowed_weekdays: vec![Weekday::Sat, Weekday::Sun],
+                    ..test_config()
+                },
+                &planner,
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                false,
+            )
+            .unwrap();
+
+        let desinfection_requests = planner.desinfection_requests.lock().unwrap();
+        assert!(!desinfection_requests.is_empty());
+        for desinfection_request in desinfection_requests.iter() {
+            assert_eq!(desinfection_request.spot_prices.len(), 48);
+            assert!(desinfection_request
+                .spot_prices
+                .iter()
+                .all(|sp| matches!(sp.from.weekday(), Weekday::Sat | Weekday::Sun)));
+        }
+    }
+
+    #[test]
+    fn get_spot_prices_for_tapwater_heating_or_desinfection_forces_overdue_desinfection_outside_allowed_weekdays(
+    ) {
+        let client = test_client();
+        // a thursday, with the cheapest window on friday
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut market_prices = vec![0.3; 5 * 24];
+        market_prices[24] = 0.01;
+        let spot_prices = hourly_spot_prices(now, &market_prices);
+
+        // act
+        let (response, desinfection_desired, _) = client
+            .get_spot_prices_for_tapwater_heating_or_desinfection(
+                &Config {
+                    desinfection_allowed_weekdays: vec![Weekday::Sat, Weekday::Sun],
+                    ..test_config()
+                },
+                &EmptyPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(241),
+                false,
+            )
+            .unwrap();
+
+        assert!(desinfection_desired);
+        assert_eq!(response.spot_prices[0].from.weekday(), Weekday::Fri);
+    }
+
+    #[test]
+    fn get_spot_prices_for_tapwater_heating_or_desinfection_does_not_force_desinfection_if_disabled(
+    ) {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.3, 0.1, 0.2, 0.05, 0.4]);
+
+        // act
+        let (response, desinfection_desired, desinfection_decision) = client
+            .get_spot_prices_for_tapwater_heating_or_desinfection(
+                &Config {
+                    force_desinfection_when_overdue: false,
+                    ..test_config()
+                },
+                &EmptyPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(241),
+                false,
+            )
+            .unwrap();
+
+        assert!(!desinfection_desired);
+        assert!(matches!(
+            desinfection_decision,
+            DesinfectionDecision::Deferred { .. }
+        ));
+        assert!(response.spot_prices.is_empty());
+    }
+
+    // picks the first window after now long enough for the requested load profile, whatever its price
+    struct FirstWindowPlanner {}
+
+    impl BestSpotPricesPlanner for FirstWindowPlanner {
+        fn get_best_spot_prices(
+            &self,
+            planning_request: &PlanningRequest,
+        ) -> Result<PlanningResponse, Box<dyn Error>> {
+            let duration_seconds: i64 = planning_request
+                .load_profile
+                .sections
+                .iter()
+                .map(|section| section.duration_seconds)
+                .sum();
+
+            Ok(PlanningResponse {
+                spot_prices: get_earliest_contiguous_spot_prices(
+                    &planning_request.spot_prices,
+                    duration_seconds,
+                    planning_request.after.unwrap(),
+                ),
+                load_profile: planning_request.load_profile.clone(),
+            })
+        }
+    }
+
+    fn eco_desinfection_config() -> Config {
+        Config {
+            eco_desinfection_temperature: Some(58.0),
+            ..test_config()
+        }
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_prefers_full_desinfection_over_eco() {
+        let client = test_client();
+        // a sunday, when a full desinfection is desired
+        let now = Utc.with_ymd_and_hms(2022, 4, 24, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
+
+        // act
+        let (response, desinfection_mode, desinfection_decision) = client
+            .get_spot_prices_for_desinfection_mode(
+                &eco_desinfection_config(),
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::Full);
+        assert!(matches!(
+            desinfection_decision,
+            DesinfectionDecision::Weekday { .. }
+        ));
+        // the desinfection load profile of the test config takes 9000 seconds
+        assert_eq!(response.spot_prices.len(), 3);
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_chooses_eco_desinfection_if_full_is_not_desired() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
+
+        // act
+        let (response, desinfection_mode, desinfection_decision) = client
+            .get_spot_prices_for_desinfection_mode(
+                &eco_desinfection_config(),
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::Eco);
+        assert_eq!(
+            desinfection_decision,
+            DesinfectionDecision::EcoHeatUp {
+                hours_since_last_heat_up: 100,
+                min_hours_since_last_heat_up: 72,
+            }
+        );
+        // without a profile of its own eco desinfection uses the 7200 seconds tap water load profile
+        assert_eq!(response.spot_prices.len(), 2);
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_uses_eco_desinfection_load_profile() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
+
+        // act
+        let (response, desinfection_mode, _) = client
+            .get_spot_prices_for_desinfection_mode(
+                &Config {
+                    eco_desinfection_load_profile: Some(LoadProfile {
+                        sections: vec![LoadProfileSection {
+                            duration_seconds: 14400,
+                            power_draw_watt: 2000.0,
+                        }],
+                    }),
+                    ..eco_desinfection_config()
+                },
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::Eco);
+        assert_eq!(response.spot_prices.len(), 4);
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_chooses_none_without_eco_desinfection_temperature() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
+
+        // act
+        let (response, desinfection_mode, desinfection_decision) = client
+            .get_spot_prices_for_desinfection_mode(
+                &test_config(),
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::None);
+        assert!(matches!(
+            desinfection_decision,
+            DesinfectionDecision::Weekday { .. }
+        ));
+        assert_eq!(response.spot_prices.len(), 2);
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_chooses_none_within_min_hours_since_last_desinfection()
+    {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
+
+        // act
+        let (_, desinfection_mode, _) = client
+            .get_spot_prices_for_desinfection_mode(
+                &eco_desinfection_config(),
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(48),
+                None,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::None);
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_counts_hours_since_last_eco_desinfection() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
+
+        // act
+        let (_, desinfection_mode, _) = client
+            .get_spot_prices_for_desinfection_mode(
+                &eco_desinfection_config(),
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                Some(now - Duration::hours(24)),
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::None);
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_chooses_none_if_eco_window_is_too_expensive() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
+
+        // act
+        let (_, desinfection_mode, _) = client
+            .get_spot_prices_for_desinfection_mode(
+                &Config {
+                    eco_desinfection_max_price: Some(0.01),
+                    ..eco_desinfection_config()
+                },
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::None);
+    }
+
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_chooses_eco_if_eco_window_is_cheap_enough() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = quarter_hourly_spot_prices(now, &[0.0; 96]);
+        let config = eco_desinfection_config();
+        let average_price = config.effective_price(&spot_prices[0]);
+
+        // act
+        let (response, desinfection_mode, _) = client
+            .get_spot_prices_for_desinfection_mode(
+                &Config {
+                    eco_desinfection_max_price: Some(average_price + 0.01),
+                    ..config
                 },
-            ],
-            load_profile: desinfection_load_profile.clone(),
-        };
-        let lowest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![],
-            load_profile: desinfection_load_profile,
-        };
+                &FirstWindowPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
 
-        let desinfection_desired = is_desinfection_desired(
-            min_hours_since_last_desinfection,
-            max_hours_since_last_desinfection,
-            &desinfection_finished_at,
-            &lowest_price_desinfection_response,
-            &highest_price_desinfection_response,
-        )?;
+        // averaged per hour, so the eight quarter hour slots compare with the same maximum as hourly ones
+        assert_eq!(desinfection_mode, DesinfectionMode::Eco);
+        assert_eq!(response.spot_prices.len(), 8);
+    }
 
-        assert_eq!(desinfection_desired, false);
+    #[test]
+    fn get_spot_prices_for_desinfection_mode_chooses_none_without_eco_window() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.2; 24]);
 
-        Ok(())
+        // act
+        let (_, desinfection_mode, desinfection_decision) = client
+            .get_spot_prices_for_desinfection_mode(
+                &eco_desinfection_config(),
+                &EmptyPlanner {},
+                &spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(desinfection_mode, DesinfectionMode::None);
+        assert!(matches!(
+            desinfection_decision,
+            DesinfectionDecision::Deferred { .. }
+        ));
     }
 
     #[test]
-    #[ignore]
-    fn is_desinfection_desired_returns_false_when_hours_since_last_desinfection_are_less_than_min_hours(
-    ) -> Result<(), Box<dyn Error>> {
-        let desinfection_load_profile = LoadProfile {
-            sections: vec![
-                LoadProfileSection {
-                    duration_seconds: 5400,
-                    power_draw_watt: 2000.0,
-                },
-                LoadProfileSection {
-                    duration_seconds: 1800,
-                    power_draw_watt: 8000.0,
+    fn get_spot_prices_for_tapwater_heating_or_desinfection_per_segment_takes_strongest_mode() {
+        let client = test_client();
+        // a sunday segment runs a full desinfection, a monday segment isn't allowed to so heats up eco instead
+        let now = Utc.with_ymd_and_hms(2022, 4, 24, 0, 0, 0).unwrap();
+        let segment_spot_prices = vec![
+            hourly_spot_prices(now, &[0.2; 12]),
+            hourly_spot_prices(now + Duration::hours(24), &[0.2; 12]),
+        ];
+
+        // act
+        let (responses, desinfection_mode, desinfection_decisions) = client
+            .get_spot_prices_for_tapwater_heating_or_desinfection_per_segment(
+                &Config {
+                    desinfection_allowed_weekdays: vec![Weekday::Sun],
+                    ..eco_desinfection_config()
                 },
-            ],
-        };
+                &FirstWindowPlanner {},
+                &segment_spot_prices,
+                now,
+                now - Duration::hours(100),
+                None,
+                false,
+            )
+            .unwrap();
 
-        let min_hours_since_last_desinfection: i64 = 96;
-        let max_hours_since_last_desinfection: i64 = 240;
-        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 11, 9, 37, 0).unwrap();
-        let highest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    market_price: 0.33,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
+        assert_eq!(responses.len(), 2);
+        assert_eq!(desinfection_mode, DesinfectionMode::Full);
+        assert!(matches!(
+            desinfection_decisions[..],
+            [
+                DesinfectionDecision::Weekday { .. },
+                DesinfectionDecision::EcoHeatUp { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn get_desired_tap_water_temperature_depends_on_desinfection_mode() {
+        let config = eco_desinfection_config();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        // act
+        let temperatures = [
+            DesinfectionMode::Full,
+            DesinfectionMode::Eco,
+            DesinfectionMode::None,
+        ]
+        .iter()
+        .map(|mode| get_desired_tap_water_temperature(&config, *mode, &[], now))
+        .collect::<Vec<f64>>();
+
+        assert_eq!(
+            temperatures,
+            vec![MAXIMUM_TAP_WATER_TEMPERATURE, 58.0, 50.0]
+        );
+    }
+
+    struct HighestSlotPlanner {}
+
+    impl BestSpotPricesPlanner for HighestSlotPlanner {
+        fn get_best_spot_prices(
+            &self,
+            planning_request: &PlanningRequest,
+        ) -> Result<PlanningResponse, Box<dyn Error>> {
+            Ok(PlanningResponse {
+                spot_prices: match planning_request.planning_strategy {
+                    PlanningStrategy::LowestPrice => None,
+                    PlanningStrategy::HighestPrice => planning_request
+                        .spot_prices
+                        .iter()
+                        .filter(|sp| sp.till <= planning_request.before.unwrap())
+                        .max_by(|a, b| a.market_price.partial_cmp(&b.market_price).unwrap()),
+                }
+                .cloned()
+                .into_iter()
+                .collect(),
+                load_profile: planning_request.load_profile.clone(),
+            })
+        }
+    }
+
+    #[test]
+    fn get_worst_spot_prices_for_blocking_heating_returns_single_worst_hour() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.30, 0.45, 0.10, 0.40, 0.20]);
+
+        // act
+        let worst_spot_prices = client
+            .get_worst_spot_prices_for_blocking_heating(
+                &test_config(),
+                &HighestSlotPlanner {},
+                &spot_prices,
+                now,
+            )
+            .unwrap();
+
+        assert_eq!(
+            worst_spot_prices
+                .iter()
+                .map(|sp| sp.from)
+                .collect::<Vec<_>>(),
+            vec![now + Duration::hours(1)]
+        );
+    }
+
+    #[test]
+    fn get_worst_spot_prices_for_blocking_heating_returns_non_contiguous_slots_above_price() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now, &[0.30, 0.45, 0.10, 0.40, 0.20]);
+        let config = test_config();
+        let blocking_price = config.effective_price(&spot_prices[0]);
+
+        // act
+        let worst_spot_prices = client
+            .get_worst_spot_prices_for_blocking_heating(
+                &Config {
+                    blocking_strategy: BlockingStrategy::AbovePrice(blocking_price),
+                    ..config
                 },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
+                &EmptyPlanner {},
+                &spot_prices,
+                now,
+            )
+            .unwrap();
+
+        assert_eq!(
+            worst_spot_prices
+                .iter()
+                .map(|sp| sp.from)
+                .collect::<Vec<_>>(),
+            vec![now + Duration::hours(1), now + Duration::hours(3)]
+        );
+    }
+
+    #[test]
+    fn get_worst_spot_prices_for_blocking_heating_returns_slots_above_percentile_of_the_day() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut spot_prices =
+            hourly_spot_prices(now, &[0.30, 0.45, 0.10, 0.40, 0.20, 0.35, 0.15, 0.25]);
+        spot_prices.extend(hourly_spot_prices(now + Duration::hours(24), &[0.90]));
+
+        // act
+        let worst_spot_prices = client
+            .get_worst_spot_prices_for_blocking_heating(
+                &Config {
+                    blocking_strategy: BlockingStrategy::AbovePercentile(75.0),
+                    ..test_config()
                 },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
+                &EmptyPlanner {},
+                &spot_prices,
+                now,
+            )
+            .unwrap();
+
+        assert_eq!(
+            worst_spot_prices
+                .iter()
+                .map(|sp| sp.from)
+                .collect::<Vec<_>>(),
+            vec![now + Duration::hours(1), now + Duration::hours(3)]
+        );
+    }
+
+    #[test]
+    fn preview_next_24_hours_returns_action_per_spot_price() {
+        let client = test_client();
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let mut spot_prices = hourly_spot_prices(now, &[0.3, 0.1, 0.2, 0.05, 0.4]);
+        spot_prices.extend(hourly_spot_prices(now + Duration::hours(24), &[0.01]));
+
+        // act
+        let preview = client
+            .preview_next_24_hours(
+                &Config {
+                    max_hours_since_last_desinfection: 100,
+                    ..test_config()
                 },
-            ],
-            load_profile: desinfection_load_profile.clone(),
+                &HighestSlotPlanner {},
+                &spot_prices,
+                now,
+            )
+            .unwrap();
+
+        assert_eq!(
+            preview
+                .iter()
+                .map(|(_, action)| action.clone())
+                .collect::<Vec<PlanAction>>(),
+            vec![
+                PlanAction::Idle,
+                PlanAction::Desinfection,
+                PlanAction::Desinfection,
+                PlanAction::Desinfection,
+                PlanAction::BlockHeating,
+            ]
+        );
+    }
+
+    struct CheapestSlotPlanner {}
+
+    impl BestSpotPricesPlanner for CheapestSlotPlanner {
+        fn get_best_spot_prices(
+            &self,
+            planning_request: &PlanningRequest,
+        ) -> Result<PlanningResponse, Box<dyn Error>> {
+            Ok(PlanningResponse {
+                spot_prices: planning_request
+                    .spot_prices
+                    .iter()
+                    .min_by(|a, b| {
+                        let total_a = a.market_price
+                            + a.market_price_tax
+                            + a.sourcing_markup_price
+                            + a.energy_tax_price;
+                        let total_b = b.market_price
+                            + b.market_price_tax
+                            + b.sourcing_markup_price
+                            + b.energy_tax_price;
+                        total_a.partial_cmp(&total_b).unwrap()
+                    })
+                    .cloned()
+                    .into_iter()
+                    .collect(),
+                load_profile: planning_request.load_profile.clone(),
+            })
+        }
+    }
+
+    fn price_components_spot_prices() -> Vec<SpotPrice> {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        vec![
+            SpotPrice {
+                market_price: 0.1,
+                energy_tax_price: 0.3,
+                ..spot_price(now, now + Duration::hours(1))
+            },
+            SpotPrice {
+                market_price: 0.2,
+                energy_tax_price: 0.0,
+                ..spot_price(now + Duration::hours(1), now + Duration::hours(2))
+            },
+        ]
+    }
+
+    #[test]
+    fn effective_price_planner_optimizes_against_configured_price_components_only() {
+        let spot_prices = price_components_spot_prices();
+        let spot_price_planner = EffectivePricePlanner {
+            planner: CheapestSlotPlanner {},
+            config: Config {
+                price_components: vec![PriceComponent::MarketPrice],
+                ..test_config()
+            },
         };
-        let lowest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile,
+
+        // act
+        let planning_response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: spot_prices.clone(),
+                load_profile: test_config().load_profile,
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: None,
+                before: None,
+            })
+            .unwrap();
+
+        assert_eq!(planning_response.spot_prices.len(), 1);
+        assert_eq!(planning_response.spot_prices[0].from, spot_prices[0].from);
+        // the original prices are returned, not the effective ones
+        assert_eq!(planning_response.spot_prices[0].energy_tax_price, 0.3);
+    }
+
+    #[test]
+    fn effective_price_planner_optimizes_against_full_price_by_default() {
+        let spot_prices = price_components_spot_prices();
+        let spot_price_planner = EffectivePricePlanner {
+            planner: CheapestSlotPlanner {},
+            config: test_config(),
         };
 
-        let desinfection_desired = is_desinfection_desired(
-            min_hours_since_last_desinfection,
-            max_hours_since_last_desinfection,
-            &desinfection_finished_at,
-            &lowest_price_desinfection_response,
-            &highest_price_desinfection_response,
-        )?;
+        // act
+        let planning_response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: spot_prices.clone(),
+                load_profile: test_config().load_profile,
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: None,
+                before: None,
+            })
+            .unwrap();
+
+        assert_eq!(planning_response.spot_prices.len(), 1);
+        assert_eq!(planning_response.spot_prices[0].from, spot_prices[1].from);
+    }
+
+    #[test]
+    fn get_active_away_until_returns_future_away_until_from_config_or_state() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let away_until = Utc.with_ymd_and_hms(2022, 4, 28, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            get_active_away_until(Some(away_until), None, now),
+            Some(away_until)
+        );
+        assert_eq!(
+            get_active_away_until(None, Some(away_until), now),
+            Some(away_until)
+        );
+        assert_eq!(get_active_away_until(None, None, now), None);
+    }
+
+    #[test]
+    fn get_active_do_not_disturb_until_returns_future_timestamp_from_state() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        // act
+        let do_not_disturb_until =
+            get_active_do_not_disturb_until(Some(now + Duration::hours(2)), now);
+
+        assert_eq!(do_not_disturb_until, Some(now + Duration::hours(2)));
+    }
+
+    #[test]
+    fn get_active_do_not_disturb_until_clears_timestamp_once_it_has_passed() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        // act
+        let do_not_disturb_until = get_active_do_not_disturb_until(Some(now), now);
+
+        assert_eq!(do_not_disturb_until, None);
+        assert_eq!(get_active_do_not_disturb_until(None, now), None);
+    }
+
+    #[test]
+    fn get_active_away_until_clears_away_until_once_it_has_passed() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        // act
+        let away_until = get_active_away_until(
+            None,
+            Some(Utc.with_ymd_and_hms(2022, 4, 20, 12, 0, 0).unwrap()),
+            now,
+        );
+
+        assert_eq!(away_until, None);
+    }
+
+    #[test]
+    fn get_active_away_until_prefers_config_over_state() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+
+        // act
+        let away_until = get_active_away_until(
+            Some(Utc.with_ymd_and_hms(2022, 4, 20, 12, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 28, 12, 0, 0).unwrap()),
+            now,
+        );
+
+        assert_eq!(away_until, None);
+    }
+
+    #[test]
+    fn redact_login_code_replaces_every_occurrence_of_the_login_code() {
+        // act
+        let redacted = redact_login_code("<Error>LOGIN;1a2b failed for 1a2b</Error>", "1a2b");
+
+        assert_eq!(
+            redacted,
+            "<Error>LOGIN;*** failed for ***</Error>".to_string()
+        );
+    }
 
-        assert_eq!(desinfection_desired, false);
+    #[test]
+    fn describe_message_keeps_set_commands_and_redacts_login() {
+        // act
+        let descriptions = [
+            OwnedMessage::Text("SET;set_0xa57344;480".to_string()),
+            OwnedMessage::Text("LOGIN;1a2b".to_string()),
+        ]
+        .iter()
+        .map(describe_message)
+        .collect::<Vec<String>>();
 
-        Ok(())
+        assert_eq!(
+            descriptions,
+            vec!["SET;set_0xa57344;480".to_string(), "LOGIN;***".to_string()]
+        );
     }
 
     #[test]
-    #[ignore]
-    fn is_desinfection_desired_returns_true_when_hours_since_last_desinfection_are_less_than_min_hours_but_total_price_for_desinfection_is_negative(
-    ) -> Result<(), Box<dyn Error>> {
-        let desinfection_load_profile = LoadProfile {
-            sections: vec![
-                LoadProfileSection {
-                    duration_seconds: 5400,
-                    power_draw_watt: 2000.0,
-                },
-                LoadProfileSection {
-                    duration_seconds: 1800,
-                    power_draw_watt: 8000.0,
-                },
-            ],
-        };
+    fn is_desinfection_deferred_returns_false_if_window_starts_at_defer_boundary() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now + Duration::hours(12), &[0.1, 0.2]);
 
-        let min_hours_since_last_desinfection: i64 = 96;
-        let max_hours_since_last_desinfection: i64 = 240;
-        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 11, 9, 37, 0).unwrap();
-        let highest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    market_price: 0.33,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile.clone(),
-        };
-        let lowest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: -0.40,
-                    market_price_tax: -0.10,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: -0.40,
-                    market_price_tax: -0.10,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile,
-        };
+        // act
+        let deferred = is_desinfection_deferred(&spot_prices, 12, now);
 
-        let desinfection_desired = is_desinfection_desired(
-            min_hours_since_last_desinfection,
-            max_hours_since_last_desinfection,
-            &desinfection_finished_at,
-            &lowest_price_desinfection_response,
-            &highest_price_desinfection_response,
-        )?;
+        assert!(!deferred);
+    }
 
-        assert_eq!(desinfection_desired, true);
+    #[test]
+    fn is_desinfection_deferred_returns_true_if_window_starts_after_defer_boundary() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(now + Duration::hours(13), &[0.1, 0.2]);
 
-        Ok(())
+        // act
+        let deferred = is_desinfection_deferred(&spot_prices, 12, now);
+
+        assert!(deferred);
     }
 
     #[test]
-    #[ignore]
-    fn is_desinfection_desired_returns_true_when_hours_since_last_desinfection_are_greater_than_max_hours(
-    ) -> Result<(), Box<dyn Error>> {
-        let desinfection_load_profile = LoadProfile {
-            sections: vec![
-                LoadProfileSection {
-                    duration_seconds: 5400,
-                    power_draw_watt: 2000.0,
-                },
-                LoadProfileSection {
-                    duration_seconds: 1800,
-                    power_draw_watt: 8000.0,
-                },
-            ],
-        };
+    fn is_desinfection_deferred_returns_true_without_window() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
 
-        let min_hours_since_last_desinfection: i64 = 96;
-        let max_hours_since_last_desinfection: i64 = 240;
-        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 4, 30, 9, 37, 0).unwrap();
-        let highest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    market_price: 0.33,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile.clone(),
-        };
-        let lowest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile,
+        // act
+        let deferred = is_desinfection_deferred(&[], 12, now);
+
+        assert!(deferred);
+    }
+
+    #[test]
+    fn get_running_desinfection_mode_recognizes_eco_window_by_its_end() {
+        let now = Utc.with_ymd_and_hms(2022, 4, 21, 12, 0, 0).unwrap();
+        let running_spot_prices = hourly_spot_prices(now - Duration::hours(1), &[0.2, 0.2]);
+
+        // act
+        let desinfection_modes = (
+            get_running_desinfection_mode(true, None, &running_spot_prices),
+            get_running_desinfection_mode(
+                false,
+                Some(now + Duration::hours(1)),
+                &running_spot_prices,
+            ),
+            get_running_desinfection_mode(
+                false,
+                Some(now - Duration::days(3)),
+                &running_spot_prices,
+            ),
+        );
+
+        assert_eq!(
+            desinfection_modes,
+            (
+                DesinfectionMode::Full,
+                DesinfectionMode::Eco,
+                DesinfectionMode::None
+            )
+        );
+    }
+
+    #[test]
+    fn send_and_await_discards_pending_messages_before_sending() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec![TEMPERATURES_RESPONSE])
+            .with_pending(vec!["<Content><name>Warmwater</name></Content>"]);
+        let mut sender = MockSender::default();
+
+        // act
+        let response = client
+            .send_and_await(
+                &mut receiver,
+                &mut sender,
+                OwnedMessage::Text("GET;0x45df90".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(response, TEMPERATURES_RESPONSE.to_string());
+        assert_eq!(sender.messages, vec!["GET;0x45df90".to_string()]);
+    }
+
+    #[test]
+    fn send_and_await_returns_connection_closed_error_if_pending_message_closes_connection() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec![TEMPERATURES_RESPONSE]);
+        receiver.pending.push_back(OwnedMessage::Close(None));
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.send_and_await(
+            &mut receiver,
+            &mut sender,
+            OwnedMessage::Text("GET;0x45df90".to_string()),
+        );
+
+        assert!(matches!(
+            result.unwrap_err(),
+            WebsocketClientError::ConnectionClosed { .. }
+        ));
+        assert!(sender.messages.is_empty());
+    }
+
+    #[test]
+    fn send_and_await_returns_connection_closed_error_with_message_in_flight() {
+        let client = test_client();
+        let mut receiver = MockReceiver::from_messages(vec![OwnedMessage::Close(None)]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.send_and_await(
+            &mut receiver,
+            &mut sender,
+            OwnedMessage::Text("GET;0x4642a8".to_string()),
+        );
+
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error,
+            WebsocketClientError::ConnectionClosed { .. }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Connection closed by heatpump while awaiting response for message GET;0x4642a8"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn send_and_await_returns_error_with_masked_login_message_if_no_response_is_received() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec![]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.send_and_await(
+            &mut receiver,
+            &mut sender,
+            OwnedMessage::Text("LOGIN;999999".to_string()),
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No response received for message LOGIN;***".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn run_session_retries_session_if_connection_is_closed() {
+        let client = WebsocketClient::with_connector(
+            WebsocketClientConfig::new(
+                "127.0.0.1".to_string(),
+                8214,
+                "999999".to_string(),
+                None,
+                false,
+                None,
+                "Lux_WS".to_string(),
+                0,
+                None,
+            )
+            .unwrap(),
+            Box::new(MockConnector {
+                connections: Mutex::new(
+                    vec![
+                        vec![
+                            OwnedMessage::Text(SCHEDULE_NAVIGATION_RESPONSE.to_string()),
+                            OwnedMessage::Close(None),
+                        ],
+                        vec![
+                            OwnedMessage::Text(SCHEDULE_NAVIGATION_RESPONSE.to_string()),
+                            OwnedMessage::Text(EMPTY_SCHEDULE_RESPONSE.to_string()),
+                        ],
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+            }),
+        );
+        let attempts = Arc::new(Mutex::new(0));
+
+        // act
+        let response = {
+            let attempts = Arc::clone(&attempts);
+            client
+                .run_session(move |client, receiver, sender, navigation| {
+                    *attempts.lock().unwrap() += 1;
+                    Ok(client.navigate_to(
+                        receiver,
+                        sender,
+                        navigation,
+                        "Klokprogramma > Warmwater > Week",
+                    )?)
+                })
+                .await
+                .unwrap()
         };
 
-        let desinfection_desired = is_desinfection_desired(
-            min_hours_since_last_desinfection,
-            max_hours_since_last_desinfection,
-            &desinfection_finished_at,
-            &lowest_price_desinfection_response,
-            &highest_price_desinfection_response,
-        )?;
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert_eq!(
+            client
+                .health_state()
+                .get_counter(PlannerCounter::ConnectionFailures),
+            1
+        );
+        assert_eq!(response, EMPTY_SCHEDULE_RESPONSE.to_string());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn run_session_lets_other_tasks_run_while_session_blocks() {
+        let client = WebsocketClient::with_connector(
+            WebsocketClientConfig::new(
+                "127.0.0.1".to_string(),
+                8214,
+                "999999".to_string(),
+                None,
+                false,
+                None,
+                "Lux_WS".to_string(),
+                0,
+                None,
+            )
+            .unwrap(),
+            Box::new(MockConnector {
+                connections: Mutex::new(
+                    vec![vec![OwnedMessage::Text(
+                        SCHEDULE_NAVIGATION_RESPONSE.to_string(),
+                    )]]
+                    .into_iter()
+                    .collect(),
+                ),
+            }),
+        );
+        let (ticked_sender, ticked_receiver) = std::sync::mpsc::channel();
+        // a current thread runtime only gets to this task while the session is awaited
+        tokio::spawn(async move {
+            ticked_sender.send(()).unwrap();
+        });
+
+        // act
+        let ticked = client
+            .run_session(move |_, _, _, _| {
+                Ok(ticked_receiver
+                    .recv_timeout(std::time::Duration::from_secs(5))
+                    .is_ok())
+            })
+            .await
+            .unwrap();
+
+        assert!(ticked);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn run_session_abandons_session_that_exceeds_session_timeout() {
+        let mut client = WebsocketClient::with_connector(
+            WebsocketClientConfig::new(
+                "127.0.0.1".to_string(),
+                8214,
+                "999999".to_string(),
+                None,
+                false,
+                None,
+                "Lux_WS".to_string(),
+                0,
+                None,
+            )
+            .unwrap(),
+            Box::new(MockConnector {
+                connections: Mutex::new(
+                    vec![vec![OwnedMessage::Text(
+                        SCHEDULE_NAVIGATION_RESPONSE.to_string(),
+                    )]]
+                    .into_iter()
+                    .collect(),
+                ),
+            }),
+        );
+        client.session_timeout = std::time::Duration::from_millis(50);
+        let (sent_sender, sent_receiver) = std::sync::mpsc::channel();
+
+        // act
+        let result = client
+            .run_session(move |_, _, sender, _| {
+                // hangs like a controller that stopped responding halfway a SET/SAVE sequence
+                thread::sleep(std::time::Duration::from_millis(200));
+                sent_sender
+                    .send(
+                        sender
+                            .send_message(&OwnedMessage::Text("SAVE;1".to_string()))
+                            .is_ok(),
+                    )
+                    .unwrap();
+                Ok(())
+            })
+            .await;
 
-        assert_eq!(desinfection_desired, true);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Session with heatpump did not finish within 50ms, abandoning it".to_string()
+        );
+        assert!(!sent_receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap());
+    }
 
-        Ok(())
+    #[test]
+    fn click_waits_for_navigation_step_delay_after_move() {
+        let client = WebsocketClient::with_connector(
+            WebsocketClientConfig::new(
+                "127.0.0.1".to_string(),
+                8214,
+                "999999".to_string(),
+                None,
+                false,
+                None,
+                "Lux_WS".to_string(),
+                50,
+                None,
+            )
+            .unwrap(),
+            Box::new(MockConnector {
+                connections: Mutex::new(std::collections::VecDeque::new()),
+            }),
+        );
+        let mut receiver = MockReceiver::new(vec![SAVED_RESPONSE, SAVED_RESPONSE]);
+        let mut sender = MockSender::default();
+        let started = std::time::Instant::now();
+
+        // act
+        client.click(&mut receiver, &mut sender).unwrap();
+
+        assert!(started.elapsed() >= std::time::Duration::from_millis(50));
+        assert_eq!(
+            sender.messages,
+            vec!["MOVE;2".to_string(), "MOVE;6".to_string()]
+        );
     }
 
     #[test]
-    #[ignore]
-    fn is_desinfection_desired_returns_true_when_hours_since_last_desinfection_between_min_and_max_hours_and_max_prices_of_best_spot_prices_is_less_than_calculated_percentage_of_max_of_all_spot_prices(
-    ) -> Result<(), Box<dyn Error>> {
-        let desinfection_load_profile = LoadProfile {
-            sections: vec![
-                LoadProfileSection {
-                    duration_seconds: 5400,
-                    power_draw_watt: 2000.0,
-                },
-                LoadProfileSection {
-                    duration_seconds: 1800,
-                    power_draw_watt: 8000.0,
-                },
-            ],
-        };
+    fn get_login_code_reads_code_from_file() {
+        let login_code_file_path = env::temp_dir().join("get-login-code-reads-code-from-file");
+        fs::write(&login_code_file_path, "123456\n").unwrap();
 
-        let min_hours_since_last_desinfection: i64 = 96;
-        let max_hours_since_last_desinfection: i64 = 240;
-        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 5, 9, 37, 0).unwrap();
-        let highest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 18, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
-                    market_price: 0.33,
-                    market_price_tax: 0.06,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
-                    market_price: 0.28,
-                    market_price_tax: 0.05,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 21, 0, 0).unwrap(),
-                    market_price: 0.25,
-                    market_price_tax: 0.04,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile.clone(),
-        };
-        let lowest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    market_price: 0.06,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile,
-        };
+        // act
+        let login_code = get_login_code(
+            None,
+            Some(login_code_file_path.to_str().unwrap().to_string()),
+        )
+        .unwrap();
 
-        let desinfection_desired = is_desinfection_desired(
-            min_hours_since_last_desinfection,
-            max_hours_since_last_desinfection,
-            &desinfection_finished_at,
-            &lowest_price_desinfection_response,
-            &highest_price_desinfection_response,
-        )?;
+        assert_eq!(login_code, "123456".to_string());
+    }
 
-        assert_eq!(desinfection_desired, true);
+    #[test]
+    fn get_login_code_prefers_file_over_env() {
+        let login_code_file_path = env::temp_dir().join("get-login-code-prefers-file-over-env");
+        fs::write(&login_code_file_path, "123456").unwrap();
 
-        Ok(())
+        // act
+        let login_code = get_login_code(
+            Some("999999".to_string()),
+            Some(login_code_file_path.to_str().unwrap().to_string()),
+        )
+        .unwrap();
+
+        assert_eq!(login_code, "123456".to_string());
     }
 
     #[test]
-    #[ignore]
-    fn is_desinfection_desired_returns_false_when_hours_since_last_desinfection_between_min_and_max_hours_and_max_prices_of_best_spot_prices_is_greater_than_or_equal_to_calculated_percentage_of_max_of_all_spot_prices(
-    ) -> Result<(), Box<dyn Error>> {
-        let desinfection_load_profile = LoadProfile {
-            sections: vec![
-                LoadProfileSection {
-                    duration_seconds: 5400,
-                    power_draw_watt: 2000.0,
-                },
-                LoadProfileSection {
-                    duration_seconds: 1800,
-                    power_draw_watt: 8000.0,
-                },
-            ],
-        };
+    fn get_login_code_returns_error_without_leaking_code_if_file_is_missing() {
+        // act
+        let result = get_login_code(
+            Some("999999".to_string()),
+            Some("/nonexistent/login-code".to_string()),
+        );
 
-        let min_hours_since_last_desinfection: i64 = 96;
-        let max_hours_since_last_desinfection: i64 = 240;
-        let desinfection_finished_at = Utc.with_ymd_and_hms(2022, 5, 6, 9, 37, 0).unwrap();
-        let highest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 18, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
-                    market_price: 0.33,
-                    market_price_tax: 0.06,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 19, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 20, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 21, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile.clone(),
-        };
-        let lowest_price_desinfection_response = PlanningResponse {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    market_price: 0.08,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 5, 12, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 5, 12, 16, 0, 0).unwrap(),
-                    market_price: 0.09,
-                    market_price_tax: 0.02,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.08,
-                },
-            ],
-            load_profile: desinfection_load_profile,
-        };
+        let message = result.unwrap_err().to_string();
+        assert!(message.starts_with("Failed reading login code from /nonexistent/login-code"));
+        assert!(!message.contains("999999"));
+    }
 
-        let desinfection_desired = is_desinfection_desired(
-            min_hours_since_last_desinfection,
-            max_hours_since_last_desinfection,
-            &desinfection_finished_at,
-            &lowest_price_desinfection_response,
-            &highest_price_desinfection_response,
-        )?;
+    #[test]
+    fn get_login_code_returns_error_without_env_and_file() {
+        // act
+        let result = get_login_code(None, None);
 
-        assert_eq!(desinfection_desired, false);
+        assert!(result.is_err());
+    }
 
-        Ok(())
+    #[test]
+    fn login_returns_auth_error_if_login_code_is_rejected() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec![BAD_LOGIN_RESPONSE]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.login(&mut receiver, &mut sender);
+
+        let error = result.err().unwrap();
+        assert!(matches!(error, WebsocketClientError::Auth { .. }));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Login rejected by heatpump, check WEBSOCKET_LOGIN_CODE; response: {}",
+                BAD_LOGIN_RESPONSE
+            )
+        );
+    }
+
+    #[test]
+    fn login_redacts_login_code_echoed_in_rejected_response() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec!["<Error>Unknown code 999999</Error>"]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.login(&mut receiver, &mut sender);
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Login rejected by heatpump, check WEBSOCKET_LOGIN_CODE; response: <Error>Unknown code ***</Error>"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn login_returns_deserialization_error_for_corrupt_navigation() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec!["<Navigation id='0x45cd88'><item>"]);
+        let mut sender = MockSender::default();
+
+        // act
+        let result = client.login(&mut receiver, &mut sender);
+
+        assert!(matches!(
+            result.err().unwrap(),
+            WebsocketClientError::Parse(_)
+        ));
+    }
+
+    const BAD_LOGIN_RESPONSE: &str = "<Content></Content>";
+
+    #[test]
+    fn login_reuses_navigation_while_controller_sends_the_same_tree() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec![
+            SCHEDULE_NAVIGATION_RESPONSE,
+            SCHEDULE_NAVIGATION_RESPONSE,
+        ]);
+        let mut sender = MockSender::default();
+        let first_navigation = client.login(&mut receiver, &mut sender).unwrap();
+
+        // act
+        let second_navigation = client.login(&mut receiver, &mut sender).unwrap();
+
+        assert!(Arc::ptr_eq(&first_navigation, &second_navigation));
+    }
+
+    #[test]
+    fn login_rebuilds_navigation_when_controller_sends_a_different_tree() {
+        let client = test_client();
+        let mut receiver = MockReceiver::new(vec![
+            SCHEDULE_NAVIGATION_RESPONSE,
+            "<Navigation id='0x45cd88'><item id='0x3dc420'><name>Klokprogramma</name><readOnly>true</readOnly><item id='0x453560'><name>Verwarmen</name><readOnly>true</readOnly><item id='0x45e118'><name>Week</name></item></item><item id='0x43e8e8'><name>Warmwater</name><readOnly>true</readOnly><item id='0x4711c0'><name>Week</name></item></item></item></Navigation>",
+        ]);
+        let mut sender = MockSender::default();
+        let first_navigation = client.login(&mut receiver, &mut sender).unwrap();
+        assert_eq!(
+            first_navigation
+                .get_navigation_item_id("Klokprogramma > Warmwater > Week")
+                .unwrap(),
+            "0x4642a8".to_string()
+        );
+
+        // act
+        let second_navigation = client.login(&mut receiver, &mut sender).unwrap();
+
+        assert_eq!(
+            second_navigation
+                .get_navigation_item_id("Klokprogramma > Warmwater > Week")
+                .unwrap(),
+            "0x4711c0".to_string()
+        );
     }
 }